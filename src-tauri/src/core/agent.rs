@@ -0,0 +1,571 @@
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{IpAddr, Ipv4Addr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, UNIX_EPOCH};
+
+use chrono::{Local, TimeZone};
+use hmac::{Hmac, Mac};
+use native_tls::{Identity, TlsAcceptor};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use subtle::ConstantTimeEq;
+
+use super::credentials::RemoteCredentials;
+use super::file::{comma_separated_number, human_readable_size};
+use super::ftp::{percent_decode, safe_filename, tls_connect};
+use super::os_path::{os_path, path_string};
+use super::path_policy::ensure_permitted_with;
+use super::temp_file::{temp_dir, write_temp_file};
+use super::types::{FileAttr, ListDirResponse, NumberFormat, PathPolicy};
+
+/// env var to specify token instead of generated one
+const TOKEN_ENV: &str = "FORSKSCOPE_AGENT_TOKEN";
+/// env var to specify password of tls identity
+const TLS_PASSWORD_ENV: &str = "FORSKSCOPE_AGENT_TLS_PASSWORD";
+/// random bytes of generated token and nonce
+const TOKEN_BYTES: usize = 16;
+/// url scheme of files served by agent
+const AGENT_URL_SCHEME: &str = "forskscope://";
+/// default address agent listens on, where traffic stays on the machine
+pub const AGENT_DEFAULT_ADDRESS: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+/// default port of agent
+pub const AGENT_DEFAULT_PORT: u16 = 7710;
+/// timeout on connection and read / write
+const AGENT_TIMEOUT_SECS: u64 = 30;
+/// wait after failed authentication to slow down guessing
+const AUTH_FAILURE_DELAY: Duration = Duration::from_secs(1);
+/// bytes read at once from reply body
+const AGENT_READ_CHUNK_SIZE: usize = 64 * 1024;
+/// max bytes of command or reply line
+const AGENT_MAX_LINE_SIZE: usize = 8 * 1024;
+/// max bytes of reply body such as file served (256 MB)
+const AGENT_MAX_BODY_SIZE: u64 = 256 * 1024 * 1024;
+/// max connections served at the same time
+const AGENT_MAX_CONNECTIONS: usize = 16;
+
+/// location parsed from `forskscope://[token@]host[:port][/path]`
+pub struct AgentLocation {
+    pub host: String,
+    pub port: u16,
+    pub token: String,
+    pub path: String,
+}
+
+/// directory entry sent by agent. formatted by app with its number format
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AgentDirEntry {
+    name: String,
+    is_dir: bool,
+    size: u64,
+    last_modified: String,
+}
+
+/// token given by env var, or generated one
+pub fn agent_token() -> Result<String, String> {
+    std::env::var(TOKEN_ENV).or_else(|_| random_hex())
+}
+
+/// serve read-only access to files under root until process is stopped
+/// each connection must authenticate first by answering challenge with token,
+/// which itself never goes over network. root and paths under it are checked against path policy.
+/// traffic is plain on loopback, which is reached from another machine by ssh port forwarding,
+/// and is tls with identity given in pkcs #12 file on any other address
+pub fn serve(
+    root: &Path,
+    address: IpAddr,
+    port: u16,
+    token: &str,
+    tls_identity: Option<&Path>,
+    policy: &PathPolicy,
+) -> Result<(), String> {
+    ensure_permitted_with(policy, &path_string(root))?;
+    let acceptor = match (address.is_loopback(), tls_identity) {
+        (true, None) => None,
+        (false, Some(x)) => Some(tls_acceptor(x)?),
+        (true, Some(_)) => return Err(format!("TLS is not used on loopback address {}", address)),
+        (false, None) => return Err(format!("TLS identity is required to listen on {}", address)),
+    };
+    let listener = TcpListener::bind((address, port))
+        .map_err(|err| format!("Failed to listen on {}:{} ({})", address, port, err))?;
+    println!(
+        "Serving {} read-only on {}:{}",
+        root.display(),
+        address,
+        port
+    );
+    if acceptor.is_none() {
+        println!(
+            "Forward port by `ssh -L {}:localhost:{} <this host>` on another machine",
+            port, port
+        );
+    }
+    println!(
+        "Open {}{}@<host>:{}/ in app on another machine",
+        AGENT_URL_SCHEME, token, port
+    );
+
+    let connections = Arc::new(AtomicUsize::new(0));
+    for stream in listener.incoming() {
+        let mut stream = match stream {
+            Ok(x) => x,
+            Err(err) => {
+                tracing::warn!("Failed to accept agent connection ({})", err);
+                continue;
+            }
+        };
+        if AGENT_MAX_CONNECTIONS <= connections.fetch_add(1, Ordering::SeqCst) {
+            connections.fetch_sub(1, Ordering::SeqCst);
+            // tls client cannot read plain reply before handshake
+            if acceptor.is_none() {
+                let _ = set_timeouts(&stream)
+                    .and_then(|_| write_reply(&mut stream, Err("Too many connections".to_owned())));
+            }
+            continue;
+        }
+        let root = root.to_owned();
+        let token = token.to_owned();
+        let policy = policy.clone();
+        let acceptor = acceptor.clone();
+        let connections = connections.clone();
+        thread::spawn(move || {
+            let peer = stream
+                .peer_addr()
+                .map(|x| x.to_string())
+                .unwrap_or_default();
+            let result = set_timeouts(&stream).and_then(|_| match &acceptor {
+                Some(x) => x
+                    .accept(stream)
+                    .map_err(|err| format!("Failed to start tls ({})", err))
+                    .and_then(|x| handle_connection(x, &root, &token, &policy)),
+                None => handle_connection(stream, &root, &token, &policy),
+            });
+            if let Err(err) = result {
+                tracing::warn!("Agent connection from {} failed ({})", peer, err);
+            }
+            connections.fetch_sub(1, Ordering::SeqCst);
+        });
+    }
+    Ok(())
+}
+
+/// check if path is url of file served by agent
+pub fn is_agent_url(path: &str) -> bool {
+    path.starts_with(AGENT_URL_SCHEME)
+}
+
+/// parse `forskscope://[token@]host[:port][/path]`
+/// token is empty when url has none
+pub fn parse_agent_url(url: &str) -> Result<AgentLocation, String> {
+    let rest = url
+        .strip_prefix(AGENT_URL_SCHEME)
+        .ok_or(format!("Not agent url: {}", url))?;
+    let (authority, path) = match rest.find('/') {
+        Some(i) => (&rest[..i], percent_decode(&rest[i..])),
+        None => (rest, "/".to_owned()),
+    };
+    let (token, hostport) = match authority.rfind('@') {
+        Some(i) => (percent_decode(&authority[..i]), &authority[i + 1..]),
+        None => (String::new(), authority),
+    };
+    let (host, port) = match hostport.rsplit_once(':') {
+        Some((host, port)) => (
+            host.to_owned(),
+            port.parse::<u16>()
+                .map_err(|_| format!("Invalid port in {}", url))?,
+        ),
+        None => (hostport.to_owned(), AGENT_DEFAULT_PORT),
+    };
+    if host.is_empty() {
+        return Err(format!("Host is missing in {}", url));
+    }
+
+    Ok(AgentLocation {
+        host,
+        port,
+        token,
+        path,
+    })
+}
+
+/// url without token to be given back to webview
+fn url_without_token(url: &str) -> String {
+    let rest = url.strip_prefix(AGENT_URL_SCHEME).unwrap_or(url);
+    let authority_end = rest.find('/').unwrap_or(rest.len());
+    let rest = match rest[..authority_end].rfind('@') {
+        Some(i) => &rest[i + 1..],
+        None => rest,
+    };
+    format!("{}{}", AGENT_URL_SCHEME, rest)
+}
+
+/// parse agent url whose token may be omitted when remembered for the same agent
+fn authenticated_location(
+    url: &str,
+    credentials: &RemoteCredentials,
+) -> Result<AgentLocation, String> {
+    let mut location = parse_agent_url(url)?;
+    let key = format!("{}{}:{}", AGENT_URL_SCHEME, location.host, location.port);
+    location.token = credentials
+        .resolve(&key, &location.token)
+        .ok_or(format!("Token is missing in {}", url_without_token(url)))?;
+    Ok(location)
+}
+
+/// list directory served by agent in the same shape as local one
+/// current dir in response does not carry token
+pub fn agent_list_dir(
+    url: &str,
+    credentials: &RemoteCredentials,
+    number_format: &NumberFormat,
+) -> Result<ListDirResponse, String> {
+    let location = authenticated_location(url, credentials)?;
+    let bytes = request(&location, "LIST", &mut |_, _| ())?;
+    let entries = serde_json::from_slice::<Vec<AgentDirEntry>>(&bytes)
+        .map_err(|err| format!("Invalid agent response ({})", err))?;
+
+    let mut dirs = vec![];
+    let mut files = vec![];
+    for entry in entries {
+        if entry.is_dir {
+            dirs.push(entry.name);
+        } else {
+            files.push(FileAttr {
+                name: entry.name,
+                bytes_size: format!(
+                    "{} bytes",
+                    comma_separated_number(entry.size, number_format)
+                ),
+                human_readable_size: human_readable_size(entry.size, number_format),
+                last_modified: entry.last_modified,
+                // content is unknown until downloaded
+                binary_comparison_only: false,
+                mime_type: None,
+            });
+        }
+    }
+    dirs.sort();
+    files.sort();
+
+    Ok(ListDirResponse {
+        current_dir: url_without_token(url),
+        dirs,
+        files,
+        partial: false,
+    })
+}
+
+/// download file served by agent into temporary file to be compared as local one
+/// bytes received so far and total are reported to `on_progress`
+pub fn agent_download(
+    url: &str,
+    credentials: &RemoteCredentials,
+    mut on_progress: impl FnMut(u64, u64),
+) -> Result<PathBuf, String> {
+    let location = authenticated_location(url, credentials)?;
+    let bytes = request(&location, "READ", &mut on_progress)?;
+
+    let dirpath = temp_dir("agent")?;
+    let filename = location
+        .path
+        .rsplit('/')
+        .next()
+        .filter(|x| !x.is_empty())
+        .unwrap_or("download");
+    let filepath = dirpath.join(safe_filename(&format!(
+        "{}-{}-{}",
+        std::process::id(),
+        location.host,
+        filename
+    )));
+    write_temp_file(&filepath, &bytes)?;
+    Ok(filepath)
+}
+
+/// connect to agent and run single command on path
+/// agent on loopback such as one forwarded by ssh is spoken to in plain, and others in tls
+fn request(
+    location: &AgentLocation,
+    command: &str,
+    on_read: &mut dyn FnMut(u64, u64),
+) -> Result<Vec<u8>, String> {
+    let addr = std::net::ToSocketAddrs::to_socket_addrs(&(location.host.as_str(), location.port))
+        .map_err(|err| format!("Failed to resolve {} ({})", location.host, err))?
+        .next()
+        .ok_or(format!("Failed to resolve {}", location.host))?;
+    let stream = TcpStream::connect_timeout(&addr, Duration::from_secs(AGENT_TIMEOUT_SECS))
+        .map_err(|err| format!("Failed to connect to {} ({})", location.host, err))?;
+    set_timeouts(&stream)?;
+    if addr.ip().is_loopback() {
+        exchange(BufReader::new(stream), location, command, on_read)
+    } else {
+        let stream = tls_connect(&location.host, stream)?;
+        exchange(BufReader::new(stream), location, command, on_read)
+    }
+}
+
+/// authenticate both sides and run single command on path
+/// protocol is line based: agent greets with `HELLO nonce` and is answered with
+/// `AUTH hmac nonce` carrying hmac of agent nonce keyed by token and app nonce,
+/// whose hmac is replied as body so that agent is proved to know token before its body is read.
+/// then `LIST path` or `READ path` is replied with `OK length` followed by body, or `ERR message`
+fn exchange<S: Read + Write>(
+    mut reader: BufReader<S>,
+    location: &AgentLocation,
+    command: &str,
+    on_read: &mut dyn FnMut(u64, u64),
+) -> Result<Vec<u8>, String> {
+    let line = read_line(&mut reader)?;
+    if let Some(message) = line.strip_prefix("ERR ") {
+        return Err(format!("Agent error: {}", message));
+    }
+    let nonce = line
+        .strip_prefix("HELLO ")
+        .ok_or(format!("Invalid agent greeting: {}", line))?;
+    let app_nonce = random_hex()?;
+    write_line(
+        reader.get_mut(),
+        &format!(
+            "AUTH {} {}",
+            hmac_sha256(location.token.as_bytes(), nonce.as_bytes())?,
+            app_nonce
+        ),
+    )?;
+    let proof = read_reply(&mut reader, AGENT_MAX_LINE_SIZE as u64, &mut |_, _| ())?;
+    let expected = hmac_sha256(location.token.as_bytes(), app_nonce.as_bytes())?;
+    if !bool::from(proof.ct_eq(expected.as_bytes())) {
+        return Err(format!("Agent failed to authenticate: {}", location.host));
+    }
+    write_line(reader.get_mut(), &format!("{} {}", command, location.path))?;
+    let ret = read_reply(&mut reader, AGENT_MAX_BODY_SIZE, on_read)?;
+    let _ = write_line(reader.get_mut(), "QUIT");
+    Ok(ret)
+}
+
+/// serve commands on connection after authentication
+fn handle_connection<S: Read + Write>(
+    stream: S,
+    root: &Path,
+    token: &str,
+    policy: &PathPolicy,
+) -> Result<(), String> {
+    let mut reader = BufReader::new(stream);
+
+    // nonce differs per connection so that answer captured once cannot be replayed
+    let nonce = random_hex()?;
+    write_line(reader.get_mut(), &format!("HELLO {}", nonce))?;
+    let expected = hmac_sha256(token.as_bytes(), nonce.as_bytes())?;
+    let line = read_line(&mut reader)?;
+    let answer = line
+        .strip_prefix("AUTH ")
+        .and_then(|x| x.split_once(' '))
+        .filter(|(mac, _)| bool::from(mac.as_bytes().ct_eq(expected.as_bytes())));
+    let Some((_, app_nonce)) = answer else {
+        thread::sleep(AUTH_FAILURE_DELAY);
+        return write_reply(reader.get_mut(), Err("Authentication failed".to_owned()));
+    };
+    let proof = hmac_sha256(token.as_bytes(), app_nonce.as_bytes())?;
+    write_reply(reader.get_mut(), Ok(proof.into_bytes()))?;
+
+    loop {
+        let line = read_line(&mut reader)?;
+        let (command, path) = line.split_once(' ').unwrap_or((line.as_str(), ""));
+        let result = match command {
+            "LIST" => served_path(root, path, policy).and_then(|x| list_entries(&x)),
+            "READ" => {
+                match served_path(root, path, policy).and_then(|x| open_served_file(&x, path)) {
+                    Ok((file, len)) => {
+                        write_file_reply(reader.get_mut(), file, len, path)?;
+                        continue;
+                    }
+                    Err(err) => Err(err),
+                }
+            }
+            "QUIT" | "" => return Ok(()),
+            _ => Err(format!("Unknown command: {}", command)),
+        };
+        write_reply(reader.get_mut(), result)?;
+    }
+}
+
+/// resolve requested path under root
+/// paths escaping root by `..` or symbolic links, or under blocked roots are rejected
+fn served_path(root: &Path, path: &str, policy: &PathPolicy) -> Result<PathBuf, String> {
+    let ret = root
+        .join(os_path(path.trim_start_matches('/')))
+        .canonicalize()
+        .map_err(|err| format!("Invalid path: {} ({})", path, err))?;
+    if !ret.starts_with(root) {
+        return Err(format!("Path out of served root: {}", path));
+    }
+    ensure_permitted_with(policy, &path_string(&ret))?;
+    Ok(ret)
+}
+
+/// open file not larger than reply body can be, with its size
+fn open_served_file(filepath: &Path, path: &str) -> Result<(File, u64), String> {
+    let file = File::open(filepath).map_err(|err| format!("Failed to read {} ({})", path, err))?;
+    let size = file
+        .metadata()
+        .map_err(|err| format!("Failed to read {} ({})", path, err))?
+        .len();
+    if AGENT_MAX_BODY_SIZE < size {
+        return Err(format!("File is too large to serve: {}", path));
+    }
+    Ok((file, size))
+}
+
+/// list directory entries as json
+fn list_entries(dirpath: &Path) -> Result<Vec<u8>, String> {
+    let read = fs::read_dir(dirpath)
+        .map_err(|err| format!("Failed to read {} ({})", dirpath.display(), err))?;
+    let entries = read
+        .filter_map(|x| x.ok())
+        .filter_map(|x| {
+            let metadata = x.metadata().ok()?;
+            let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+            Some(AgentDirEntry {
+                name: path_string(Path::new(&x.file_name())),
+                is_dir: metadata.is_dir(),
+                size: metadata.len(),
+                last_modified: Local
+                    .timestamp_nanos(modified.as_nanos() as i64)
+                    .format("%Y-%m-%d %H:%M:%S")
+                    .to_string(),
+            })
+        })
+        .collect::<Vec<_>>();
+    serde_json::to_vec(&entries).map_err(|err| format!("Failed to serialize entries ({})", err))
+}
+
+/// set timeouts on read / write
+fn set_timeouts(stream: &TcpStream) -> Result<(), String> {
+    let timeout = Some(Duration::from_secs(AGENT_TIMEOUT_SECS));
+    stream
+        .set_read_timeout(timeout)
+        .and_then(|_| stream.set_write_timeout(timeout))
+        .map_err(|err| format!("Failed to set timeout ({})", err))
+}
+
+/// read line without line ending. empty on closed connection
+/// line longer than limit is refused without being buffered
+fn read_line<S: Read>(reader: &mut BufReader<S>) -> Result<String, String> {
+    let mut line = String::new();
+    reader
+        .by_ref()
+        .take(AGENT_MAX_LINE_SIZE as u64)
+        .read_line(&mut line)
+        .map_err(|err| format!("Failed to read agent message ({})", err))?;
+    if line.len() == AGENT_MAX_LINE_SIZE && !line.ends_with('\n') {
+        return Err("Agent message is too long".to_owned());
+    }
+    Ok(line.trim_end_matches(['\r', '\n']).to_owned())
+}
+
+/// write line
+fn write_line(stream: &mut impl Write, line: &str) -> Result<(), String> {
+    stream
+        .write_all(format!("{}\n", line).as_bytes())
+        .and_then(|_| stream.flush())
+        .map_err(|err| format!("Failed to send agent message ({})", err))
+}
+
+/// write `OK length` and body, or `ERR message`
+fn write_reply(stream: &mut impl Write, result: Result<Vec<u8>, String>) -> Result<(), String> {
+    match result {
+        Ok(body) => {
+            write_line(stream, &format!("OK {}", body.len()))?;
+            stream
+                .write_all(&body)
+                .and_then(|_| stream.flush())
+                .map_err(|err| format!("Failed to send agent message ({})", err))
+        }
+        Err(err) => write_line(stream, &format!("ERR {}", err.replace('\n', " "))),
+    }
+}
+
+/// write `OK length` and file copied chunk by chunk so that file is not held in memory
+fn write_file_reply(
+    stream: &mut impl Write,
+    file: File,
+    len: u64,
+    path: &str,
+) -> Result<(), String> {
+    write_line(stream, &format!("OK {}", len))?;
+    let copied = io::copy(&mut file.take(len), stream)
+        .and_then(|x| stream.flush().map(|_| x))
+        .map_err(|err| format!("Failed to send agent message ({})", err))?;
+    // connection cannot go on when file shrank after its length was sent
+    if copied < len {
+        return Err(format!("File changed while being served: {}", path));
+    }
+    Ok(())
+}
+
+/// read reply and its body not longer than `max_len`
+/// bytes of body received so far and its length are reported to `on_read`.
+/// body is allocated as received so that announced length alone does not take memory
+fn read_reply<S: Read>(
+    reader: &mut BufReader<S>,
+    max_len: u64,
+    on_read: &mut dyn FnMut(u64, u64),
+) -> Result<Vec<u8>, String> {
+    let line = read_line(reader)?;
+    if let Some(message) = line.strip_prefix("ERR ") {
+        return Err(format!("Agent error: {}", message));
+    }
+    let len = line
+        .strip_prefix("OK ")
+        .and_then(|x| x.parse::<u64>().ok())
+        .ok_or(format!("Invalid agent reply: {}", line))?;
+    if max_len < len {
+        return Err(format!("Agent reply is too large: {} bytes", len));
+    }
+    let mut ret = vec![];
+    let mut chunk = vec![0; AGENT_READ_CHUNK_SIZE];
+    // read chunk by chunk to report bytes received
+    while (ret.len() as u64) < len {
+        let size = (len - ret.len() as u64).min(AGENT_READ_CHUNK_SIZE as u64) as usize;
+        reader
+            .read_exact(&mut chunk[..size])
+            .map_err(|err| format!("Failed to read agent message ({})", err))?;
+        ret.extend_from_slice(&chunk[..size]);
+        on_read(ret.len() as u64, len);
+    }
+    Ok(ret)
+}
+
+/// hmac-sha256 of message in hex
+fn hmac_sha256(key: &[u8], message: &[u8]) -> Result<String, String> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key)
+        .map_err(|err| format!("Invalid agent token ({})", err))?;
+    mac.update(message);
+    Ok(mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|x| format!("{:02x}", x))
+        .collect())
+}
+
+/// random hex from os so that token and nonce cannot be guessed
+fn random_hex() -> Result<String, String> {
+    let mut bytes = [0_u8; TOKEN_BYTES];
+    getrandom::getrandom(&mut bytes)
+        .map_err(|err| format!("Failed to generate random bytes ({})", err))?;
+    Ok(bytes.iter().map(|x| format!("{:02x}", x)).collect())
+}
+
+/// tls acceptor with identity in pkcs #12 file, whose password is given by env var
+fn tls_acceptor(filepath: &Path) -> Result<TlsAcceptor, String> {
+    let bytes = fs::read(filepath)
+        .map_err(|err| format!("Failed to read {} ({})", filepath.display(), err))?;
+    let password = std::env::var(TLS_PASSWORD_ENV).unwrap_or_default();
+    let identity = Identity::from_pkcs12(&bytes, &password)
+        .map_err(|err| format!("Invalid TLS identity {} ({})", filepath.display(), err))?;
+    TlsAcceptor::new(identity).map_err(|err| format!("Failed to init tls ({})", err))
+}
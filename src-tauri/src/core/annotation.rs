@@ -0,0 +1,115 @@
+use chrono::Local;
+
+use super::store;
+use super::types::{Annotation, AnnotationInput, AnnotationStore, ComparisonAnnotations};
+
+/// file name to persist annotations
+const ANNOTATIONS_STORE_FILENAME: &str = "annotations.json";
+
+/// get annotations attached to comparison
+pub fn annotations(
+    app_handle: &tauri::AppHandle,
+    old: &str,
+    new: &str,
+) -> Result<Vec<Annotation>, String> {
+    let store: AnnotationStore = store::load(app_handle, ANNOTATIONS_STORE_FILENAME)?;
+    let ret = store
+        .comparisons
+        .into_iter()
+        .find(|x| x.old == old && x.new == new)
+        .map(|x| x.annotations)
+        .unwrap_or_default();
+    Ok(ret)
+}
+
+/// attach annotation to comparison
+pub fn add_annotation(
+    app_handle: &tauri::AppHandle,
+    old: &str,
+    new: &str,
+    input: AnnotationInput,
+) -> Result<Annotation, String> {
+    let mut store: AnnotationStore = store::load(app_handle, ANNOTATIONS_STORE_FILENAME)?;
+
+    let comparison = match store
+        .comparisons
+        .iter()
+        .position(|x| x.old == old && x.new == new)
+    {
+        Some(i) => &mut store.comparisons[i],
+        None => {
+            store.comparisons.push(ComparisonAnnotations {
+                old: old.to_owned(),
+                new: new.to_owned(),
+                annotations: vec![],
+            });
+            store.comparisons.last_mut().unwrap()
+        }
+    };
+
+    let id = comparison
+        .annotations
+        .iter()
+        .map(|x| x.id + 1)
+        .max()
+        .unwrap_or_default();
+    let annotation = Annotation {
+        id,
+        kind: input.kind,
+        side: input.side,
+        diff_index: input.diff_index,
+        line_index: input.line_index,
+        text: input.text,
+        created_at: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+    };
+    comparison.annotations.push(annotation.clone());
+
+    store::save(app_handle, ANNOTATIONS_STORE_FILENAME, &store)?;
+    Ok(annotation)
+}
+
+/// detach annotation from comparison
+pub fn remove_annotation(
+    app_handle: &tauri::AppHandle,
+    old: &str,
+    new: &str,
+    id: u64,
+) -> Result<(), String> {
+    let mut store: AnnotationStore = store::load(app_handle, ANNOTATIONS_STORE_FILENAME)?;
+
+    let comparison = match store
+        .comparisons
+        .iter_mut()
+        .find(|x| x.old == old && x.new == new)
+    {
+        Some(x) => x,
+        None => return Err(format!("No annotations found on {} and {}", old, new)),
+    };
+    let annotations_count = comparison.annotations.len();
+    comparison.annotations.retain(|x| x.id != id);
+    if comparison.annotations.len() == annotations_count {
+        return Err(format!("Annotation not found: {}", id));
+    }
+    store.comparisons.retain(|x| !x.annotations.is_empty());
+
+    store::save(app_handle, ANNOTATIONS_STORE_FILENAME, &store)
+}
+
+/// replace annotations attached to comparison such as on bundle import
+pub fn set_annotations(
+    app_handle: &tauri::AppHandle,
+    old: &str,
+    new: &str,
+    annotations: Vec<Annotation>,
+) -> Result<(), String> {
+    let mut store: AnnotationStore = store::load(app_handle, ANNOTATIONS_STORE_FILENAME)?;
+    store.comparisons.retain(|x| x.old != old || x.new != new);
+    if !annotations.is_empty() {
+        store.comparisons.push(ComparisonAnnotations {
+            old: old.to_owned(),
+            new: new.to_owned(),
+            annotations,
+        });
+    }
+    store::save(app_handle, ANNOTATIONS_STORE_FILENAME, &store)
+}
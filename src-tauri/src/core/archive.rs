@@ -0,0 +1,271 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Read;
+
+use flate2::read::GzDecoder;
+
+use super::diff::collect_relative_filepaths;
+use super::os_path::os_path;
+use super::types::{ArchiveEntry, EntryStatus, TreeEntryDiff};
+
+/// zip end of central directory signature
+const ZIP_EOCD_SIGNATURE: u32 = 0x0605_4b50;
+/// zip central directory file header signature
+const ZIP_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x0201_4b50;
+/// tar block size
+const TAR_BLOCK_SIZE: usize = 512;
+
+/// check if bytes are zip archive
+pub fn is_zip(bytes: &[u8]) -> bool {
+    bytes.starts_with(b"PK\x03\x04") || bytes.starts_with(b"PK\x05\x06")
+}
+
+/// check if bytes are tar archive
+pub fn is_tar(bytes: &[u8]) -> bool {
+    bytes.get(257..262) == Some(b"ustar")
+}
+
+/// check if bytes are gzip compressed such as `.tar.gz`
+pub fn is_gzip(bytes: &[u8]) -> bool {
+    bytes.starts_with(&[0x1f, 0x8b])
+}
+
+/// decompress gzip bytes
+pub fn gunzip(bytes: &[u8]) -> Result<Vec<u8>, String> {
+    let mut ret = vec![];
+    GzDecoder::new(bytes)
+        .read_to_end(&mut ret)
+        .map_err(|err| format!("Failed to decompress gzip ({})", err))?;
+    Ok(ret)
+}
+
+/// read archive file. `.tar.gz` is decompressed into tar
+pub fn archive_bytes(filepath: &str) -> Result<Vec<u8>, String> {
+    let bytes = fs::read(os_path(filepath))
+        .map_err(|err| format!("Failed to read {} ({})", filepath, err))?;
+    if is_gzip(&bytes) {
+        gunzip(&bytes)
+    } else {
+        Ok(bytes)
+    }
+}
+
+/// crc-32 as used in zip
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut table = [0_u32; 256];
+    for (i, x) in table.iter_mut().enumerate() {
+        *x = (0..8).fold(i as u32, |c, _| {
+            if c & 1 != 0 {
+                0xedb8_8320 ^ (c >> 1)
+            } else {
+                c >> 1
+            }
+        });
+    }
+    !bytes.iter().fold(!0_u32, |c, x| {
+        table[((c ^ *x as u32) & 0xff) as usize] ^ (c >> 8)
+    })
+}
+
+/// compare directory with zip or tar archive entry by entry aligned by relative path
+/// single top-level folder in archive such as `project-1.0/` is ignored
+/// when directory does not have it
+pub fn dir_archive_diffs(
+    dir: &str,
+    archive: &str,
+    archive_is_old: bool,
+) -> Result<Vec<TreeEntryDiff>, String> {
+    let bytes = archive_bytes(archive)?;
+    let entries = archive_entries(&bytes).ok_or(format!("Not supported archive: {}", archive))?;
+    let mut dir_files = vec![];
+    collect_relative_filepaths(&os_path(dir), "", &mut dir_files)?;
+    dir_files.sort();
+
+    let file_entries = entries
+        .iter()
+        .filter(|x| !x.is_dir)
+        .map(|x| (x.name.trim_start_matches("./"), x))
+        .collect::<Vec<_>>();
+    let top_level = file_entries
+        .first()
+        .and_then(|x| x.0.split_once('/'))
+        .map(|x| format!("{}/", x.0));
+    let strip_prefix = top_level.filter(|prefix| {
+        file_entries
+            .iter()
+            .all(|x| x.0.starts_with(prefix.as_str()))
+            && !os_path(dir).join(prefix.trim_end_matches('/')).exists()
+    });
+    let archive_files = file_entries
+        .into_iter()
+        .map(|(name, entry)| {
+            let path = match &strip_prefix {
+                Some(prefix) => name.strip_prefix(prefix.as_str()).unwrap_or(name),
+                None => name,
+            };
+            (path.to_owned(), entry)
+        })
+        .collect::<BTreeMap<_, _>>();
+
+    let mut paths = dir_files
+        .iter()
+        .chain(archive_files.keys())
+        .cloned()
+        .collect::<Vec<_>>();
+    paths.sort();
+    paths.dedup();
+
+    paths
+        .into_iter()
+        .map(|path| {
+            let in_dir = dir_files.binary_search(&path).is_ok();
+            let status = match (in_dir, archive_files.get(&path)) {
+                (true, Some(entry)) => {
+                    let filepath = os_path(dir).join(os_path(&path));
+                    let local = fs::read(&filepath).map_err(|err| {
+                        format!("Failed to read {} ({})", filepath.display(), err)
+                    })?;
+                    if archive_entry_equals(&bytes, entry, &local) {
+                        EntryStatus::Equal
+                    } else {
+                        EntryStatus::Modified
+                    }
+                }
+                (true, None) if archive_is_old => EntryStatus::NewOnly,
+                (true, None) => EntryStatus::OldOnly,
+                _ if archive_is_old => EntryStatus::OldOnly,
+                _ => EntryStatus::NewOnly,
+            };
+            Ok(TreeEntryDiff { path, status })
+        })
+        .collect()
+}
+
+/// check if archive entry has the same content as local file
+/// zip entries are compared by crc-32 so that they are not decompressed
+fn archive_entry_equals(bytes: &[u8], entry: &ArchiveEntry, local: &[u8]) -> bool {
+    if entry.size != local.len() as u64 {
+        return false;
+    }
+    match entry.crc32 {
+        Some(x) => x == crc32(local),
+        None => {
+            let start = entry.data_offset as usize;
+            bytes.get(start..start + local.len()) == Some(local)
+        }
+    }
+}
+
+/// list entries in zip or tar archive
+/// returns none when bytes are not supported archive
+pub fn archive_entries(bytes: &[u8]) -> Option<Vec<ArchiveEntry>> {
+    if is_zip(bytes) {
+        return zip_entries(bytes);
+    }
+    if is_tar(bytes) {
+        return tar_entries(bytes);
+    }
+    None
+}
+
+/// list entries in zip central directory
+fn zip_entries(bytes: &[u8]) -> Option<Vec<ArchiveEntry>> {
+    // end of central directory is put in last 64 KB including comment
+    let search_start = bytes.len().saturating_sub(22 + u16::MAX as usize);
+    let eocd = (search_start..bytes.len().saturating_sub(21))
+        .rev()
+        .find(|i| le_u32(bytes, *i) == Some(ZIP_EOCD_SIGNATURE))?;
+    let entries_count = le_u16(bytes, eocd + 10)? as usize;
+    let mut pos = le_u32(bytes, eocd + 16)? as usize;
+
+    let mut ret = Vec::with_capacity(entries_count);
+    for _ in 0..entries_count {
+        if le_u32(bytes, pos)? != ZIP_CENTRAL_DIRECTORY_SIGNATURE {
+            return None;
+        }
+        let crc32 = le_u32(bytes, pos + 16)?;
+        let compressed_size = le_u32(bytes, pos + 20)? as u64;
+        let size = le_u32(bytes, pos + 24)? as u64;
+        let name_len = le_u16(bytes, pos + 28)? as usize;
+        let extra_len = le_u16(bytes, pos + 30)? as usize;
+        let comment_len = le_u16(bytes, pos + 32)? as usize;
+        let flags = le_u16(bytes, pos + 8)?;
+        let method = le_u16(bytes, pos + 10)?;
+        let local_header_offset = le_u32(bytes, pos + 42)? as u64;
+        let name = String::from_utf8_lossy(bytes.get(pos + 46..pos + 46 + name_len)?).to_string();
+
+        ret.push(ArchiveEntry {
+            is_dir: name.ends_with('/'),
+            name,
+            size,
+            compressed_size,
+            crc32: Some(crc32),
+            encrypted: flags & 0x01 != 0,
+            method,
+            data_offset: local_header_offset,
+        });
+        pos += 46 + name_len + extra_len + comment_len;
+    }
+    Some(ret)
+}
+
+/// list entries in tar headers
+fn tar_entries(bytes: &[u8]) -> Option<Vec<ArchiveEntry>> {
+    let mut ret = vec![];
+    let mut pos = 0;
+    while pos + TAR_BLOCK_SIZE <= bytes.len() {
+        let header = &bytes[pos..pos + TAR_BLOCK_SIZE];
+        // end of archive
+        if header.iter().all(|x| *x == 0) {
+            break;
+        }
+
+        let name = tar_str(&header[0..100]);
+        let prefix = tar_str(&header[345..500]);
+        let name = if prefix.is_empty() {
+            name
+        } else {
+            format!("{}/{}", prefix, name)
+        };
+        let size = u64::from_str_radix(tar_str(&header[124..136]).trim(), 8).unwrap_or_default();
+        let type_flag = header[156];
+
+        // regular files, directories and links only. extended headers are skipped
+        if matches!(type_flag, 0 | b'0' | b'1' | b'2' | b'5' | b'7') {
+            ret.push(ArchiveEntry {
+                is_dir: type_flag == b'5' || name.ends_with('/'),
+                name,
+                size,
+                compressed_size: size,
+                crc32: None,
+                encrypted: false,
+                method: 0,
+                data_offset: (pos + TAR_BLOCK_SIZE) as u64,
+            });
+        }
+
+        let data_blocks = (size as usize).div_ceil(TAR_BLOCK_SIZE);
+        pos += TAR_BLOCK_SIZE * (1 + data_blocks);
+    }
+    Some(ret)
+}
+
+/// read nul-terminated string in tar header field
+fn tar_str(field: &[u8]) -> String {
+    let end = field.iter().position(|x| *x == 0).unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..end]).to_string()
+}
+
+/// read little endian u16
+fn le_u16(bytes: &[u8], pos: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(
+        bytes.get(pos..pos + 2)?.try_into().ok()?,
+    ))
+}
+
+/// read little endian u32
+fn le_u32(bytes: &[u8], pos: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        bytes.get(pos..pos + 4)?.try_into().ok()?,
+    ))
+}
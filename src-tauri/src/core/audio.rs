@@ -0,0 +1,372 @@
+use std::collections::BTreeMap;
+use std::fs;
+
+use super::field_diff::field_diffs;
+use super::os_path::os_path;
+use super::types::AudioComparison;
+
+/// buckets of coarse waveform envelope over whole duration
+const WAVEFORM_BUCKETS: usize = 512;
+/// mp3 bitrates in kbps by index on mpeg1 layer 1, 2, 3 and mpeg2 layer 1, 2 and 3
+const MP3_BITRATES: [[u32; 15]; 5] = [
+    [
+        0, 32, 64, 96, 128, 160, 192, 224, 256, 288, 320, 352, 384, 416, 448,
+    ],
+    [
+        0, 32, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384,
+    ],
+    [
+        0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320,
+    ],
+    [
+        0, 32, 48, 56, 64, 80, 96, 112, 128, 144, 160, 176, 192, 224, 256,
+    ],
+    [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160],
+];
+/// mp3 sample rates by index on mpeg1
+const MP3_SAMPLE_RATES: [u32; 3] = [44100, 48000, 32000];
+
+/// audio stream properties with coarse waveform when samples are available
+struct AudioInfo {
+    format: String,
+    duration_secs: f64,
+    sample_rate: u32,
+    channels: u16,
+    // average on variable bitrate
+    bitrate_kbps: u32,
+    bits_per_sample: Option<u16>,
+    // peak amplitude per bucket in 0.0 to 1.0
+    waveform: Option<Vec<f32>>,
+}
+
+/// compare wav or mp3 files by duration, sample rate, channels and bitrate instead of bytes
+/// and by coarse waveform similarity when `with_waveform` is set and both are pcm wav
+pub fn audio_comparison(
+    old: &str,
+    new: &str,
+    with_waveform: bool,
+) -> Result<AudioComparison, String> {
+    let old_info = audio_info(old, with_waveform)?;
+    let new_info = audio_info(new, with_waveform)?;
+    let waveform_similarity = match (&old_info.waveform, &new_info.waveform) {
+        (Some(old), Some(new)) => Some(waveform_similarity(old, new)),
+        _ => None,
+    };
+    Ok(AudioComparison {
+        fields: field_diffs(&audio_fields(&old_info), &audio_fields(&new_info)),
+        old_duration_secs: old_info.duration_secs,
+        new_duration_secs: new_info.duration_secs,
+        waveform_similarity,
+    })
+}
+
+/// properties as key-value fields
+fn audio_fields(info: &AudioInfo) -> BTreeMap<String, String> {
+    let mut ret = BTreeMap::new();
+    ret.insert("Format".to_owned(), info.format.to_owned());
+    ret.insert(
+        "Duration".to_owned(),
+        format!("{:.3} s", info.duration_secs),
+    );
+    ret.insert("SampleRate".to_owned(), format!("{} Hz", info.sample_rate));
+    ret.insert("Channels".to_owned(), info.channels.to_string());
+    ret.insert("Bitrate".to_owned(), format!("{} kbps", info.bitrate_kbps));
+    if let Some(x) = info.bits_per_sample {
+        ret.insert("BitsPerSample".to_owned(), x.to_string());
+    }
+    ret
+}
+
+/// read audio properties of wav or mp3
+fn audio_info(filepath: &str, with_waveform: bool) -> Result<AudioInfo, String> {
+    let bytes = fs::read(os_path(filepath))
+        .map_err(|err| format!("Failed to read {} ({})", filepath, err))?;
+    let ret = if bytes.starts_with(b"RIFF") && bytes.get(8..12) == Some(b"WAVE") {
+        wav_info(&bytes, with_waveform)
+    } else {
+        mp3_info(&bytes)
+    };
+    ret.ok_or(format!("Unsupported audio format: {}", filepath))
+}
+
+/// walk riff chunks of wav to find format and sample data
+fn wav_info(bytes: &[u8], with_waveform: bool) -> Option<AudioInfo> {
+    let mut fmt = None;
+    let mut data = None;
+    let mut pos = 12;
+    while pos + 8 <= bytes.len() {
+        let chunk_id = &bytes[pos..pos + 4];
+        let len = u32::from_le_bytes(bytes[pos + 4..pos + 8].try_into().unwrap()) as usize;
+        // data chunk of file being recorded may be longer than file
+        let chunk = &bytes[pos + 8..(pos + 8 + len).min(bytes.len())];
+        match chunk_id {
+            b"fmt " if 16 <= chunk.len() => fmt = Some(chunk),
+            b"data" => data = Some(chunk),
+            _ => {}
+        }
+        pos += 8 + len + len % 2;
+    }
+
+    let fmt = fmt?;
+    let format_tag = u16::from_le_bytes([fmt[0], fmt[1]]);
+    let channels = u16::from_le_bytes([fmt[2], fmt[3]]);
+    let sample_rate = u32::from_le_bytes(fmt[4..8].try_into().unwrap());
+    let byte_rate = u32::from_le_bytes(fmt[8..12].try_into().unwrap());
+    let block_align = u16::from_le_bytes([fmt[12], fmt[13]]);
+    let bits_per_sample = u16::from_le_bytes([fmt[14], fmt[15]]);
+    let data = data.unwrap_or_default();
+    let duration_secs = if 0 < byte_rate {
+        data.len() as f64 / byte_rate as f64
+    } else {
+        0.0
+    };
+    // 1 is pcm, 3 is ieee float and 0xfffe is extensible which is pcm mostly
+    let format = match format_tag {
+        1 => "WAV (PCM)".to_owned(),
+        3 => "WAV (IEEE float)".to_owned(),
+        0xfffe => "WAV (extensible)".to_owned(),
+        x => format!("WAV (format 0x{:04x})", x),
+    };
+    let waveform = if with_waveform && matches!(format_tag, 1 | 3 | 0xfffe) {
+        pcm_waveform(data, block_align as usize, bits_per_sample, format_tag == 3)
+    } else {
+        None
+    };
+    Some(AudioInfo {
+        format,
+        duration_secs,
+        sample_rate,
+        channels,
+        bitrate_kbps: byte_rate * 8 / 1000,
+        bits_per_sample: Some(bits_per_sample),
+        waveform,
+    })
+}
+
+/// peak amplitude of samples of all channels per bucket
+fn pcm_waveform(
+    data: &[u8],
+    block_align: usize,
+    bits_per_sample: u16,
+    is_float: bool,
+) -> Option<Vec<f32>> {
+    let sample_bytes = (bits_per_sample as usize).div_ceil(8);
+    if block_align == 0 || sample_bytes == 0 || block_align < sample_bytes {
+        return None;
+    }
+    // normalized amplitude of sample
+    let amplitude = |x: &[u8]| -> f32 {
+        match (sample_bytes, is_float) {
+            (4, true) => f32::from_le_bytes(x.try_into().unwrap()).abs().min(1.0),
+            // 8 bit is unsigned
+            (1, _) => (x[0] as f32 - 128.0).abs() / 128.0,
+            (2, _) => i16::from_le_bytes([x[0], x[1]]).unsigned_abs() as f32 / 32768.0,
+            (3, _) => {
+                let x = i32::from_le_bytes([0, x[0], x[1], x[2]]) >> 8;
+                x.unsigned_abs() as f32 / 8_388_608.0
+            }
+            (4, _) => {
+                i32::from_le_bytes(x.try_into().unwrap()).unsigned_abs() as f32 / 2_147_483_648.0
+            }
+            _ => 0.0,
+        }
+    };
+
+    let frames = data.chunks_exact(block_align).collect::<Vec<_>>();
+    if frames.is_empty() {
+        return Some(vec![0.0; WAVEFORM_BUCKETS]);
+    }
+    let ret = (0..WAVEFORM_BUCKETS)
+        .map(|i| {
+            let start = i * frames.len() / WAVEFORM_BUCKETS;
+            let end = ((i + 1) * frames.len() / WAVEFORM_BUCKETS).max(start + 1);
+            frames[start..end.min(frames.len())]
+                .iter()
+                .flat_map(|frame| frame.chunks_exact(sample_bytes).map(amplitude))
+                .fold(0.0, f32::max)
+        })
+        .collect();
+    Some(ret)
+}
+
+/// similarity of waveform envelopes in 0.0 to 1.0
+/// envelopes are normalized by their peaks so that gain change alone is ignored
+fn waveform_similarity(old: &[f32], new: &[f32]) -> f32 {
+    let normalized = |x: &[f32]| {
+        let peak = x.iter().copied().fold(0.0, f32::max);
+        x.iter()
+            .map(|x| if 0.0 < peak { x / peak } else { 0.0 })
+            .collect::<Vec<_>>()
+    };
+    let old = normalized(old);
+    let new = normalized(new);
+    let len = old.len().min(new.len());
+    if len == 0 {
+        return 1.0;
+    }
+    let difference = old
+        .iter()
+        .zip(&new)
+        .map(|(a, b)| (a - b).abs())
+        .sum::<f32>()
+        / len as f32;
+    1.0 - difference
+}
+
+/// find first mp3 frame and read its header with xing or vbri header for duration
+fn mp3_info(bytes: &[u8]) -> Option<AudioInfo> {
+    // id3v2 tag precedes frames
+    let mut pos = if bytes.starts_with(b"ID3") && 10 <= bytes.len() {
+        let size = bytes[6..10]
+            .iter()
+            .fold(0, |acc, x| (acc << 7) | (*x & 0x7f) as usize);
+        10 + size
+    } else {
+        0
+    };
+    let header = loop {
+        let header = mp3_frame_header(bytes.get(pos..pos + 4)?);
+        // next frame is checked to avoid false sync in data
+        if let Some(x) = header.filter(|x| {
+            bytes
+                .get(pos + x.frame_len..pos + x.frame_len + 4)
+                .map_or(true, |next| mp3_frame_header(next).is_some())
+        }) {
+            break x;
+        }
+        pos += 1;
+    };
+
+    // id3v1 tag at end of file is not audio
+    let mut audio_len = bytes.len() - pos;
+    if 128 <= bytes.len() && bytes[bytes.len() - 128..].starts_with(b"TAG") {
+        audio_len = audio_len.saturating_sub(128);
+    }
+    let frame = &bytes[pos..(pos + header.frame_len).min(bytes.len())];
+    let side_info_len = match (header.is_mpeg1, header.channels) {
+        (true, 1) => 17,
+        (true, _) => 32,
+        (false, 1) => 9,
+        (false, _) => 17,
+    };
+    let be_u32 = |x: &[u8], at: usize| -> Option<u32> {
+        Some(u32::from_be_bytes(x.get(at..at + 4)?.try_into().ok()?))
+    };
+
+    // frames count and bytes of variable bitrate stream
+    let mut vbr = None;
+    let xing = frame.get(4 + side_info_len..);
+    if let Some(xing) = xing.filter(|x| x.starts_with(b"Xing") || x.starts_with(b"Info")) {
+        let flags = be_u32(xing, 4)?;
+        let frames = if flags & 1 != 0 {
+            be_u32(xing, 8)
+        } else {
+            None
+        };
+        let vbr_bytes = if flags & 2 != 0 {
+            be_u32(xing, if flags & 1 != 0 { 12 } else { 8 })
+        } else {
+            None
+        };
+        vbr = frames.map(|x| (x, vbr_bytes));
+    } else if frame.get(36..40) == Some(b"VBRI") {
+        vbr = be_u32(frame, 50).map(|frames| (frames, be_u32(frame, 46)));
+    }
+
+    let (duration_secs, bitrate_kbps) = match vbr {
+        Some((frames, vbr_bytes)) if 0 < frames => {
+            let duration_secs =
+                frames as f64 * header.samples_per_frame as f64 / header.sample_rate as f64;
+            let vbr_bytes = vbr_bytes.map_or(audio_len as f64, |x| x as f64);
+            (
+                duration_secs,
+                (vbr_bytes * 8.0 / duration_secs / 1000.0).round() as u32,
+            )
+        }
+        _ => (
+            audio_len as f64 * 8.0 / (header.bitrate_kbps as f64 * 1000.0),
+            header.bitrate_kbps,
+        ),
+    };
+    let format = format!(
+        "MPEG-{} Layer {}{}",
+        header.version,
+        header.layer,
+        if vbr.is_some() { " (VBR)" } else { "" }
+    );
+    Some(AudioInfo {
+        format,
+        duration_secs,
+        sample_rate: header.sample_rate,
+        channels: header.channels,
+        bitrate_kbps,
+        bits_per_sample: None,
+        waveform: None,
+    })
+}
+
+/// mp3 frame header fields
+struct Mp3FrameHeader {
+    version: &'static str,
+    is_mpeg1: bool,
+    layer: u8,
+    bitrate_kbps: u32,
+    sample_rate: u32,
+    channels: u16,
+    samples_per_frame: u32,
+    frame_len: usize,
+}
+
+/// parse 4 bytes of mp3 frame header. none on reserved or free format values
+fn mp3_frame_header(x: &[u8]) -> Option<Mp3FrameHeader> {
+    if x.len() < 4 || x[0] != 0xff || x[1] & 0xe0 != 0xe0 {
+        return None;
+    }
+    let (version, is_mpeg1, sample_rate_divisor) = match (x[1] >> 3) & 3 {
+        0 => ("2.5", false, 4),
+        2 => ("2", false, 2),
+        3 => ("1", true, 1),
+        _ => return None,
+    };
+    let layer = match (x[1] >> 1) & 3 {
+        1 => 3,
+        2 => 2,
+        3 => 1,
+        _ => return None,
+    };
+    let bitrate_index = (x[2] >> 4) as usize;
+    let sample_rate_index = ((x[2] >> 2) & 3) as usize;
+    if bitrate_index == 0 || bitrate_index == 15 || sample_rate_index == 3 {
+        return None;
+    }
+    let bitrate_table = match (is_mpeg1, layer) {
+        (true, x) => x as usize - 1,
+        (false, 1) => 3,
+        (false, _) => 4,
+    };
+    let bitrate_kbps = MP3_BITRATES[bitrate_table][bitrate_index];
+    let sample_rate = MP3_SAMPLE_RATES[sample_rate_index] / sample_rate_divisor;
+    let padding = ((x[2] >> 1) & 1) as usize;
+    let channels = if x[3] >> 6 == 3 { 1 } else { 2 };
+    let samples_per_frame = match (layer, is_mpeg1) {
+        (1, _) => 384,
+        (3, false) => 576,
+        _ => 1152,
+    };
+    let frame_len = if layer == 1 {
+        (12 * bitrate_kbps as usize * 1000 / sample_rate as usize + padding) * 4
+    } else {
+        samples_per_frame as usize / 8 * bitrate_kbps as usize * 1000 / sample_rate as usize
+            + padding
+    };
+    Some(Mp3FrameHeader {
+        version,
+        is_mpeg1,
+        layer,
+        bitrate_kbps,
+        sample_rate,
+        channels,
+        samples_per_frame,
+        frame_len,
+    })
+}
@@ -0,0 +1,400 @@
+use std::fs;
+
+use super::field_diff::field_diff;
+use super::os_path::os_path;
+use super::types::{BinaryField, MetadataFieldDiff};
+
+/// max repeated structures such as png chunks and zip entries to be parsed
+const MAX_REPEATED_STRUCTURES: usize = 256;
+
+/// field collector over bytes
+struct Fields<'a> {
+    bytes: &'a [u8],
+    little_endian: bool,
+    fields: Vec<BinaryField>,
+}
+
+impl<'a> Fields<'a> {
+    fn new(bytes: &'a [u8], little_endian: bool) -> Self {
+        Fields {
+            bytes,
+            little_endian,
+            fields: vec![],
+        }
+    }
+
+    fn push(&mut self, offset: usize, length: usize, name: &str, value: String) {
+        self.fields.push(BinaryField {
+            offset: offset as u64,
+            length: length as u64,
+            name: name.to_owned(),
+            value,
+        });
+    }
+
+    /// read unsigned integer of 1, 2, 4 or 8 bytes
+    fn read_uint(&self, offset: usize, length: usize) -> Option<u64> {
+        let bytes = self.bytes.get(offset..offset.checked_add(length)?)?;
+        let fold = |acc: u64, x: &u8| (acc << 8) | *x as u64;
+        if self.little_endian {
+            Some(bytes.iter().rev().fold(0, fold))
+        } else {
+            Some(bytes.iter().fold(0, fold))
+        }
+    }
+
+    /// add integer field shown in decimal
+    fn uint(&mut self, offset: usize, length: usize, name: &str) -> Option<u64> {
+        let value = self.read_uint(offset, length)?;
+        self.push(offset, length, name, value.to_string());
+        Some(value)
+    }
+
+    /// add integer field shown in hex such as flags and checksum
+    fn hex(&mut self, offset: usize, length: usize, name: &str) -> Option<u64> {
+        let value = self.read_uint(offset, length)?;
+        self.push(
+            offset,
+            length,
+            name,
+            format!("0x{:0width$x}", value, width = length * 2),
+        );
+        Some(value)
+    }
+
+    /// add integer field with label of known value
+    fn labeled(
+        &mut self,
+        offset: usize,
+        length: usize,
+        name: &str,
+        label: fn(u64) -> Option<&'static str>,
+    ) -> Option<u64> {
+        let value = self.read_uint(offset, length)?;
+        let shown = match label(value) {
+            Some(x) => format!("{} ({})", value, x),
+            None => value.to_string(),
+        };
+        self.push(offset, length, name, shown);
+        Some(value)
+    }
+
+    /// add text field. non-printable chars are escaped
+    fn text(&mut self, offset: usize, length: usize, name: &str) -> Option<()> {
+        let bytes = self.bytes.get(offset..offset.checked_add(length)?)?;
+        let value = bytes
+            .iter()
+            .take_while(|x| **x != 0)
+            .map(|x| {
+                if x.is_ascii_graphic() || *x == b' ' {
+                    (*x as char).to_string()
+                } else {
+                    format!("\\x{:02x}", x)
+                }
+            })
+            .collect::<String>();
+        self.push(offset, length, name, value);
+        Some(())
+    }
+}
+
+/// parse header of known binary format into named fields
+/// empty when format is not known
+pub fn binary_fields(filepath: &str) -> Result<Vec<BinaryField>, String> {
+    let bytes = fs::read(os_path(filepath))
+        .map_err(|err| format!("Failed to read {} ({})", filepath, err))?;
+
+    let fields = if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        let mut fields = Fields::new(&bytes, false);
+        png_fields(&mut fields);
+        fields.fields
+    } else if bytes.starts_with(b"PK\x03\x04") || bytes.starts_with(b"PK\x05\x06") {
+        let mut fields = Fields::new(&bytes, true);
+        zip_fields(&mut fields);
+        fields.fields
+    } else if bytes.starts_with(b"\x7fELF") {
+        let mut fields = Fields::new(&bytes, bytes.get(5) != Some(&2));
+        elf_fields(&mut fields);
+        fields.fields
+    } else if bytes.starts_with(b"MZ") {
+        let mut fields = Fields::new(&bytes, true);
+        pe_fields(&mut fields);
+        fields.fields
+    } else if bytes.starts_with(b"RIFF") && bytes.get(8..12) == Some(b"WAVE") {
+        let mut fields = Fields::new(&bytes, true);
+        wav_fields(&mut fields);
+        fields.fields
+    } else {
+        vec![]
+    };
+    Ok(fields)
+}
+
+/// compare fields structurally by name in order of appearance
+pub fn binary_field_diffs(old: &str, new: &str) -> Result<Vec<MetadataFieldDiff>, String> {
+    let old_fields = binary_fields(old)?;
+    let new_fields = binary_fields(new)?;
+
+    let mut names = old_fields.iter().map(|x| &x.name).collect::<Vec<_>>();
+    for field in new_fields.iter() {
+        if !names.contains(&&field.name) {
+            names.push(&field.name);
+        }
+    }
+
+    let value = |fields: &Vec<BinaryField>, name: &str| {
+        fields
+            .iter()
+            .find(|x| x.name == name)
+            .map(|x| x.value.to_owned())
+    };
+    let ret = names
+        .into_iter()
+        .filter_map(|name| field_diff(name, value(&old_fields, name), value(&new_fields, name)))
+        .collect();
+    Ok(ret)
+}
+
+/// png signature and chunks with ihdr fields
+fn png_fields(fields: &mut Fields) -> Option<()> {
+    fields.push(0, 8, "PNG.Signature", "\\x89PNG\\r\\n\\x1a\\n".to_owned());
+    let mut pos = 8;
+    for i in 0..MAX_REPEATED_STRUCTURES {
+        let prefix = format!("PNG.Chunk[{}]", i);
+        let length = fields.uint(pos, 4, &format!("{}.Length", prefix))? as usize;
+        fields.text(pos + 4, 4, &format!("{}.Type", prefix))?;
+        let chunk_type = fields.bytes.get(pos + 4..pos + 8)?;
+        let data = pos + 8;
+        if chunk_type == b"IHDR" {
+            fields.uint(data, 4, "PNG.IHDR.Width")?;
+            fields.uint(data + 4, 4, "PNG.IHDR.Height")?;
+            fields.uint(data + 8, 1, "PNG.IHDR.BitDepth")?;
+            fields.labeled(data + 9, 1, "PNG.IHDR.ColorType", |x| match x {
+                0 => Some("grayscale"),
+                2 => Some("truecolor"),
+                3 => Some("indexed"),
+                4 => Some("grayscale with alpha"),
+                6 => Some("truecolor with alpha"),
+                _ => None,
+            })?;
+            fields.uint(data + 10, 1, "PNG.IHDR.Compression")?;
+            fields.uint(data + 11, 1, "PNG.IHDR.Filter")?;
+            fields.uint(data + 12, 1, "PNG.IHDR.Interlace")?;
+        }
+        fields.hex(data + length, 4, &format!("{}.CRC", prefix))?;
+        if chunk_type == b"IEND" {
+            break;
+        }
+        pos = data + length + 4;
+    }
+    Some(())
+}
+
+/// zip local file headers and end of central directory
+fn zip_fields(fields: &mut Fields) -> Option<()> {
+    let mut pos = 0;
+    for i in 0..MAX_REPEATED_STRUCTURES {
+        if fields.bytes.get(pos..pos + 4) != Some(b"PK\x03\x04") {
+            break;
+        }
+        let prefix = format!("ZIP.Entry[{}]", i);
+        fields.hex(pos, 4, &format!("{}.Signature", prefix))?;
+        fields.uint(pos + 4, 2, &format!("{}.Version", prefix))?;
+        let flags = fields.hex(pos + 6, 2, &format!("{}.Flags", prefix))?;
+        fields.labeled(pos + 8, 2, &format!("{}.Method", prefix), |x| match x {
+            0 => Some("stored"),
+            8 => Some("deflate"),
+            12 => Some("bzip2"),
+            14 => Some("lzma"),
+            93 => Some("zstd"),
+            99 => Some("aes"),
+            _ => None,
+        })?;
+        fields.hex(pos + 10, 2, &format!("{}.ModTime", prefix))?;
+        fields.hex(pos + 12, 2, &format!("{}.ModDate", prefix))?;
+        fields.hex(pos + 14, 4, &format!("{}.CRC32", prefix))?;
+        let compressed_size = fields.uint(pos + 18, 4, &format!("{}.CompressedSize", prefix))?;
+        fields.uint(pos + 22, 4, &format!("{}.UncompressedSize", prefix))?;
+        let name_length = fields.uint(pos + 26, 2, &format!("{}.NameLength", prefix))? as usize;
+        let extra_length = fields.uint(pos + 28, 2, &format!("{}.ExtraLength", prefix))? as usize;
+        fields.text(pos + 30, name_length, &format!("{}.Name", prefix))?;
+        // sizes follow data in descriptor when bit 3 is set
+        if flags & 0x08 != 0 {
+            break;
+        }
+        pos += 30 + name_length + extra_length + compressed_size as usize;
+    }
+
+    // comment after end of central directory is up to 65535 bytes
+    let search_start = fields.bytes.len().saturating_sub(22 + 65535);
+    let eocd = fields.bytes[search_start..]
+        .windows(4)
+        .rposition(|x| x == b"PK\x05\x06")?
+        + search_start;
+    fields.hex(eocd, 4, "ZIP.EOCD.Signature")?;
+    fields.uint(eocd + 4, 2, "ZIP.EOCD.DiskNumber")?;
+    fields.uint(eocd + 6, 2, "ZIP.EOCD.CentralDirectoryDisk")?;
+    fields.uint(eocd + 8, 2, "ZIP.EOCD.EntriesOnDisk")?;
+    fields.uint(eocd + 10, 2, "ZIP.EOCD.Entries")?;
+    fields.uint(eocd + 12, 4, "ZIP.EOCD.CentralDirectorySize")?;
+    fields.uint(eocd + 16, 4, "ZIP.EOCD.CentralDirectoryOffset")?;
+    let comment_length = fields.uint(eocd + 20, 2, "ZIP.EOCD.CommentLength")? as usize;
+    fields.text(eocd + 22, comment_length, "ZIP.EOCD.Comment")
+}
+
+/// elf identification and file header
+fn elf_fields(fields: &mut Fields) -> Option<()> {
+    fields.text(0, 4, "ELF.Magic")?;
+    let class = fields.labeled(4, 1, "ELF.Class", |x| match x {
+        1 => Some("32-bit"),
+        2 => Some("64-bit"),
+        _ => None,
+    })?;
+    fields.labeled(5, 1, "ELF.Data", |x| match x {
+        1 => Some("little endian"),
+        2 => Some("big endian"),
+        _ => None,
+    })?;
+    fields.uint(6, 1, "ELF.IdentVersion")?;
+    fields.labeled(7, 1, "ELF.OSABI", |x| match x {
+        0 => Some("System V"),
+        3 => Some("Linux"),
+        9 => Some("FreeBSD"),
+        12 => Some("OpenBSD"),
+        _ => None,
+    })?;
+    fields.uint(8, 1, "ELF.ABIVersion")?;
+    fields.labeled(16, 2, "ELF.Type", |x| match x {
+        1 => Some("relocatable"),
+        2 => Some("executable"),
+        3 => Some("shared object"),
+        4 => Some("core"),
+        _ => None,
+    })?;
+    fields.labeled(18, 2, "ELF.Machine", |x| match x {
+        0x03 => Some("x86"),
+        0x08 => Some("MIPS"),
+        0x14 => Some("PowerPC"),
+        0x28 => Some("ARM"),
+        0x3e => Some("x86-64"),
+        0xb7 => Some("AArch64"),
+        0xf3 => Some("RISC-V"),
+        _ => None,
+    })?;
+    fields.uint(20, 4, "ELF.Version")?;
+
+    // address and offset fields are 4 bytes on 32-bit and 8 bytes on 64-bit
+    let word = if class == 2 { 8 } else { 4 };
+    let mut pos = 24;
+    fields.hex(pos, word, "ELF.Entry")?;
+    pos += word;
+    fields.uint(pos, word, "ELF.ProgramHeaderOffset")?;
+    pos += word;
+    fields.uint(pos, word, "ELF.SectionHeaderOffset")?;
+    pos += word;
+    fields.hex(pos, 4, "ELF.Flags")?;
+    fields.uint(pos + 4, 2, "ELF.HeaderSize")?;
+    fields.uint(pos + 6, 2, "ELF.ProgramHeaderEntrySize")?;
+    fields.uint(pos + 8, 2, "ELF.ProgramHeaderCount")?;
+    fields.uint(pos + 10, 2, "ELF.SectionHeaderEntrySize")?;
+    fields.uint(pos + 12, 2, "ELF.SectionHeaderCount")?;
+    fields.uint(pos + 14, 2, "ELF.SectionNameIndex")?;
+    Some(())
+}
+
+/// pe coff header, optional header and section table
+fn pe_fields(fields: &mut Fields) -> Option<()> {
+    fields.text(0, 2, "PE.DOSMagic")?;
+    let pe = fields.hex(0x3c, 4, "PE.NewHeaderOffset")? as usize;
+    if fields.bytes.get(pe..pe + 4) != Some(b"PE\0\0") {
+        return None;
+    }
+    fields.text(pe, 4, "PE.Signature")?;
+    fields.labeled(pe + 4, 2, "PE.Machine", |x| match x {
+        0x014c => Some("i386"),
+        0x01c4 => Some("ARMv7"),
+        0x8664 => Some("x86-64"),
+        0xaa64 => Some("ARM64"),
+        _ => None,
+    })?;
+    let sections_count = fields.uint(pe + 6, 2, "PE.NumberOfSections")? as usize;
+    fields.uint(pe + 8, 4, "PE.TimeDateStamp")?;
+    fields.hex(pe + 12, 4, "PE.PointerToSymbolTable")?;
+    fields.uint(pe + 16, 4, "PE.NumberOfSymbols")?;
+    let optional_header_size = fields.uint(pe + 20, 2, "PE.SizeOfOptionalHeader")? as usize;
+    fields.hex(pe + 22, 2, "PE.Characteristics")?;
+
+    let opt = pe + 24;
+    if 0 < optional_header_size {
+        let magic = fields.labeled(opt, 2, "PE.Optional.Magic", |x| match x {
+            0x10b => Some("PE32"),
+            0x20b => Some("PE32+"),
+            _ => None,
+        })?;
+        fields.uint(opt + 2, 1, "PE.Optional.MajorLinkerVersion")?;
+        fields.uint(opt + 3, 1, "PE.Optional.MinorLinkerVersion")?;
+        fields.uint(opt + 4, 4, "PE.Optional.SizeOfCode")?;
+        fields.hex(opt + 16, 4, "PE.Optional.AddressOfEntryPoint")?;
+        if magic == 0x20b {
+            fields.hex(opt + 24, 8, "PE.Optional.ImageBase")?;
+        } else {
+            fields.hex(opt + 28, 4, "PE.Optional.ImageBase")?;
+        }
+        fields.uint(opt + 56, 4, "PE.Optional.SizeOfImage")?;
+        fields.uint(opt + 60, 4, "PE.Optional.SizeOfHeaders")?;
+        fields.hex(opt + 64, 4, "PE.Optional.CheckSum")?;
+        fields.labeled(opt + 68, 2, "PE.Optional.Subsystem", |x| match x {
+            1 => Some("native"),
+            2 => Some("windows gui"),
+            3 => Some("windows console"),
+            10 => Some("efi application"),
+            _ => None,
+        })?;
+        fields.hex(opt + 70, 2, "PE.Optional.DllCharacteristics")?;
+    }
+
+    let sections = opt + optional_header_size;
+    for i in 0..sections_count.min(MAX_REPEATED_STRUCTURES) {
+        let pos = sections + i * 40;
+        let prefix = format!("PE.Section[{}]", i);
+        fields.text(pos, 8, &format!("{}.Name", prefix))?;
+        fields.uint(pos + 8, 4, &format!("{}.VirtualSize", prefix))?;
+        fields.hex(pos + 12, 4, &format!("{}.VirtualAddress", prefix))?;
+        fields.uint(pos + 16, 4, &format!("{}.SizeOfRawData", prefix))?;
+        fields.hex(pos + 20, 4, &format!("{}.PointerToRawData", prefix))?;
+        fields.hex(pos + 36, 4, &format!("{}.Characteristics", prefix))?;
+    }
+    Some(())
+}
+
+/// riff header and wave chunks with fmt fields
+fn wav_fields(fields: &mut Fields) -> Option<()> {
+    fields.text(0, 4, "WAV.ChunkID")?;
+    fields.uint(4, 4, "WAV.ChunkSize")?;
+    fields.text(8, 4, "WAV.Format")?;
+    let mut pos = 12;
+    for i in 0..MAX_REPEATED_STRUCTURES {
+        let prefix = format!("WAV.Chunk[{}]", i);
+        fields.text(pos, 4, &format!("{}.ID", prefix))?;
+        let size = fields.uint(pos + 4, 4, &format!("{}.Size", prefix))? as usize;
+        let data = pos + 8;
+        if fields.bytes.get(pos..pos + 4) == Some(b"fmt ") {
+            fields.labeled(data, 2, "WAV.fmt.AudioFormat", |x| match x {
+                1 => Some("PCM"),
+                3 => Some("IEEE float"),
+                6 => Some("A-law"),
+                7 => Some("mu-law"),
+                0xfffe => Some("extensible"),
+                _ => None,
+            })?;
+            fields.uint(data + 2, 2, "WAV.fmt.Channels")?;
+            fields.uint(data + 4, 4, "WAV.fmt.SampleRate")?;
+            fields.uint(data + 8, 4, "WAV.fmt.ByteRate")?;
+            fields.uint(data + 12, 2, "WAV.fmt.BlockAlign")?;
+            fields.uint(data + 14, 2, "WAV.fmt.BitsPerSample")?;
+        }
+        // chunks are aligned to 2 bytes
+        pos = data + size + size % 2;
+    }
+    Some(())
+}
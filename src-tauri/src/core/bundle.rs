@@ -0,0 +1,138 @@
+use std::fs::{self, File};
+use std::io::{Cursor, Read, Write};
+use std::path::Path;
+
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use super::annotation::{annotations, set_annotations};
+use super::os_path::{os_path, os_str_string, path_string};
+use super::temp_file::{temp_dir, write_temp_file};
+use super::types::{BundleManifest, ComparisonBundle, ComparisonOptions};
+
+/// entry name of manifest in bundle
+const MANIFEST_ENTRY_NAME: &str = "manifest.json";
+/// dir in bundle to keep old file
+const OLD_ENTRY_DIR: &str = "old";
+/// dir in bundle to keep new file
+const NEW_ENTRY_DIR: &str = "new";
+/// bundle format version written into manifest
+const BUNDLE_VERSION: u32 = 1;
+
+/// export comparison into single `.forskscope` archive with both files, options and annotations
+/// so that it can be opened exactly as it is on another machine
+pub fn export_bundle(
+    app_handle: &tauri::AppHandle,
+    old: &str,
+    new: &str,
+    options: ComparisonOptions,
+    bundle_filepath: &str,
+) -> Result<(), String> {
+    let manifest = BundleManifest {
+        version: BUNDLE_VERSION,
+        old_name: file_name(old),
+        new_name: file_name(new),
+        options,
+        annotations: annotations(app_handle, old, new)?,
+    };
+    let serialized = serde_json::to_vec_pretty(&manifest)
+        .map_err(|err| format!("Failed to serialize bundle manifest ({})", err))?;
+
+    let file = File::create(os_path(bundle_filepath))
+        .map_err(|err| format!("Failed to create {} ({})", bundle_filepath, err))?;
+    let mut writer = ZipWriter::new(file);
+    let mut add_entry = |name: String, bytes: &[u8]| -> Result<(), String> {
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+        writer
+            .start_file(name.as_str(), options)
+            .map_err(|err| format!("Failed to add {} to bundle ({})", name, err))?;
+        writer
+            .write_all(bytes)
+            .map_err(|err| format!("Failed to add {} to bundle ({})", name, err))
+    };
+    add_entry(MANIFEST_ENTRY_NAME.to_owned(), &serialized)?;
+    for (dir, filepath, name) in [
+        (OLD_ENTRY_DIR, old, &manifest.old_name),
+        (NEW_ENTRY_DIR, new, &manifest.new_name),
+    ] {
+        if filepath.is_empty() {
+            continue;
+        }
+        let bytes = fs::read(os_path(filepath))
+            .map_err(|err| format!("Failed to read {} ({})", filepath, err))?;
+        add_entry(format!("{}/{}", dir, name), &bytes)?;
+    }
+    writer
+        .finish()
+        .map_err(|err| format!("Failed to write {} ({})", bundle_filepath, err))?;
+    Ok(())
+}
+
+/// import `.forskscope` archive with files extracted into temp dir
+/// annotations are attached to extracted file pair
+pub fn import_bundle(
+    app_handle: &tauri::AppHandle,
+    bundle_filepath: &str,
+) -> Result<ComparisonBundle, String> {
+    let bytes = fs::read(os_path(bundle_filepath))
+        .map_err(|err| format!("Failed to read {} ({})", bundle_filepath, err))?;
+    let mut archive = ZipArchive::new(Cursor::new(bytes))
+        .map_err(|err| format!("Failed to open {} as bundle ({})", bundle_filepath, err))?;
+    let mut entry_bytes = |name: &str| -> Result<Vec<u8>, String> {
+        let mut entry = archive
+            .by_name(name)
+            .map_err(|err| format!("Failed to find {} in bundle ({})", name, err))?;
+        let mut ret = vec![];
+        entry
+            .read_to_end(&mut ret)
+            .map_err(|err| format!("Failed to read {} in bundle ({})", name, err))?;
+        Ok(ret)
+    };
+
+    let manifest: BundleManifest = serde_json::from_slice(&entry_bytes(MANIFEST_ENTRY_NAME)?)
+        .map_err(|err| format!("Failed to parse bundle manifest ({})", err))?;
+    if BUNDLE_VERSION < manifest.version {
+        return Err(format!(
+            "Bundle version {} is not supported",
+            manifest.version
+        ));
+    }
+
+    let stem = os_path(bundle_filepath)
+        .file_stem()
+        .map(os_str_string)
+        .unwrap_or_default();
+    let dirpath = temp_dir("bundle")?.join(format!("{}-{}", stem, std::process::id()));
+    let mut extract = |dir: &str, name: &str| -> Result<String, String> {
+        // name is reduced to file name so that entry is not extracted outside dir
+        let name = file_name(name);
+        if name.is_empty() {
+            return Ok(String::new());
+        }
+        let bytes = entry_bytes(&format!("{}/{}", dir, name))?;
+        let dirpath = dirpath.join(dir);
+        fs::create_dir_all(&dirpath)
+            .map_err(|err| format!("Failed to create {} ({})", dirpath.display(), err))?;
+        let filepath = dirpath.join(os_path(&name));
+        write_temp_file(&filepath, &bytes)?;
+        Ok(path_string(&filepath))
+    };
+    let old = extract(OLD_ENTRY_DIR, &manifest.old_name)?;
+    let new = extract(NEW_ENTRY_DIR, &manifest.new_name)?;
+
+    set_annotations(app_handle, &old, &new, manifest.annotations.clone())?;
+    Ok(ComparisonBundle {
+        old,
+        new,
+        options: manifest.options,
+        annotations: manifest.annotations,
+    })
+}
+
+/// file name of path, or empty when path is empty
+fn file_name(filepath: &str) -> String {
+    os_path(filepath)
+        .file_name()
+        .map(|x| path_string(Path::new(x)))
+        .unwrap_or_default()
+}
@@ -0,0 +1,181 @@
+use std::ffi::OsString;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+
+use similar::DiffTag;
+
+use super::agent::{agent_token, serve, AGENT_DEFAULT_ADDRESS, AGENT_DEFAULT_PORT};
+use super::diff::lines_diff_response;
+use super::file::{file_extension, filepaths_content_with_mode};
+use super::os_path::{os_str_string, path_string};
+use super::patch::selected_hunks_patch;
+use super::settings::headless_path_policy;
+use super::temp_file::{temp_dir, write_temp_file};
+
+/// arg to run comparison without window and print result
+pub const CLI_ARG: &str = "--cli";
+/// arg to run as agent serving files read-only to app on another machine
+pub const SERVE_ARG: &str = "--serve";
+/// arg to specify port agent listens on
+const PORT_ARG: &str = "--port";
+/// arg to specify address agent listens on instead of loopback
+const BIND_ARG: &str = "--bind";
+/// arg to specify pkcs #12 file of tls identity, required on address other than loopback
+const TLS_IDENTITY_ARG: &str = "--tls-identity";
+/// path arg to read side from stdin
+const STDIN_ARG: &str = "-";
+
+/// exit code on no differences
+const EXIT_SAME: i32 = 0;
+/// exit code on differences found
+const EXIT_DIFFERENT: i32 = 1;
+/// exit code on failure
+const EXIT_FAILURE: i32 = 2;
+
+/// run headless comparison when launched with `--cli old new`
+/// returns exit code, or none when app should start with window
+/// or run agent when launched with `--serve [--port port] [--bind address --tls-identity file] [root]`
+pub fn run(args: Vec<OsString>) -> Option<i32> {
+    if args.first().map(|x| x.to_string_lossy()).as_deref() == Some(SERVE_ARG) {
+        return Some(run_agent(&args[1..]));
+    }
+    if args.first().map(|x| x.to_string_lossy()).as_deref() != Some(CLI_ARG) {
+        return None;
+    }
+    let paths = args
+        .iter()
+        .skip(1)
+        .map(|x| os_str_string(x))
+        .collect::<Vec<_>>();
+    if paths.len() != 2 {
+        eprintln!("Usage: forskscope {} <old> <new>", CLI_ARG);
+        eprintln!("  `{}` reads one side from stdin", STDIN_ARG);
+        return Some(EXIT_FAILURE);
+    }
+    if paths.iter().all(|x| x == STDIN_ARG) {
+        eprintln!("Only one side can be read from stdin");
+        return Some(EXIT_FAILURE);
+    }
+
+    match compare(&paths[0], &paths[1]) {
+        Ok(true) => Some(EXIT_SAME),
+        Ok(false) => Some(EXIT_DIFFERENT),
+        Err(err) => {
+            eprintln!("{}", err);
+            Some(EXIT_FAILURE)
+        }
+    }
+}
+
+/// serve files under root until process is stopped
+fn run_agent(args: &[OsString]) -> i32 {
+    let mut address = AGENT_DEFAULT_ADDRESS;
+    let mut port = AGENT_DEFAULT_PORT;
+    let mut tls_identity = None;
+    let mut root = PathBuf::from(".");
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let valid = match arg.to_string_lossy().as_ref() {
+            PORT_ARG => args
+                .next()
+                .and_then(|x| x.to_string_lossy().parse().ok())
+                .map(|x| port = x),
+            BIND_ARG => args
+                .next()
+                .and_then(|x| x.to_string_lossy().parse().ok())
+                .map(|x| address = x),
+            TLS_IDENTITY_ARG => args.next().map(|x| tls_identity = Some(PathBuf::from(x))),
+            _ => {
+                root = PathBuf::from(arg);
+                Some(())
+            }
+        };
+        if valid.is_none() {
+            eprintln!(
+                "Usage: forskscope {} [{} <port>] [{} <address> {} <file>] [root]",
+                SERVE_ARG, PORT_ARG, BIND_ARG, TLS_IDENTITY_ARG
+            );
+            return EXIT_FAILURE;
+        }
+    }
+    let root = match root.canonicalize() {
+        Ok(x) => x,
+        Err(err) => {
+            eprintln!("Invalid root: {} ({})", root.display(), err);
+            return EXIT_FAILURE;
+        }
+    };
+
+    let result = agent_token().and_then(|token| {
+        serve(
+            &root,
+            address,
+            port,
+            &token,
+            tls_identity.as_deref(),
+            &headless_path_policy(),
+        )
+    });
+    match result {
+        Ok(_) => EXIT_SAME,
+        Err(err) => {
+            eprintln!("{}", err);
+            EXIT_FAILURE
+        }
+    }
+}
+
+/// compare and print differences in unified format
+/// returns true when no differences are found
+fn compare(old: &str, new: &str) -> Result<bool, String> {
+    // stdin is materialized with extension of the other side
+    // so that comparison mode is detected as on files
+    let stdin_filepath = if old == STDIN_ARG {
+        Some(stdin_to_file(&file_extension(new))?)
+    } else if new == STDIN_ARG {
+        Some(stdin_to_file(&file_extension(old))?)
+    } else {
+        None
+    };
+    let local_filepath = |path: &str| match (&stdin_filepath, path == STDIN_ARG) {
+        (Some(x), true) => x.to_owned(),
+        _ => path.to_owned(),
+    };
+
+    let result = filepaths_content_with_mode(&local_filepath(old), &local_filepath(new), None);
+    if let Some(x) = &stdin_filepath {
+        let _ = fs::remove_file(x);
+    }
+    let (read_contents, _) = result?;
+    let response = lines_diff_response(read_contents[0].clone(), read_contents[1].clone());
+    if response.diffs.iter().all(|x| x.diff_kind == DiffTag::Equal) {
+        return Ok(true);
+    }
+
+    // all hunks are included
+    let patch = selected_hunks_patch(old, new, &response.diffs, |_| true);
+    io::stdout()
+        .lock()
+        .write_all(patch.as_bytes())
+        .map_err(|err| format!("Failed to write stdout ({})", err))?;
+    Ok(false)
+}
+
+/// write stdin into temporary file
+fn stdin_to_file(extension: &str) -> Result<String, String> {
+    let mut bytes = vec![];
+    io::stdin()
+        .read_to_end(&mut bytes)
+        .map_err(|err| format!("Failed to read stdin ({})", err))?;
+
+    let dirpath = temp_dir("stdin")?;
+    let filename = if extension.is_empty() {
+        format!("{}-stdin", std::process::id())
+    } else {
+        format!("{}-stdin.{}", std::process::id(), extension)
+    };
+    let filepath = dirpath.join(filename);
+    write_temp_file(&filepath, &bytes)?;
+    Ok(path_string(&filepath))
+}
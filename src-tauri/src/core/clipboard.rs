@@ -0,0 +1,84 @@
+use std::io::Write;
+use std::path::PathBuf;
+
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use tauri_plugin_clipboard_manager::ClipboardExt;
+
+use super::archive::crc32;
+use super::file::fnv1a_digest;
+use super::temp_file::{temp_dir, write_temp_file};
+
+/// png file signature
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+/// png color type of 8-bit rgba
+const PNG_COLOR_TYPE_RGBA: u8 = 6;
+
+/// save image on clipboard as png into temporary file so that it is compared as image file
+/// file is named by digest so that the same image copied twice is saved once
+pub fn clipboard_image_file(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let image = app_handle
+        .clipboard()
+        .read_image()
+        .map_err(|err| format!("Failed to read image on clipboard ({})", err))?;
+    let png = png_bytes(image.width(), image.height(), image.rgba())?;
+
+    let dirpath = temp_dir("clipboard")?;
+    let filepath = dirpath.join(format!(
+        "{}-{:016x}.png",
+        std::process::id(),
+        fnv1a_digest(&png)
+    ));
+    // dir is private so that file already there is the one saved before
+    if !filepath.is_file() {
+        write_temp_file(&filepath, &png)?;
+    }
+    Ok(filepath)
+}
+
+/// encode rgba pixels into png without filtering rows
+fn png_bytes(width: u32, height: u32, rgba: &[u8]) -> Result<Vec<u8>, String> {
+    let row_length = width as usize * 4;
+    if width == 0 || height == 0 || rgba.len() != row_length * height as usize {
+        return Err(format!(
+            "Invalid image on clipboard: {}x{} ({} bytes)",
+            width,
+            height,
+            rgba.len()
+        ));
+    }
+
+    // each row starts with filter type 0 (none)
+    let mut encoder = ZlibEncoder::new(vec![], Compression::default());
+    for row in rgba.chunks_exact(row_length) {
+        encoder
+            .write_all(&[0])
+            .and_then(|_| encoder.write_all(row))
+            .map_err(|err| format!("Failed to compress image ({})", err))?;
+    }
+    let compressed = encoder
+        .finish()
+        .map_err(|err| format!("Failed to compress image ({})", err))?;
+
+    let mut header = vec![];
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    // bit depth, color type, compression, filter and interlace methods
+    header.extend_from_slice(&[8, PNG_COLOR_TYPE_RGBA, 0, 0, 0]);
+
+    let mut ret = PNG_SIGNATURE.to_vec();
+    push_png_chunk(&mut ret, b"IHDR", &header);
+    push_png_chunk(&mut ret, b"IDAT", &compressed);
+    push_png_chunk(&mut ret, b"IEND", &[]);
+    Ok(ret)
+}
+
+/// append png chunk of length, type, data and crc of type and data
+fn push_png_chunk(png: &mut Vec<u8>, chunk_type: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(chunk_type);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
@@ -0,0 +1,109 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use super::archive::{is_tar, is_zip};
+use super::document::{ODT_EXTENSIONS, RTF_SIGNATURE};
+use super::file::is_textfile;
+use super::os_path::os_path;
+use super::types::CompareMode;
+
+/// bytes read from head of file to sniff its content
+const SNIFF_BYTES: usize = 1024;
+
+/// file extensions of structured text
+const STRUCTURED_EXTENSIONS: [&str; 5] = ["json", "xml", "yaml", "yml", "toml"];
+/// file extensions of spreadsheet
+const SPREADSHEET_EXTENSIONS: [&str; 1] = ["xlsx"];
+/// file extensions of archive
+const ARCHIVE_EXTENSIONS: [&str; 4] = ["zip", "tar", "jar", "war"];
+
+/// decide comparison mode on file pair
+/// empty file path on either side follows the other side
+pub fn pair_compare_mode(old: &str, new: &str) -> CompareMode {
+    let old_mode = file_compare_mode(old);
+    let new_mode = file_compare_mode(new);
+    match (old_mode, new_mode) {
+        (Some(CompareMode::Spreadsheet), None) | (None, Some(CompareMode::Spreadsheet)) => {
+            // spreadsheet diff requires both sides
+            CompareMode::Binary
+        }
+        (Some(old_mode), None) => old_mode,
+        (None, Some(new_mode)) => new_mode,
+        (Some(old_mode), Some(new_mode)) if old_mode == new_mode => old_mode,
+        (Some(old_mode), Some(new_mode)) if is_textual(old_mode) && is_textual(new_mode) => {
+            CompareMode::Text
+        }
+        _ => CompareMode::Binary,
+    }
+}
+
+/// decide comparison mode on file by extension and content sniffing
+/// returns none when file path is empty or file does not exist
+pub fn file_compare_mode(filepath: &str) -> Option<CompareMode> {
+    if filepath.is_empty() || !os_path(filepath).is_file() {
+        return None;
+    }
+
+    let extension = Path::new(filepath)
+        .extension()
+        .map(|x| x.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let head = file_head(filepath);
+
+    if SPREADSHEET_EXTENSIONS.contains(&extension.as_str()) && is_zip(&head) {
+        return Some(CompareMode::Spreadsheet);
+    }
+    if (ODT_EXTENSIONS.contains(&extension.as_str()) && is_zip(&head))
+        || head.starts_with(RTF_SIGNATURE)
+    {
+        return Some(CompareMode::Document);
+    }
+    if is_image(&head) {
+        return Some(CompareMode::Image);
+    }
+    if is_zip(&head)
+        || is_tar(&head)
+        || (ARCHIVE_EXTENSIONS.contains(&extension.as_str()) && !head.is_empty())
+    {
+        return Some(CompareMode::Archive);
+    }
+    if is_textfile(filepath) {
+        if STRUCTURED_EXTENSIONS.contains(&extension.as_str()) || is_json(&head) {
+            return Some(CompareMode::Structured);
+        }
+        return Some(CompareMode::Text);
+    }
+    Some(CompareMode::Binary)
+}
+
+/// check if mode compares text
+fn is_textual(mode: CompareMode) -> bool {
+    matches!(mode, CompareMode::Text | CompareMode::Structured)
+}
+
+/// read head of file
+fn file_head(filepath: &str) -> Vec<u8> {
+    let mut ret = vec![];
+    if let Ok(file) = File::open(os_path(filepath)) {
+        let _ = file.take(SNIFF_BYTES as u64).read_to_end(&mut ret);
+    }
+    ret
+}
+
+/// check magic numbers of common image formats
+fn is_image(head: &[u8]) -> bool {
+    head.starts_with(b"\x89PNG\r\n\x1a\n")
+        || head.starts_with(&[0xff, 0xd8, 0xff])
+        || head.starts_with(b"GIF87a")
+        || head.starts_with(b"GIF89a")
+        || head.starts_with(b"BM")
+        || (head.starts_with(b"RIFF") && head.get(8..12) == Some(b"WEBP"))
+}
+
+/// check if text looks like json
+fn is_json(head: &[u8]) -> bool {
+    let head = String::from_utf8_lossy(head);
+    let head = head.trim_start_matches('\u{feff}').trim_start();
+    head.starts_with('{') || head.starts_with('[')
+}
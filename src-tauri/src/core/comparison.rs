@@ -0,0 +1,197 @@
+use super::diff::{
+    case_insensitive_lines_diff_response, classify_comment_hunks, lines_diff_response_within,
+    streamed_lines_diff_response,
+};
+use super::diff_rows::{inline_rows, split_rows};
+use super::file::{filepaths_content_with_mode, textfile_content_with_charset};
+use super::file_lock::ensure_accessible;
+use super::history;
+use super::ignored_hunk;
+use super::path_remap::{remapped_content, remapping_applies};
+use super::rules::{apply_ignored_texts, matching_rule};
+use super::saved::SavedContents;
+use super::settings;
+use super::similarity::diffs_similarity;
+use super::str::reveal_invisible_chars;
+use super::transform::apply_transforms;
+use super::types::{
+    ChangeLevel, CompareMode, ComparisonOptions, ComparisonRule, DiffSide, LinesDiff,
+    LinesDiffResponse, LinesDiffStreamEvent, ReadContent,
+};
+
+/// hunks sent at once on streaming
+const STREAM_BATCH_SIZE: usize = 64;
+
+/// contents of both files read for comparison with compare mode and rule applied
+pub struct ComparedContents {
+    pub old: ReadContent,
+    pub new: ReadContent,
+    pub compare_mode: CompareMode,
+    pub rule: Option<ComparisonRule>,
+}
+
+/// read both files for comparison
+/// text is decoded with charset in options or comparison rule matching file paths instead of
+/// detected one, and comparison mode is detected unless specified.
+/// contents read are tracked as saved ones before any transform
+pub fn read_compared_files(
+    app_handle: &tauri::AppHandle,
+    saved_contents: &SavedContents,
+    old: &str,
+    new: &str,
+    options: &ComparisonOptions,
+) -> Result<ComparedContents, String> {
+    ensure_accessible(old, false)?;
+    ensure_accessible(new, false)?;
+    let comparison_rules = settings::comparison_rules(app_handle);
+    let rule = matching_rule(&comparison_rules, old, new);
+    let compare_mode = options.compare_mode.or(rule.and_then(|x| x.compare_mode));
+    let (mut read_contents, compare_mode) = filepaths_content_with_mode(old, new, compare_mode)?;
+    let mut new_read = read_contents.remove(1);
+    let mut old_read = read_contents.remove(0);
+    let rule_charset = rule
+        .filter(|_| compare_mode == CompareMode::Text)
+        .and_then(|x| x.charset.as_deref());
+    // decompressed content is kept as detected because file itself is not text
+    if let Some(charset) = options
+        .old_charset
+        .as_deref()
+        .filter(|x| !x.is_empty())
+        .or(rule_charset)
+    {
+        if old_read.compression.is_none() {
+            old_read = textfile_content_with_charset(old, charset)?;
+        }
+    }
+    if let Some(charset) = options
+        .new_charset
+        .as_deref()
+        .filter(|x| !x.is_empty())
+        .or(rule_charset)
+    {
+        if new_read.compression.is_none() {
+            new_read = textfile_content_with_charset(new, charset)?;
+        }
+    }
+    saved_contents.track(old, &old_read);
+    saved_contents.track(new, &new_read);
+
+    Ok(ComparedContents {
+        old: old_read,
+        new: new_read,
+        compare_mode,
+        rule: rule.cloned(),
+    })
+}
+
+/// collect diff of contents read from files, or of unsaved buffers put in place of them
+/// so that both are compared alike with transforms, ignored texts, path remapping,
+/// invisible char markers, comment classification and ignored hunks.
+/// hunks are streamed to `on_hunks` as they are built when it is given
+pub fn compared_contents_response(
+    app_handle: &tauri::AppHandle,
+    old: &str,
+    new: &str,
+    contents: ComparedContents,
+    options: &ComparisonOptions,
+    on_hunks: Option<&tauri::ipc::Channel<LinesDiffStreamEvent>>,
+) -> Result<LinesDiffResponse, String> {
+    let ComparedContents {
+        old: mut old_read,
+        new: mut new_read,
+        compare_mode,
+        rule,
+    } = contents;
+    // transformed after saved content is tracked so that rendered form is not saved into file
+    apply_transforms(&mut old_read, &options.old_transforms)?;
+    apply_transforms(&mut new_read, &options.new_transforms)?;
+    if let Some(rule) = &rule {
+        apply_ignored_texts(&mut old_read, rule);
+        apply_ignored_texts(&mut new_read, rule);
+    }
+    let is_text = matches!(compare_mode, CompareMode::Text | CompareMode::Structured);
+    if is_text {
+        let path_remapping = settings::path_remapping(app_handle);
+        if remapping_applies(&path_remapping) {
+            old_read.content = remapped_content(&old_read.content, &path_remapping, DiffSide::Old);
+            new_read.content = remapped_content(&new_read.content, &path_remapping, DiffSide::New);
+        }
+    }
+    // markers are put after saved content is tracked so that they are not saved into file
+    if options.reveal_invisible.unwrap_or_default() && is_text {
+        old_read.content = reveal_invisible_chars(&old_read.content);
+        new_read.content = reveal_invisible_chars(&new_read.content);
+    }
+
+    let ignore_case = options.ignore_case.unwrap_or_default() && is_text;
+    let timeout = settings::diff_timeout(app_handle);
+    let classify = |diffs: &mut [LinesDiff]| classify_comment_hunks(diffs, new);
+    let response = match on_hunks.filter(|_| !ignore_case) {
+        // hunks are streamed as they are built so that first differences are shown early
+        Some(on_hunks) => {
+            on_hunks
+                .send(LinesDiffStreamEvent::Started {
+                    old_charset: old_read.charset.to_owned(),
+                    new_charset: new_read.charset.to_owned(),
+                })
+                .map_err(|err| err.to_string())?;
+            let response = streamed_lines_diff_response(
+                old_read,
+                new_read,
+                timeout,
+                STREAM_BATCH_SIZE,
+                classify,
+                |diffs| {
+                    on_hunks
+                        .send(LinesDiffStreamEvent::Hunks { diffs })
+                        .map_err(|err| err.to_string())
+                },
+            )?;
+            on_hunks
+                .send(LinesDiffStreamEvent::Finished {
+                    diffs_count: response.diffs.len(),
+                    moved_diff_indices: response
+                        .diffs
+                        .iter()
+                        .filter(|x| x.change_level == ChangeLevel::Moved)
+                        .map(|x| x.diff_index)
+                        .collect(),
+                })
+                .map_err(|err| err.to_string())?;
+            response
+        }
+        None => {
+            let mut response = if ignore_case {
+                case_insensitive_lines_diff_response(old_read, new_read)
+            } else {
+                lines_diff_response_within(old_read, new_read, timeout)
+            };
+            classify(&mut response.diffs);
+            response
+        }
+    };
+    let mut response = LinesDiffResponse {
+        compare_mode: Some(compare_mode),
+        comparison_rule: rule,
+        ..response
+    };
+    if options.with_rows.unwrap_or_default() {
+        response.split_rows = Some(split_rows(&response.diffs));
+        response.inline_rows = Some(inline_rows(&response.diffs));
+    }
+    match ignored_hunk::ignored_diff_indices(app_handle, old, new, &response.diffs) {
+        Ok(x) => response.ignored_hunks = x,
+        Err(err) => tracing::warn!("Failed to load ignored hunks ({})", err),
+    }
+    // failure to record history should not fail comparison
+    if let Err(err) = history::record(
+        app_handle,
+        old,
+        new,
+        Some(compare_mode),
+        Some(diffs_similarity(&response.diffs)),
+    ) {
+        tracing::warn!("Failed to record history ({})", err);
+    }
+    Ok(response)
+}
@@ -0,0 +1,123 @@
+use std::fs::{self, File};
+use std::io::Read;
+
+use bzip2::read::MultiBzDecoder;
+use flate2::read::MultiGzDecoder;
+use xz2::read::XzDecoder;
+
+use super::archive::is_tar;
+use super::file::bytes_content;
+use super::os_path::os_path;
+use super::types::{CompressionFormat, CompressionInfo, ReadContent};
+
+/// bytes enough to detect compression format by magic number
+const COMPRESSION_HEADER_SIZE: usize = 6;
+/// max size of decompressed content, against decompression bomb
+const MAX_DECOMPRESSED_SIZE: u64 = 256 * 1024 * 1024;
+
+/// detect single-file compression format by magic number
+pub fn compression_format(bytes: &[u8]) -> Option<CompressionFormat> {
+    if bytes.starts_with(&[0x1f, 0x8b]) {
+        Some(CompressionFormat::Gzip)
+    } else if bytes.starts_with(b"BZh") {
+        Some(CompressionFormat::Bzip2)
+    } else if bytes.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+        Some(CompressionFormat::Xz)
+    } else if bytes.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+        Some(CompressionFormat::Zstd)
+    } else {
+        None
+    }
+}
+
+/// decompress bytes in format
+/// concatenated members are decompressed together as rotated logs often are
+/// fails when decompressed content exceeds max size
+pub fn decompress(bytes: &[u8], format: CompressionFormat) -> Result<Vec<u8>, String> {
+    let limit = MAX_DECOMPRESSED_SIZE + 1;
+    let mut ret = vec![];
+    let read = match format {
+        CompressionFormat::Gzip => MultiGzDecoder::new(bytes).take(limit).read_to_end(&mut ret),
+        CompressionFormat::Bzip2 => MultiBzDecoder::new(bytes).take(limit).read_to_end(&mut ret),
+        CompressionFormat::Xz => XzDecoder::new_multi_decoder(bytes)
+            .take(limit)
+            .read_to_end(&mut ret),
+        CompressionFormat::Zstd => zstd::stream::read::Decoder::new(bytes)
+            .and_then(|x| x.take(limit).read_to_end(&mut ret)),
+    };
+    read.map_err(|err| format!("Failed to decompress {:?} ({})", format, err))?;
+    if MAX_DECOMPRESSED_SIZE < ret.len() as u64 {
+        return Err(format!(
+            "Failed to decompress {:?} (content exceeds {} bytes)",
+            format, MAX_DECOMPRESSED_SIZE
+        ));
+    }
+    Ok(ret)
+}
+
+/// read contents of file pair decompressing single-file wrappers such as `app.log.1.gz`
+/// returns none when neither side is compressed, or either is compressed archive
+/// such as `.tar.gz` which is compared as archive instead
+/// also returns none when decompression fails so that files are compared as they are
+pub fn decompressed_contents(old: &str, new: &str) -> Result<Option<Vec<ReadContent>>, String> {
+    // only header is read here so that uncompressed files are not read twice
+    let old_format = compression_format(&file_header(old)?);
+    let new_format = compression_format(&file_header(new)?);
+    if old_format.is_none() && new_format.is_none() {
+        return Ok(None);
+    }
+    let old_bytes = file_bytes(old)?;
+    let new_bytes = file_bytes(new)?;
+
+    let mut ret = vec![];
+    for (bytes, format) in [(old_bytes, old_format), (new_bytes, new_format)] {
+        let format = match format {
+            Some(x) => x,
+            None => {
+                ret.push(bytes_content(&bytes));
+                continue;
+            }
+        };
+        let decompressed = match decompress(&bytes, format) {
+            Ok(x) => x,
+            Err(err) => {
+                tracing::warn!("{}", err);
+                return Ok(None);
+            }
+        };
+        if is_tar(&decompressed) {
+            return Ok(None);
+        }
+        ret.push(ReadContent {
+            compression: Some(CompressionInfo {
+                format,
+                compressed_size: bytes.len() as u64,
+                decompressed_size: decompressed.len() as u64,
+            }),
+            ..bytes_content(&decompressed)
+        });
+    }
+    Ok(Some(ret))
+}
+
+/// read file head to detect compression format. empty on empty file path
+fn file_header(filepath: &str) -> Result<Vec<u8>, String> {
+    if filepath.is_empty() {
+        return Ok(vec![]);
+    }
+    let file = File::open(os_path(filepath))
+        .map_err(|err| format!("Failed to open {} ({})", filepath, err))?;
+    let mut ret = vec![];
+    file.take(COMPRESSION_HEADER_SIZE as u64)
+        .read_to_end(&mut ret)
+        .map_err(|err| format!("Failed to read {} ({})", filepath, err))?;
+    Ok(ret)
+}
+
+/// read file. empty on empty file path
+fn file_bytes(filepath: &str) -> Result<Vec<u8>, String> {
+    if filepath.is_empty() {
+        return Ok(vec![]);
+    }
+    fs::read(os_path(filepath)).map_err(|err| format!("Failed to read {} ({})", filepath, err))
+}
@@ -0,0 +1,67 @@
+use super::compressed::decompressed_contents;
+use super::diff::lines_diff_response;
+use super::file::filepaths_content_with_mode;
+use super::types::{CompareMode, ConcatenatedDiffResponse, ReadContent, SourceBoundary};
+
+/// compare multiple files per side concatenated in order into one logical document
+/// such as rotated logs `app.log.2`, `app.log.1` and `app.log`
+/// compressed rotations such as `app.log.3.gz` are decompressed
+pub fn concatenated_diff(
+    old_filepaths: &[String],
+    new_filepaths: &[String],
+) -> Result<ConcatenatedDiffResponse, String> {
+    let (old_read, old_sources) = concatenated_content(old_filepaths)?;
+    let (new_read, new_sources) = concatenated_content(new_filepaths)?;
+    Ok(ConcatenatedDiffResponse {
+        diff: lines_diff_response(old_read, new_read),
+        old_sources,
+        new_sources,
+    })
+}
+
+/// concatenate text contents with line ranges of source files as boundaries
+/// charset is the one of the first file or `(mixed)` when they differ
+fn concatenated_content(
+    filepaths: &[String],
+) -> Result<(ReadContent, Vec<SourceBoundary>), String> {
+    let mut content = String::new();
+    let mut charsets = vec![];
+    let mut sources = vec![];
+    let mut start_line = 1;
+    for filepath in filepaths {
+        let read = match decompressed_contents(filepath, "")? {
+            Some(mut x) => x.remove(0),
+            None => filepaths_content_with_mode(filepath, "", Some(CompareMode::Text))?
+                .0
+                .remove(0),
+        };
+        let mut file_content = read.content;
+        // last line without line ending would be joined with first line of next file
+        if !file_content.is_empty() && !file_content.ends_with('\n') {
+            file_content.push('\n');
+        }
+        let lines_count = file_content.matches('\n').count();
+        sources.push(SourceBoundary {
+            filepath: filepath.to_owned(),
+            start_line,
+            end_line: start_line + lines_count,
+            source_start_line: 1,
+        });
+        start_line += lines_count;
+        content.push_str(&file_content);
+        charsets.push(read.charset);
+    }
+
+    charsets.dedup();
+    let charset = match charsets.as_slice() {
+        [x] => x.to_owned(),
+        [] => String::new(),
+        _ => "(mixed)".to_owned(),
+    };
+    let read = ReadContent {
+        charset,
+        content,
+        ..Default::default()
+    };
+    Ok((read, sources))
+}
@@ -0,0 +1,20 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// app state to keep secrets of remote locations such as agent token or ftp password
+/// urls given back to webview do not carry them, and ones without them are completed
+/// with secret remembered for the same location
+#[derive(Default)]
+pub struct RemoteCredentials(Mutex<HashMap<String, String>>);
+
+impl RemoteCredentials {
+    /// remember secret given in url, or get one remembered when url has none
+    pub fn resolve(&self, location: &str, secret: &str) -> Option<String> {
+        let mut secrets = self.0.lock().unwrap();
+        if !secret.is_empty() {
+            secrets.insert(location.to_owned(), secret.to_owned());
+            return Some(secret.to_owned());
+        }
+        secrets.get(location).cloned()
+    }
+}
@@ -1,89 +1,755 @@
 use std::{
+    collections::HashSet,
     ffi::OsString,
     fs::{metadata, read_dir, File},
     io::{BufReader, Read},
     path::{Path, PathBuf},
+    time::{Duration, Instant},
 };
 
-use similar::{DiffTag, TextDiff};
+use similar::{DiffOp, DiffTag, TextDiff};
 
 use super::{
-    file::{arg_to_filepath, validate_filepath},
-    str::{multibyte_str_byte_indices, split_lines_with_endings},
-    types::{CharsDiff, CharsDiffLines, CompareSetItem, LinesDiff},
+    diff_rows::{line_map, minimap},
+    file::{arg_to_filepath, file_extension, validate_filepath},
+    os_path::{os_path, os_str_string},
+    str::{multibyte_str_byte_indices, split_lines_with_endings, split_long_lines},
+    types::{
+        ChangeLevel, CharsDiff, CharsDiffLines, CharsGroup, CharsetMismatch, CompareSetItem,
+        DiffSide, EntryStatus, HunkClassification, LineMapSegment, LinesDiff, LinesDiffResponse,
+        PaneEdit, PartialLinesDiff, ReadContent, TreeEntryDiff,
+    },
 };
 
+/// max chars in line to be compared and shown at once
+/// longer lines such as minified code are segmented
+const MAX_LINE_LENGTH: usize = 4096;
+/// max chars in hunk to be compared on chars
+const MAX_CHARS_DIFF_LENGTH: usize = 65536;
+/// equal chars shorter than this between changes are grouped into the changes
+/// so that single-line diff is not fragmented
+const MIN_EQUAL_CHARS_GROUP: usize = 3;
+/// equal lines around edit re-compared in partial re-diff
+/// so that hunks next to edit can be realigned
+const PARTIAL_DIFF_CONTEXT_LINES: usize = 8;
+/// lines of each side compared at once on streaming diff
+const STREAM_WINDOW_LINES: usize = 10000;
+/// time budget of lines diff unless configured
+/// coarse hunks found until then are returned on pathological inputs such as repetitive lines
+pub const DEFAULT_DIFF_TIMEOUT_MILLIS: u64 = 10000;
+
 pub fn lines_diffs(old_content: &str, new_content: &str) -> Vec<LinesDiff> {
-    let diff = TextDiff::configure().diff_lines(old_content, new_content);
-    let old_lines: Vec<String> = split_lines_with_endings(old_content);
-    let new_lines: Vec<String> = split_lines_with_endings(new_content);
-    diff.ops()
+    lines_diffs_within(
+        old_content,
+        new_content,
+        Duration::from_millis(DEFAULT_DIFF_TIMEOUT_MILLIS),
+    )
+    .0
+}
+
+/// lines diffs computed in time budget, with whether it ran out
+/// hunks are coarse, such as large replacement, when it ran out
+pub fn lines_diffs_within(
+    old_content: &str,
+    new_content: &str,
+    timeout: Duration,
+) -> (Vec<LinesDiff>, bool) {
+    let old_lines: Vec<String> =
+        split_long_lines(split_lines_with_endings(old_content), MAX_LINE_LENGTH);
+    let new_lines: Vec<String> =
+        split_long_lines(split_lines_with_endings(new_content), MAX_LINE_LENGTH);
+    // no deadline when timeout is too long to be represented
+    let deadline = Instant::now().checked_add(timeout);
+    let ops = lines_diff_ops_until(&old_lines, &new_lines, deadline);
+    // judged on diff step only, not on building lines diffs afterward
+    let timed_out = deadline.is_some_and(|x| x <= Instant::now());
+    let mut diffs = ops
         .iter()
         .enumerate()
-        .map(|(diff_index, x)| {
-            let diff_kind = x.tag();
-            match diff_kind {
-                DiffTag::Equal => {
-                    let old_range = x.old_range();
-                    let lines_count = old_range.end - old_range.start;
-                    let lines = old_lines[old_range.start..old_range.end].to_vec();
-                    LinesDiff {
-                        diff_index,
-                        diff_kind,
-                        lines_count,
-                        old_lines: lines.to_owned(),
-                        new_lines: lines,
-                    }
-                }
-                DiffTag::Delete => {
-                    let old_range = x.old_range();
-                    let lines_count = old_range.end - old_range.start;
-                    let old_lines = old_lines[old_range.start..old_range.end].to_vec();
-                    LinesDiff {
-                        diff_index,
-                        diff_kind,
-                        lines_count,
-                        old_lines,
-                        new_lines: vec![],
-                    }
+        .map(|(diff_index, x)| lines_diff(diff_index, x, &old_lines, &new_lines))
+        .collect::<Vec<LinesDiff>>();
+    mark_moved_hunks(&mut diffs);
+    (diffs, timed_out)
+}
+
+/// lines diffs aligned ignoring letter case such as for sql and case-insensitive languages
+/// original text is kept, and lines differing only in case are split out of equal hunks
+/// into modified hunks flagged as case only
+pub fn case_insensitive_lines_diffs(old_content: &str, new_content: &str) -> Vec<LinesDiff> {
+    let old_lines: Vec<String> =
+        split_long_lines(split_lines_with_endings(old_content), MAX_LINE_LENGTH);
+    let new_lines: Vec<String> =
+        split_long_lines(split_lines_with_endings(new_content), MAX_LINE_LENGTH);
+    let lowercase = |lines: &[String]| lines.iter().map(|x| x.to_lowercase()).collect::<Vec<_>>();
+    let ops = lines_diff_ops(&lowercase(&old_lines), &lowercase(&new_lines));
+
+    let mut diffs: Vec<LinesDiff> = vec![];
+    for op in ops {
+        if op.tag() != DiffTag::Equal {
+            diffs.push(lines_diff(diffs.len(), &op, &old_lines, &new_lines));
+            continue;
+        }
+        let old_start = op.old_range().start;
+        let new_start = op.new_range().start;
+        let len = op.old_range().len();
+        let is_same = |i: usize| old_lines[old_start + i] == new_lines[new_start + i];
+        // runs of identical lines and of lines differing in case
+        let mut start = 0;
+        while start < len {
+            let same = is_same(start);
+            let end = (start + 1..len)
+                .find(|i| is_same(*i) != same)
+                .unwrap_or(len);
+            let (old_index, new_index) = (old_start + start, new_start + start);
+            let run = if same {
+                DiffOp::Equal {
+                    old_index,
+                    new_index,
+                    len: end - start,
                 }
-                DiffTag::Insert => {
-                    let new_range = x.new_range();
-                    let lines_count = new_range.end - new_range.start;
-                    let new_lines = new_lines[new_range.start..new_range.end].to_vec();
-                    LinesDiff {
-                        diff_index,
-                        diff_kind,
-                        lines_count,
-                        old_lines: vec![],
-                        new_lines,
-                    }
+            } else {
+                DiffOp::Replace {
+                    old_index,
+                    old_len: end - start,
+                    new_index,
+                    new_len: end - start,
                 }
-                DiffTag::Replace => {
-                    let old_range = x.old_range();
-                    let new_range = x.new_range();
+            };
+            diffs.push(lines_diff(diffs.len(), &run, &old_lines, &new_lines));
+            start = end;
+        }
+    }
+    mark_moved_hunks(&mut diffs);
+    diffs
+}
 
-                    let old_lines_count = old_range.end - old_range.start;
-                    let new_lines_count = new_range.end - new_range.start;
-                    let lines_count = if old_lines_count < new_lines_count {
-                        new_lines_count
-                    } else {
-                        old_lines_count
-                    };
-                    let old_lines = old_lines[old_range.start..old_range.end].to_vec();
-                    let new_lines = new_lines[new_range.start..new_range.end].to_vec();
-
-                    LinesDiff {
-                        diff_index,
-                        diff_kind: diff_kind.to_owned(),
-                        lines_count,
-                        old_lines,
-                        new_lines,
-                    }
+/// lines diffs aligned by keys given to lines instead of lines themselves
+/// such as template lines and lines matching them. lines of each side are kept on equal hunks
+pub fn keyed_lines_diffs(
+    old_content: &str,
+    new_content: &str,
+    keys: impl FnOnce(&[String], &[String]) -> (Vec<String>, Vec<String>),
+) -> Vec<LinesDiff> {
+    let old_lines: Vec<String> =
+        split_long_lines(split_lines_with_endings(old_content), MAX_LINE_LENGTH);
+    let new_lines: Vec<String> =
+        split_long_lines(split_lines_with_endings(new_content), MAX_LINE_LENGTH);
+    let (old_keys, new_keys) = keys(&old_lines, &new_lines);
+    let mut diffs = lines_diff_ops(&old_keys, &new_keys)
+        .iter()
+        .enumerate()
+        .map(|(diff_index, x)| {
+            let mut diff = lines_diff(diff_index, x, &old_lines, &new_lines);
+            if x.tag() == DiffTag::Equal {
+                diff.new_lines = new_lines[x.new_range()].to_vec();
+            }
+            diff
+        })
+        .collect::<Vec<LinesDiff>>();
+    mark_moved_hunks(&mut diffs);
+    diffs
+}
+
+/// lines diffs updated after edit in pane
+/// only hunks around edited lines are re-computed and the others are kept
+/// returns all diffs updated and part of them changed from previous ones
+pub fn partial_lines_diffs(
+    diffs: &[LinesDiff],
+    edit: &PaneEdit,
+) -> (Vec<LinesDiff>, PartialLinesDiff) {
+    let side_range = |x: &LineMapSegment| match edit.side {
+        DiffSide::Old => (x.old_start, x.old_end),
+        DiffSide::New => (x.new_start, x.new_end),
+    };
+    let segments = line_map(diffs);
+    let is_touched = |x: &LineMapSegment| {
+        let (start, end) = side_range(x);
+        if start == end {
+            edit.start_line <= start && start <= edit.end_line
+        } else if edit.start_line == edit.end_line {
+            start <= edit.start_line && edit.start_line < end
+        } else {
+            start < edit.end_line && edit.start_line < end
+        }
+    };
+    let touched = segments
+        .iter()
+        .enumerate()
+        .filter(|(_, x)| is_touched(x))
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+    // edit after last line such as appending touches last segment
+    let last_index = segments.len().saturating_sub(1);
+    // neighbours of changed hunks are included so that hunks merged or split by edit are realigned
+    // equal lines give context by themselves
+    let is_equal = |i: usize| diffs.get(i).is_some_and(|x| x.diff_kind == DiffTag::Equal);
+    let first = *touched.first().unwrap_or(&last_index);
+    let first = if is_equal(first) {
+        first
+    } else {
+        first.saturating_sub(1)
+    };
+    let last = *touched.last().unwrap_or(&last_index);
+    let last = if is_equal(last) {
+        last
+    } else {
+        (last + 1).min(last_index)
+    };
+
+    // equal lines far from edit at both ends of region are kept out of re-comparison
+    let window_start = edit.start_line.saturating_sub(PARTIAL_DIFF_CONTEXT_LINES);
+    let window_end = edit.end_line + PARTIAL_DIFF_CONTEXT_LINES;
+    let mut prefix = diffs.get(..first).unwrap_or_default().to_vec();
+    let mut suffix = diffs.get(last + 1..).unwrap_or_default().to_vec();
+    let mut region_old = vec![];
+    let mut region_new = vec![];
+    for (i, diff) in diffs.iter().enumerate().take(last + 1).skip(first) {
+        let mut lines = diff.old_lines.clone();
+        if diff.diff_kind != DiffTag::Equal || (i != first && i != last) {
+            region_old.extend(lines);
+            region_new.extend(diff.new_lines.clone());
+            continue;
+        }
+        let mut line = side_range(&segments[i]).0;
+        let line_numbers = lines
+            .iter()
+            .map(|x| {
+                let ret = line;
+                if x.ends_with('\n') || x.ends_with('\r') {
+                    line += 1;
                 }
+                ret
+            })
+            .collect::<Vec<_>>();
+        if i == last {
+            let end = line_numbers
+                .iter()
+                .position(|x| window_end <= *x)
+                .unwrap_or(lines.len());
+            let kept = lines.split_off(end);
+            if !kept.is_empty() {
+                suffix.insert(0, equal_lines_diff(kept));
+            }
+        }
+        if i == first {
+            let start = line_numbers
+                .iter()
+                .position(|x| window_start <= *x)
+                .unwrap_or(lines.len())
+                .min(lines.len());
+            let kept = lines.drain(..start).collect::<Vec<_>>();
+            if !kept.is_empty() {
+                prefix.push(equal_lines_diff(kept));
             }
+        }
+        region_old.extend(lines.clone());
+        region_new.extend(lines);
+    }
+
+    // edit is applied to region on edited side in line numbers relative to region
+    let region_start_line = 1 + prefix
+        .iter()
+        .flat_map(|x| match edit.side {
+            DiffSide::Old => &x.old_lines,
+            DiffSide::New => &x.new_lines,
         })
-        .collect::<Vec<LinesDiff>>()
+        .filter(|x| x.ends_with('\n') || x.ends_with('\r'))
+        .count();
+    let edited = match edit.side {
+        DiffSide::Old => &mut region_old,
+        DiffSide::New => &mut region_new,
+    };
+    let mut lines = split_lines_with_endings(&edited.concat());
+    let start = edit
+        .start_line
+        .saturating_sub(region_start_line)
+        .min(lines.len());
+    let end = edit
+        .end_line
+        .saturating_sub(region_start_line)
+        .clamp(start, lines.len());
+    lines.splice(start..end, split_lines_with_endings(&edit.text));
+    *edited = split_long_lines(lines, MAX_LINE_LENGTH);
+
+    let region_diffs = lines_diff_ops(&region_old, &region_new)
+        .iter()
+        .map(|x| lines_diff(0, x, &region_old, &region_new))
+        .collect::<Vec<_>>();
+    let mut updated: Vec<LinesDiff> = vec![];
+    for diff in prefix.into_iter().chain(region_diffs).chain(suffix) {
+        match updated.last_mut() {
+            Some(x) if x.diff_kind == DiffTag::Equal && diff.diff_kind == DiffTag::Equal => {
+                x.lines_count += diff.lines_count;
+                x.old_lines.extend(diff.old_lines);
+                x.new_lines.extend(diff.new_lines);
+            }
+            _ => updated.push(diff),
+        }
+    }
+    for (i, diff) in updated.iter_mut().enumerate() {
+        diff.diff_index = i;
+        // moved hunks are marked again because their counterparts may be edited
+        diff.change_level = match (diff.change_level, diff.diff_kind) {
+            (ChangeLevel::Moved, DiffTag::Delete) => {
+                change_level(ChangeLevel::Removed, &diff.old_lines)
+            }
+            (ChangeLevel::Moved, DiffTag::Insert) => {
+                change_level(ChangeLevel::Added, &diff.new_lines)
+            }
+            (x, _) => x,
+        };
+    }
+    mark_moved_hunks(&mut updated);
+
+    let same = |a: &LinesDiff, b: &LinesDiff| {
+        a.diff_kind == b.diff_kind
+            && a.change_level == b.change_level
+            && a.old_lines == b.old_lines
+            && a.new_lines == b.new_lines
+    };
+    let start_index = updated
+        .iter()
+        .zip(diffs)
+        .take_while(|(a, b)| same(a, b))
+        .count();
+    let common_suffix = updated[start_index..]
+        .iter()
+        .rev()
+        .zip(diffs[start_index..].iter().rev())
+        .take_while(|(a, b)| same(a, b))
+        .count();
+    let changed_count = updated.len() - start_index - common_suffix;
+    let partial = PartialLinesDiff {
+        diffs: updated[start_index..start_index + changed_count].to_vec(),
+        start_index,
+        changed_count,
+        replaced_count: diffs.len() - start_index - common_suffix,
+    };
+    (updated, partial)
+}
+
+/// equal lines diff whose index is set afterwards
+fn equal_lines_diff(lines: Vec<String>) -> LinesDiff {
+    LinesDiff {
+        diff_index: 0,
+        diff_kind: DiffTag::Equal,
+        lines_count: lines.len(),
+        old_lines: lines.clone(),
+        new_lines: lines,
+        classification: HunkClassification::default(),
+        change_level: ChangeLevel::Unchanged,
+    }
+}
+
+/// build lines diff response from contents read
+pub fn lines_diff_response(old_read: ReadContent, new_read: ReadContent) -> LinesDiffResponse {
+    lines_diff_response_within(
+        old_read,
+        new_read,
+        Duration::from_millis(DEFAULT_DIFF_TIMEOUT_MILLIS),
+    )
+}
+
+/// build lines diff response in time budget
+/// response is flagged as timed out with coarse hunks when diff does not finish in it
+pub fn lines_diff_response_within(
+    old_read: ReadContent,
+    new_read: ReadContent,
+    timeout: Duration,
+) -> LinesDiffResponse {
+    let (diffs, timed_out) = lines_diffs_within(
+        old_read.content.as_str(),
+        new_read.content.as_str(),
+        timeout,
+    );
+    LinesDiffResponse {
+        timed_out,
+        ..diffs_response(diffs, old_read, new_read)
+    }
+}
+
+/// build lines diff response in time budget, passing hunks in batches as they are built
+pub fn streamed_lines_diff_response(
+    old_read: ReadContent,
+    new_read: ReadContent,
+    timeout: Duration,
+    batch_size: usize,
+    classify: impl Fn(&mut [LinesDiff]),
+    on_batch: impl FnMut(Vec<LinesDiff>) -> Result<(), String>,
+) -> Result<LinesDiffResponse, String> {
+    let (diffs, timed_out) = stream_lines_diffs(
+        old_read.content.as_str(),
+        new_read.content.as_str(),
+        timeout,
+        batch_size,
+        classify,
+        on_batch,
+    )?;
+    Ok(LinesDiffResponse {
+        timed_out,
+        ..diffs_response(diffs, old_read, new_read)
+    })
+}
+
+/// lines diff response where letter case is ignored to align lines
+pub fn case_insensitive_lines_diff_response(
+    old_read: ReadContent,
+    new_read: ReadContent,
+) -> LinesDiffResponse {
+    let diffs = case_insensitive_lines_diffs(old_read.content.as_str(), new_read.content.as_str());
+    diffs_response(diffs, old_read, new_read)
+}
+
+/// build lines diff response from diffs of contents read
+pub fn diffs_response(
+    diffs: Vec<LinesDiff>,
+    old_read: ReadContent,
+    new_read: ReadContent,
+) -> LinesDiffResponse {
+    LinesDiffResponse {
+        charset_mismatch: charset_mismatch(&old_read, &new_read),
+        minimap: minimap(&diffs),
+        single_line_diff: single_line_chars_diff(&old_read.content, &new_read.content),
+        old_charset: old_read.charset,
+        new_charset: new_read.charset,
+        old_charset_candidates: old_read.charset_candidates,
+        new_charset_candidates: new_read.charset_candidates,
+        compare_mode: None,
+        diffs,
+        split_rows: None,
+        inline_rows: None,
+        old_compression: old_read.compression,
+        new_compression: new_read.compression,
+        old_mime_type: old_read.mime_type,
+        new_mime_type: new_read.mime_type,
+        comparison_rule: None,
+        ignored_hunks: vec![],
+        timed_out: false,
+    }
+}
+
+/// mismatch of charsets both sides are decoded with so that it is warned explicitly
+/// labels in parentheses such as `(binary)` are not charsets and empty side has none
+fn charset_mismatch(old_read: &ReadContent, new_read: &ReadContent) -> Option<CharsetMismatch> {
+    let is_charset =
+        |read: &ReadContent| !read.charset.is_empty() && !read.charset.starts_with('(');
+    if !is_charset(old_read)
+        || !is_charset(new_read)
+        || old_read.charset.eq_ignore_ascii_case(&new_read.charset)
+    {
+        return None;
+    }
+    Some(CharsetMismatch {
+        old_charset: old_read.charset.to_owned(),
+        new_charset: new_read.charset.to_owned(),
+        old_lossy: old_read.content.contains('\u{FFFD}'),
+        new_lossy: new_read.content.contains('\u{FFFD}'),
+    })
+}
+
+/// build lines diffs window by window, passing hunks in batches as soon as they are built
+/// so that first differences are shown before whole contents are compared.
+/// hunks are classified by `classify` once before passed so that they agree with ones returned
+/// returns all diffs with moved hunks marked, and whether diff ran out of time budget.
+/// moved hunks are known only after all hunks are built so that they are marked on returned ones
+pub fn stream_lines_diffs(
+    old_content: &str,
+    new_content: &str,
+    timeout: Duration,
+    batch_size: usize,
+    classify: impl Fn(&mut [LinesDiff]),
+    mut on_batch: impl FnMut(Vec<LinesDiff>) -> Result<(), String>,
+) -> Result<(Vec<LinesDiff>, bool), String> {
+    let old_lines: Vec<String> =
+        split_long_lines(split_lines_with_endings(old_content), MAX_LINE_LENGTH);
+    let new_lines: Vec<String> =
+        split_long_lines(split_lines_with_endings(new_content), MAX_LINE_LENGTH);
+    // no deadline when timeout is too long to be represented
+    let deadline = Instant::now().checked_add(timeout);
+    let mut timed_out = false;
+
+    let mut diffs: Vec<LinesDiff> = vec![];
+    // last diff is kept unsent because equal lines in next window may be joined to it
+    let mut sent = 0;
+    let (mut old_start, mut new_start) = (0, 0);
+    loop {
+        let old_end = (old_start + STREAM_WINDOW_LINES).min(old_lines.len());
+        let new_end = (new_start + STREAM_WINDOW_LINES).min(new_lines.len());
+        let old_window = &old_lines[old_start..old_end];
+        let new_window = &new_lines[new_start..new_end];
+        let mut ops = lines_diff_ops_until(old_window, new_window, deadline);
+        timed_out |= deadline.is_some_and(|x| x <= Instant::now());
+
+        let is_last = old_end == old_lines.len() && new_end == new_lines.len();
+        // lines from last equal ones are compared again together with next window
+        // so that hunk cut at window end is realigned
+        let resumed = ops
+            .iter()
+            .rposition(|x| x.tag() == DiffTag::Equal)
+            .filter(|x| !is_last && 0 < *x);
+        let (old_advance, new_advance) = match resumed {
+            Some(i) => (ops[i].old_range().start, ops[i].new_range().start),
+            None => (old_window.len(), new_window.len()),
+        };
+        if let Some(i) = resumed {
+            ops.truncate(i);
+        }
+
+        for x in &ops {
+            let diff = lines_diff(diffs.len(), x, old_window, new_window);
+            match diffs.last_mut() {
+                Some(last)
+                    if last.diff_kind == DiffTag::Equal && diff.diff_kind == DiffTag::Equal =>
+                {
+                    last.lines_count += diff.lines_count;
+                    last.old_lines.extend(diff.old_lines);
+                    last.new_lines.extend(diff.new_lines);
+                }
+                _ => diffs.push(diff),
+            }
+        }
+        while sent + batch_size < diffs.len() {
+            classify(&mut diffs[sent..sent + batch_size]);
+            on_batch(diffs[sent..sent + batch_size].to_vec())?;
+            sent += batch_size;
+        }
+
+        if is_last {
+            break;
+        }
+        old_start += old_advance;
+        new_start += new_advance;
+    }
+    if sent < diffs.len() {
+        classify(&mut diffs[sent..]);
+        on_batch(diffs[sent..].to_vec())?;
+    }
+    // hunks sent are not changed any more except change level of moved ones
+    mark_moved_hunks(&mut diffs);
+    Ok((diffs, timed_out))
+}
+
+/// diff ops on lines split beforehand
+pub fn lines_diff_ops(old_lines: &[String], new_lines: &[String]) -> Vec<DiffOp> {
+    let deadline = Instant::now() + Duration::from_millis(DEFAULT_DIFF_TIMEOUT_MILLIS);
+    lines_diff_ops_until(old_lines, new_lines, Some(deadline))
+}
+
+/// diff ops on lines split beforehand, giving up fine alignment at deadline if any
+fn lines_diff_ops_until(
+    old_lines: &[String],
+    new_lines: &[String],
+    deadline: Option<Instant>,
+) -> Vec<DiffOp> {
+    let old_lines = old_lines.iter().map(|x| x.as_str()).collect::<Vec<&str>>();
+    let new_lines = new_lines.iter().map(|x| x.as_str()).collect::<Vec<&str>>();
+    TextDiff::configure()
+        .deadline(deadline)
+        .diff_slices(&old_lines, &new_lines)
+        .ops()
+        .to_vec()
+}
+
+/// build lines diff from diff op
+fn lines_diff(
+    diff_index: usize,
+    x: &DiffOp,
+    old_lines: &[String],
+    new_lines: &[String],
+) -> LinesDiff {
+    let diff_kind = x.tag();
+    match diff_kind {
+        DiffTag::Equal => {
+            let old_range = x.old_range();
+            let lines_count = old_range.end - old_range.start;
+            let lines = old_lines[old_range.start..old_range.end].to_vec();
+            LinesDiff {
+                diff_index,
+                diff_kind,
+                lines_count,
+                old_lines: lines.to_owned(),
+                new_lines: lines,
+                classification: HunkClassification::default(),
+                change_level: ChangeLevel::Unchanged,
+            }
+        }
+        DiffTag::Delete => {
+            let old_range = x.old_range();
+            let lines_count = old_range.end - old_range.start;
+            let old_lines = old_lines[old_range.start..old_range.end].to_vec();
+            LinesDiff {
+                diff_index,
+                diff_kind,
+                lines_count,
+                classification: hunk_classification(&old_lines, &[]),
+                change_level: change_level(ChangeLevel::Removed, &old_lines),
+                old_lines,
+                new_lines: vec![],
+            }
+        }
+        DiffTag::Insert => {
+            let new_range = x.new_range();
+            let lines_count = new_range.end - new_range.start;
+            let new_lines = new_lines[new_range.start..new_range.end].to_vec();
+            LinesDiff {
+                diff_index,
+                diff_kind,
+                lines_count,
+                classification: hunk_classification(&[], &new_lines),
+                change_level: change_level(ChangeLevel::Added, &new_lines),
+                old_lines: vec![],
+                new_lines,
+            }
+        }
+        DiffTag::Replace => {
+            let old_range = x.old_range();
+            let new_range = x.new_range();
+
+            let old_lines_count = old_range.end - old_range.start;
+            let new_lines_count = new_range.end - new_range.start;
+            let lines_count = if old_lines_count < new_lines_count {
+                new_lines_count
+            } else {
+                old_lines_count
+            };
+            let old_lines = old_lines[old_range.start..old_range.end].to_vec();
+            let new_lines = new_lines[new_range.start..new_range.end].to_vec();
+
+            LinesDiff {
+                diff_index,
+                diff_kind: diff_kind.to_owned(),
+                lines_count,
+                classification: hunk_classification(&old_lines, &new_lines),
+                change_level: change_level(ChangeLevel::Modified, &new_lines),
+                old_lines,
+                new_lines,
+            }
+        }
+    }
+}
+
+/// change level of hunk. conflict markers take precedence
+fn change_level(level: ChangeLevel, lines: &[String]) -> ChangeLevel {
+    const CONFLICT_MARKERS: [&str; 3] = ["<<<<<<< ", "=======", ">>>>>>> "];
+    let has_conflict_marker = lines.iter().any(|line| {
+        CONFLICT_MARKERS
+            .iter()
+            .any(|x| line.starts_with(x) && (*x != "=======" || line.trim_end() == *x))
+    });
+    if has_conflict_marker {
+        ChangeLevel::Conflict
+    } else {
+        level
+    }
+}
+
+/// mark removed and added hunks with the same lines as moved
+/// blank lines and indentation are ignored
+fn mark_moved_hunks(diffs: &mut [LinesDiff]) {
+    let key = |lines: &[String]| {
+        lines
+            .iter()
+            .map(|x| x.trim())
+            .filter(|x| !x.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    let removed = diffs
+        .iter()
+        .filter(|x| x.change_level == ChangeLevel::Removed)
+        .map(|x| key(&x.old_lines))
+        .filter(|x| !x.is_empty())
+        .collect::<HashSet<_>>();
+    let added = diffs
+        .iter()
+        .filter(|x| x.change_level == ChangeLevel::Added)
+        .map(|x| key(&x.new_lines))
+        .filter(|x| !x.is_empty())
+        .collect::<HashSet<_>>();
+    for diff in diffs.iter_mut() {
+        let moved = match diff.change_level {
+            ChangeLevel::Removed => added.contains(&key(&diff.old_lines)),
+            ChangeLevel::Added => removed.contains(&key(&diff.new_lines)),
+            _ => false,
+        };
+        if moved {
+            diff.change_level = ChangeLevel::Moved;
+        }
+    }
+}
+
+/// classify hunk by what differs. comment is classified afterwards by language
+fn hunk_classification(old_lines: &[String], new_lines: &[String]) -> HunkClassification {
+    let old = old_lines.concat();
+    let new = new_lines.concat();
+    let without_whitespace = |s: &str| s.chars().filter(|x| !x.is_whitespace()).collect::<String>();
+    let without_eol = |lines: &[String]| {
+        lines
+            .iter()
+            .map(|x| x.trim_end_matches(['\r', '\n']).to_owned())
+            .collect::<Vec<_>>()
+    };
+    HunkClassification {
+        whitespace_only: without_whitespace(&old) == without_whitespace(&new),
+        case_only: old.to_lowercase() == new.to_lowercase(),
+        comment_only: false,
+        eol_only: old_lines.len() == new_lines.len()
+            && without_eol(old_lines) == without_eol(new_lines),
+    }
+}
+
+/// flag hunks whose differences are only in comment or blank lines
+/// comment syntax is decided by file extension and lines are judged by their beginning
+pub fn classify_comment_hunks(diffs: &mut [LinesDiff], filepath: &str) {
+    let (line_prefixes, block_delimiters) = match comment_syntax(&file_extension(filepath)) {
+        Some(x) => x,
+        None => return,
+    };
+    let is_code = |line: &&String| {
+        let line = line.trim();
+        !(line.is_empty()
+            || line_prefixes.iter().any(|x| line.starts_with(x))
+            || block_delimiters.iter().any(|(start, end)| {
+                // lines inside block such as ` * ...` are judged by leading asterisk
+                line.starts_with(start)
+                    || line.ends_with(end)
+                    || (*start == "/*" && line.starts_with('*'))
+            }))
+    };
+    for diff in diffs.iter_mut().filter(|x| x.diff_kind != DiffTag::Equal) {
+        let old_code = diff.old_lines.iter().filter(is_code).map(|x| x.trim());
+        let new_code = diff.new_lines.iter().filter(is_code).map(|x| x.trim());
+        diff.classification.comment_only = old_code.eq(new_code);
+    }
+}
+
+/// line comment prefixes and block comment delimiters
+type CommentSyntax = (
+    &'static [&'static str],
+    &'static [(&'static str, &'static str)],
+);
+
+/// comment syntax of language
+fn comment_syntax(extension: &str) -> Option<CommentSyntax> {
+    const C_BLOCK: &[(&str, &str)] = &[("/*", "*/")];
+    const XML_BLOCK: &[(&str, &str)] = &[("<!--", "-->")];
+    let ret: CommentSyntax = match extension {
+        "c" | "h" | "cc" | "cpp" | "hpp" | "cs" | "java" | "kt" | "scala" | "swift" | "go"
+        | "rs" | "js" | "mjs" | "cjs" | "jsx" | "ts" | "tsx" | "dart" | "php" => (&["//"], C_BLOCK),
+        "css" | "scss" | "less" => (&["//"], C_BLOCK),
+        "py" | "sh" | "bash" | "zsh" | "rb" | "pl" | "r" | "yaml" | "yml" | "toml" | "conf"
+        | "cfg" | "dockerfile" | "mk" => (&["#"], &[]),
+        "ini" | "properties" => (&["#", ";", "!"], &[]),
+        "sql" => (&["--"], C_BLOCK),
+        "lua" | "hs" => (&["--"], &[]),
+        "tex" | "erl" => (&["%"], &[]),
+        "lisp" | "clj" | "el" | "asm" => (&[";"], &[]),
+        "vim" => (&["\""], &[]),
+        "html" | "htm" | "xml" | "svg" | "xhtml" | "md" => (&[], XML_BLOCK),
+        "svelte" | "vue" => (&["//"], &[("<!--", "-->"), ("/*", "*/")]),
+        _ => return None,
+    };
+    Some(ret)
 }
 
 pub fn chars_diffs(lines_diffs: &Vec<LinesDiff>) -> Vec<CharsDiffLines> {
@@ -92,11 +758,91 @@ pub fn chars_diffs(lines_diffs: &Vec<LinesDiff>) -> Vec<CharsDiffLines> {
         .map(|x| {
             let old_str = x.old_lines.join("");
             let new_str = x.new_lines.join("");
+            if MAX_CHARS_DIFF_LENGTH < old_str.len() + new_str.len() {
+                return plain_chars_diff(x);
+            }
             chars_diff(x.diff_index, old_str.as_str(), new_str.as_str())
         })
         .collect()
 }
 
+/// pure chars diff grouped when both inputs are effectively single lines
+/// such as urls, hashes and long command lines
+/// none when either has multiple lines, they are equal or too long to compare
+pub fn single_line_chars_diff(old: &str, new: &str) -> Option<Vec<CharsGroup>> {
+    let old = old.trim_end_matches(['\n', '\r']);
+    let new = new.trim_end_matches(['\n', '\r']);
+    let is_single_line = |s: &str| !s.contains(['\n', '\r']);
+    if !is_single_line(old)
+        || !is_single_line(new)
+        || old == new
+        || MAX_CHARS_DIFF_LENGTH < old.len() + new.len()
+    {
+        return None;
+    }
+
+    let old_chars = old.chars().collect::<Vec<_>>();
+    let new_chars = new.chars().collect::<Vec<_>>();
+    // ranges in ops are on chars
+    let ops = TextDiff::configure().diff_chars(old, new).ops().to_vec();
+
+    let mut ret: Vec<CharsGroup> = vec![];
+    for (i, x) in ops.iter().enumerate() {
+        let old_str = old_chars[x.old_range()].iter().collect::<String>();
+        let new_str = new_chars[x.new_range()].iter().collect::<String>();
+        // equal chars between changes are grouped into them when short
+        let is_short_gap = x.tag() == DiffTag::Equal
+            && x.old_range().len() < MIN_EQUAL_CHARS_GROUP
+            && 0 < i
+            && i + 1 < ops.len();
+        match ret.last_mut() {
+            Some(last)
+                if last.diff_kind != DiffTag::Equal
+                    && (is_short_gap || x.tag() != DiffTag::Equal) =>
+            {
+                last.old_chars.push_str(&old_str);
+                last.new_chars.push_str(&new_str);
+                last.diff_kind = match (last.old_chars.is_empty(), last.new_chars.is_empty()) {
+                    (true, _) => DiffTag::Insert,
+                    (_, true) => DiffTag::Delete,
+                    _ => DiffTag::Replace,
+                };
+            }
+            _ => ret.push(CharsGroup {
+                diff_kind: x.tag(),
+                old_chars: old_str,
+                new_chars: new_str,
+            }),
+        }
+    }
+    Some(ret)
+}
+
+/// chars diff marking whole lines without comparing chars
+/// to avoid hang on giant hunks
+fn plain_chars_diff(x: &LinesDiff) -> CharsDiffLines {
+    let (old_kind, new_kind) = match x.diff_kind {
+        DiffTag::Equal => (DiffTag::Equal, DiffTag::Equal),
+        _ => (DiffTag::Delete, DiffTag::Insert),
+    };
+    let plain_lines = |lines: &Vec<String>, diff_kind: DiffTag| {
+        lines
+            .iter()
+            .map(|line| {
+                vec![CharsDiff {
+                    diff_kind,
+                    chars: line.to_owned(),
+                }]
+            })
+            .collect()
+    };
+    CharsDiffLines {
+        diff_index: x.diff_index,
+        old_lines: plain_lines(&x.old_lines, old_kind),
+        new_lines: plain_lines(&x.new_lines, new_kind),
+    }
+}
+
 fn chars_diff(diff_index: usize, old_str: &str, new_str: &str) -> CharsDiffLines {
     let mut old_lines: Vec<Vec<CharsDiff>> = vec![];
     let mut new_lines: Vec<Vec<CharsDiff>> = vec![];
@@ -290,18 +1036,69 @@ fn chars_diff(diff_index: usize, old_str: &str, new_str: &str) -> CharsDiffLines
 
 /// digest comparison around files
 pub fn file_digest_diff(filename: &str, old_dir: &str, new_dir: &str) -> Result<bool, String> {
-    let old_filepath = Path::new(old_dir).join(filename);
-    let new_filepath = Path::new(new_dir).join(filename);
+    let old_filepath = os_path(old_dir).join(os_path(filename));
+    let new_filepath = os_path(new_dir).join(os_path(filename));
     filepaths_digest_diff(&old_filepath, &new_filepath)
 }
 
 /// digest comparison around directories
 pub fn dir_digest_diff(dirname: &str, old_dir: &str, new_dir: &str) -> Result<bool, String> {
-    let old_dirpath = Path::new(old_dir).join(dirname);
-    let new_dirpath = Path::new(new_dir).join(dirname);
+    let old_dirpath = os_path(old_dir).join(os_path(dirname));
+    let new_dirpath = os_path(new_dir).join(os_path(dirname));
     dirpaths_digest_diff(&old_dirpath, &new_dirpath)
 }
 
+/// compare directories recursively entry by entry
+/// paths are relative to the directories and separated by `/`
+/// entries compared and total are reported to `on_progress`
+pub fn dir_tree_diffs(
+    old_dir: &str,
+    new_dir: &str,
+    mut on_progress: impl FnMut(u64, u64),
+) -> Result<Vec<TreeEntryDiff>, String> {
+    let mut old_files = vec![];
+    let mut new_files = vec![];
+    collect_relative_filepaths(&os_path(old_dir), "", &mut old_files)?;
+    collect_relative_filepaths(&os_path(new_dir), "", &mut new_files)?;
+    old_files.sort();
+    new_files.sort();
+
+    let mut paths = old_files
+        .iter()
+        .chain(new_files.iter())
+        .cloned()
+        .collect::<Vec<_>>();
+    paths.sort();
+    paths.dedup();
+
+    let total = paths.len() as u64;
+    paths
+        .into_iter()
+        .enumerate()
+        .map(|(i, path)| {
+            on_progress(i as u64, total);
+            let status = match (
+                old_files.binary_search(&path).is_ok(),
+                new_files.binary_search(&path).is_ok(),
+            ) {
+                (true, true) => {
+                    if filepaths_digest_diff(
+                        &os_path(old_dir).join(os_path(&path)),
+                        &os_path(new_dir).join(os_path(&path)),
+                    )? {
+                        EntryStatus::Equal
+                    } else {
+                        EntryStatus::Modified
+                    }
+                }
+                (true, false) => EntryStatus::OldOnly,
+                _ => EntryStatus::NewOnly,
+            };
+            Ok(TreeEntryDiff { path, status })
+        })
+        .collect()
+}
+
 /// decide comparison mode
 pub fn binary_comparison_only(filepath: &str) -> bool {
     match validate_filepath(filepath) {
@@ -328,9 +1125,15 @@ pub fn startup_compare_set_item(filepath: &Option<OsString>) -> CompareSetItem {
 }
 
 /// digest comparison around file paths
-fn filepaths_digest_diff(old_filepath: &PathBuf, new_filepath: &PathBuf) -> Result<bool, String> {
-    let old_metadata = metadata(old_filepath).expect("Failed to get file metadata on old");
-    let new_metadata = metadata(new_filepath).expect("Failed to get file metadata on new");
+pub fn filepaths_digest_diff(
+    old_filepath: &PathBuf,
+    new_filepath: &PathBuf,
+) -> Result<bool, String> {
+    let read_error = |filepath: &PathBuf, err: std::io::Error| {
+        format!("Failed to read {} ({})", filepath.display(), err)
+    };
+    let old_metadata = metadata(old_filepath).map_err(|err| read_error(old_filepath, err))?;
+    let new_metadata = metadata(new_filepath).map_err(|err| read_error(new_filepath, err))?;
 
     // compare file size
     if old_metadata.len() != new_metadata.len() {
@@ -338,8 +1141,8 @@ fn filepaths_digest_diff(old_filepath: &PathBuf, new_filepath: &PathBuf) -> Resu
     }
 
     // compare file bytes
-    let old_file = File::open(old_filepath).expect("Failed to open file on old");
-    let new_file = File::open(new_filepath).expect("Failed to open file on new");
+    let old_file = File::open(old_filepath).map_err(|err| read_error(old_filepath, err))?;
+    let new_file = File::open(new_filepath).map_err(|err| read_error(new_filepath, err))?;
 
     let mut old_reader = BufReader::new(old_file);
     let mut new_reader = BufReader::new(new_file);
@@ -352,10 +1155,10 @@ fn filepaths_digest_diff(old_filepath: &PathBuf, new_filepath: &PathBuf) -> Resu
     loop {
         let old_bytes = old_reader
             .read(&mut old_buffer)
-            .expect("Failed to read buffer on old");
+            .map_err(|err| read_error(old_filepath, err))?;
         let new_bytes = new_reader
             .read(&mut new_buffer)
-            .expect("Failed to read buffer on new");
+            .map_err(|err| read_error(new_filepath, err))?;
 
         if old_bytes != new_bytes {
             return Ok(false);
@@ -374,6 +1177,34 @@ fn filepaths_digest_diff(old_filepath: &PathBuf, new_filepath: &PathBuf) -> Resu
     Ok(true)
 }
 
+/// collect file paths under directory recursively
+pub fn collect_relative_filepaths(
+    root: &Path,
+    relative_dir: &str,
+    filepaths: &mut Vec<String>,
+) -> Result<(), String> {
+    let dirpath = root.join(os_path(relative_dir));
+    let entries = read_dir(&dirpath)
+        .map_err(|err| format!("Invalid path: {} ({})", dirpath.display(), err))?;
+
+    for entry in entries {
+        let entry = entry.map_err(|err| format!("Failed to get entry ({})", err))?;
+        let name = os_str_string(&entry.file_name());
+        let path = entry.path();
+        let relative_path = if relative_dir.is_empty() {
+            name
+        } else {
+            format!("{}/{}", relative_dir, name)
+        };
+        if path.is_dir() {
+            collect_relative_filepaths(root, &relative_path, filepaths)?;
+        } else if path.is_file() {
+            filepaths.push(relative_path);
+        }
+    }
+    Ok(())
+}
+
 /// digest comparison around directory paths
 fn dirpaths_digest_diff(old_dirpath: &PathBuf, new_dirpath: &PathBuf) -> Result<bool, String> {
     let mut old_files = vec![];
@@ -418,8 +1249,7 @@ fn dirpaths_digest_diff(old_dirpath: &PathBuf, new_dirpath: &PathBuf) -> Result<
         if old_file.file_name() != new_file.file_name() {
             return Ok(false);
         }
-        if !filepaths_digest_diff(old_file, new_file).expect("Failed to compare files on read dir")
-        {
+        if !filepaths_digest_diff(old_file, new_file)? {
             return Ok(false);
         }
     }
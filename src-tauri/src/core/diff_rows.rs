@@ -0,0 +1,368 @@
+use similar::DiffTag;
+
+use super::types::{
+    DiffRow, DiffRowKind, DiffSide, LineMapSegment, LinesDiff, Minimap, PaneLine, PaneLines,
+};
+
+/// max buckets in minimap. lines per bucket grows on large diffs
+const MINIMAP_MAX_BUCKETS: usize = 256;
+/// rows of pane between checkpoints of pane index
+const PANE_INDEX_STRIDE: usize = 1024;
+
+/// line number counter on a side
+/// counts up only after line ending so that segments of long line share number
+struct LineCounter(usize);
+
+impl LineCounter {
+    fn next(&mut self, line: &str) -> usize {
+        let ret = self.0;
+        if line.ends_with('\n') || line.ends_with('\r') {
+            self.0 += 1;
+        }
+        ret
+    }
+}
+
+/// index of rows in pane so that lines in range are found without walking from top
+#[derive(Default)]
+pub struct PaneIndex {
+    /// 1-based line numbers where hunks start on old and new sides
+    hunk_lines: Vec<(usize, usize)>,
+    old: SideIndex,
+    new: SideIndex,
+}
+
+/// rows on a side of pane index
+#[derive(Default)]
+struct SideIndex {
+    total_rows: usize,
+    /// position of every `PANE_INDEX_STRIDE`th row
+    checkpoints: Vec<RowPosition>,
+}
+
+/// row position by hunk, offset in it, line number and segment of long line
+#[derive(Clone, Copy)]
+struct RowPosition {
+    diff_position: usize,
+    offset: usize,
+    line_number: usize,
+    segment: usize,
+}
+
+/// side-by-side rows where old and new lines in hunk are aligned
+pub fn split_rows(diffs: &[LinesDiff]) -> Vec<DiffRow> {
+    let mut old_counter = LineCounter(1);
+    let mut new_counter = LineCounter(1);
+    let (mut old_segment, mut new_segment) = (0, 0);
+
+    let mut ret = vec![];
+    for diff in diffs {
+        let rows_count = diff.old_lines.len().max(diff.new_lines.len());
+        for i in 0..rows_count {
+            let old_text = diff.old_lines.get(i);
+            let new_text = diff.new_lines.get(i);
+            let kind = match (diff.diff_kind, old_text, new_text) {
+                (DiffTag::Equal, _, _) => DiffRowKind::Equal,
+                (_, Some(_), Some(_)) => DiffRowKind::Changed,
+                (_, Some(_), None) => DiffRowKind::Removed,
+                _ => DiffRowKind::Added,
+            };
+            ret.push(DiffRow {
+                diff_index: diff.diff_index,
+                kind,
+                old_line_number: old_text.map(|x| old_counter.next(x)),
+                new_line_number: new_text.map(|x| new_counter.next(x)),
+                old_continuation: old_text.is_some() && 0 < old_segment,
+                new_continuation: new_text.is_some() && 0 < new_segment,
+                old_text: old_text.cloned(),
+                new_text: new_text.cloned(),
+            });
+            if let Some(x) = old_text {
+                old_segment = next_segment(old_segment, x);
+            }
+            if let Some(x) = new_text {
+                new_segment = next_segment(new_segment, x);
+            }
+        }
+    }
+    ret
+}
+
+/// single stream rows where removed lines are followed by added lines in hunk
+pub fn inline_rows(diffs: &[LinesDiff]) -> Vec<DiffRow> {
+    let mut old_counter = LineCounter(1);
+    let mut new_counter = LineCounter(1);
+    let (mut old_segment, mut new_segment) = (0, 0);
+
+    let mut ret = vec![];
+    for diff in diffs {
+        if diff.diff_kind == DiffTag::Equal {
+            for line in diff.old_lines.iter() {
+                ret.push(DiffRow {
+                    diff_index: diff.diff_index,
+                    kind: DiffRowKind::Equal,
+                    old_line_number: Some(old_counter.next(line)),
+                    new_line_number: Some(new_counter.next(line)),
+                    old_continuation: 0 < old_segment,
+                    new_continuation: 0 < new_segment,
+                    old_text: Some(line.to_owned()),
+                    new_text: Some(line.to_owned()),
+                });
+                old_segment = next_segment(old_segment, line);
+                new_segment = next_segment(new_segment, line);
+            }
+            continue;
+        }
+        for line in diff.old_lines.iter() {
+            ret.push(DiffRow {
+                diff_index: diff.diff_index,
+                kind: DiffRowKind::Removed,
+                old_line_number: Some(old_counter.next(line)),
+                new_line_number: None,
+                old_continuation: 0 < old_segment,
+                new_continuation: false,
+                old_text: Some(line.to_owned()),
+                new_text: None,
+            });
+            old_segment = next_segment(old_segment, line);
+        }
+        for line in diff.new_lines.iter() {
+            ret.push(DiffRow {
+                diff_index: diff.diff_index,
+                kind: DiffRowKind::Added,
+                old_line_number: None,
+                new_line_number: Some(new_counter.next(line)),
+                old_continuation: false,
+                new_continuation: 0 < new_segment,
+                old_text: None,
+                new_text: Some(line.to_owned()),
+            });
+            new_segment = next_segment(new_segment, line);
+        }
+    }
+    ret
+}
+
+/// build index of rows in pane on both sides
+pub fn pane_index(diffs: &[LinesDiff]) -> PaneIndex {
+    let mut ret = PaneIndex::default();
+    let mut old_counter = LineCounter(1);
+    let mut new_counter = LineCounter(1);
+    let (mut old_segment, mut new_segment) = (0, 0);
+    for (diff_position, diff) in diffs.iter().enumerate() {
+        ret.hunk_lines.push((old_counter.0, new_counter.0));
+        for (index, counter, segment, lines) in [
+            (
+                &mut ret.old,
+                &mut old_counter,
+                &mut old_segment,
+                &diff.old_lines,
+            ),
+            (
+                &mut ret.new,
+                &mut new_counter,
+                &mut new_segment,
+                &diff.new_lines,
+            ),
+        ] {
+            for (offset, line) in lines.iter().enumerate() {
+                if index.total_rows % PANE_INDEX_STRIDE == 0 {
+                    index.checkpoints.push(RowPosition {
+                        diff_position,
+                        offset,
+                        line_number: counter.0,
+                        segment: *segment,
+                    });
+                }
+                counter.next(line);
+                *segment = next_segment(*segment, line);
+                index.total_rows += 1;
+            }
+        }
+    }
+    ret
+}
+
+/// lines of pane on side in range for virtual scrolling of huge text
+/// range is counted in rows of pane so that segments of long line are rows of their own
+/// lines are walked only from checkpoint of index just before range
+pub fn pane_lines(
+    diffs: &[LinesDiff],
+    index: &PaneIndex,
+    side: DiffSide,
+    start: usize,
+    count: usize,
+) -> PaneLines {
+    let side_index = match side {
+        DiffSide::Old => &index.old,
+        DiffSide::New => &index.new,
+    };
+    let total_lines = side_index.total_rows;
+    let end = start.saturating_add(count).min(total_lines);
+
+    let mut lines = vec![];
+    if let Some(checkpoint) = side_index.checkpoints.get(start / PANE_INDEX_STRIDE) {
+        let mut position = *checkpoint;
+        let mut counter = LineCounter(checkpoint.line_number);
+        let mut row = start / PANE_INDEX_STRIDE * PANE_INDEX_STRIDE;
+        while row < end && position.diff_position < diffs.len() {
+            let diff = &diffs[position.diff_position];
+            let Some(line) = side_lines(diff, side).get(position.offset) else {
+                position.diff_position += 1;
+                position.offset = 0;
+                continue;
+            };
+            let line_number = counter.next(line);
+            if start <= row {
+                lines.push(PaneLine {
+                    id: pane_line_id(
+                        index,
+                        diff,
+                        position.diff_position,
+                        side,
+                        line_number,
+                        position.segment,
+                    ),
+                    line_number,
+                    continuation: 0 < position.segment,
+                    text: line.to_owned(),
+                    diff_index: diff.diff_index,
+                    changed: diff.diff_kind != DiffTag::Equal,
+                });
+            }
+            position.offset += 1;
+            position.segment = next_segment(position.segment, line);
+            row += 1;
+        }
+    }
+    PaneLines {
+        start,
+        total_lines,
+        lines,
+    }
+}
+
+/// id of line in pane by line numbers on both sides and segment of long line such as `12:14:0`
+/// line number on side where line is absent is empty so that lines of equal hunk share id
+fn pane_line_id(
+    index: &PaneIndex,
+    diff: &LinesDiff,
+    diff_position: usize,
+    side: DiffSide,
+    line_number: usize,
+    segment: usize,
+) -> String {
+    let (old_start, new_start) = index.hunk_lines[diff_position];
+    let (old_line, new_line) = match (side, diff.diff_kind) {
+        (DiffSide::Old, DiffTag::Equal) => (line_number, new_start + line_number - old_start),
+        (DiffSide::New, DiffTag::Equal) => (old_start + line_number - new_start, line_number),
+        (DiffSide::Old, _) => return format!("{}::{}", line_number, segment),
+        (DiffSide::New, _) => return format!(":{}:{}", line_number, segment),
+    };
+    format!("{}:{}:{}", old_line, new_line, segment)
+}
+
+/// segment number of row next to line, which is reset after line ending
+fn next_segment(segment: usize, line: &str) -> usize {
+    if line.ends_with('\n') || line.ends_with('\r') {
+        0
+    } else {
+        segment + 1
+    }
+}
+
+/// row in pane on side where line starts, or the last row when line is beyond side
+/// so that position saved before files changed is restored as near as possible
+pub fn line_row(diffs: &[LinesDiff], side: DiffSide, line_number: usize) -> Option<usize> {
+    let mut counter = LineCounter(1);
+    let mut position = 0;
+    for diff in diffs {
+        for line in side_lines(diff, side) {
+            if line_number <= counter.next(line) {
+                return Some(position);
+            }
+            position += 1;
+        }
+    }
+    position.checked_sub(1)
+}
+
+/// old-line to new-line alignment map per hunk
+/// line ranges are 1-based and end-exclusive so that empty side has the same start and end
+/// lines in equal hunks correspond one by one, and lines in other hunks correspond as ranges
+pub fn line_map(diffs: &[LinesDiff]) -> Vec<LineMapSegment> {
+    let mut old_counter = LineCounter(1);
+    let mut new_counter = LineCounter(1);
+
+    diffs
+        .iter()
+        .map(|diff| {
+            let old_start = old_counter.0;
+            let new_start = new_counter.0;
+            LineMapSegment {
+                diff_index: diff.diff_index,
+                diff_kind: diff.diff_kind,
+                old_start,
+                old_end: counted_end(&mut old_counter, &diff.old_lines),
+                new_start,
+                new_end: counted_end(&mut new_counter, &diff.new_lines),
+            }
+        })
+        .collect()
+}
+
+/// lines of hunk on side
+fn side_lines(diff: &LinesDiff, side: DiffSide) -> &[String] {
+    match side {
+        DiffSide::Old => &diff.old_lines,
+        DiffSide::New => &diff.new_lines,
+    }
+}
+
+/// count lines up and get end-exclusive line number
+/// last line without line ending at end of file is included
+fn counted_end(counter: &mut LineCounter, lines: &[String]) -> usize {
+    for line in lines {
+        counter.next(line);
+    }
+    match lines.last() {
+        Some(x) if !x.ends_with('\n') && !x.ends_with('\r') => counter.0 + 1,
+        _ => counter.0,
+    }
+}
+
+/// change density per bucket of split rows for overview minimap and scrollbar heatmap
+/// each value is ratio of changed rows in bucket between 0.0 and 1.0
+pub fn minimap(diffs: &[LinesDiff]) -> Minimap {
+    let rows_count = diffs
+        .iter()
+        .map(|x| x.old_lines.len().max(x.new_lines.len()))
+        .sum::<usize>();
+    let lines_per_bucket = rows_count.div_ceil(MINIMAP_MAX_BUCKETS).max(1);
+
+    let mut changed_rows = vec![0_usize; rows_count.div_ceil(lines_per_bucket)];
+    let mut row = 0;
+    for diff in diffs {
+        let rows = diff.old_lines.len().max(diff.new_lines.len());
+        if diff.diff_kind != DiffTag::Equal {
+            for i in row..row + rows {
+                changed_rows[i / lines_per_bucket] += 1;
+            }
+        }
+        row += rows;
+    }
+
+    let densities = changed_rows
+        .iter()
+        .enumerate()
+        .map(|(i, changed)| {
+            // last bucket may have fewer rows
+            let bucket_rows = lines_per_bucket.min(rows_count - i * lines_per_bucket);
+            *changed as f32 / bucket_rows as f32
+        })
+        .collect();
+    Minimap {
+        lines_per_bucket,
+        densities,
+    }
+}
@@ -0,0 +1,344 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs::metadata;
+use std::path::Path;
+use std::sync::Mutex;
+
+use super::diff::dir_tree_diffs;
+use super::file::file_extension;
+use super::path_remap::{remapped_equal, remapping_applies};
+use super::progress::Progress;
+use super::settings;
+use super::store;
+use super::types::{
+    CachedDirDiff, DirDiffCache, DirDiffFilter, DirDiffPage, DirEntryChange, DirEntryDiff,
+    DirRollup, DirRollupRank, EntryStatus, OperationKind, ProgressUnit,
+};
+use super::watch::{changed_paths, file_stamps};
+
+/// entries returned at once when limit is not specified
+const DEFAULT_PAGE_LIMIT: usize = 200;
+/// file name to persist folder comparison results across launches
+const DIR_DIFF_CACHE_STORE_FILENAME: &str = "dir_diff_cache.json";
+/// max folder comparisons persisted. least recent ones are dropped
+const MAX_CACHED_DIR_DIFFS: usize = 10;
+
+/// app state to keep folder comparison results
+/// so that filters and pages are applied without comparing directories again
+#[derive(Default)]
+pub struct DirDiffResults(Mutex<HashMap<(String, String), Vec<DirEntryDiff>>>);
+
+impl DirDiffResults {
+    /// get page of comparison result passing filter
+    /// directories are compared at the first call and also when `refresh` is true
+    /// result of last launch is restored at the first call instead, with changed entries stale
+    #[allow(clippy::too_many_arguments)]
+    pub fn page(
+        &self,
+        app_handle: &tauri::AppHandle,
+        old_dir: &str,
+        new_dir: &str,
+        filter: &DirDiffFilter,
+        offset: usize,
+        limit: Option<usize>,
+        refresh: bool,
+    ) -> Result<DirDiffPage, String> {
+        let key = (old_dir.to_owned(), new_dir.to_owned());
+        if !refresh && !self.0.lock().unwrap().contains_key(&key) {
+            if let Some(entries) = restored_entries(app_handle, old_dir, new_dir) {
+                self.0.lock().unwrap().insert(key.clone(), entries);
+            }
+        }
+        if refresh || !self.0.lock().unwrap().contains_key(&key) {
+            // compared out of lock because it may take long
+            let entries = compared_entries(app_handle, old_dir, new_dir)?;
+            self.0.lock().unwrap().insert(key.clone(), entries);
+        }
+
+        let results = self.0.lock().unwrap();
+        let filtered = results[&key]
+            .iter()
+            .filter(|x| passes_filter(x, filter))
+            .collect::<Vec<_>>();
+        let entries = filtered
+            .iter()
+            .skip(offset)
+            .take(limit.unwrap_or(DEFAULT_PAGE_LIMIT))
+            .map(|x| (*x).clone())
+            .collect();
+        Ok(DirDiffPage {
+            entries,
+            total_count: filtered.len(),
+            offset,
+            stale_count: results[&key].iter().filter(|x| x.stale).count(),
+        })
+    }
+
+    /// get all entries of comparison result. directories are compared unless kept
+    /// result with stale entries is not used because it may be wrong
+    pub fn entries(
+        &self,
+        app_handle: &tauri::AppHandle,
+        old_dir: &str,
+        new_dir: &str,
+    ) -> Result<Vec<DirEntryDiff>, String> {
+        let key = (old_dir.to_owned(), new_dir.to_owned());
+        if let Some(x) = self.0.lock().unwrap().get(&key) {
+            if x.iter().all(|x| !x.stale) {
+                return Ok(x.clone());
+            }
+        }
+        let entries = compared_entries(app_handle, old_dir, new_dir)?;
+        self.0.lock().unwrap().insert(key, entries.clone());
+        Ok(entries)
+    }
+
+    /// get directories ranked by changes under them so that diverged subtrees are found
+    /// directories deeper than `max_depth` are rolled up into their ancestors only
+    pub fn rollups(
+        &self,
+        app_handle: &tauri::AppHandle,
+        old_dir: &str,
+        new_dir: &str,
+        rank: DirRollupRank,
+        max_depth: Option<usize>,
+    ) -> Result<Vec<DirRollup>, String> {
+        let entries = self.entries(app_handle, old_dir, new_dir)?;
+        Ok(dir_rollups(&entries, rank, max_depth))
+    }
+
+    /// apply incremental changes to comparison result kept
+    pub fn apply_changes(&self, old_dir: &str, new_dir: &str, changes: &[DirEntryChange]) {
+        let mut results = self.0.lock().unwrap();
+        let Some(entries) = results.get_mut(&(old_dir.to_owned(), new_dir.to_owned())) else {
+            return;
+        };
+        for change in changes {
+            let pos = entries.binary_search_by(|x| x.path.as_str().cmp(&change.path));
+            match (pos, &change.entry) {
+                (Ok(i), Some(entry)) => entries[i] = entry.clone(),
+                (Ok(i), None) => {
+                    entries.remove(i);
+                }
+                (Err(i), Some(entry)) => entries.insert(i, entry.clone()),
+                (Err(_), None) => (),
+            }
+        }
+    }
+
+    /// forget comparison result
+    pub fn release(&self, old_dir: &str, new_dir: &str) {
+        self.0
+            .lock()
+            .unwrap()
+            .remove(&(old_dir.to_owned(), new_dir.to_owned()));
+    }
+}
+
+/// compare directories and persist result to be restored at next launch
+/// failure to persist does not fail comparison
+fn compared_entries(
+    app_handle: &tauri::AppHandle,
+    old_dir: &str,
+    new_dir: &str,
+) -> Result<Vec<DirEntryDiff>, String> {
+    // stamped before comparison so that files changed meanwhile are stale at next launch
+    let stamps = file_stamps(old_dir).and_then(|x| Ok((x, file_stamps(new_dir)?)));
+    let mut progress = Progress::start(
+        app_handle,
+        OperationKind::Scan,
+        old_dir,
+        ProgressUnit::Entries,
+    );
+    let entries = dir_entry_diffs(old_dir, new_dir, |processed, total| {
+        progress.update(processed, Some(total))
+    });
+    let mut entries = progress.finish(entries)?;
+    // files differing only in remapped paths in them are equal
+    let path_remapping = settings::path_remapping(app_handle);
+    if remapping_applies(&path_remapping) {
+        for entry in entries
+            .iter_mut()
+            .filter(|x| x.status == EntryStatus::Modified)
+        {
+            if remapped_equal(
+                &Path::new(old_dir).join(&entry.path),
+                &Path::new(new_dir).join(&entry.path),
+                &path_remapping,
+            ) {
+                entry.status = EntryStatus::Equal;
+            }
+        }
+    }
+    let cached = stamps.and_then(|(old_stamps, new_stamps)| {
+        let mut cache: DirDiffCache = store::load(app_handle, DIR_DIFF_CACHE_STORE_FILENAME)?;
+        cache
+            .items
+            .retain(|x| x.old_dir != old_dir || x.new_dir != new_dir);
+        cache.items.insert(
+            0,
+            CachedDirDiff {
+                old_dir: old_dir.to_owned(),
+                new_dir: new_dir.to_owned(),
+                old_stamps,
+                new_stamps,
+                entries: entries.clone(),
+            },
+        );
+        cache.items.truncate(MAX_CACHED_DIR_DIFFS);
+        store::save(app_handle, DIR_DIFF_CACHE_STORE_FILENAME, &cache)
+    });
+    if let Err(err) = cached {
+        tracing::warn!("Failed to cache folder comparison result ({})", err);
+    }
+    Ok(entries)
+}
+
+/// folder comparison result persisted at last launch
+/// entries of files changed since then are marked stale with status guessed by existence
+fn restored_entries(
+    app_handle: &tauri::AppHandle,
+    old_dir: &str,
+    new_dir: &str,
+) -> Option<Vec<DirEntryDiff>> {
+    let cache: DirDiffCache = store::load(app_handle, DIR_DIFF_CACHE_STORE_FILENAME)
+        .inspect_err(|err| tracing::warn!("Failed to load folder comparison cache ({})", err))
+        .ok()?;
+    let cached = cache
+        .items
+        .into_iter()
+        .find(|x| x.old_dir == old_dir && x.new_dir == new_dir)?;
+    let old_stamps = file_stamps(old_dir).ok()?;
+    let new_stamps = file_stamps(new_dir).ok()?;
+
+    let mut entries = cached
+        .entries
+        .into_iter()
+        .map(|x| (x.path.clone(), x))
+        .collect::<BTreeMap<_, _>>();
+    let mut changed = changed_paths(&cached.old_stamps, &old_stamps);
+    changed.extend(changed_paths(&cached.new_stamps, &new_stamps));
+    for path in changed {
+        let old_size = old_stamps.get(&path).map(|x| x.0);
+        let new_size = new_stamps.get(&path).map(|x| x.0);
+        let status = match (old_size, new_size) {
+            (Some(_), Some(_)) => match entries.get(&path).map(|x| x.status) {
+                Some(EntryStatus::Equal) => EntryStatus::Equal,
+                _ => EntryStatus::Modified,
+            },
+            (Some(_), None) => EntryStatus::OldOnly,
+            (None, Some(_)) => EntryStatus::NewOnly,
+            (None, None) => {
+                entries.remove(&path);
+                continue;
+            }
+        };
+        entries.insert(
+            path.clone(),
+            DirEntryDiff {
+                path,
+                status,
+                old_size,
+                new_size,
+                stale: true,
+            },
+        );
+    }
+    Some(entries.into_values().collect())
+}
+
+/// compare directories recursively with file sizes
+/// entries are sorted by path
+fn dir_entry_diffs(
+    old_dir: &str,
+    new_dir: &str,
+    on_progress: impl FnMut(u64, u64),
+) -> Result<Vec<DirEntryDiff>, String> {
+    let file_size =
+        |dir: &str, path: &str| metadata(Path::new(dir).join(path)).ok().map(|x| x.len());
+    let ret = dir_tree_diffs(old_dir, new_dir, on_progress)?
+        .into_iter()
+        .map(|x| DirEntryDiff {
+            old_size: file_size(old_dir, &x.path),
+            new_size: file_size(new_dir, &x.path),
+            path: x.path,
+            status: x.status,
+            stale: false,
+        })
+        .collect();
+    Ok(ret)
+}
+
+/// check if entry matches all filter conditions
+fn passes_filter(entry: &DirEntryDiff, filter: &DirDiffFilter) -> bool {
+    if !filter.statuses.is_empty() && !filter.statuses.contains(&entry.status) {
+        return false;
+    }
+
+    let extension = file_extension(&entry.path);
+    let matches_extension = |extensions: &Vec<String>| {
+        extensions
+            .iter()
+            .any(|x| x.trim_start_matches('.').to_lowercase() == extension)
+    };
+    if !filter.include_extensions.is_empty() && !matches_extension(&filter.include_extensions) {
+        return false;
+    }
+    if matches_extension(&filter.exclude_extensions) {
+        return false;
+    }
+
+    let size = entry.old_size.max(entry.new_size).unwrap_or_default();
+    if filter.min_size.is_some_and(|x| size < x) || filter.max_size.is_some_and(|x| x < size) {
+        return false;
+    }
+    true
+}
+
+/// aggregate changed entries into every ancestor directory and sort by rank
+/// root directory is not included because it has all
+fn dir_rollups(
+    entries: &[DirEntryDiff],
+    rank: DirRollupRank,
+    max_depth: Option<usize>,
+) -> Vec<DirRollup> {
+    let mut rollups: BTreeMap<String, DirRollup> = BTreeMap::new();
+    for entry in entries.iter().filter(|x| x.status != EntryStatus::Equal) {
+        let components = entry.path.split('/').collect::<Vec<_>>();
+        let depth = (components.len() - 1).min(max_depth.unwrap_or(usize::MAX));
+        for i in 1..=depth {
+            let path = components[..i].join("/");
+            let rollup = rollups.entry(path.clone()).or_insert(DirRollup {
+                path,
+                changed_files: 0,
+                modified_files: 0,
+                old_only_files: 0,
+                new_only_files: 0,
+                changed_bytes: 0,
+            });
+            rollup.changed_files += 1;
+            match entry.status {
+                EntryStatus::Modified => rollup.modified_files += 1,
+                EntryStatus::OldOnly => rollup.old_only_files += 1,
+                EntryStatus::NewOnly => rollup.new_only_files += 1,
+                EntryStatus::Equal => (),
+            }
+            rollup.changed_bytes += entry.old_size.max(entry.new_size).unwrap_or_default();
+        }
+    }
+
+    let mut ret = rollups.into_values().collect::<Vec<_>>();
+    ret.sort_by(|a, b| {
+        let (a_rank, b_rank) = match rank {
+            DirRollupRank::ChangedFiles => (
+                (a.changed_files as u64, a.changed_bytes),
+                (b.changed_files as u64, b.changed_bytes),
+            ),
+            DirRollupRank::ChangedBytes => (
+                (a.changed_bytes, a.changed_files as u64),
+                (b.changed_bytes, b.changed_files as u64),
+            ),
+        };
+        b_rank.cmp(&a_rank).then_with(|| a.path.cmp(&b.path))
+    });
+    ret
+}
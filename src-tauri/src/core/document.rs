@@ -0,0 +1,252 @@
+use std::fs;
+use std::io::{Cursor, Read};
+
+use encoding_rs::{Encoding, WINDOWS_1252};
+use zip::ZipArchive;
+
+use super::file::file_extension;
+use super::os_path::os_path;
+use super::protected::xml_unescape;
+use super::types::ReadContent;
+
+/// file extensions of opendocument text
+pub const ODT_EXTENSIONS: [&str; 2] = ["odt", "ott"];
+/// head of rich text
+pub const RTF_SIGNATURE: &[u8] = b"{\\rtf";
+
+/// rtf destinations whose content is not document text
+const RTF_SKIPPED_DESTINATIONS: [&str; 11] = [
+    "fonttbl",
+    "colortbl",
+    "stylesheet",
+    "info",
+    "pict",
+    "object",
+    "listtable",
+    "listoverridetable",
+    "rsidtbl",
+    "generator",
+    "themedata",
+];
+
+/// read text in opendocument text or rich text document
+/// paragraphs are put on lines so that documents are compared as text
+pub fn document_content(filepath: &str) -> Result<ReadContent, String> {
+    let bytes = fs::read(os_path(filepath))
+        .map_err(|err| format!("Failed to read {} ({})", filepath, err))?;
+    let (charset, content) = if bytes.starts_with(RTF_SIGNATURE) {
+        ("(rtf)", rtf_text(&bytes))
+    } else if ODT_EXTENSIONS.contains(&file_extension(filepath).as_str()) {
+        ("(odt)", odt_text(bytes)?)
+    } else {
+        return Err(format!("Not supported document: {}", filepath));
+    };
+    Ok(ReadContent {
+        charset: charset.to_owned(),
+        content,
+        ..Default::default()
+    })
+}
+
+/// text in content.xml of opendocument
+/// headings and paragraphs are put on lines
+fn odt_text(bytes: Vec<u8>) -> Result<String, String> {
+    let mut archive = ZipArchive::new(Cursor::new(bytes)).map_err(|err| err.to_string())?;
+    let mut xml = String::new();
+    archive
+        .by_name("content.xml")
+        .map_err(|err| err.to_string())?
+        .read_to_string(&mut xml)
+        .map_err(|err| err.to_string())?;
+    let body = xml.split("<office:body>").nth(1).unwrap_or(&xml);
+
+    let mut ret = String::new();
+    let mut rest = body;
+    while let Some(start) = rest.find('<') {
+        ret.push_str(&xml_unescape(&rest[..start]));
+        let end = rest[start..]
+            .find('>')
+            .map_or(rest.len(), |x| start + x + 1);
+        let tag = &rest[start..end];
+        let name = tag
+            .trim_start_matches(['<', '/'])
+            .split([' ', '/', '>'])
+            .next()
+            .unwrap_or_default();
+        match name {
+            "text:p" | "text:h" if tag.starts_with("</") || tag.ends_with("/>") => ret.push('\n'),
+            "text:line-break" => ret.push('\n'),
+            "text:tab" => ret.push('\t'),
+            // run of spaces compressed by count
+            "text:s" => {
+                let count = tag
+                    .split("text:c=\"")
+                    .nth(1)
+                    .and_then(|x| x.split('"').next())
+                    .and_then(|x| x.parse::<usize>().ok())
+                    .unwrap_or(1);
+                ret.push_str(&" ".repeat(count));
+            }
+            _ => (),
+        }
+        rest = &rest[end..];
+    }
+    ret.push_str(&xml_unescape(rest));
+    Ok(ret)
+}
+
+/// plain text in rich text
+/// control words for paragraphs, tabs and special chars are converted and the others dropped
+fn rtf_text(bytes: &[u8]) -> String {
+    // code page declared by `\ansicpg` decodes `\'hh` bytes
+    let mut encoding = WINDOWS_1252;
+    let mut ret = String::new();
+    // text bytes are decoded together so that multibyte chars are kept
+    let mut pending_bytes: Vec<u8> = vec![];
+    // skipped flag of each group and chars to skip after `\uN`
+    let mut groups: Vec<(bool, usize)> = vec![(false, 1)];
+    let mut skip_chars = 0;
+    let mut i = 0;
+
+    let flush = |pending_bytes: &mut Vec<u8>, ret: &mut String, encoding: &'static Encoding| {
+        if !pending_bytes.is_empty() {
+            ret.push_str(&encoding.decode_without_bom_handling(pending_bytes).0);
+            pending_bytes.clear();
+        }
+    };
+
+    while i < bytes.len() {
+        let skipped = groups.last().is_some_and(|x| x.0);
+        match bytes[i] {
+            b'{' => {
+                let unicode_skip = groups.last().map_or(1, |x| x.1);
+                groups.push((skipped, unicode_skip));
+                i += 1;
+            }
+            b'}' => {
+                groups.pop();
+                i += 1;
+            }
+            b'\r' | b'\n' => i += 1,
+            b'\\' => {
+                let next = bytes.get(i + 1).copied().unwrap_or_default();
+                if next == b'\'' {
+                    let hex = bytes.get(i + 2..i + 4).unwrap_or_default();
+                    let byte = std::str::from_utf8(hex)
+                        .ok()
+                        .and_then(|x| u8::from_str_radix(x, 16).ok());
+                    if let Some(byte) = byte {
+                        if skip_chars > 0 {
+                            skip_chars -= 1;
+                        } else if !skipped {
+                            pending_bytes.push(byte);
+                        }
+                    }
+                    i += 4;
+                    continue;
+                }
+                flush(&mut pending_bytes, &mut ret, encoding);
+                if !next.is_ascii_alphabetic() {
+                    // control symbol
+                    if !skipped {
+                        match next {
+                            b'\\' | b'{' | b'}' => ret.push(next as char),
+                            b'~' => ret.push('\u{a0}'),
+                            b'_' => ret.push('\u{2011}'),
+                            // ignorable destination
+                            b'*' => {
+                                if let Some(x) = groups.last_mut() {
+                                    x.0 = true;
+                                }
+                            }
+                            _ => (),
+                        }
+                    }
+                    i += 2;
+                    continue;
+                }
+
+                let word_start = i + 1;
+                let mut end = word_start;
+                while end < bytes.len() && bytes[end].is_ascii_alphabetic() {
+                    end += 1;
+                }
+                let word = String::from_utf8_lossy(&bytes[word_start..end]).to_string();
+                let parameter_start = end;
+                if end < bytes.len() && bytes[end] == b'-' {
+                    end += 1;
+                }
+                while end < bytes.len() && bytes[end].is_ascii_digit() {
+                    end += 1;
+                }
+                let parameter = std::str::from_utf8(&bytes[parameter_start..end])
+                    .ok()
+                    .and_then(|x| x.parse::<i32>().ok());
+                // space delimiting control word is part of it
+                if end < bytes.len() && bytes[end] == b' ' {
+                    end += 1;
+                }
+                i = end;
+
+                if RTF_SKIPPED_DESTINATIONS.contains(&word.as_str()) {
+                    if let Some(x) = groups.last_mut() {
+                        x.0 = true;
+                    }
+                    continue;
+                }
+                match word.as_str() {
+                    "ansicpg" => {
+                        if let Some(x) = parameter.and_then(code_page_encoding) {
+                            encoding = x;
+                        }
+                    }
+                    "uc" => {
+                        if let Some(x) = groups.last_mut() {
+                            x.1 = parameter.unwrap_or(1).max(0) as usize;
+                        }
+                    }
+                    _ if skipped => (),
+                    "par" | "line" | "row" | "sect" | "page" => ret.push('\n'),
+                    "tab" | "cell" => ret.push('\t'),
+                    "emdash" => ret.push('\u{2014}'),
+                    "endash" => ret.push('\u{2013}'),
+                    "bullet" => ret.push('\u{2022}'),
+                    "lquote" => ret.push('\u{2018}'),
+                    "rquote" => ret.push('\u{2019}'),
+                    "ldblquote" => ret.push('\u{201c}'),
+                    "rdblquote" => ret.push('\u{201d}'),
+                    // negative values are used for code points over 32767
+                    "u" => {
+                        let code_point = parameter.unwrap_or_default().rem_euclid(0x10000) as u32;
+                        ret.push(char::from_u32(code_point).unwrap_or('\u{fffd}'));
+                        skip_chars = groups.last().map_or(1, |x| x.1);
+                    }
+                    _ => (),
+                }
+            }
+            x => {
+                if skip_chars > 0 {
+                    skip_chars -= 1;
+                } else if !skipped {
+                    pending_bytes.push(x);
+                }
+                i += 1;
+            }
+        }
+    }
+    flush(&mut pending_bytes, &mut ret, encoding);
+    ret
+}
+
+/// encoding of windows code page
+fn code_page_encoding(code_page: i32) -> Option<&'static Encoding> {
+    let label = match code_page {
+        932 => "shift_jis".to_owned(),
+        936 => "gbk".to_owned(),
+        949 => "euc-kr".to_owned(),
+        950 => "big5".to_owned(),
+        65001 => "utf-8".to_owned(),
+        x => format!("windows-{}", x),
+    };
+    Encoding::for_label(label.as_bytes())
+}
@@ -0,0 +1,119 @@
+use std::fs::File;
+use std::io::{self, Read};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tauri::Emitter;
+
+use super::os_path::os_path;
+use super::progress::Progress;
+use super::types::{BinaryEquality, BinaryEqualityProgressEvent, OperationKind, ProgressUnit};
+
+/// event emitted while files are checked for equality
+pub const BINARY_EQUALITY_PROGRESS_EVENT: &str = "binary-equality-progress";
+/// bytes read from each file at once
+const EQUALITY_CHUNK_SIZE: usize = 8 * 1024 * 1024;
+/// min interval between progress events
+const PROGRESS_EVENT_INTERVAL: Duration = Duration::from_millis(200);
+
+/// check if files are identical streaming them chunk by chunk without building dump
+/// stops at first differing offset. both files are read in parallel
+/// and progress is emitted so that multi-gigabyte files can be checked before full binary diff
+pub fn binary_equality(
+    app_handle: &tauri::AppHandle,
+    old: &str,
+    new: &str,
+) -> Result<BinaryEquality, String> {
+    let mut progress = Progress::start(app_handle, OperationKind::Hash, old, ProgressUnit::Bytes);
+    let result = streamed_equality(app_handle, old, new, &mut progress);
+    progress.finish(result)
+}
+
+/// compare files chunk by chunk reporting bytes compared
+fn streamed_equality(
+    app_handle: &tauri::AppHandle,
+    old: &str,
+    new: &str,
+    progress: &mut Progress,
+) -> Result<BinaryEquality, String> {
+    let open = |filepath: &str| {
+        File::open(os_path(filepath))
+            .map_err(|err| format!("Failed to open {} ({})", filepath, err))
+    };
+    let mut old_file = open(old)?;
+    let mut new_file = open(new)?;
+    let size = |file: &File, filepath: &str| {
+        file.metadata()
+            .map(|x| x.len())
+            .map_err(|err| format!("Failed to get metadata of {} ({})", filepath, err))
+    };
+    let old_size = size(&old_file, old)?;
+    let new_size = size(&new_file, new)?;
+    let total_bytes = old_size.min(new_size);
+
+    let mut old_buffer = vec![0; EQUALITY_CHUNK_SIZE];
+    let mut new_buffer = vec![0; EQUALITY_CHUNK_SIZE];
+    let mut offset = 0_u64;
+    let mut last_emitted = Instant::now();
+    let first_difference_offset = loop {
+        let (old_read, new_read) = thread::scope(|s| {
+            let old_read = s.spawn(|| read_chunk(&mut old_file, &mut old_buffer));
+            let new_read = read_chunk(&mut new_file, &mut new_buffer);
+            (old_read.join().unwrap_or(Ok(0)), new_read)
+        });
+        let old_read = old_read.map_err(|err| format!("Failed to read {} ({})", old, err))?;
+        let new_read = new_read.map_err(|err| format!("Failed to read {} ({})", new, err))?;
+
+        let len = old_read.min(new_read);
+        // compared at once and searched only when differing
+        if old_buffer[..len] != new_buffer[..len] {
+            let i = (0..len)
+                .find(|i| old_buffer[*i] != new_buffer[*i])
+                .unwrap_or_default();
+            break Some(offset + i as u64);
+        }
+        if old_read != new_read {
+            // one file ends while the other continues
+            break Some(offset + len as u64);
+        }
+        if old_read == 0 {
+            break None;
+        }
+        offset += len as u64;
+        progress.update(offset, Some(total_bytes));
+
+        if PROGRESS_EVENT_INTERVAL <= last_emitted.elapsed() {
+            last_emitted = Instant::now();
+            let event = BinaryEqualityProgressEvent {
+                old: old.to_owned(),
+                new: new.to_owned(),
+                compared_bytes: offset,
+                total_bytes,
+            };
+            if let Err(err) = app_handle.emit(BINARY_EQUALITY_PROGRESS_EVENT, event) {
+                tracing::warn!("Failed to emit binary equality progress ({})", err);
+            }
+        }
+    };
+
+    Ok(BinaryEquality {
+        identical: first_difference_offset.is_none(),
+        first_difference_offset,
+        old_size,
+        new_size,
+    })
+}
+
+/// fill buffer as much as possible. shorter only at end of file
+fn read_chunk(file: &mut File, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut ret = 0;
+    while ret < buffer.len() {
+        match file.read(&mut buffer[ret..]) {
+            Ok(0) => break,
+            Ok(x) => ret += x,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(ret)
+}
@@ -0,0 +1,222 @@
+use std::ffi::OsString;
+use std::path::Path;
+use std::process::Command;
+use std::thread;
+
+use super::diff::startup_compare_set_item;
+use super::os_path::os_path;
+use super::path_policy::ensure_permitted;
+use super::types::{CompareSet, ExternalTool, ToolInvocation, ToolInvocationKind};
+use super::vcs::git;
+
+/// executable arg on launch by git as difftool: `--difftool LOCAL REMOTE [MERGED]`
+pub const DIFFTOOL_ARG: &str = "--difftool";
+/// executable arg on launch by git as mergetool: `--mergetool LOCAL BASE REMOTE MERGED`
+pub const MERGETOOL_ARG: &str = "--mergetool";
+/// placeholder in external tool args replaced with old file path
+const OLD_PLACEHOLDER: &str = "{old}";
+/// placeholder in external tool args replaced with new file path
+const NEW_PLACEHOLDER: &str = "{new}";
+/// name of tool registered in git config
+const GIT_TOOL_NAME: &str = "forskscope";
+
+/// check if app is launched by git as difftool or mergetool
+/// such launch waits for its own window instead of being forwarded to running app
+pub fn is_tool_arg(arg: &str) -> bool {
+    arg == DIFFTOOL_ARG || arg == MERGETOOL_ARG
+}
+
+/// compare set on launch by git as difftool or mergetool
+/// paths missing in args are taken from `LOCAL`, `REMOTE`, `BASE` and `MERGED` env vars
+/// git exports to tool command. paths not permitted by path policy are dropped
+pub fn tool_args_compare_set(app_handle: &tauri::AppHandle, args: &[String]) -> Option<CompareSet> {
+    let (flag, args) = args.split_first()?;
+    let arg = |i: usize, env: &str| {
+        args.get(i)
+            .filter(|x| !x.is_empty())
+            .cloned()
+            .or_else(|| std::env::var(env).ok())
+            .filter(|x| ensure_permitted(app_handle, x).is_ok())
+    };
+    let (kind, local, remote, base, merged) = match flag.as_str() {
+        DIFFTOOL_ARG => (
+            ToolInvocationKind::Diff,
+            arg(0, "LOCAL"),
+            arg(1, "REMOTE"),
+            None,
+            arg(2, "MERGED"),
+        ),
+        MERGETOOL_ARG => (
+            ToolInvocationKind::Merge,
+            arg(0, "LOCAL"),
+            arg(2, "REMOTE"),
+            arg(1, "BASE"),
+            arg(3, "MERGED"),
+        ),
+        _ => return None,
+    };
+    if kind == ToolInvocationKind::Merge && merged.is_none() {
+        tracing::warn!("Merged file is not given to mergetool");
+        return None;
+    }
+    Some(CompareSet {
+        old: startup_compare_set_item(&local.map(Into::into)),
+        new: startup_compare_set_item(&remote.map(Into::into)),
+        tool: Some(ToolInvocation { kind, base, merged }),
+    })
+}
+
+/// external tools commonly installed
+pub fn external_tool_presets() -> Vec<ExternalTool> {
+    let tool = |name: &str, program: &str, args: &[&str]| ExternalTool {
+        name: name.to_owned(),
+        program: program.to_owned(),
+        args: args.iter().map(|x| x.to_string()).collect(),
+    };
+    let bcompare = if cfg!(target_os = "windows") {
+        "BCompare.exe"
+    } else {
+        "bcompare"
+    };
+    let vimdiff = if cfg!(target_os = "windows") {
+        // launched directly in new console. never via `cmd /c` which re-parses file paths
+        tool("vimdiff", "vimdiff", &[OLD_PLACEHOLDER, NEW_PLACEHOLDER])
+    } else if cfg!(target_os = "macos") {
+        tool(
+            "vimdiff",
+            "open",
+            &[
+                "-a",
+                "Terminal",
+                "-n",
+                "--args",
+                "vimdiff",
+                OLD_PLACEHOLDER,
+                NEW_PLACEHOLDER,
+            ],
+        )
+    } else {
+        // vimdiff needs terminal
+        tool(
+            "vimdiff",
+            "x-terminal-emulator",
+            &["-e", "vimdiff", OLD_PLACEHOLDER, NEW_PLACEHOLDER],
+        )
+    };
+    vec![
+        tool(
+            "Beyond Compare",
+            bcompare,
+            &[OLD_PLACEHOLDER, NEW_PLACEHOLDER],
+        ),
+        tool("Meld", "meld", &[OLD_PLACEHOLDER, NEW_PLACEHOLDER]),
+        vimdiff,
+    ]
+}
+
+/// launch external tool with file pair without waiting for it
+/// `{old}` and `{new}` in args are replaced with file paths. paths are passed as args of their own
+/// when placeholders are whole args. paths with chars meaningful to shell are refused
+/// where they may be parsed again, such as inside longer args or by command interpreter
+pub fn launch_external_tool(tool: &ExternalTool, old: &str, new: &str) -> Result<(), String> {
+    let interpreted = is_command_interpreter(&tool.program);
+    let args = tool
+        .args
+        .iter()
+        .map(|x| {
+            if x == OLD_PLACEHOLDER {
+                checked_path(old, interpreted).map(|x| os_path(x).into_os_string())
+            } else if x == NEW_PLACEHOLDER {
+                checked_path(new, interpreted).map(|x| os_path(x).into_os_string())
+            } else if x.contains(OLD_PLACEHOLDER) || x.contains(NEW_PLACEHOLDER) {
+                Ok(x.replace(OLD_PLACEHOLDER, checked_path(old, true)?)
+                    .replace(NEW_PLACEHOLDER, checked_path(new, true)?)
+                    .into())
+            } else {
+                Ok(x.into())
+            }
+        })
+        .collect::<Result<Vec<OsString>, String>>()?;
+    let mut command = Command::new(&tool.program);
+    command.args(args);
+    // console tool such as vimdiff gets its own console window
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NEW_CONSOLE: u32 = 0x0000_0010;
+        command.creation_flags(CREATE_NEW_CONSOLE);
+    }
+    let mut child = command
+        .spawn()
+        .map_err(|err| format!("Failed to launch {} ({})", tool.name, err))?;
+    // reaped so that it does not remain as zombie
+    thread::spawn(move || child.wait());
+    Ok(())
+}
+
+/// check if program parses its args as command line again such as `cmd` or batch file
+fn is_command_interpreter(program: &str) -> bool {
+    const COMMAND_INTERPRETERS: [&str; 8] = [
+        "cmd",
+        "command",
+        "powershell",
+        "pwsh",
+        "sh",
+        "bash",
+        "zsh",
+        "wscript",
+    ];
+    let path = Path::new(program);
+    let stem = path
+        .file_stem()
+        .map(|x| x.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let extension = path
+        .extension()
+        .map(|x| x.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    COMMAND_INTERPRETERS.contains(&stem.as_str()) || extension == "bat" || extension == "cmd"
+}
+
+/// path to be put into args, refused when it may be parsed again and has chars meaningful to shell
+fn checked_path(path: &str, reparsed: bool) -> Result<&str, String> {
+    const SHELL_META_CHARS: &[char] = &[
+        '"', '\'', '`', '$', '&', '|', ';', '<', '>', '^', '%', '!', '\n', '\r',
+    ];
+    if reparsed && path.contains(SHELL_META_CHARS) {
+        return Err(format!(
+            "Path with special chars cannot be passed to external tool: {}",
+            path
+        ));
+    }
+    Ok(path)
+}
+
+/// register app as difftool and mergetool in global git config
+/// used by `git difftool --tool=forskscope` and `git mergetool --tool=forskscope`
+pub fn register_git_tool() -> Result<(), String> {
+    let exe = std::env::current_exe()
+        .map_err(|err| format!("Failed to get executable path ({})", err))?;
+    let exe = exe.to_string_lossy().replace('\\', "/");
+    let difftool_cmd = format!(
+        "\"{}\" {} \"$LOCAL\" \"$REMOTE\" \"$MERGED\"",
+        exe, DIFFTOOL_ARG
+    );
+    let mergetool_cmd = format!(
+        "\"{}\" {} \"$LOCAL\" \"$BASE\" \"$REMOTE\" \"$MERGED\"",
+        exe, MERGETOOL_ARG
+    );
+    let entries = [
+        (format!("difftool.{}.cmd", GIT_TOOL_NAME), difftool_cmd),
+        (format!("mergetool.{}.cmd", GIT_TOOL_NAME), mergetool_cmd),
+        // exit code tells whether merge is resolved
+        (
+            format!("mergetool.{}.trustExitCode", GIT_TOOL_NAME),
+            "true".to_owned(),
+        ),
+    ];
+    for (key, value) in entries {
+        git(".", &["config", "--global", &key, &value])?;
+    }
+    Ok(())
+}
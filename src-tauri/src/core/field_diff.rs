@@ -0,0 +1,52 @@
+use std::collections::BTreeMap;
+
+use super::types::{EntryStatus, MetadataFieldDiff};
+
+/// compare key-value fields
+pub fn field_diffs(
+    old_fields: &BTreeMap<String, String>,
+    new_fields: &BTreeMap<String, String>,
+) -> Vec<MetadataFieldDiff> {
+    let mut keys = old_fields
+        .keys()
+        .chain(new_fields.keys())
+        .collect::<Vec<_>>();
+    keys.sort();
+    keys.dedup();
+
+    keys.into_iter()
+        .filter_map(|key| {
+            field_diff(
+                key,
+                old_fields.get(key).cloned(),
+                new_fields.get(key).cloned(),
+            )
+        })
+        .collect()
+}
+
+/// compare field by its values on both sides. none when absent on both sides
+/// callers iterate fields in their own order such as order of appearance
+pub fn field_diff(
+    key: &str,
+    old: Option<String>,
+    new: Option<String>,
+) -> Option<MetadataFieldDiff> {
+    Some(MetadataFieldDiff {
+        key: key.to_owned(),
+        status: entry_status(old.as_ref(), new.as_ref())?,
+        old,
+        new,
+    })
+}
+
+/// status of entry by its values on both sides. none when absent on both sides
+pub fn entry_status<T: PartialEq>(old: Option<&T>, new: Option<&T>) -> Option<EntryStatus> {
+    match (old, new) {
+        (Some(old), Some(new)) if old == new => Some(EntryStatus::Equal),
+        (Some(_), Some(_)) => Some(EntryStatus::Modified),
+        (Some(_), None) => Some(EntryStatus::OldOnly),
+        (None, Some(_)) => Some(EntryStatus::NewOnly),
+        (None, None) => None,
+    }
+}
@@ -1,19 +1,32 @@
 use std::ffi::OsString;
 use std::fs;
-use std::io::{BufRead, BufReader, Error as IOError, Read, Write};
+use std::io::{Error as IOError, Read, Write};
 use std::path::PathBuf;
-use std::time::UNIX_EPOCH;
+use std::time::{Duration, UNIX_EPOCH};
 use std::{fs::File, path::Path};
 
 use chardetng::EncodingDetector;
 use chrono::{Local, TimeZone};
-use encoding_rs::{Encoding, UTF_8};
+use encoding_rs::{Encoding, UTF_8, WINDOWS_1252};
 use sheets_diff::core::diff::Diff;
 use sheets_diff::core::unified_format::{unified_diff, SplitUnifiedDiffContent};
 
+use super::archive::archive_entries;
+use super::compare_mode::{file_compare_mode, pair_compare_mode};
+use super::compressed::decompressed_contents;
 use super::diff::binary_comparison_only;
+use super::document::document_content;
+use super::markup::normalized_markup;
+use super::media::media_metadata;
+use super::os_path::{os_path, path_string};
+use super::round_trip::round_trip_bytes;
+use super::sniff::{sniff_bytes, sniff_file};
 use super::str::bytes_to_hex_dump;
-use super::types::{FileAttr, ListDirResponse, ReadContent};
+use super::types::{
+    CharsetCandidate, CompareMode, FileAttr, ListDirResponse, MarkupOptions, NumberFormat,
+    ReadContent, SizeUnit, SortKey, SortOrder,
+};
+use super::wide_text::{decode_wide, encode_wide, wide_encoding_for_bom, wide_encoding_for_label};
 
 /// default charset
 const UTF8_CHARSET: &str = "UTF-8";
@@ -22,47 +35,93 @@ const NOT_TEXTFILE_CHARSET: &str = "(bytes array)";
 
 /// validate file path to compare
 pub fn validate_filepath(filepath: &str) -> Option<bool> {
-    if !Path::new(filepath).exists() {
+    if !os_path(filepath).exists() {
         return None;
     }
-    Some(is_textfile(filepath) || filepath.ends_with(".xlsx"))
+    Some(file_compare_mode(filepath) != Some(CompareMode::Binary))
 }
 
 /// get content from file paths on old file and new file
 pub fn filepaths_content(old: &str, new: &str) -> Result<Vec<ReadContent>, String> {
-    let old_is_textfile = is_textfile(old);
-    let new_is_textfile = is_textfile(new);
-    if old_is_textfile && new_is_textfile {
-        return Ok(vec![textfile_content(old), textfile_content(new)]);
-    }
-    if old_is_textfile && new.is_empty() {
-        return Ok(vec![textfile_content(old), ReadContent::default()]);
-    }
-    if old.is_empty() && new_is_textfile {
-        return Ok(vec![ReadContent::default(), textfile_content(new)]);
-    }
+    filepaths_content_with_mode(old, new, None).map(|x| x.0)
+}
 
-    if old.ends_with(".xlsx") && new.ends_with(".xlsx") {
-        let diff = Diff::new(old, new);
-        let split_unified_diff = unified_diff(&diff).split();
-        return Ok(vec![
-            excel_content(&split_unified_diff.old),
-            excel_content(&split_unified_diff.new),
-        ]);
+/// get content from file paths in comparison mode
+/// mode is detected on file pair unless specified
+pub fn filepaths_content_with_mode(
+    old: &str,
+    new: &str,
+    mode: Option<CompareMode>,
+) -> Result<(Vec<ReadContent>, CompareMode), String> {
+    // compressed single files are compared with decompressed content
+    if mode.is_none() {
+        if let Some(read_contents) = decompressed_contents(old, new)? {
+            return Ok((read_contents, CompareMode::Text));
+        }
     }
-
-    Ok(vec![binary_content(old), binary_content(new)])
+    let mode = mode.unwrap_or_else(|| pair_compare_mode(old, new));
+
+    let read_contents = match mode {
+        CompareMode::Text => vec![
+            side_content(old, textfile_content)?,
+            side_content(new, textfile_content)?,
+        ],
+        CompareMode::Structured => vec![
+            side_content(old, structured_content)?,
+            side_content(new, structured_content)?,
+        ],
+        CompareMode::Spreadsheet => {
+            if old.is_empty() || new.is_empty() {
+                return Err("Spreadsheet comparison requires both files".to_owned());
+            }
+            // mode may be specified by user, and workbook reader panics on other files
+            for filepath in [old, new] {
+                if file_compare_mode(filepath) != Some(CompareMode::Spreadsheet) {
+                    return Err(format!("Not a spreadsheet: {}", filepath));
+                }
+            }
+            let diff = Diff::new(old, new);
+            let split_unified_diff = unified_diff(&diff).split();
+            vec![
+                excel_content(&split_unified_diff.old),
+                excel_content(&split_unified_diff.new),
+            ]
+        }
+        CompareMode::Archive => vec![
+            side_content(old, archive_content)?,
+            side_content(new, archive_content)?,
+        ],
+        CompareMode::Image => vec![
+            side_content(old, image_content)?,
+            side_content(new, image_content)?,
+        ],
+        CompareMode::Document => vec![
+            side_content(old, document_or_binary_content)?,
+            side_content(new, document_or_binary_content)?,
+        ],
+        CompareMode::Binary => vec![
+            side_content(old, binary_content)?,
+            side_content(new, binary_content)?,
+        ],
+    };
+    Ok((read_contents, mode))
 }
 
 /// list files and directories in directory
-pub fn list_dir(current_dir: &str) -> Result<ListDirResponse, String> {
+/// sizes are formatted with `number_format`
+pub fn list_dir(
+    current_dir: &str,
+    sort_key: SortKey,
+    sort_order: SortOrder,
+    number_format: &NumberFormat,
+) -> Result<ListDirResponse, String> {
     let target_dir = match target_dir(current_dir) {
         Ok(x) => x,
         Err(err) => return Err(err.to_string()),
     };
 
     let mut dirs = Vec::<String>::new();
-    let mut files = Vec::<FileAttr>::new();
+    let mut files = Vec::<(FileAttr, u64, Duration)>::new();
 
     let read = match std::fs::read_dir(target_dir.as_path()) {
         Ok(x) => x,
@@ -73,7 +132,7 @@ pub fn list_dir(current_dir: &str) -> Result<ListDirResponse, String> {
     for x in read {
         match x {
             Ok(dir_entry) => {
-                let name = dir_entry.file_name().to_string_lossy().to_string();
+                let name = path_string(Path::new(&dir_entry.file_name()));
                 match dir_entry.metadata() {
                     Ok(metadata) => {
                         if metadata.is_dir() {
@@ -88,39 +147,84 @@ pub fn list_dir(current_dir: &str) -> Result<ListDirResponse, String> {
                             .unwrap();
                         let local_timestamp = Local.timestamp_nanos(modified.as_nanos() as i64);
                         let last_modified = local_timestamp.format("%Y-%m-%d %H:%M:%S").to_string();
-                        files.push(FileAttr {
-                            name,
-                            bytes_size: format!("{} bytes", comma_separated_number(metadata.len())),
-                            human_readable_size: human_readable_size(metadata.len()),
-                            last_modified,
-                            binary_comparison_only: binary_comparison_only(
-                                &dir_entry.path().to_string_lossy(),
-                            ),
-                        })
+                        files.push((
+                            FileAttr {
+                                name,
+                                bytes_size: format!(
+                                    "{} bytes",
+                                    comma_separated_number(metadata.len(), number_format)
+                                ),
+                                human_readable_size: human_readable_size(
+                                    metadata.len(),
+                                    number_format,
+                                ),
+                                last_modified,
+                                binary_comparison_only: binary_comparison_only(&path_string(
+                                    &dir_entry.path(),
+                                )),
+                                mime_type: Some(
+                                    sniff_file(&path_string(&dir_entry.path()))
+                                        .mime_type
+                                        .to_owned(),
+                                ),
+                            },
+                            metadata.len(),
+                            modified,
+                        ))
                     }
                     _ => {}
                 }
             }
             // todo
-            Err(err) => println!("Failed to get dir/file info due to {}", err),
+            Err(err) => tracing::warn!("Failed to get dir/file info ({})", err),
         }
     }
 
     dirs.sort();
-    files.sort();
+    files.sort_by(|a, b| match sort_key {
+        SortKey::Name => a.0.cmp(&b.0),
+        SortKey::Size => a.1.cmp(&b.1).then_with(|| a.0.cmp(&b.0)),
+        SortKey::Modified => a.2.cmp(&b.2).then_with(|| a.0.cmp(&b.0)),
+        SortKey::Extension => file_extension(&a.0.name)
+            .cmp(&file_extension(&b.0.name))
+            .then_with(|| a.0.cmp(&b.0)),
+    });
+    if sort_order == SortOrder::Desc {
+        // dirs are sorted only by name
+        if sort_key == SortKey::Name {
+            dirs.reverse();
+        }
+        files.reverse();
+    }
 
     Ok(ListDirResponse {
-        current_dir: target_dir.to_string_lossy().to_string(),
+        current_dir: path_string(&target_dir),
         dirs: dirs,
-        files: files,
+        files: files.into_iter().map(|x| x.0).collect(),
+        partial: false,
     })
 }
 
 /// save to file
+/// lines not edited are written as they are in existing file so that no-op save keeps it intact
 pub fn save(filepath: &str, content: &str, charset: &str) -> Result<(), IOError> {
-    let encoding = Encoding::for_label(charset.as_bytes()).unwrap_or(UTF_8);
-    let (encoded, _, _) = encoding.encode(content);
-    let mut file = File::create(filepath)?;
+    // file may not exist yet such as on save as
+    let original = fs::read(os_path(filepath)).unwrap_or_default();
+    let encoded = match wide_encoding_for_label(charset) {
+        // bom is kept as in existing file and put on new file for tools to detect encoding
+        Some(encoding) => {
+            let with_bom = original.is_empty() || wide_encoding_for_bom(&original).is_some();
+            encode_wide(content, encoding, with_bom)
+        }
+        None => {
+            let encoding = Encoding::for_label(charset.as_bytes()).unwrap_or(UTF_8);
+            round_trip_bytes(&original, content, encoding)
+        }
+    };
+    if encoded == original && !original.is_empty() {
+        return Ok(());
+    }
+    let mut file = File::create(os_path(filepath))?;
     file.write_all(&encoded)?;
     Ok(())
 }
@@ -157,11 +261,11 @@ pub fn file_manager_command() -> &'static str {
 }
 
 /// convert executable argument to file path
+/// non-utf-8 path is kept losslessly by `path_string`
 pub fn arg_to_filepath(arg: &Option<OsString>) -> Option<String> {
     if let Some(s) = arg {
-        let s = s.to_string_lossy();
-        if Path::new(s.as_ref()).is_file() {
-            Some(s.into_owned())
+        if Path::new(s).is_file() {
+            Some(path_string(Path::new(s)))
         } else {
             None
         }
@@ -170,27 +274,28 @@ pub fn arg_to_filepath(arg: &Option<OsString>) -> Option<String> {
     }
 }
 
-/// check if file is text file
-fn is_textfile(filepath: &str) -> bool {
-    let file = File::open(filepath);
-    match file {
-        Ok(f) => {
-            let mut reader = BufReader::new(f);
-            let mut buffer = String::new();
-            reader.read_line(&mut buffer).is_ok()
-        }
-        Err(_) => false,
-    }
+/// check if file is text file by content sniffing
+/// text in any charset including utf-16 is text while known binary formats are not
+pub fn is_textfile(filepath: &str) -> bool {
+    sniff_file(filepath).is_text
 }
 
 /// get content from text file
-fn textfile_content(filepath: &str) -> ReadContent {
-    let mut file = File::open(filepath).expect(format!("failed to open {}", filepath).as_str());
+fn textfile_content(filepath: &str) -> Result<ReadContent, String> {
+    let mut file = File::open(os_path(filepath))
+        .map_err(|err| format!("Failed to open {} ({})", filepath, err))?;
     let mut buffer = Vec::new();
-    file.read_to_end(&mut buffer).unwrap();
+    file.read_to_end(&mut buffer)
+        .map_err(|err| format!("Failed to read {} ({})", filepath, err))?;
+
+    Ok(bytes_content(&buffer))
+}
 
-    let is_binary = buffer.windows(2).any(|window| window[0] == 0x00);
-    if is_binary {
+/// get content from bytes read as text
+pub fn bytes_content(buffer: &[u8]) -> ReadContent {
+    let sniffed = sniff_bytes(buffer);
+    let mime_type = Some(sniffed.mime_type.to_owned());
+    if !sniffed.is_text {
         const BYTES_ARRAY_ROW_LENGTH: usize = 16;
         let mut grid = String::new();
         for chunk in buffer.chunks(BYTES_ARRAY_ROW_LENGTH) {
@@ -202,30 +307,137 @@ fn textfile_content(filepath: &str) -> ReadContent {
         return ReadContent {
             charset: NOT_TEXTFILE_CHARSET.to_owned(),
             content: grid,
+            mime_type,
+            ..Default::default()
+        };
+    }
+    if let Some(encoding) = sniffed.encoding {
+        let (decoded, had_errors) = decode_wide(buffer, encoding);
+        if had_errors {
+            tracing::warn!("Malformed {} text", encoding.name())
+        }
+        return ReadContent {
+            charset: encoding.name().to_owned(),
+            content: decoded,
+            charset_candidates: vec![CharsetCandidate {
+                charset: encoding.name().to_owned(),
+                confidence: 100.0,
+                detected: true,
+            }],
+            compression: None,
+            mime_type,
         };
     }
 
-    match std::str::from_utf8(&buffer) {
+    match std::str::from_utf8(buffer) {
         Ok(x) => {
             return ReadContent {
                 charset: UTF8_CHARSET.to_owned(),
                 content: x.to_owned(),
+                charset_candidates: vec![CharsetCandidate {
+                    charset: UTF8_CHARSET.to_owned(),
+                    confidence: 100.0,
+                    detected: true,
+                }],
+                compression: None,
+                mime_type,
             }
         }
         Err(_) => (),
     }
 
     let mut detector = EncodingDetector::new();
-    detector.feed(&buffer, true);
+    detector.feed(buffer, true);
     let encoding = detector.guess(None, false);
-    let (decoded, _, had_errors) = encoding.decode(&buffer);
+    let (decoded, _, had_errors) = encoding.decode(buffer);
     if had_errors {
-        eprint!("not binary, not utf-8 text and not any other encoded text.")
+        tracing::warn!("Not binary, not utf-8 text and not any other encoded text")
     }
     ReadContent {
         charset: encoding.name().to_owned(),
         content: decoded.to_string(),
+        charset_candidates: charset_candidates(buffer, &detector, encoding),
+        compression: None,
+        mime_type,
+    }
+}
+
+/// get content from text file decoded with charset specified
+pub fn textfile_content_with_charset(filepath: &str, charset: &str) -> Result<ReadContent, String> {
+    if let Some(encoding) = wide_encoding_for_label(charset) {
+        let buffer = fs::read(os_path(filepath))
+            .map_err(|err| format!("Failed to read {} ({})", filepath, err))?;
+        return Ok(ReadContent {
+            charset: encoding.name().to_owned(),
+            content: decode_wide(&buffer, encoding).0,
+            mime_type: Some(sniff_bytes(&buffer).mime_type.to_owned()),
+            ..Default::default()
+        });
+    }
+    let encoding = match Encoding::for_label(charset.as_bytes()) {
+        Some(x) => x,
+        None => return Err(format!("Unknown charset: {}", charset)),
+    };
+    let buffer = fs::read(os_path(filepath))
+        .map_err(|err| format!("Failed to read {} ({})", filepath, err))?;
+
+    let mut detector = EncodingDetector::new();
+    detector.feed(&buffer, true);
+    let detected = detector.guess(None, false);
+
+    let (decoded, _, _) = encoding.decode(&buffer);
+    Ok(ReadContent {
+        charset: encoding.name().to_owned(),
+        content: decoded.to_string(),
+        charset_candidates: charset_candidates(&buffer, &detector, detected),
+        compression: None,
+        mime_type: Some(sniff_bytes(&buffer).mime_type.to_owned()),
+    })
+}
+
+/// evaluate likely charsets on bytes
+/// detector is asked with top level domain hints to collect alternatives to its winner
+fn charset_candidates(
+    buffer: &[u8],
+    detector: &EncodingDetector,
+    detected: &'static Encoding,
+) -> Vec<CharsetCandidate> {
+    const TLD_HINTS: [&[u8]; 12] = [
+        b"jp", b"cn", b"tw", b"kr", b"ru", b"ua", b"gr", b"tr", b"il", b"th", b"vn", b"pl",
+    ];
+
+    let mut encodings: Vec<&'static Encoding> = vec![];
+    let hinted = TLD_HINTS.iter().map(|tld| detector.guess(Some(tld), false));
+    for encoding in [detected, UTF_8, WINDOWS_1252].into_iter().chain(hinted) {
+        if !encodings.contains(&encoding) {
+            encodings.push(encoding);
+        }
     }
+
+    let mut ret = encodings
+        .into_iter()
+        .map(|encoding| {
+            let (decoded, _) = encoding.decode_without_bom_handling(buffer);
+            let chars_count = decoded.chars().count();
+            let malformed_count = decoded.chars().filter(|x| *x == '\u{FFFD}').count();
+            let confidence = if chars_count == 0 {
+                100.0
+            } else {
+                (1.0 - malformed_count as f32 / chars_count as f32) * 100.0
+            };
+            CharsetCandidate {
+                charset: encoding.name().to_owned(),
+                confidence,
+                detected: encoding == detected,
+            }
+        })
+        .collect::<Vec<_>>();
+    ret.sort_by(|a, b| {
+        b.detected
+            .cmp(&a.detected)
+            .then_with(|| b.confidence.total_cmp(&a.confidence))
+    });
+    ret
 }
 
 /// read content from ms excel
@@ -250,17 +462,119 @@ fn excel_content(split_unified_diff_content: &Vec<SplitUnifiedDiffContent>) -> R
     ReadContent {
         charset: "(Excel)".to_owned(),
         content,
+        ..Default::default()
+    }
+}
+
+/// read content of one side. empty file path means side is not specified
+fn side_content(
+    filepath: &str,
+    read: impl Fn(&str) -> Result<ReadContent, String>,
+) -> Result<ReadContent, String> {
+    if filepath.is_empty() {
+        return Ok(ReadContent::default());
     }
+    read(filepath)
+}
+
+/// read structured text
+/// json, yaml and toml are normalized with sorted keys and indentation, and xml with one tag
+/// per line and sorted attributes, so that only semantic changes remain
+/// content which fails to be parsed is kept as it is
+fn structured_content(filepath: &str) -> Result<ReadContent, String> {
+    let read = textfile_content(filepath)?;
+    let normalized = match file_extension(filepath).as_str() {
+        "xml" => Some(normalized_markup(
+            &read.content,
+            &MarkupOptions {
+                ignore_attribute_order: true,
+                ignore_whitespace: true,
+            },
+        )),
+        "yaml" | "yml" => serde_yaml::from_str::<serde_json::Value>(&read.content)
+            .ok()
+            .and_then(|x| serde_yaml::to_string(&x).ok()),
+        "toml" => toml::from_str::<toml::Table>(&read.content)
+            .ok()
+            .and_then(|x| toml::to_string_pretty(&x).ok()),
+        // json is detected also by content
+        _ => serde_json::from_str::<serde_json::Value>(&read.content)
+            .ok()
+            .and_then(|x| serde_json::to_string_pretty(&x).ok())
+            .map(|x| format!("{}\n", x)),
+    };
+    Ok(match normalized {
+        Some(content) => ReadContent { content, ..read },
+        None => read,
+    })
+}
+
+/// read archive entries as listing
+fn archive_content(filepath: &str) -> Result<ReadContent, String> {
+    let bytes = fs::read(os_path(filepath))
+        .map_err(|err| format!("Failed to read {} ({})", filepath, err))?;
+    let entries = match archive_entries(&bytes) {
+        Some(x) => x,
+        None => return binary_content(filepath),
+    };
+    let content = entries
+        .iter()
+        .map(|x| match x.crc32 {
+            Some(crc32) => format!("{}\t{} bytes\tcrc32 {:08x}\n", x.name, x.size, crc32),
+            None => format!("{}\t{} bytes\n", x.name, x.size),
+        })
+        .collect();
+    Ok(ReadContent {
+        charset: "(archive)".to_owned(),
+        content,
+        ..Default::default()
+    })
+}
+
+/// read text extracted from document, or read as binary when extraction fails
+fn document_or_binary_content(filepath: &str) -> Result<ReadContent, String> {
+    document_content(filepath).or_else(|err| {
+        tracing::warn!("Failed to extract text from {} ({})", filepath, err);
+        binary_content(filepath)
+    })
+}
+
+/// read image metadata with pixel data digest
+fn image_content(filepath: &str) -> Result<ReadContent, String> {
+    let bytes = fs::read(os_path(filepath))
+        .map_err(|err| format!("Failed to read {} ({})", filepath, err))?;
+    let mut content = media_metadata(filepath)?
+        .into_iter()
+        .map(|(key, value)| format!("{}: {}\n", key, value))
+        .collect::<String>();
+    content.push_str(&format!("Bytes: {}\n", bytes.len()));
+    content.push_str(&format!("Digest (FNV-1a): {:016x}\n", fnv1a_digest(&bytes)));
+    Ok(ReadContent {
+        charset: "(image)".to_owned(),
+        content,
+        ..Default::default()
+    })
+}
+
+/// FNV-1a 64-bit digest to tell if bytes differ
+pub fn fnv1a_digest(bytes: &[u8]) -> u64 {
+    const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+    bytes.iter().fold(FNV_OFFSET_BASIS, |hash, x| {
+        (hash ^ *x as u64).wrapping_mul(FNV_PRIME)
+    })
 }
 
 /// read content as bynary
-fn binary_content(filepath: &str) -> ReadContent {
-    let read_bytes = fs::read(Path::new(filepath)).expect("Failed to read file in binary mode");
+fn binary_content(filepath: &str) -> Result<ReadContent, String> {
+    let read_bytes = fs::read(os_path(filepath))
+        .map_err(|err| format!("Failed to read {} ({})", filepath, err))?;
     let hex_dump = bytes_to_hex_dump(&read_bytes);
-    ReadContent {
+    Ok(ReadContent {
         charset: "(binary)".to_owned(),
         content: hex_dump,
-    }
+        ..Default::default()
+    })
 }
 
 /// convert pathbuf to os dependent one
@@ -274,13 +588,16 @@ fn os_path_buf(path_buf: &PathBuf) -> PathBuf {
         // extended-length path prefix sometimes appears on windows
         const WINDOWS_EXTENDED_LENGTH_PATH_PREFIX: &str = r"\\?\";
 
-        let windows_path_buf = path_buf
-            .to_str()
-            .expect("Failed to convert current_dir to string");
-        if windows_path_buf.starts_with(WINDOWS_EXTENDED_LENGTH_PATH_PREFIX) {
-            PathBuf::from(&windows_path_buf[WINDOWS_EXTENDED_LENGTH_PATH_PREFIX.len()..])
-        } else {
-            windows_path_buf.into()
+        // compared as wide chars so that path not valid in unicode is kept as it is
+        use std::os::windows::ffi::{OsStrExt, OsStringExt};
+
+        let prefix = WINDOWS_EXTENDED_LENGTH_PATH_PREFIX
+            .encode_utf16()
+            .collect::<Vec<_>>();
+        let wide = path_buf.as_os_str().encode_wide().collect::<Vec<_>>();
+        match wide.strip_prefix(prefix.as_slice()) {
+            Some(x) => PathBuf::from(OsString::from_wide(x)),
+            None => path_buf.to_owned(),
         }
     }
 }
@@ -290,55 +607,75 @@ fn target_dir(current_dir: &str) -> Result<PathBuf, IOError> {
     let ret = if current_dir.is_empty() {
         std::env::current_dir().expect("Failed to get current directory")
     } else {
-        Path::new(current_dir).canonicalize()?
+        os_path(current_dir).canonicalize()?
     };
     Ok(os_path_buf(&ret))
 }
 
-/// add separator commnas to number
-fn comma_separated_number(num: u64) -> String {
-    let num_str = num.to_string();
-
-    let mut ret = String::new();
-    for (i, c) in num_str.chars().rev().enumerate() {
-        if i != 0 && i % 3 == 0 {
-            ret.push(',');
-        }
-        ret.push(c);
-    }
+/// lowercase file extension as sort key or filter
+pub fn file_extension(name: &str) -> String {
+    Path::new(name)
+        .extension()
+        .map(|x| x.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
 
-    ret.chars().rev().collect()
+/// add separators to digit groups of number
+pub fn comma_separated_number(num: u64, number_format: &NumberFormat) -> String {
+    grouped_digits(&num.to_string(), number_format)
 }
 
 /// convert file size to human readable number
-fn human_readable_size(size: u64) -> String {
-    const UNIT: u64 = 1024;
-    const K: u64 = UNIT;
-    const M: u64 = UNIT.pow(2);
-    const G: u64 = UNIT.pow(3);
-    const T: u64 = UNIT.pow(4);
-
-    let (size, unit) = if size >= T {
-        (size as f64 / T as f64, "TB")
-    } else if size >= G {
-        (size as f64 / G as f64, "GB")
-    } else if size >= M {
-        (size as f64 / M as f64, "MB")
-    } else if size >= K {
-        (size as f64 / K as f64, "KB")
-    } else {
-        (size as f64, "bytes")
+pub fn human_readable_size(size: u64, number_format: &NumberFormat) -> String {
+    let (unit_base, units) = match number_format.size_unit {
+        SizeUnit::Binary => (1024_u64, ["KB", "MB", "GB", "TB"]),
+        SizeUnit::Si => (1000_u64, ["kB", "MB", "GB", "TB"]),
     };
 
-    let size_str = size.to_string();
-    let size_str_parts = size_str.split(".").collect::<Vec<&str>>();
-    let int = size_str_parts[0].parse::<u64>().unwrap();
-    let comma_separated_int = comma_separated_number(int);
-
-    let comma_separated_size = if 1 < size_str_parts.len() {
-        format!("{}.{:.2}", comma_separated_int, size_str_parts[1])
-    } else {
-        comma_separated_int
+    let exponent = units
+        .iter()
+        .enumerate()
+        .rev()
+        .map(|(i, _)| i as u32 + 1)
+        .find(|x| unit_base.pow(*x) <= size);
+    let mut exponent = match exponent {
+        Some(x) => x,
+        None => {
+            return format!("{} bytes", comma_separated_number(size, number_format));
+        }
     };
-    format!("{} {}", comma_separated_size, unit)
+
+    // rounded to 2 decimal places instead of truncated
+    // and promoted to next unit when rounded up to unit base such as `1024.00 KB`
+    let rounded_value =
+        |exponent: u32| (size as f64 / unit_base.pow(exponent) as f64 * 100.0).round() / 100.0;
+    if (exponent as usize) < units.len() && unit_base as f64 <= rounded_value(exponent) {
+        exponent += 1;
+    }
+    let rounded = format!("{:.2}", rounded_value(exponent));
+    let (int, fraction) = rounded.split_once('.').unwrap_or((rounded.as_str(), "00"));
+    format!(
+        "{}{}{} {}",
+        grouped_digits(int, number_format),
+        number_format.decimal_separator,
+        fraction,
+        units[exponent as usize - 1]
+    )
+}
+
+/// insert grouping separator into digits from the right
+fn grouped_digits(digits: &str, number_format: &NumberFormat) -> String {
+    if number_format.grouping_size == 0 {
+        return digits.to_owned();
+    }
+
+    let mut ret = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        let rest = digits.len() - i;
+        if i != 0 && rest % number_format.grouping_size == 0 {
+            ret.push_str(&number_format.grouping_separator);
+        }
+        ret.push(c);
+    }
+    ret
 }
@@ -0,0 +1,85 @@
+use std::fs::OpenOptions;
+use std::io::{Error as IOError, ErrorKind};
+use std::thread;
+use std::time::Duration;
+
+use super::os_path::{os_path, os_str_string, path_string};
+use super::types::FileUseStatus;
+
+/// error message prefix for frontend to tell file in use from other failures and offer retry
+pub const FILE_IN_USE_ERROR: &str = "File in use by another application";
+/// attempts to open file in use before giving up
+const LOCK_RETRY_ATTEMPTS: u32 = 3;
+/// interval between attempts to open file in use
+const LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(300);
+
+/// check if file can be opened for reading or writing before actual access
+/// retries briefly because lock is often released soon such as on autosave
+/// returns error starting with `FILE_IN_USE_ERROR` when file is still locked
+pub fn ensure_accessible(filepath: &str, write: bool) -> Result<(), String> {
+    if filepath.is_empty() || !os_path(filepath).is_file() {
+        return Ok(());
+    }
+    let mut attempt = 1;
+    loop {
+        match open(filepath, write) {
+            Ok(_) => return Ok(()),
+            Err(err) if is_in_use_error(&err) => {
+                if LOCK_RETRY_ATTEMPTS <= attempt {
+                    return Err(format!("{}: {} ({})", FILE_IN_USE_ERROR, filepath, err));
+                }
+                thread::sleep(LOCK_RETRY_INTERVAL);
+                attempt += 1;
+            }
+            // other failures are reported by actual access
+            Err(_) => return Ok(()),
+        }
+    }
+}
+
+/// check if file is locked or opened in office apps which leave lock files beside
+pub fn file_use_status(filepath: &str) -> FileUseStatus {
+    let locked = open(filepath, true).is_err_and(|err| is_in_use_error(&err));
+    FileUseStatus {
+        locked,
+        lock_file: lock_file(filepath),
+    }
+}
+
+/// open file without modifying it
+fn open(filepath: &str, write: bool) -> Result<(), IOError> {
+    OpenOptions::new()
+        .read(true)
+        .write(write)
+        .open(os_path(filepath))
+        .map(|_| ())
+}
+
+/// check if io error is caused by lock or sharing violation
+fn is_in_use_error(err: &IOError) -> bool {
+    // ERROR_SHARING_VIOLATION and ERROR_LOCK_VIOLATION
+    #[cfg(target_os = "windows")]
+    if matches!(err.raw_os_error(), Some(32) | Some(33)) {
+        return true;
+    }
+    err.kind() == ErrorKind::WouldBlock
+}
+
+/// lock file left by microsoft office (`~$name.xlsx`) or libreoffice (`.~lock.name.xlsx#`)
+fn lock_file(filepath: &str) -> Option<String> {
+    let path = os_path(filepath);
+    let dir = path.parent()?;
+    let filename = os_str_string(path.file_name()?);
+    // office replaces first chars of long names
+    let office_names = [
+        format!("~${}", filename),
+        format!("~${}", filename.chars().skip(1).collect::<String>()),
+        format!("~${}", filename.chars().skip(2).collect::<String>()),
+    ];
+    office_names
+        .iter()
+        .chain([format!(".~lock.{}#", filename)].iter())
+        .map(|x| dir.join(x))
+        .find(|x| x.is_file())
+        .map(|x| path_string(&x))
+}
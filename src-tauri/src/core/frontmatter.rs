@@ -0,0 +1,223 @@
+use std::collections::BTreeMap;
+
+use super::diff::lines_diff_response;
+use super::field_diff::field_diffs;
+use super::types::{FrontmatterDiff, ReadContent};
+
+/// delimiter of yaml frontmatter
+const YAML_DELIMITER: &str = "---";
+/// delimiter of toml frontmatter such as in hugo
+const TOML_DELIMITER: &str = "+++";
+
+/// frontmatter split from body of file
+struct Frontmatter {
+    fields: BTreeMap<String, String>,
+    body: String,
+    body_start_line: usize,
+}
+
+/// compare static-site content such as markdown with frontmatter in two sections
+/// frontmatter is compared by fields and body as text
+/// file without frontmatter is compared as body only
+pub fn frontmatter_diff(old_read: ReadContent, new_read: ReadContent) -> FrontmatterDiff {
+    let old = split_frontmatter(&old_read.content);
+    let new = split_frontmatter(&new_read.content);
+    FrontmatterDiff {
+        frontmatter: field_diffs(&old.fields, &new.fields),
+        body: lines_diff_response(
+            ReadContent {
+                content: old.body,
+                ..old_read
+            },
+            ReadContent {
+                content: new.body,
+                ..new_read
+            },
+        ),
+        old_body_start_line: old.body_start_line,
+        new_body_start_line: new.body_start_line,
+    }
+}
+
+/// split frontmatter between delimiter lines at head of content
+fn split_frontmatter(content: &str) -> Frontmatter {
+    let no_frontmatter = || Frontmatter {
+        fields: BTreeMap::new(),
+        body: content.to_owned(),
+        body_start_line: 0,
+    };
+    let content_without_bom = content.strip_prefix('\u{FEFF}').unwrap_or(content);
+    let lines = content_without_bom
+        .split_inclusive('\n')
+        .collect::<Vec<_>>();
+    let Some(delimiter) = lines
+        .first()
+        .map(|x| x.trim_end())
+        .filter(|x| [YAML_DELIMITER, TOML_DELIMITER].contains(x))
+    else {
+        return no_frontmatter();
+    };
+    let Some(end) = (1..lines.len()).find(|i| {
+        let line = lines[*i].trim_end();
+        line == delimiter || (delimiter == YAML_DELIMITER && line == "...")
+    }) else {
+        return no_frontmatter();
+    };
+
+    let frontmatter_lines = lines[1..end]
+        .iter()
+        .map(|x| x.trim_end_matches(['\r', '\n']))
+        .collect::<Vec<_>>();
+    let fields = if delimiter == YAML_DELIMITER {
+        yaml_fields(&frontmatter_lines)
+    } else {
+        toml_fields(&frontmatter_lines)
+    };
+    Frontmatter {
+        fields,
+        body: lines[end + 1..].concat(),
+        body_start_line: end + 1,
+    }
+}
+
+/// yaml fields flattened into dotted keys by indentation
+/// sequence items are indexed and block scalars are joined with line feeds
+fn yaml_fields(lines: &[&str]) -> BTreeMap<String, String> {
+    let mut ret = BTreeMap::new();
+    // indent and key of each mapping or item enclosing current line
+    let mut parents: Vec<(usize, String)> = vec![];
+    // count of items in each sequence by its key
+    let mut item_counts: BTreeMap<String, usize> = BTreeMap::new();
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        i += 1;
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let mut indent = line.len() - trimmed.len();
+        let is_item = trimmed.starts_with("- ") || trimmed == "-";
+        // sequence may be at the same indent as its key
+        while parents
+            .last()
+            .is_some_and(|x| indent < x.0 || (indent == x.0 && (!is_item || x.1.ends_with(']'))))
+        {
+            parents.pop();
+        }
+        let parent_key = |parents: &[(usize, String)]| {
+            parents.last().map(|x| x.1.to_owned()).unwrap_or_default()
+        };
+
+        let mut entry = trimmed;
+        // item of sequence, which may start mapping in it
+        if let Some(rest) = trimmed
+            .strip_prefix("- ")
+            .or((trimmed == "-").then_some(""))
+        {
+            let sequence_key = parent_key(&parents);
+            let count = item_counts.entry(sequence_key.to_owned()).or_default();
+            let item_key = format!("{}[{}]", sequence_key, count);
+            *count += 1;
+            parents.push((indent, item_key.to_owned()));
+            let rest = rest.trim_start();
+            if !is_mapping_entry(rest) {
+                if !rest.is_empty() {
+                    ret.insert(item_key, unquoted(rest));
+                }
+                continue;
+            }
+            indent += trimmed.len() - rest.len();
+            entry = rest;
+        }
+        if !is_mapping_entry(entry) {
+            // continuation of plain multi-line scalar
+            let key = parent_key(&parents);
+            let value = ret.entry(key).or_insert_with(String::new);
+            if !value.is_empty() {
+                value.push(' ');
+            }
+            value.push_str(entry.trim());
+            continue;
+        }
+
+        let (key, value) = entry.split_once(':').unwrap_or((entry, ""));
+        let parent = parent_key(&parents);
+        let key = unquoted(key);
+        let path = if parent.is_empty() {
+            key
+        } else {
+            format!("{}.{}", parent, key)
+        };
+        let value = value.trim();
+        if value.is_empty() {
+            parents.push((indent, path));
+            continue;
+        }
+        if value.starts_with(['|', '>']) {
+            let mut block = vec![];
+            while i < lines.len() {
+                let next = lines[i];
+                let next_trimmed = next.trim_start();
+                if !next_trimmed.is_empty() && next.len() - next_trimmed.len() <= indent {
+                    break;
+                }
+                block.push(next_trimmed.trim_end());
+                i += 1;
+            }
+            let separator = if value.starts_with('|') { "\n" } else { " " };
+            ret.insert(path, block.join(separator).trim_end().to_owned());
+            continue;
+        }
+        ret.insert(path, unquoted(value));
+    }
+    ret
+}
+
+/// toml fields flattened into dotted keys with tables as prefixes
+fn toml_fields(lines: &[&str]) -> BTreeMap<String, String> {
+    let mut ret = BTreeMap::new();
+    let mut table = String::new();
+    for line in lines {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with('[') && line.ends_with(']') {
+            table = line.trim_matches(['[', ']']).trim().to_owned();
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let key = unquoted(key.trim());
+        let path = if table.is_empty() {
+            key
+        } else {
+            format!("{}.{}", table, key)
+        };
+        ret.insert(path, unquoted(value.trim()));
+    }
+    ret
+}
+
+/// check if yaml line is `key: value` or `key:` out of quoted scalar
+fn is_mapping_entry(s: &str) -> bool {
+    if s.starts_with(['"', '\'', '[', '{']) {
+        return false;
+    }
+    s.find(':')
+        .is_some_and(|i| s[i + 1..].is_empty() || s[i + 1..].starts_with(' '))
+}
+
+/// scalar without surrounding quotes
+fn unquoted(s: &str) -> String {
+    let s = s.trim();
+    let quoted = 2 <= s.len()
+        && ((s.starts_with('"') && s.ends_with('"')) || (s.starts_with('\'') && s.ends_with('\'')));
+    if quoted {
+        s[1..s.len() - 1].to_owned()
+    } else {
+        s.to_owned()
+    }
+}
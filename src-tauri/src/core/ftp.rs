@@ -0,0 +1,512 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use native_tls::{TlsConnector, TlsStream};
+
+use super::credentials::RemoteCredentials;
+use super::file::{comma_separated_number, human_readable_size};
+use super::temp_file::{temp_dir, write_temp_file};
+use super::types::{FileAttr, ListDirResponse, NumberFormat};
+
+/// default port of ftp control connection
+const FTP_DEFAULT_PORT: u16 = 21;
+/// timeout on connection and read / write
+const FTP_TIMEOUT_SECS: u64 = 30;
+/// bytes read at once from data connection
+const FTP_READ_CHUNK_SIZE: usize = 64 * 1024;
+
+/// location parsed from `ftp://` or `ftps://` url
+pub struct FtpLocation {
+    pub secure: bool,
+    pub host: String,
+    pub port: u16,
+    pub user: String,
+    pub password: String,
+    pub path: String,
+}
+
+/// control or data connection, optionally over tls
+enum FtpStream {
+    Plain(TcpStream),
+    Tls(Box<TlsStream<TcpStream>>),
+}
+
+impl Read for FtpStream {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            FtpStream::Plain(x) => x.read(buf),
+            FtpStream::Tls(x) => x.read(buf),
+        }
+    }
+}
+
+impl Write for FtpStream {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            FtpStream::Plain(x) => x.write(buf),
+            FtpStream::Tls(x) => x.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            FtpStream::Plain(x) => x.flush(),
+            FtpStream::Tls(x) => x.flush(),
+        }
+    }
+}
+
+/// logged-in ftp session
+struct FtpSession {
+    control: BufReader<FtpStream>,
+    host: String,
+    secure: bool,
+}
+
+impl FtpSession {
+    /// connect and log in
+    fn connect(location: &FtpLocation) -> Result<Self, String> {
+        let stream = tcp_connect(&location.host, location.port)?;
+        let mut session = FtpSession {
+            control: BufReader::new(FtpStream::Plain(stream)),
+            host: location.host.to_owned(),
+            secure: false,
+        };
+        session.expect_response(&[220])?;
+
+        if location.secure {
+            // explicit tls
+            session.command("AUTH TLS", &[234])?;
+            let stream = match session.control.into_inner() {
+                FtpStream::Plain(x) => x,
+                FtpStream::Tls(_) => unreachable!(),
+            };
+            session = FtpSession {
+                control: BufReader::new(FtpStream::Tls(Box::new(tls_connect(
+                    &location.host,
+                    stream,
+                )?))),
+                host: location.host.to_owned(),
+                secure: true,
+            };
+        }
+
+        let user = if location.user.is_empty() {
+            "anonymous"
+        } else {
+            location.user.as_str()
+        };
+        let (code, _) = session.command(&format!("USER {}", user), &[230, 331])?;
+        if code == 331 {
+            session.command(&format!("PASS {}", location.password), &[230])?;
+        }
+        if session.secure {
+            session.command("PBSZ 0", &[200])?;
+            session.command("PROT P", &[200])?;
+        }
+        session.command("TYPE I", &[200])?;
+        Ok(session)
+    }
+
+    /// send command and check reply code
+    fn command(&mut self, command: &str, expected: &[u32]) -> Result<(u32, String), String> {
+        let stream = self.control.get_mut();
+        stream
+            .write_all(format!("{}\r\n", command).as_bytes())
+            .and_then(|_| stream.flush())
+            .map_err(|err| format!("Failed to send ftp command ({})", err))?;
+        self.expect_response(expected)
+    }
+
+    /// read reply which may span multiple lines and check its code
+    fn expect_response(&mut self, expected: &[u32]) -> Result<(u32, String), String> {
+        let mut line = String::new();
+        self.control
+            .read_line(&mut line)
+            .map_err(|err| format!("Failed to read ftp reply ({})", err))?;
+        let code = line
+            .get(0..3)
+            .and_then(|x| x.parse::<u32>().ok())
+            .ok_or(format!("Invalid ftp reply: {}", line.trim()))?;
+        let mut message = line.clone();
+        // multi-line reply ends with line starting with code and space
+        if line.as_bytes().get(3) == Some(&b'-') {
+            let terminator = format!("{} ", code);
+            loop {
+                line.clear();
+                let read = self
+                    .control
+                    .read_line(&mut line)
+                    .map_err(|err| format!("Failed to read ftp reply ({})", err))?;
+                if read == 0 {
+                    break;
+                }
+                message.push_str(&line);
+                if line.starts_with(&terminator) {
+                    break;
+                }
+            }
+        }
+        if !expected.contains(&code) {
+            return Err(format!("Unexpected ftp reply: {}", message.trim()));
+        }
+        Ok((code, message))
+    }
+
+    /// run command transferring data over passive connection and get the data
+    /// bytes received so far are reported to `on_read` chunk by chunk
+    fn transfer(&mut self, command: &str, on_read: &mut dyn FnMut(u64)) -> Result<Vec<u8>, String> {
+        let (_, reply) = self.command("PASV", &[227])?;
+        let port = pasv_port(&reply)?;
+        // address in reply is ignored because it may be private one behind nat
+        let stream = tcp_connect(&self.host, port)?;
+
+        self.command(command, &[125, 150])?;
+        let mut data = if self.secure {
+            FtpStream::Tls(Box::new(tls_connect(&self.host, stream)?))
+        } else {
+            FtpStream::Plain(stream)
+        };
+        let mut ret = vec![];
+        let mut buffer = vec![0; FTP_READ_CHUNK_SIZE];
+        loop {
+            let read_bytes = match data.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(x) => x,
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(format!("Failed to read ftp data ({})", err)),
+            };
+            ret.extend_from_slice(&buffer[..read_bytes]);
+            on_read(ret.len() as u64);
+        }
+        drop(data);
+        self.expect_response(&[226, 250])?;
+        Ok(ret)
+    }
+
+    /// log out
+    fn quit(mut self) {
+        let _ = self.command("QUIT", &[221]);
+    }
+}
+
+/// check if path is ftp url
+pub fn is_ftp_url(path: &str) -> bool {
+    path.starts_with("ftp://") || path.starts_with("ftps://")
+}
+
+/// parse `ftp[s]://[user[:password]@]host[:port][/path]`
+pub fn parse_ftp_url(url: &str) -> Result<FtpLocation, String> {
+    let (secure, rest) = if let Some(rest) = url.strip_prefix("ftps://") {
+        (true, rest)
+    } else if let Some(rest) = url.strip_prefix("ftp://") {
+        (false, rest)
+    } else {
+        return Err(format!("Not ftp url: {}", url));
+    };
+
+    let (authority, path) = match rest.find('/') {
+        Some(i) => (&rest[..i], percent_decode(&rest[i..])),
+        None => (rest, "/".to_owned()),
+    };
+    let (userinfo, hostport) = match authority.rfind('@') {
+        Some(i) => (&authority[..i], &authority[i + 1..]),
+        None => ("", authority),
+    };
+    let (user, password) = match userinfo.split_once(':') {
+        Some((user, password)) => (percent_decode(user), percent_decode(password)),
+        None => (percent_decode(userinfo), String::new()),
+    };
+    let (host, port) = match hostport.rsplit_once(':') {
+        Some((host, port)) => (
+            host.to_owned(),
+            port.parse::<u16>()
+                .map_err(|_| format!("Invalid port in {}", url))?,
+        ),
+        None => (hostport.to_owned(), FTP_DEFAULT_PORT),
+    };
+    if host.is_empty() {
+        return Err(format!("Host is missing in {}", url));
+    }
+    // line break would end ftp command and start another one
+    if [&user, &password, &path]
+        .iter()
+        .any(|x| x.contains(['\r', '\n']))
+    {
+        return Err(format!(
+            "Line break is not allowed in {}",
+            url_without_password(url)
+        ));
+    }
+
+    Ok(FtpLocation {
+        secure,
+        host,
+        port,
+        user,
+        password,
+        path,
+    })
+}
+
+/// url without password to be given back to webview
+fn url_without_password(url: &str) -> String {
+    let scheme_end = url.find("://").map(|x| x + 3).unwrap_or_default();
+    let (scheme, rest) = url.split_at(scheme_end);
+    let authority_end = rest.find('/').unwrap_or(rest.len());
+    let (authority, path) = rest.split_at(authority_end);
+    let authority = match authority.rfind('@') {
+        Some(i) => {
+            let user = authority[..i].split(':').next().unwrap_or_default();
+            format!("{}@{}", user, &authority[i + 1..])
+        }
+        None => authority.to_owned(),
+    };
+    format!("{}{}{}", scheme, authority, path)
+}
+
+/// parse ftp url whose password may be omitted when remembered for the same user and server
+fn authenticated_location(
+    url: &str,
+    credentials: &RemoteCredentials,
+) -> Result<FtpLocation, String> {
+    let mut location = parse_ftp_url(url)?;
+    let key = format!(
+        "{}://{}@{}:{}",
+        if location.secure { "ftps" } else { "ftp" },
+        location.user,
+        location.host,
+        location.port
+    );
+    location.password = credentials
+        .resolve(&key, &location.password)
+        .unwrap_or_default();
+    Ok(location)
+}
+
+/// list remote directory in the same shape as local one
+/// current dir in response does not carry password
+pub fn ftp_list_dir(
+    url: &str,
+    credentials: &RemoteCredentials,
+    number_format: &NumberFormat,
+) -> Result<ListDirResponse, String> {
+    let location = authenticated_location(url, credentials)?;
+    let mut session = FtpSession::connect(&location)?;
+
+    let (entries, machine_readable) =
+        match session.transfer(&format!("MLSD {}", location.path), &mut |_| ()) {
+            Ok(x) => (x, true),
+            // server not supporting mlsd
+            Err(_) => (
+                session.transfer(&format!("LIST {}", location.path), &mut |_| ())?,
+                false,
+            ),
+        };
+    session.quit();
+
+    let mut dirs = vec![];
+    let mut files = vec![];
+    for line in String::from_utf8_lossy(&entries).lines() {
+        let entry = if machine_readable {
+            parse_mlsd_line(line)
+        } else {
+            parse_list_line(line)
+        };
+        let (name, is_dir, size, last_modified) = match entry {
+            Some(x) => x,
+            None => continue,
+        };
+        if name == "." || name == ".." {
+            continue;
+        }
+        if is_dir {
+            dirs.push(name);
+        } else {
+            files.push(FileAttr {
+                name,
+                bytes_size: format!("{} bytes", comma_separated_number(size, number_format)),
+                human_readable_size: human_readable_size(size, number_format),
+                last_modified,
+                // content is unknown until downloaded
+                binary_comparison_only: false,
+                mime_type: None,
+            });
+        }
+    }
+    dirs.sort();
+    files.sort();
+
+    Ok(ListDirResponse {
+        current_dir: url_without_password(url),
+        dirs,
+        files,
+        partial: false,
+    })
+}
+
+/// download remote file into temporary file to be compared as local one
+/// bytes received so far are reported to `on_progress`.
+/// caller removes the file after reading it
+pub fn ftp_download(
+    url: &str,
+    credentials: &RemoteCredentials,
+    mut on_progress: impl FnMut(u64),
+) -> Result<PathBuf, String> {
+    let location = authenticated_location(url, credentials)?;
+    let mut session = FtpSession::connect(&location)?;
+    let bytes = session.transfer(&format!("RETR {}", location.path), &mut on_progress)?;
+    session.quit();
+
+    let dirpath = temp_dir("ftp")?;
+    let filename = location
+        .path
+        .rsplit('/')
+        .next()
+        .filter(|x| !x.is_empty())
+        .unwrap_or("download");
+    let filepath = dirpath.join(safe_filename(&format!(
+        "{}-{}-{}",
+        std::process::id(),
+        location.host,
+        filename
+    )));
+    write_temp_file(&filepath, &bytes)?;
+    Ok(filepath)
+}
+
+/// remote name made safe as local file name
+/// path separators, drive letters and other chars invalid on some platforms are replaced
+pub fn safe_filename(name: &str) -> String {
+    let ret = name
+        .chars()
+        .map(|x| {
+            if x.is_control() || ['/', '\\', ':', '*', '?', '"', '<', '>', '|'].contains(&x) {
+                '_'
+            } else {
+                x
+            }
+        })
+        .collect::<String>();
+    match ret.trim_matches(['.', ' ']) {
+        "" => "download".to_owned(),
+        x => x.to_owned(),
+    }
+}
+
+/// connect tcp with timeouts
+pub fn tcp_connect(host: &str, port: u16) -> Result<TcpStream, String> {
+    let stream = TcpStream::connect((host, port))
+        .map_err(|err| format!("Failed to connect {}:{} ({})", host, port, err))?;
+    let timeout = Some(Duration::from_secs(FTP_TIMEOUT_SECS));
+    stream
+        .set_read_timeout(timeout)
+        .and_then(|_| stream.set_write_timeout(timeout))
+        .map_err(|err| format!("Failed to set timeout ({})", err))?;
+    Ok(stream)
+}
+
+/// start tls on connected stream
+pub fn tls_connect(host: &str, stream: TcpStream) -> Result<TlsStream<TcpStream>, String> {
+    let connector = TlsConnector::new().map_err(|err| format!("Failed to init tls ({})", err))?;
+    connector
+        .connect(host, stream)
+        .map_err(|err| format!("Failed to start tls with {} ({})", host, err))
+}
+
+/// get data port from `227 Entering Passive Mode (h1,h2,h3,h4,p1,p2)`
+fn pasv_port(reply: &str) -> Result<u16, String> {
+    let start = reply
+        .find('(')
+        .ok_or(format!("Invalid PASV reply: {}", reply))?;
+    let end = reply
+        .find(')')
+        .ok_or(format!("Invalid PASV reply: {}", reply))?;
+    let numbers = reply[start + 1..end]
+        .split(',')
+        .map(|x| x.trim().parse::<u16>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| format!("Invalid PASV reply: {}", reply))?;
+    if numbers.len() != 6 {
+        return Err(format!("Invalid PASV reply: {}", reply));
+    }
+    Ok(numbers[4] * 256 + numbers[5])
+}
+
+/// parse mlsd line such as `type=file;size=12;modify=20240102030405; name`
+fn parse_mlsd_line(line: &str) -> Option<(String, bool, u64, String)> {
+    let (facts, name) = line.split_once(' ')?;
+    let mut is_dir = false;
+    let mut size = 0;
+    let mut last_modified = String::new();
+    for fact in facts.split(';') {
+        let (key, value) = match fact.split_once('=') {
+            Some(x) => x,
+            None => continue,
+        };
+        match key.to_lowercase().as_str() {
+            "type" => {
+                let value = value.to_lowercase();
+                if value == "cdir" || value == "pdir" {
+                    return None;
+                }
+                is_dir = value == "dir";
+            }
+            "size" => size = value.parse().unwrap_or_default(),
+            "modify" if 14 <= value.len() => {
+                last_modified = format!(
+                    "{}-{}-{} {}:{}:{}",
+                    &value[0..4],
+                    &value[4..6],
+                    &value[6..8],
+                    &value[8..10],
+                    &value[10..12],
+                    &value[12..14]
+                )
+            }
+            _ => {}
+        }
+    }
+    Some((name.to_owned(), is_dir, size, last_modified))
+}
+
+/// parse unix style list line such as `-rw-r--r-- 1 user group 12 Jan 2 03:04 name`
+fn parse_list_line(line: &str) -> Option<(String, bool, u64, String)> {
+    let mut fields = line.split_whitespace();
+    let permissions = fields.next()?;
+    let size = fields.nth(3)?.parse::<u64>().ok()?;
+    let last_modified = [fields.next()?, fields.next()?, fields.next()?].join(" ");
+    // name may contain spaces
+    let name_start = line.find(&last_modified)? + last_modified.len();
+    let name = line[name_start..].trim();
+    // symlink shown as `name -> target`
+    let name = name.split(" -> ").next()?;
+    Some((
+        name.to_owned(),
+        permissions.starts_with('d'),
+        size,
+        last_modified,
+    ))
+}
+
+/// decode percent-encoded string in url
+pub fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut ret = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or_default();
+            if let Ok(x) = u8::from_str_radix(hex, 16) {
+                ret.push(x);
+                i += 3;
+                continue;
+            }
+        }
+        ret.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&ret).to_string()
+}
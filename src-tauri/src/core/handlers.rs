@@ -1,34 +1,156 @@
 // use tauri::Manager;
+use tauri_plugin_clipboard_manager::ClipboardExt;
 
+use std::collections::BTreeMap;
+use std::fs;
 use std::path::{Path, MAIN_SEPARATOR};
 use std::process::Command;
 
 use tauri::Manager;
 
-use super::diff::{self, chars_diffs, lines_diffs, startup_compare_set_item};
-use super::file::{self, file_manager_command, filepaths_content};
-use super::types::{CharsDiffResponse, CompareSet, LinesDiff, LinesDiffResponse, ListDirResponse};
+use super::agent::{self, agent_download, is_agent_url};
+use super::annotation;
+use super::archive::dir_archive_diffs;
+use super::audio::audio_comparison;
+use super::binary_template::{binary_field_diffs, binary_fields};
+use super::bundle;
+use super::clipboard::clipboard_image_file;
+use super::compare_mode::pair_compare_mode;
+use super::comparison::{compared_contents_response, read_compared_files};
+use super::concat::concatenated_diff;
+use super::credentials::RemoteCredentials;
+use super::diff::{
+    self, chars_diffs, lines_diff_response, single_line_chars_diff, startup_compare_set_item,
+};
+use super::diff_rows::{self, inline_rows, split_rows};
+use super::dir_diff::DirDiffResults;
+use super::equality::binary_equality;
+use super::external_tool::{self, external_tool_presets, is_tool_arg, tool_args_compare_set};
+use super::file::{self, file_manager_command, filepaths_content_with_mode};
+use super::file_lock::{self, ensure_accessible};
+use super::frontmatter::frontmatter_diff;
+use super::ftp::{self, ftp_download, is_ftp_url};
+use super::history;
+use super::hunk_copy::hunks_text;
+use super::ignored_hunk;
+use super::include::expanded_includes_diff;
+use super::integrity;
+use super::key_value::key_value_diffs;
+use super::kube_manifest::kube_manifest_content;
+use super::line_set::line_set_diff;
+use super::localization::localization_diffs;
+use super::location;
+use super::logging;
+use super::markup::markup_content;
+use super::media::{media_metadata, metadata_diffs};
+use super::navigation::{emit_navigation, NavigationStates};
+use super::netfs;
+use super::os_path::{os_str_string, path_string};
+use super::package::package_diffs;
+use super::pairing;
+use super::palette;
+use super::patch::{selected_hunks_patch, write_patch};
+use super::path_policy::{self, ensure_permitted};
+use super::permission::{self, permission_diffs, permissions_equal};
+use super::preview;
+use super::process::command_output_content;
+use super::profile::profile_comparison;
+use super::progress::Progress;
+use super::protected::{self, Password};
+use super::protobuf::protobuf_content;
+use super::range::range_content;
+use super::records::record_diffs;
+use super::registry::registry_diffs;
+use super::replace::replace_preview;
+use super::saved::SavedContents;
+use super::schedule;
+use super::scratch::{scratch_id, ScratchBuffers};
+use super::settings;
+use super::shell::{self, is_shell_arg, shell_args_compare_set};
+use super::similarity::{self, filepaths_similarity, similar_pairs};
+use super::snippet::snippet_diff;
+use super::spool::SpoolStore;
+use super::spreadsheet::keyed_sheet_row_diffs;
+use super::sql_dump::sql_dump_content;
+use super::sync;
+use super::template::template_diff_response;
+use super::text_stats;
+use super::types::{
+    Annotation, AnnotationInput, AudioComparison, BinaryEquality, BinaryField, CharsDiffResponse,
+    CharsGroup, CommandSpec, CompareMode, CompareSet, ComparisonBundle, ComparisonOptions,
+    ComparisonRule, ComparisonSummary, ConcatenatedDiffResponse, ContentRange, DiffRow, DiffSide,
+    DiffView, DirDiffFilter, DirDiffPage, DirEntryDiff, DirRollup, DirRollupRank, ExternalTool,
+    FilePairing, FilePairingResult, FilePreview, FileRevision, FileTimelineStep, FileUseStatus,
+    FrontmatterDiff, HistoryQuery, HistoryQueryResponse, HunkCopyFormat, HunkTextStats,
+    LineAuthorship, LineMapSegment, LineRange, LineSetDiff, LinesDiff, LinesDiffResponse,
+    LinesDiffStreamEvent, ListDirResponse, LocalizationEntryDiff, LogEntry, LowMemoryMode,
+    MarkupOptions, MetadataCopy, MetadataFieldDiff, NavigationDirection, NumberFormat,
+    OpenComparison, OperationKind, Palette, PaletteKind, PaneEdit, PaneLines, PartialLinesDiff,
+    PathCheck, PathPolicy, PathRemapping, PermissionDiff, PinnedDir, ProfileComparison,
+    ProgressUnit, ReadContent, RecentDir, RecordDiff, ReplacePreview, ReplaceQuery, RestoredView,
+    RunSummary, Schedule, ScheduleInput, ScratchBuffer, SheetRowDiff, ShellSelection, SimilarPair,
+    SnippetDiff, SortKey, SortOrder, SpoolInfo, SyncDirection, SyncPlan, SyncRun, SyncScriptKind,
+    Transform, TreeEntryDiff, ViewNavigation, ViewState, WorkingTreeEntryDiff,
+};
+use super::vcs::{
+    self, file_revisions, ref_file_content, ref_tree_diffs, timeline_step, working_file_content,
+    working_tree_diffs,
+};
+use super::view_state;
+use super::watch::DirWatches;
+use super::web::{is_web_url, web_download};
+use super::workspace::OpenComparisons;
+use super::xlsx_export;
 
 #[tauri::command]
 /// app starter to collect frontend startup info
 pub fn ready(app_handle: tauri::AppHandle) -> CompareSet {
-    let mut args = app_handle
+    let args = app_handle
         .env()
         .args_os
         .into_iter()
         // first arg is executable themself
-        .skip(1);
+        .skip(1)
+        .collect::<Vec<_>>();
 
-    let old = startup_compare_set_item(&args.next());
-    let new = startup_compare_set_item(&args.next());
-    CompareSet { old, new }
+    // launched via context menu entry
+    let shell_args = args.iter().map(|x| os_str_string(x)).collect::<Vec<_>>();
+    if shell_args.first().is_some_and(|x| is_tool_arg(x)) {
+        if let Some(compare_set) = tool_args_compare_set(&app_handle, &shell_args) {
+            return compare_set;
+        }
+    }
+    if shell_args.first().is_some_and(|x| is_shell_arg(x)) {
+        match shell_args_compare_set(&app_handle, &shell_args) {
+            Ok(Some(compare_set)) => return compare_set,
+            Ok(None) => {}
+            Err(err) => tracing::warn!("Failed to handle args ({})", err),
+        }
+        return CompareSet {
+            old: startup_compare_set_item(&None),
+            new: startup_compare_set_item(&None),
+            tool: None,
+        };
+    }
+
+    // paths passed by another process are dropped when not permitted by path policy
+    let mut args = args
+        .into_iter()
+        .map(|x| Some(x).filter(|x| ensure_permitted(&app_handle, &os_str_string(x)).is_ok()));
+    let old = startup_compare_set_item(&args.next().flatten());
+    let new = startup_compare_set_item(&args.next().flatten());
+    CompareSet {
+        old,
+        new,
+        tool: None,
+    }
 }
 
-#[tauri::command]
+#[tauri::command(async)]
 /// check if path is file (excluding symlink)
-pub fn is_file(filepath: &str) -> Result<bool, String> {
-    let path = Path::new(filepath);
-    let metadata = path.metadata().expect("Failed to get file metadata");
+/// metadata is fetched with timeout on network path
+pub async fn is_file(filepath: &str) -> Result<bool, String> {
+    let metadata = netfs::metadata(filepath)?;
 
     if !metadata.is_symlink() {
         Ok(metadata.is_file())
@@ -38,20 +160,250 @@ pub fn is_file(filepath: &str) -> Result<bool, String> {
 }
 
 #[tauri::command(async)]
+// command args are fields in ipc payload
+#[allow(clippy::too_many_arguments)]
 /// collect diff around content to file paths
-pub async fn diff_filepaths(old: &str, new: &str) -> Result<LinesDiffResponse, String> {
-    let (old_read, new_read) = match filepaths_content(old, new) {
-        Ok(read_contents) => (&read_contents[0].clone(), &read_contents[1].clone()),
-        Err(err) => return Err(err),
+/// text is decoded with `old_charset` / `new_charset` when specified instead of detected one
+/// and comparison mode is detected unless `compare_mode` is specified
+/// both split and inline view rows are built from the same diff when `with_rows` is true
+/// invisible chars such as zero-width space and bidi controls are marked by `reveal_invisible`
+/// lines are aligned ignoring letter case by `ignore_case` with case-only changes flagged
+/// paths embedded in text are normalized by path remapping in settings
+/// each side is transformed by `old_transforms` / `new_transforms` such as jq filter in order
+/// options not specified default to comparison rule in settings matching file path
+pub async fn diff_filepaths(
+    app_handle: tauri::AppHandle,
+    saved_contents: tauri::State<'_, SavedContents>,
+    old: &str,
+    new: &str,
+    old_charset: Option<&str>,
+    new_charset: Option<&str>,
+    compare_mode: Option<CompareMode>,
+    with_rows: Option<bool>,
+    reveal_invisible: Option<bool>,
+    ignore_case: Option<bool>,
+    old_transforms: Option<Vec<Transform>>,
+    new_transforms: Option<Vec<Transform>>,
+    on_hunks: Option<tauri::ipc::Channel<LinesDiffStreamEvent>>,
+) -> Result<LinesDiffResponse, String> {
+    let options = ComparisonOptions {
+        old_charset: old_charset.map(str::to_owned),
+        new_charset: new_charset.map(str::to_owned),
+        compare_mode,
+        with_rows,
+        reveal_invisible,
+        ignore_case,
+        old_transforms: old_transforms.unwrap_or_default(),
+        new_transforms: new_transforms.unwrap_or_default(),
     };
+    let contents = read_compared_files(&app_handle, &saved_contents, old, new, &options)?;
+    compared_contents_response(&app_handle, old, new, contents, &options, on_hunks.as_ref())
+}
 
-    let diffs = lines_diffs(old_read.content.as_str(), new_read.content.as_str());
+#[tauri::command(async)]
+/// compare only ranges of files such as lines 100 - 500 of each or byte offsets of binaries
+/// so that only known region of huge files is read
+pub async fn diff_filepath_ranges(
+    old: &str,
+    new: &str,
+    old_range: ContentRange,
+    new_range: ContentRange,
+) -> Result<LinesDiffResponse, String> {
+    let old_read = range_content(old, &old_range)?;
+    let new_read = range_content(new, &new_range)?;
+    Ok(lines_diff_response(old_read, new_read))
+}
 
-    Ok(LinesDiffResponse {
-        old_charset: old_read.charset.to_owned(),
-        new_charset: new_read.charset.to_owned(),
-        diffs,
-    })
+#[tauri::command(async)]
+/// compare two line ranges within the same file such as near-duplicated blocks
+/// `content` is compared instead of file when specified such as unsaved buffer
+pub async fn diff_snippets(
+    filepath: &str,
+    content: Option<String>,
+    old_range: LineRange,
+    new_range: LineRange,
+    dedent: bool,
+) -> Result<SnippetDiff, String> {
+    let read = match content {
+        Some(content) => ReadContent {
+            content,
+            ..Default::default()
+        },
+        None => {
+            let (mut read_contents, _) =
+                filepaths_content_with_mode(filepath, "", Some(CompareMode::Text))?;
+            read_contents.remove(0)
+        }
+    };
+    snippet_diff(read, &old_range, &new_range, dedent)
+}
+
+#[tauri::command(async)]
+/// compare actual file against template file with placeholders for golden-file testing
+/// `{{ANY}}`, `{{NUMBER}}` and `{{/regex/}}` in template match variable text in actual file
+pub async fn diff_template(template: &str, actual: &str) -> Result<LinesDiffResponse, String> {
+    let (mut read_contents, _) =
+        filepaths_content_with_mode(template, actual, Some(CompareMode::Text))?;
+    let actual_read = read_contents.remove(1);
+    let template_read = read_contents.remove(0);
+    template_diff_response(template_read, actual_read)
+}
+
+#[tauri::command(async)]
+/// compare static-site content such as markdown in two sections
+/// with yaml or toml frontmatter compared by fields and body compared as text
+pub async fn diff_frontmatter(old: &str, new: &str) -> Result<FrontmatterDiff, String> {
+    let (mut read_contents, _) = filepaths_content_with_mode(old, new, Some(CompareMode::Text))?;
+    let new_read = read_contents.remove(1);
+    let old_read = read_contents.remove(0);
+    Ok(frontmatter_diff(old_read, new_read))
+}
+
+#[tauri::command(async)]
+/// compare files as sets of lines sorted and deduplicated such as lists of ids or hosts
+/// where order is meaningless, with lines only on either side listed
+pub async fn diff_line_sets(old: &str, new: &str) -> Result<LineSetDiff, String> {
+    let (mut read_contents, _) = filepaths_content_with_mode(old, new, Some(CompareMode::Text))?;
+    let new_read = read_contents.remove(1);
+    let old_read = read_contents.remove(0);
+    Ok(line_set_diff(old_read, new_read))
+}
+
+#[tauri::command(async)]
+/// profile both files with structural stats such as line lengths, byte histogram,
+/// indentation style and language for quick look before full diff
+pub async fn profile_filepaths(old: &str, new: &str) -> Result<ProfileComparison, String> {
+    profile_comparison(old, new)
+}
+
+#[tauri::command(async)]
+/// compare multiple files per side concatenated in order such as rotated logs
+/// line ranges of source files are returned as boundaries
+pub async fn diff_concatenated(
+    old_filepaths: Vec<String>,
+    new_filepaths: Vec<String>,
+) -> Result<ConcatenatedDiffResponse, String> {
+    concatenated_diff(&old_filepaths, &new_filepaths)
+}
+
+#[tauri::command(async)]
+/// compare config files such as nginx, ssh_config and apache ones with includes expanded
+/// line ranges of included files are returned as boundaries
+pub async fn diff_expanded_includes(
+    old: &str,
+    new: &str,
+) -> Result<ConcatenatedDiffResponse, String> {
+    expanded_includes_diff(old, new)
+}
+
+#[tauri::command(async)]
+/// compare files as sets of records such as json lines and sql dumps
+/// aligned by content or by json `key` field rather than position
+pub async fn diff_records(
+    old: &str,
+    new: &str,
+    key: Option<&str>,
+) -> Result<Vec<RecordDiff>, String> {
+    record_diffs(old, new, key)
+}
+
+#[tauri::command(async)]
+/// compare spreadsheets with rows aligned by values of key columns instead of position
+pub async fn diff_sheet_rows(
+    old: &str,
+    new: &str,
+    key_columns: Vec<String>,
+    has_header: bool,
+) -> Result<Vec<SheetRowDiff>, String> {
+    keyed_sheet_row_diffs(old, new, &key_columns, has_header)
+}
+
+#[tauri::command(async)]
+/// export new workbook with cells changed from old one highlighted and commented with old values
+pub async fn export_annotated_workbook(
+    old: &str,
+    new: &str,
+    export_filepath: &str,
+) -> Result<(), String> {
+    xlsx_export::export_annotated_workbook(old, new, export_filepath)
+}
+
+#[tauri::command]
+/// detect comparison mode on file pair
+pub fn compare_mode(old: &str, new: &str) -> CompareMode {
+    pair_compare_mode(old, new)
+}
+
+#[tauri::command(async)]
+/// collect diff around stdout of commands
+pub async fn diff_commands(
+    old: CommandSpec,
+    new: CommandSpec,
+) -> Result<LinesDiffResponse, String> {
+    let old_read = command_output_content(&old)?;
+    let new_read = command_output_content(&new)?;
+
+    Ok(lines_diff_response(old_read, new_read))
+}
+
+#[tauri::command(async)]
+/// collect diff around protobuf-encoded files decoded into text trees
+pub async fn diff_protobuf(
+    old: &str,
+    new: &str,
+    proto_filepath: Option<&str>,
+    message_type: Option<&str>,
+) -> Result<LinesDiffResponse, String> {
+    let old_read = protobuf_content(old, proto_filepath, message_type)?;
+    let new_read = protobuf_content(new, proto_filepath, message_type)?;
+
+    Ok(lines_diff_response(old_read, new_read))
+}
+
+#[tauri::command(async)]
+/// collect diff around html, xml, jsx or vue templates tokenized into tags and texts
+/// attribute order and whitespace between tags can be ignored
+pub async fn diff_markup(
+    old: &str,
+    new: &str,
+    options: Option<MarkupOptions>,
+) -> Result<LinesDiffResponse, String> {
+    let options = options.unwrap_or_default();
+    let old_read = markup_content(old, &options)?;
+    let new_read = markup_content(new, &options)?;
+
+    Ok(lines_diff_response(old_read, new_read))
+}
+
+#[tauri::command(async)]
+/// collect diff between kubernetes or helm manifests split into documents by kind and name
+/// volatile fields such as `resourceVersion`, `managedFields` and `creationTimestamp` are ignored
+pub async fn diff_kube_manifests(old: &str, new: &str) -> Result<LinesDiffResponse, String> {
+    let old_read = kube_manifest_content(old)?;
+    let new_read = kube_manifest_content(new)?;
+
+    Ok(lines_diff_response(old_read, new_read))
+}
+
+#[tauri::command(async)]
+/// collect diff between mysqldump or pg_dump files split into per-table sections
+/// dump comments and settings are ignored and inserted rows are aligned by primary key
+pub async fn diff_sql_dumps(old: &str, new: &str) -> Result<LinesDiffResponse, String> {
+    let old_read = sql_dump_content(old)?;
+    let new_read = sql_dump_content(new)?;
+
+    Ok(lines_diff_response(old_read, new_read))
+}
+
+#[tauri::command(async)]
+/// check if files are identical or get first differing offset without building dump
+/// as fast pre-step before binary diff of huge files. progress is emitted as event
+pub async fn check_binary_equality(
+    app_handle: tauri::AppHandle,
+    old: &str,
+    new: &str,
+) -> Result<BinaryEquality, String> {
+    binary_equality(&app_handle, old, new)
 }
 
 #[tauri::command(async)]
@@ -62,9 +414,66 @@ pub async fn diff_chars(lines_diffs: Vec<LinesDiff>) -> Result<CharsDiffResponse
 }
 
 #[tauri::command]
+/// compare single-line inputs such as urls and hashes on chars grouped
+/// none when either has multiple lines
+pub fn diff_single_lines(old: &str, new: &str) -> Option<Vec<CharsGroup>> {
+    single_line_chars_diff(old, new)
+}
+
+#[tauri::command(async)]
 /// list directory to draw files and dirs
-pub fn list_dir(current_dir: &str) -> Result<ListDirResponse, String> {
-    file::list_dir(current_dir)
+/// network path is listed with timeout not to block backend
+pub async fn list_dir(
+    app_handle: tauri::AppHandle,
+    current_dir: &str,
+    sort_key: Option<SortKey>,
+    sort_order: Option<SortOrder>,
+) -> Result<ListDirResponse, String> {
+    let response = netfs::list_dir(
+        current_dir,
+        sort_key.unwrap_or_default(),
+        sort_order.unwrap_or_default(),
+        &settings::number_format(&app_handle),
+    )?;
+    // failure to record recent dir should not fail listing
+    if let Err(err) = location::record_visit(&app_handle, &response.current_dir) {
+        tracing::warn!("Failed to record recent dir ({})", err);
+    }
+    Ok(response)
+}
+
+#[tauri::command]
+/// get dirs visited recently in browser, latest first
+pub fn recent_dirs(app_handle: tauri::AppHandle) -> Result<Vec<RecentDir>, String> {
+    location::recent_dirs(&app_handle)
+}
+
+#[tauri::command]
+/// remove dir from recent ones, or all of them when `dir` is not specified
+pub fn remove_recent_dir(app_handle: tauri::AppHandle, dir: Option<&str>) -> Result<(), String> {
+    location::remove_recent_dir(&app_handle, dir)
+}
+
+#[tauri::command]
+/// get dirs pinned in browser
+pub fn pinned_dirs(app_handle: tauri::AppHandle) -> Result<Vec<PinnedDir>, String> {
+    location::pinned_dirs(&app_handle)
+}
+
+#[tauri::command]
+/// pin dir in browser with optional label shown instead of path
+pub fn pin_dir(
+    app_handle: tauri::AppHandle,
+    dir: &str,
+    label: Option<String>,
+) -> Result<(), String> {
+    location::pin_dir(&app_handle, dir, label)
+}
+
+#[tauri::command]
+/// unpin dir in browser
+pub fn unpin_dir(app_handle: tauri::AppHandle, dir: &str) -> Result<(), String> {
+    location::unpin_dir(&app_handle, dir)
 }
 
 #[tauri::command]
@@ -81,25 +490,106 @@ pub fn binary_comparison_only(filepath: &str) -> Result<bool, String> {
 
 #[tauri::command]
 /// collect file digest diff to be shown in explorer
-pub fn file_digest_diff(filename: &str, old_dir: &str, new_dir: &str) -> Result<bool, String> {
-    diff::file_digest_diff(filename, old_dir, new_dir)
+/// permissions, ownership and acl are also compared when `compare_permissions` is true
+pub fn file_digest_diff(
+    filename: &str,
+    old_dir: &str,
+    new_dir: &str,
+    compare_permissions: Option<bool>,
+) -> Result<bool, String> {
+    if !diff::file_digest_diff(filename, old_dir, new_dir)? {
+        return Ok(false);
+    }
+    if !compare_permissions.unwrap_or_default() {
+        return Ok(true);
+    }
+    permissions_equal(
+        &Path::new(old_dir).join(filename),
+        &Path::new(new_dir).join(filename),
+    )
 }
 
 #[tauri::command]
 /// collect directory digest diff to be shown in explorer
-pub fn dir_digest_diff(dirname: &str, old_dir: &str, new_dir: &str) -> Result<bool, String> {
-    diff::dir_digest_diff(dirname, old_dir, new_dir)
+/// permissions, ownership and acl are also compared when `compare_permissions` is true
+pub fn dir_digest_diff(
+    dirname: &str,
+    old_dir: &str,
+    new_dir: &str,
+    compare_permissions: Option<bool>,
+) -> Result<bool, String> {
+    if !diff::dir_digest_diff(dirname, old_dir, new_dir)? {
+        return Ok(false);
+    }
+    if !compare_permissions.unwrap_or_default() {
+        return Ok(true);
+    }
+    permissions_equal(
+        &Path::new(old_dir).join(dirname),
+        &Path::new(new_dir).join(dirname),
+    )
+}
+
+#[tauri::command]
+/// compare permissions, ownership and acl of entry in directories
+pub fn permission_diff(name: &str, old_dir: &str, new_dir: &str) -> Result<PermissionDiff, String> {
+    permission::permission_diff(name, old_dir, new_dir)
+}
+
+#[tauri::command(async)]
+/// collect entries whose permissions differ under directories
+pub async fn dir_permission_diffs(
+    old_dir: &str,
+    new_dir: &str,
+) -> Result<Vec<PermissionDiff>, String> {
+    permission_diffs(old_dir, new_dir)
 }
 
 #[tauri::command]
 /// save text into file
-pub fn save(filepath: &str, content: &str, charset: &str) -> Result<(), String> {
+pub fn save(
+    saved_contents: tauri::State<SavedContents>,
+    filepath: &str,
+    content: &str,
+    charset: &str,
+) -> Result<(), String> {
+    ensure_accessible(filepath, true)?;
     match file::save(filepath, content, charset) {
-        Ok(_) => Ok(()),
+        Ok(_) => {
+            saved_contents.track(
+                filepath,
+                &ReadContent {
+                    charset: charset.to_owned(),
+                    content: content.to_owned(),
+                    ..Default::default()
+                },
+            );
+            Ok(())
+        }
         Err(err) => Err(err.to_string()),
     }
 }
 
+#[tauri::command(async)]
+/// collect diff between content last read or saved and content edited in pane
+pub async fn diff_with_saved(
+    saved_contents: tauri::State<'_, SavedContents>,
+    filepath: &str,
+    content: &str,
+) -> Result<LinesDiffResponse, String> {
+    let saved = match saved_contents.get(filepath) {
+        Some(x) => x,
+        None => return Err(format!("Saved version not tracked: {}", filepath)),
+    };
+
+    let edited = ReadContent {
+        charset: saved.charset.to_owned(),
+        content: content.to_owned(),
+        ..Default::default()
+    };
+    Ok(lines_diff_response(saved, edited))
+}
+
 #[tauri::command]
 /// open file manager with directory path specifid
 pub fn open_with_file_manager(dirpath: &str) -> Result<(), String> {
@@ -116,3 +606,1315 @@ pub fn open_with_file_manager(dirpath: &str) -> Result<(), String> {
 
     Ok(())
 }
+
+#[tauri::command]
+/// get bookmarks and comments attached to comparison
+pub fn annotations(
+    app_handle: tauri::AppHandle,
+    old: &str,
+    new: &str,
+) -> Result<Vec<Annotation>, String> {
+    annotation::annotations(&app_handle, old, new)
+}
+
+#[tauri::command]
+/// attach bookmark or comment to line or hunk
+pub fn add_annotation(
+    app_handle: tauri::AppHandle,
+    old: &str,
+    new: &str,
+    annotation: AnnotationInput,
+) -> Result<Annotation, String> {
+    annotation::add_annotation(&app_handle, old, new, annotation)
+}
+
+#[tauri::command]
+/// detach bookmark or comment
+pub fn remove_annotation(
+    app_handle: tauri::AppHandle,
+    old: &str,
+    new: &str,
+    id: u64,
+) -> Result<(), String> {
+    annotation::remove_annotation(&app_handle, old, new, id)
+}
+
+#[tauri::command]
+/// mark hunk as reviewed so that it is suppressed on comparing again, or unmark it
+/// mark is kept until text of hunk changes
+pub fn set_hunk_ignored(
+    app_handle: tauri::AppHandle,
+    old: &str,
+    new: &str,
+    hunk: LinesDiff,
+    ignored: bool,
+) -> Result<(), String> {
+    ignored_hunk::set_hunk_ignored(&app_handle, old, new, &hunk, ignored)
+}
+
+#[tauri::command]
+/// get low-memory mode
+pub fn low_memory_mode(spool_store: tauri::State<SpoolStore>) -> LowMemoryMode {
+    spool_store.mode()
+}
+
+#[tauri::command]
+/// set low-memory mode
+pub fn set_low_memory_mode(spool_store: tauri::State<SpoolStore>, mode: LowMemoryMode) {
+    spool_store.set_mode(mode)
+}
+
+#[tauri::command]
+/// check if file should be read in low-memory mode
+pub fn low_memory_required(
+    spool_store: tauri::State<SpoolStore>,
+    filepath: &str,
+) -> Result<bool, String> {
+    spool_store.low_memory_required(filepath)
+}
+
+#[tauri::command(async)]
+/// spool file content into temporary file to be served in chunks
+pub async fn spool_file(
+    app_handle: tauri::AppHandle,
+    spool_store: tauri::State<'_, SpoolStore>,
+    filepath: &str,
+    binary: bool,
+) -> Result<SpoolInfo, String> {
+    let mut progress = Progress::start(
+        &app_handle,
+        OperationKind::Read,
+        filepath,
+        ProgressUnit::Bytes,
+    );
+    let result = spool_store.spool(filepath, binary, |processed, total| {
+        progress.update(processed, Some(total))
+    });
+    progress.finish(result)
+}
+
+#[tauri::command]
+/// get chunk of lines from spooled content
+pub fn spooled_lines(
+    spool_store: tauri::State<SpoolStore>,
+    id: u64,
+    start: usize,
+    count: usize,
+) -> Result<Vec<String>, String> {
+    spool_store.lines(id, start, count)
+}
+
+#[tauri::command]
+/// release spooled content
+pub fn release_spool(spool_store: tauri::State<SpoolStore>, id: u64) {
+    spool_store.release(id)
+}
+
+#[tauri::command(async)]
+/// get similarity in percentage between files without building diff
+pub async fn similarity_filepaths(old: &str, new: &str) -> Result<f32, String> {
+    filepaths_similarity(old, new)
+}
+
+#[tauri::command(async)]
+/// get similarity in percentage between texts without building diff
+pub async fn similarity_texts(old: &str, new: &str) -> Result<f32, String> {
+    Ok(similarity::similarity(old, new))
+}
+
+#[tauri::command(async)]
+/// pair files renamed between directories by similarity
+pub async fn similar_filepaths_pairs(
+    old_dir: &str,
+    new_dir: &str,
+    threshold: f32,
+) -> Result<Vec<SimilarPair>, String> {
+    similar_pairs(old_dir, new_dir, threshold)
+}
+
+#[tauri::command]
+/// check if entries can be registered in os file manager context menu
+/// so that ui hides registration on os not supported such as macos
+pub fn context_menu_supported() -> bool {
+    shell::is_context_menu_supported()
+}
+
+#[tauri::command]
+/// register "compare with forskscope" entries in os file manager context menu
+pub fn register_context_menu() -> Result<(), String> {
+    shell::register_context_menu()
+}
+
+#[tauri::command]
+/// remember file as left side of next comparison until right side is chosen
+pub fn select_left_side(
+    app_handle: tauri::AppHandle,
+    filepath: &str,
+) -> Result<ShellSelection, String> {
+    shell::select_left(&app_handle, filepath)
+}
+
+#[tauri::command]
+/// get left side selected in this or previous launch and waiting for right side
+pub fn left_side_selection(app_handle: tauri::AppHandle) -> Result<Option<ShellSelection>, String> {
+    shell::left_selection(&app_handle)
+}
+
+#[tauri::command]
+/// get compare set of left side selected and file chosen as right side
+/// selection is cleared on pairing
+pub fn compare_with_left_side(
+    app_handle: tauri::AppHandle,
+    filepath: &str,
+) -> Result<CompareSet, String> {
+    shell::compare_with_selected(&app_handle, filepath)
+}
+
+#[tauri::command]
+/// forget left side selected
+pub fn clear_left_side_selection(app_handle: tauri::AppHandle) -> Result<(), String> {
+    shell::clear_left_selection(&app_handle)
+}
+
+#[tauri::command]
+/// register app as difftool and mergetool in global git config
+pub fn register_git_tool() -> Result<(), String> {
+    external_tool::register_git_tool()
+}
+
+#[tauri::command]
+/// finish merge on launch by git as mergetool
+/// exit code tells git whether merged file is resolved
+pub fn finish_merge(app_handle: tauri::AppHandle, resolved: bool) {
+    app_handle.exit(if resolved { 0 } else { 1 });
+}
+
+#[tauri::command]
+/// get external diff tools configured, or presets when none is configured
+pub fn external_tools(app_handle: tauri::AppHandle) -> Result<Vec<ExternalTool>, String> {
+    let ret = settings::external_tools(&app_handle)?;
+    if ret.is_empty() {
+        Ok(external_tool_presets())
+    } else {
+        Ok(ret)
+    }
+}
+
+#[tauri::command]
+/// update external diff tools
+pub fn set_external_tools(
+    app_handle: tauri::AppHandle,
+    external_tools: Vec<ExternalTool>,
+) -> Result<(), String> {
+    settings::set_external_tools(&app_handle, external_tools)
+}
+
+#[tauri::command]
+/// get time budget of lines diff in milliseconds
+pub fn diff_timeout(app_handle: tauri::AppHandle) -> u64 {
+    settings::diff_timeout(&app_handle).as_millis() as u64
+}
+
+#[tauri::command]
+/// update time budget of lines diff in milliseconds. none resets it to default
+/// diff running out of it returns coarse hunks flagged as timed out
+pub fn set_diff_timeout(
+    app_handle: tauri::AppHandle,
+    diff_timeout_millis: Option<u64>,
+) -> Result<(), String> {
+    settings::set_diff_timeout(&app_handle, diff_timeout_millis)
+}
+
+#[tauri::command]
+/// open file pair in external diff tool such as beyond compare, meld or vimdiff
+pub fn launch_external_tool(
+    app_handle: tauri::AppHandle,
+    name: &str,
+    old: &str,
+    new: &str,
+) -> Result<(), String> {
+    let tools = external_tools(app_handle)?;
+    let tool = tools
+        .iter()
+        .find(|x| x.name == name)
+        .ok_or(format!("External tool not found: {}", name))?;
+    external_tool::launch_external_tool(tool, old, new)
+}
+
+#[tauri::command]
+/// remove entries registered in os file manager context menu
+pub fn unregister_context_menu() -> Result<(), String> {
+    shell::unregister_context_menu()
+}
+
+#[tauri::command(async)]
+/// compare trees at two refs of repository as directories
+pub async fn diff_refs_tree(
+    repo: &str,
+    old_ref: &str,
+    new_ref: &str,
+    dir: Option<&str>,
+) -> Result<Vec<TreeEntryDiff>, String> {
+    ref_tree_diffs(repo, old_ref, new_ref, dir)
+}
+
+#[tauri::command(async)]
+/// collect diff around file at two refs of repository
+pub async fn diff_refs_file(
+    repo: &str,
+    old_ref: &str,
+    new_ref: &str,
+    path: &str,
+) -> Result<LinesDiffResponse, String> {
+    let old_read = ref_file_content(repo, old_ref, path)?;
+    let new_read = ref_file_content(repo, new_ref, path)?;
+    Ok(lines_diff_response(old_read, new_read))
+}
+
+#[tauri::command(async)]
+/// compare working tree of repository with tree at its earlier commit as directories
+/// untracked files are flagged apart from files added to index
+pub async fn diff_working_tree(
+    repo: &str,
+    git_ref: &str,
+    dir: Option<&str>,
+) -> Result<Vec<WorkingTreeEntryDiff>, String> {
+    working_tree_diffs(repo, git_ref, dir)
+}
+
+#[tauri::command(async)]
+/// collect diff around file at ref of repository and the file in its working tree
+pub async fn diff_working_tree_file(
+    repo: &str,
+    git_ref: &str,
+    path: &str,
+) -> Result<LinesDiffResponse, String> {
+    let old_read = ref_file_content(repo, git_ref, path)?;
+    let new_read = working_file_content(repo, path)?;
+    Ok(lines_diff_response(old_read, new_read))
+}
+
+#[tauri::command(async)]
+/// list commits touching git-tracked file, newest first, following renames
+pub async fn file_timeline(filepath: &str) -> Result<Vec<FileRevision>, String> {
+    file_revisions(filepath)
+}
+
+#[tauri::command(async)]
+/// compare file at revision in its history with working copy
+/// index steps back in history from 0 for the newest revision
+pub async fn diff_file_timeline(filepath: &str, index: usize) -> Result<FileTimelineStep, String> {
+    timeline_step(filepath, index)
+}
+
+#[tauri::command(async)]
+/// compare wav or mp3 files by duration, sample rate, channels and bitrate
+/// coarse waveform similarity is measured on pcm wav when `with_waveform` is true
+pub async fn diff_audio(
+    old: &str,
+    new: &str,
+    with_waveform: Option<bool>,
+) -> Result<AudioComparison, String> {
+    audio_comparison(old, new, with_waveform.unwrap_or_default())
+}
+
+#[tauri::command(async)]
+/// extract metadata such as exif and id3 of media file
+pub async fn media_file_metadata(filepath: &str) -> Result<BTreeMap<String, String>, String> {
+    media_metadata(filepath)
+}
+
+#[tauri::command(async)]
+/// compare metadata of media files field by field
+pub async fn diff_media_metadata(old: &str, new: &str) -> Result<Vec<MetadataFieldDiff>, String> {
+    metadata_diffs(old, new)
+}
+
+#[tauri::command(async)]
+/// list directory on ftp / ftps server
+pub async fn ftp_list_dir(
+    app_handle: tauri::AppHandle,
+    remote_credentials: tauri::State<'_, RemoteCredentials>,
+    url: &str,
+) -> Result<ListDirResponse, String> {
+    ftp::ftp_list_dir(
+        url,
+        &remote_credentials,
+        &settings::number_format(&app_handle),
+    )
+}
+
+#[tauri::command(async)]
+/// list directory served by forskscope agent on another machine
+pub async fn agent_list_dir(
+    app_handle: tauri::AppHandle,
+    remote_credentials: tauri::State<'_, RemoteCredentials>,
+    url: &str,
+) -> Result<ListDirResponse, String> {
+    agent::agent_list_dir(
+        url,
+        &remote_credentials,
+        &settings::number_format(&app_handle),
+    )
+}
+
+#[tauri::command(async)]
+/// save image on clipboard into temporary png file to be compared as either side
+/// such as two screenshots compared in image mode without saving them first
+pub async fn clipboard_image(app_handle: tauri::AppHandle) -> Result<String, String> {
+    Ok(path_string(&clipboard_image_file(&app_handle)?))
+}
+
+#[tauri::command(async)]
+/// fetch text behind url such as gist, code permalink or paste into temporary file
+/// so that it is compared as either side in any mode
+pub async fn web_url_file(app_handle: tauri::AppHandle, url: &str) -> Result<String, String> {
+    let mut progress = Progress::start(
+        &app_handle,
+        OperationKind::Download,
+        url,
+        ProgressUnit::Bytes,
+    );
+    let downloaded = web_download(&app_handle, url, |processed, total| {
+        progress.update(processed, total)
+    });
+    progress.finish(downloaded).map(|x| path_string(&x))
+}
+
+#[tauri::command(async)]
+/// collect diff around files either of which may be on ftp / ftps server, agent or web
+pub async fn diff_remote_filepaths(
+    app_handle: tauri::AppHandle,
+    remote_credentials: tauri::State<'_, RemoteCredentials>,
+    old: &str,
+    new: &str,
+) -> Result<LinesDiffResponse, String> {
+    let local_filepath = |path: &str| -> Result<String, String> {
+        if !is_ftp_url(path) && !is_agent_url(path) && !is_web_url(path) {
+            return Ok(path.to_owned());
+        }
+        let mut progress = Progress::start(
+            &app_handle,
+            OperationKind::Download,
+            path,
+            ProgressUnit::Bytes,
+        );
+        let downloaded = if is_ftp_url(path) {
+            // size is not known before transfer ends
+            ftp_download(path, &remote_credentials, |processed| {
+                progress.update(processed, None)
+            })
+        } else if is_web_url(path) {
+            web_download(&app_handle, path, |processed, total| {
+                progress.update(processed, total)
+            })
+        } else {
+            agent_download(path, &remote_credentials, |processed, total| {
+                progress.update(processed, Some(total))
+            })
+        };
+        progress.finish(downloaded).map(|x| path_string(&x))
+    };
+    let old_filepath = local_filepath(old);
+    let new_filepath = local_filepath(new);
+    let read = match (&old_filepath, &new_filepath) {
+        (Ok(old_filepath), Ok(new_filepath)) => {
+            filepaths_content_with_mode(old_filepath, new_filepath, None)
+        }
+        (Err(err), _) | (_, Err(err)) => Err(err.to_owned()),
+    };
+    // downloaded files are no longer needed once read
+    for (path, filepath) in [(old, old_filepath), (new, new_filepath)] {
+        match filepath {
+            Ok(filepath) if filepath != path => {
+                let _ = fs::remove_file(filepath);
+            }
+            _ => {}
+        }
+    }
+    let (read_contents, compare_mode) = read?;
+    Ok(LinesDiffResponse {
+        compare_mode: Some(compare_mode),
+        ..lines_diff_response(read_contents[0].clone(), read_contents[1].clone())
+    })
+}
+
+#[tauri::command]
+/// get saved comparison sessions to be re-run
+pub fn schedules(app_handle: tauri::AppHandle) -> Result<Vec<Schedule>, String> {
+    schedule::schedules(&app_handle)
+}
+
+#[tauri::command]
+/// save comparison session to be re-run at launch or at intervals
+pub fn add_schedule(
+    app_handle: tauri::AppHandle,
+    input: ScheduleInput,
+) -> Result<Schedule, String> {
+    schedule::add_schedule(&app_handle, input)
+}
+
+#[tauri::command]
+/// remove saved comparison session
+pub fn remove_schedule(app_handle: tauri::AppHandle, id: u64) -> Result<(), String> {
+    schedule::remove_schedule(&app_handle, id)
+}
+
+#[tauri::command(async)]
+/// run saved comparison session now and get whether result changed since last run
+pub async fn run_schedule(app_handle: tauri::AppHandle, id: u64) -> Result<RunSummary, String> {
+    schedule::run_schedule(&app_handle, id)
+}
+
+#[tauri::command(async)]
+// command args are fields in ipc payload
+#[allow(clippy::too_many_arguments)]
+/// get page of folder comparison result filtered by status, extension and size
+/// directories are compared again only when `refresh` is true
+/// result of last launch is shown at first with changed entries marked stale
+pub async fn filtered_dir_diffs(
+    app_handle: tauri::AppHandle,
+    dir_diff_results: tauri::State<'_, DirDiffResults>,
+    old_dir: &str,
+    new_dir: &str,
+    filter: Option<DirDiffFilter>,
+    offset: Option<usize>,
+    limit: Option<usize>,
+    refresh: Option<bool>,
+) -> Result<DirDiffPage, String> {
+    dir_diff_results.page(
+        &app_handle,
+        old_dir,
+        new_dir,
+        &filter.unwrap_or_default(),
+        offset.unwrap_or_default(),
+        limit,
+        refresh.unwrap_or_default(),
+    )
+}
+
+#[tauri::command(async)]
+/// rank directories of folder comparison by changed files or bytes under them
+/// to find subtrees diverged most
+pub async fn dir_diff_rollups(
+    app_handle: tauri::AppHandle,
+    dir_diff_results: tauri::State<'_, DirDiffResults>,
+    old_dir: &str,
+    new_dir: &str,
+    rank: Option<DirRollupRank>,
+    max_depth: Option<usize>,
+) -> Result<Vec<DirRollup>, String> {
+    dir_diff_results.rollups(
+        &app_handle,
+        old_dir,
+        new_dir,
+        rank.unwrap_or_default(),
+        max_depth,
+    )
+}
+
+#[tauri::command(async)]
+/// verify files under root against manifest of sha256sums, sfv or custom json
+/// result is in folder comparison format where missing files are old only
+/// and files not listed are new only
+pub async fn verify_manifest(
+    app_handle: tauri::AppHandle,
+    manifest: &str,
+    root: &str,
+) -> Result<Vec<DirEntryDiff>, String> {
+    let mut progress = Progress::start(
+        &app_handle,
+        OperationKind::Hash,
+        root,
+        ProgressUnit::Entries,
+    );
+    let result = integrity::verify_manifest(manifest, root, |processed, total| {
+        progress.update(processed, Some(total))
+    });
+    progress.finish(result)
+}
+
+#[tauri::command]
+/// forget folder comparison result kept for filters and pages
+pub fn release_dir_diffs(
+    dir_diff_results: tauri::State<'_, DirDiffResults>,
+    old_dir: &str,
+    new_dir: &str,
+) {
+    dir_diff_results.release(old_dir, new_dir)
+}
+
+#[tauri::command(async)]
+/// list operations to make one folder match the other on folder comparison result
+/// nothing is executed
+pub async fn sync_plan(
+    app_handle: tauri::AppHandle,
+    dir_diff_results: tauri::State<'_, DirDiffResults>,
+    old_dir: &str,
+    new_dir: &str,
+    direction: SyncDirection,
+) -> Result<SyncPlan, String> {
+    let entries = dir_diff_results.entries(&app_handle, old_dir, new_dir)?;
+    Ok(sync::sync_plan(old_dir, new_dir, &entries, direction))
+}
+
+#[tauri::command]
+/// export sync plan as shell script
+pub fn sync_plan_script(plan: SyncPlan, script_kind: SyncScriptKind) -> String {
+    sync::sync_plan_script(&plan, script_kind)
+}
+
+#[tauri::command(async)]
+/// execute sync plan confirmed by user with progress events and journal
+/// deleted files are moved into app data dir instead when `use_trash` is true
+pub async fn execute_sync(
+    app_handle: tauri::AppHandle,
+    plan: SyncPlan,
+    use_trash: Option<bool>,
+) -> Result<SyncRun, String> {
+    sync::execute_sync(&app_handle, &plan, use_trash.unwrap_or_default())
+}
+
+#[tauri::command(async)]
+/// copy timestamps, and permissions when `with_permissions` is true, from files on source side
+/// to the other side after their contents are confirmed identical
+/// to quiet metadata-only differences on next folder comparisons
+pub async fn copy_file_metadata(
+    old_dir: &str,
+    new_dir: &str,
+    paths: Vec<String>,
+    source: DiffSide,
+    with_permissions: Option<bool>,
+) -> Result<Vec<MetadataCopy>, String> {
+    Ok(sync::copy_metadata(
+        old_dir,
+        new_dir,
+        &paths,
+        source,
+        with_permissions.unwrap_or_default(),
+    ))
+}
+
+#[tauri::command]
+/// get executed synchronizations for auditing
+pub fn sync_journal(app_handle: tauri::AppHandle) -> Result<Vec<SyncRun>, String> {
+    sync::sync_journal(&app_handle)
+}
+
+#[tauri::command]
+/// keep watching both roots of folder comparison
+/// to emit incremental changes of entries instead of comparing directories again
+pub fn watch_dir_diffs(
+    app_handle: tauri::AppHandle,
+    dir_watches: tauri::State<'_, DirWatches>,
+    old_dir: &str,
+    new_dir: &str,
+) -> Result<(), String> {
+    dir_watches.watch(app_handle, old_dir, new_dir)
+}
+
+#[tauri::command]
+/// stop watching folder comparison
+pub fn unwatch_dir_diffs(dir_watches: tauri::State<'_, DirWatches>, old_dir: &str, new_dir: &str) {
+    dir_watches.unwatch(old_dir, new_dir)
+}
+
+#[tauri::command(async)]
+/// get who last touched each line added or changed on new side as overlay parallel to its lines
+pub async fn blame_overlay(old: &str, new: &str) -> Result<Vec<Option<LineAuthorship>>, String> {
+    vcs::blame_overlay(old, new)
+}
+
+#[tauri::command]
+/// get number format to show sizes
+pub fn number_format(app_handle: tauri::AppHandle) -> NumberFormat {
+    settings::number_format(&app_handle)
+}
+
+#[tauri::command]
+/// get rules of default comparison options per extension or glob
+pub fn comparison_rules(app_handle: tauri::AppHandle) -> Vec<ComparisonRule> {
+    settings::comparison_rules(&app_handle)
+}
+
+#[tauri::command]
+/// update rules of default comparison options. former rules take precedence
+pub fn set_comparison_rules(
+    app_handle: tauri::AppHandle,
+    comparison_rules: Vec<ComparisonRule>,
+) -> Result<(), String> {
+    settings::set_comparison_rules(&app_handle, comparison_rules)
+}
+
+#[tauri::command]
+/// get rules to treat roots and drive letters of paths as equivalent
+pub fn path_remapping(app_handle: tauri::AppHandle) -> PathRemapping {
+    settings::path_remapping(&app_handle)
+}
+
+#[tauri::command]
+/// update rules to treat roots and drive letters of paths as equivalent
+/// used by folder comparison and by paths embedded in compared text files
+pub fn set_path_remapping(
+    app_handle: tauri::AppHandle,
+    path_remapping: PathRemapping,
+) -> Result<(), String> {
+    settings::set_path_remapping(&app_handle, path_remapping)
+}
+
+#[tauri::command]
+/// get allowed and blocked roots of paths read
+pub fn path_policy(app_handle: tauri::AppHandle) -> PathPolicy {
+    settings::path_policy(&app_handle)
+}
+
+#[tauri::command]
+/// update allowed and blocked roots of paths read
+/// paths passed by another process such as context menu are refused by them
+pub fn set_path_policy(
+    app_handle: tauri::AppHandle,
+    path_policy: PathPolicy,
+) -> Result<(), String> {
+    settings::set_path_policy(&app_handle, path_policy)
+}
+
+#[tauri::command]
+/// check path against policy to refuse it or confirm warnings such as on system directory
+/// before reading from it
+pub fn check_path_policy(app_handle: tauri::AppHandle, path: &str) -> PathCheck {
+    path_policy::check_path(&app_handle, path)
+}
+
+#[tauri::command]
+/// update number format to show sizes
+pub fn set_number_format(
+    app_handle: tauri::AppHandle,
+    number_format: NumberFormat,
+) -> Result<(), String> {
+    settings::set_number_format(&app_handle, number_format)
+}
+
+#[tauri::command]
+/// get conventional number format of locale as preset
+pub fn locale_number_format(locale: &str) -> NumberFormat {
+    settings::locale_number_format(locale)
+}
+
+#[tauri::command(async)]
+/// parse header of known binary format such as png, zip, elf, pe and wav into fields
+/// to annotate hex view
+pub async fn binary_template_fields(filepath: &str) -> Result<Vec<BinaryField>, String> {
+    binary_fields(filepath)
+}
+
+#[tauri::command(async)]
+/// compare header fields of known binary format structurally
+pub async fn diff_binary_template(old: &str, new: &str) -> Result<Vec<MetadataFieldDiff>, String> {
+    binary_field_diffs(old, new)
+}
+
+#[tauri::command(async)]
+/// compare directory with zip or tar archive such as `.tar.gz` aligned by relative path
+/// either of `old` and `new` may be archive
+pub async fn diff_dir_archive(old: &str, new: &str) -> Result<Vec<TreeEntryDiff>, String> {
+    if Path::new(old).is_dir() {
+        dir_archive_diffs(old, new, false)
+    } else {
+        dir_archive_diffs(new, old, true)
+    }
+}
+
+#[tauri::command]
+/// search comparison history by path substring and date range
+pub fn comparison_history(
+    app_handle: tauri::AppHandle,
+    query: Option<HistoryQuery>,
+) -> Result<HistoryQueryResponse, String> {
+    history::query(&app_handle, &query.unwrap_or_default())
+}
+
+#[tauri::command]
+/// remove all comparison history
+pub fn clear_comparison_history(app_handle: tauri::AppHandle) -> Result<(), String> {
+    history::clear(&app_handle)
+}
+
+#[tauri::command]
+/// get navigation state of view such as current hunk, focused pane and collapsed regions
+pub fn navigation_state(
+    navigation_states: tauri::State<'_, NavigationStates>,
+    view_id: &str,
+) -> ViewNavigation {
+    navigation_states.get(view_id)
+}
+
+#[tauri::command]
+/// set hunks to navigate after diff is built
+pub fn set_navigation_hunks(
+    app_handle: tauri::AppHandle,
+    navigation_states: tauri::State<'_, NavigationStates>,
+    view_id: &str,
+    hunk_diff_indices: Vec<usize>,
+) -> ViewNavigation {
+    let navigation = navigation_states.set_hunks(view_id, hunk_diff_indices);
+    emit_navigation(&app_handle, view_id, &navigation);
+    navigation
+}
+
+#[tauri::command]
+/// move current hunk to first, previous, next or last
+pub fn navigate_hunk(
+    app_handle: tauri::AppHandle,
+    navigation_states: tauri::State<'_, NavigationStates>,
+    view_id: &str,
+    direction: NavigationDirection,
+) -> ViewNavigation {
+    let navigation = navigation_states.navigate(view_id, direction);
+    emit_navigation(&app_handle, view_id, &navigation);
+    navigation
+}
+
+#[tauri::command]
+/// jump to hunk at position
+pub fn go_to_hunk(
+    app_handle: tauri::AppHandle,
+    navigation_states: tauri::State<'_, NavigationStates>,
+    view_id: &str,
+    hunk: usize,
+) -> Result<ViewNavigation, String> {
+    let navigation = navigation_states.go_to_hunk(view_id, hunk)?;
+    emit_navigation(&app_handle, view_id, &navigation);
+    Ok(navigation)
+}
+
+#[tauri::command]
+/// focus old or new pane
+pub fn focus_pane(
+    app_handle: tauri::AppHandle,
+    navigation_states: tauri::State<'_, NavigationStates>,
+    view_id: &str,
+    side: DiffSide,
+) -> ViewNavigation {
+    let navigation = navigation_states.focus_pane(view_id, side);
+    emit_navigation(&app_handle, view_id, &navigation);
+    navigation
+}
+
+#[tauri::command]
+/// collapse or expand region
+pub fn set_collapsed(
+    app_handle: tauri::AppHandle,
+    navigation_states: tauri::State<'_, NavigationStates>,
+    view_id: &str,
+    diff_index: usize,
+    collapsed: bool,
+) -> ViewNavigation {
+    let navigation = navigation_states.set_collapsed(view_id, diff_index, collapsed);
+    emit_navigation(&app_handle, view_id, &navigation);
+    navigation
+}
+
+#[tauri::command]
+/// forget navigation state of closed view
+pub fn release_navigation(navigation_states: tauri::State<'_, NavigationStates>, view_id: &str) {
+    navigation_states.release(view_id)
+}
+
+#[tauri::command]
+/// build split or inline view rows from diffs already collected
+pub fn diff_rows(diffs: Vec<LinesDiff>, view: DiffView) -> Vec<DiffRow> {
+    match view {
+        DiffView::Split => split_rows(&diffs),
+        DiffView::Inline => inline_rows(&diffs),
+    }
+}
+
+#[tauri::command]
+/// get old-line to new-line alignment map from diffs already collected
+/// for synchronized scrolling and minimap
+pub fn line_map(diffs: Vec<LinesDiff>) -> Vec<LineMapSegment> {
+    diff_rows::line_map(&diffs)
+}
+
+#[tauri::command(async)]
+/// get char counts, display widths and scripts per changed hunk from diffs already collected
+/// for translation reviews. hunks and lines whose width changed beyond threshold are flagged
+pub async fn hunk_text_stats(
+    diffs: Vec<LinesDiff>,
+    threshold_percent: Option<u32>,
+) -> Vec<HunkTextStats> {
+    text_stats::hunk_text_stats(&diffs, threshold_percent)
+}
+
+#[tauri::command(async)]
+/// update diff of comparison open in tab after edit in pane by re-computing hunks around it
+/// only changed hunks are returned, and diff kept in backend is updated
+pub async fn diff_partially(
+    open_comparisons: tauri::State<'_, OpenComparisons>,
+    id: u64,
+    edit: PaneEdit,
+) -> Result<PartialLinesDiff, String> {
+    open_comparisons.edit_partially(id, &edit)
+}
+
+#[tauri::command]
+/// create scratch buffer to be compared without backing file
+pub fn create_scratch_buffer(
+    scratch_buffers: tauri::State<'_, ScratchBuffers>,
+    content: Option<String>,
+) -> ScratchBuffer {
+    scratch_buffers.create(content.unwrap_or_default())
+}
+
+#[tauri::command]
+/// replace content of scratch buffer edited in pane
+pub fn update_scratch_buffer(
+    scratch_buffers: tauri::State<'_, ScratchBuffers>,
+    id: u64,
+    content: String,
+) -> Result<(), String> {
+    scratch_buffers.update(id, content)
+}
+
+#[tauri::command]
+/// discard scratch buffer
+pub fn release_scratch_buffer(scratch_buffers: tauri::State<'_, ScratchBuffers>, id: u64) {
+    scratch_buffers.release(id)
+}
+
+#[tauri::command(async)]
+/// collect diff around sides either of which may be scratch buffer such as `scratch://0`
+/// file side is read as text
+pub async fn diff_with_scratch(
+    scratch_buffers: tauri::State<'_, ScratchBuffers>,
+    old: &str,
+    new: &str,
+) -> Result<LinesDiffResponse, String> {
+    let side_content = |path: &str| match scratch_id(path) {
+        Some(id) => scratch_buffers.content(id),
+        None => filepaths_content_with_mode(path, "", Some(CompareMode::Text))
+            .map(|(read_contents, _)| read_contents[0].clone()),
+    };
+    let old_read = side_content(old)?;
+    let new_read = side_content(new)?;
+    Ok(lines_diff_response(old_read, new_read))
+}
+
+#[tauri::command]
+/// save scratch buffer into file with charset
+/// buffer is discarded because side is backed by file afterwards
+pub fn save_scratch_as(
+    saved_contents: tauri::State<'_, SavedContents>,
+    scratch_buffers: tauri::State<'_, ScratchBuffers>,
+    id: u64,
+    filepath: &str,
+    charset: &str,
+) -> Result<(), String> {
+    let read = scratch_buffers.content(id)?;
+    ensure_accessible(filepath, true)?;
+    file::save(filepath, &read.content, charset).map_err(|err| err.to_string())?;
+    saved_contents.track(
+        filepath,
+        &ReadContent {
+            charset: charset.to_owned(),
+            ..read
+        },
+    );
+    scratch_buffers.release(id);
+    Ok(())
+}
+
+#[tauri::command(async)]
+/// compare file manifests of packages listing added, removed and changed files
+/// each side is package archive (`.deb`, `.rpm` or `.msi`) or name of installed package
+pub async fn diff_packages(old: &str, new: &str) -> Result<Vec<TreeEntryDiff>, String> {
+    package_diffs(old, new)
+}
+
+#[tauri::command]
+/// get colors of change levels on palette in settings
+/// css is also returned to be embedded in exports
+pub fn palette(app_handle: tauri::AppHandle) -> Result<Palette, String> {
+    let settings = settings::settings(&app_handle)?;
+    Ok(palette::palette(settings.palette_kind))
+}
+
+#[tauri::command]
+/// switch palette such as color-blind-safe ones and get its colors
+pub fn set_palette_kind(
+    app_handle: tauri::AppHandle,
+    palette_kind: PaletteKind,
+) -> Result<Palette, String> {
+    settings::set_palette_kind(&app_handle, palette_kind)?;
+    Ok(palette::palette(palette_kind))
+}
+
+#[tauri::command(async)]
+/// propose pairs of files in directories by name patterns
+/// for the user to confirm before batch comparison
+pub fn propose_file_pairings(old_dir: &str, new_dir: &str) -> Result<Vec<FilePairing>, String> {
+    pairing::propose_file_pairings(old_dir, new_dir)
+}
+
+#[tauri::command(async)]
+/// compare confirmed pairs of files in batch
+pub fn compare_file_pairings(
+    old_dir: &str,
+    new_dir: &str,
+    pairings: Vec<FilePairing>,
+) -> Vec<FilePairingResult> {
+    pairing::compare_file_pairings(old_dir, new_dir, &pairings)
+}
+
+#[tauri::command]
+/// check if file requires password to be compared so that user is prompted
+pub fn is_password_protected(filepath: &str) -> Result<bool, String> {
+    protected::is_password_protected(filepath)
+}
+
+#[tauri::command(async)]
+/// compare password-protected archives or office files decrypted in memory only
+/// passwords are used for this comparison and never kept
+pub async fn diff_protected_filepaths(
+    old: &str,
+    new: &str,
+    old_password: Option<Password>,
+    new_password: Option<Password>,
+) -> Result<LinesDiffResponse, String> {
+    let old_read = protected::protected_content(old, old_password.as_ref())?;
+    let new_read = protected::protected_content(new, new_password.as_ref())?;
+    Ok(lines_diff_response(old_read, new_read))
+}
+
+#[tauri::command]
+/// compare flat config files such as `.properties`, `.ini` and `.env` by key
+/// values of secret keys are masked. default patterns are used unless specified
+pub fn diff_key_values(
+    old: &str,
+    new: &str,
+    secret_key_patterns: Option<Vec<String>>,
+) -> Result<Vec<MetadataFieldDiff>, String> {
+    key_value_diffs(old, new, secret_key_patterns.as_deref())
+}
+
+#[tauri::command]
+/// compare localization files such as `.po`, `.strings` and android `strings.xml` by msgid or key
+/// with changes classified as new, removed, changed translation and changed source
+pub fn diff_localizations(old: &str, new: &str) -> Result<Vec<LocalizationEntryDiff>, String> {
+    localization_diffs(old, new)
+}
+
+#[tauri::command]
+/// compare windows registry exports (`.reg`) by hive path of key and value regardless of ordering
+pub fn diff_registry(old: &str, new: &str) -> Result<Vec<MetadataFieldDiff>, String> {
+    registry_diffs(old, new)
+}
+
+#[tauri::command]
+/// get recent backend log entries to report problems with context
+pub fn recent_log_entries(
+    app_handle: tauri::AppHandle,
+    limit: Option<usize>,
+) -> Result<Vec<LogEntry>, String> {
+    logging::recent_log_entries(&app_handle, limit)
+}
+
+#[tauri::command]
+/// check if file is locked or opened by another application before retry
+pub fn file_use_status(filepath: &str) -> FileUseStatus {
+    file_lock::file_use_status(filepath)
+}
+
+#[tauri::command(async)]
+/// preview single file selected in browser pane to confirm it before comparing
+pub async fn file_preview(filepath: &str, lines: Option<usize>) -> Result<FilePreview, String> {
+    preview::file_preview(filepath, lines)
+}
+
+#[tauri::command]
+/// open comparison of file pair in new tab with view state saved on previous session
+pub fn open_comparison(
+    app_handle: tauri::AppHandle,
+    open_comparisons: tauri::State<'_, OpenComparisons>,
+    old: &str,
+    new: &str,
+    options: Option<ComparisonOptions>,
+) -> OpenComparison {
+    let view_state = view_state::view_state(&app_handle, old, new).unwrap_or_else(|err| {
+        tracing::warn!("Failed to load view state ({})", err);
+        ViewState::default()
+    });
+    open_comparisons.open(old, new, options.unwrap_or_default(), view_state)
+}
+
+#[tauri::command]
+/// list comparisons open in tabs such as after webview reload
+pub fn list_comparisons(
+    open_comparisons: tauri::State<'_, OpenComparisons>,
+) -> Vec<ComparisonSummary> {
+    open_comparisons.list()
+}
+
+#[tauri::command]
+/// get comparison open in tab with its options, unsaved buffers and cached diff
+pub fn comparison(
+    open_comparisons: tauri::State<'_, OpenComparisons>,
+    id: u64,
+) -> Result<OpenComparison, String> {
+    open_comparisons.get(id)
+}
+
+#[tauri::command]
+/// replace options of comparison open in tab
+pub fn update_comparison_options(
+    open_comparisons: tauri::State<'_, OpenComparisons>,
+    id: u64,
+    options: ComparisonOptions,
+) -> Result<(), String> {
+    open_comparisons.update_options(id, options)
+}
+
+#[tauri::command]
+/// keep text edited in pane of comparison open in tab, or discard it with none
+pub fn update_comparison_buffer(
+    open_comparisons: tauri::State<'_, OpenComparisons>,
+    id: u64,
+    side: DiffSide,
+    content: Option<String>,
+) -> Result<(), String> {
+    open_comparisons.update_buffer(id, side, content)
+}
+
+#[tauri::command(async)]
+/// collect diff of comparison open in tab with unsaved buffers in place of file contents
+/// cached diff is returned unless `refresh` is true
+/// hunks and rows are left out on `summary_only` so that huge diff is not sent at once
+/// and lines are got by `comparison_pane_lines` instead
+pub async fn diff_comparison(
+    app_handle: tauri::AppHandle,
+    saved_contents: tauri::State<'_, SavedContents>,
+    open_comparisons: tauri::State<'_, OpenComparisons>,
+    id: u64,
+    refresh: Option<bool>,
+    summary_only: Option<bool>,
+) -> Result<LinesDiffResponse, String> {
+    let summarized = |response: LinesDiffResponse| {
+        if !summary_only.unwrap_or_default() {
+            return response;
+        }
+        LinesDiffResponse {
+            diffs: vec![],
+            split_rows: None,
+            inline_rows: None,
+            ..response
+        }
+    };
+    let comparison = open_comparisons.get(id)?;
+    if let Some(diff) = comparison.diff.filter(|_| !refresh.unwrap_or_default()) {
+        return Ok(summarized(diff));
+    }
+    let options = comparison.options;
+    let mut contents = read_compared_files(
+        &app_handle,
+        &saved_contents,
+        &comparison.old,
+        &comparison.new,
+        &options,
+    )?;
+    // buffers replace contents after saved ones are tracked so that they are not taken as saved
+    if let Some(content) = comparison.old_buffer {
+        contents.old.content = content;
+    }
+    if let Some(content) = comparison.new_buffer {
+        contents.new.content = content;
+    }
+    let response = compared_contents_response(
+        &app_handle,
+        &comparison.old,
+        &comparison.new,
+        contents,
+        &options,
+        None,
+    )?;
+    open_comparisons.cache_diff(id, response.clone())?;
+    Ok(summarized(response))
+}
+
+#[tauri::command(async)]
+/// preview search and replace over pane content with diff against it before applying
+pub async fn preview_replace(
+    content: String,
+    query: ReplaceQuery,
+) -> Result<ReplacePreview, String> {
+    replace_preview(&content, &query)
+}
+
+#[tauri::command(async)]
+/// search and replace over pane of comparison open in tab
+/// result is kept as unsaved buffer which can be undone
+pub async fn replace_in_comparison(
+    open_comparisons: tauri::State<'_, OpenComparisons>,
+    id: u64,
+    side: DiffSide,
+    query: ReplaceQuery,
+) -> Result<ReplacePreview, String> {
+    let comparison = open_comparisons.get(id)?;
+    let (buffer, filepath) = match side {
+        DiffSide::Old => (comparison.old_buffer, comparison.old),
+        DiffSide::New => (comparison.new_buffer, comparison.new),
+    };
+    let content = match buffer {
+        Some(x) => x,
+        None => {
+            filepaths_content_with_mode(&filepath, "", Some(CompareMode::Text))?
+                .0
+                .remove(0)
+                .content
+        }
+    };
+    let preview = replace_preview(&content, &query)?;
+    if 0 < preview.replaced_count {
+        open_comparisons.update_buffer(id, side, Some(preview.content.to_owned()))?;
+    }
+    Ok(preview)
+}
+
+#[tauri::command]
+/// undo the latest edit on buffers of comparison open in tab such as replacement
+/// side restored is returned, or none when there is nothing to undo
+pub fn undo_comparison_buffer(
+    open_comparisons: tauri::State<'_, OpenComparisons>,
+    id: u64,
+) -> Result<Option<DiffSide>, String> {
+    open_comparisons.undo_buffer(id)
+}
+
+#[tauri::command]
+/// include hunk of comparison open in tab in patch export or exclude it
+pub fn set_hunk_included(
+    open_comparisons: tauri::State<'_, OpenComparisons>,
+    id: u64,
+    diff_index: usize,
+    included: bool,
+) -> Result<(), String> {
+    open_comparisons.set_hunk_included(id, diff_index, included)
+}
+
+#[tauri::command(async)]
+/// export unified patch of comparison open in tab containing only hunks included
+/// patch is written into `patch_filepath` when specified and returned
+pub async fn export_hunks_patch(
+    app_handle: tauri::AppHandle,
+    saved_contents: tauri::State<'_, SavedContents>,
+    open_comparisons: tauri::State<'_, OpenComparisons>,
+    id: u64,
+    patch_filepath: Option<&str>,
+) -> Result<String, String> {
+    let comparison = open_comparisons.get(id)?;
+    let response =
+        diff_comparison(app_handle, saved_contents, open_comparisons, id, None, None).await?;
+    let patch = selected_hunks_patch(&comparison.old, &comparison.new, &response.diffs, |x| {
+        !comparison.excluded_hunks.contains(&x)
+    });
+    if let Some(filepath) = patch_filepath.filter(|x| !x.is_empty()) {
+        write_patch(filepath, &patch)?;
+    }
+    Ok(patch)
+}
+
+#[tauri::command(async)]
+/// copy hunk of comparison open in tab, or all hunks when `diff_index` is not specified, to clipboard
+/// text copied is returned
+pub async fn copy_hunks(
+    app_handle: tauri::AppHandle,
+    saved_contents: tauri::State<'_, SavedContents>,
+    open_comparisons: tauri::State<'_, OpenComparisons>,
+    id: u64,
+    diff_index: Option<usize>,
+    format: HunkCopyFormat,
+) -> Result<String, String> {
+    let comparison = open_comparisons.get(id)?;
+    let response = diff_comparison(
+        app_handle.clone(),
+        saved_contents,
+        open_comparisons,
+        id,
+        None,
+        None,
+    )
+    .await?;
+    let text = hunks_text(
+        &comparison.old,
+        &comparison.new,
+        &response.diffs,
+        diff_index,
+        format,
+    );
+    app_handle
+        .clipboard()
+        .write_text(text.clone())
+        .map_err(|err| format!("Failed to copy to clipboard ({})", err))?;
+    Ok(text)
+}
+
+#[tauri::command]
+/// get lines in range on side of comparison open in tab for virtual scrolling of huge text
+/// comparison must be diffed beforehand
+pub fn comparison_pane_lines(
+    open_comparisons: tauri::State<'_, OpenComparisons>,
+    id: u64,
+    side: DiffSide,
+    start: usize,
+    count: usize,
+) -> Result<PaneLines, String> {
+    open_comparisons.pane_lines(id, side, start, count)
+}
+
+#[tauri::command]
+/// keep scroll position and collapsed hunks of comparison to be restored on reopen
+pub fn update_comparison_view_state(
+    app_handle: tauri::AppHandle,
+    open_comparisons: tauri::State<'_, OpenComparisons>,
+    id: u64,
+    view_state: ViewState,
+) -> Result<(), String> {
+    let comparison = open_comparisons.get(id)?;
+    view_state::save_view_state(&app_handle, &comparison.old, &comparison.new, &view_state)?;
+    open_comparisons.update_view_state(id, view_state)
+}
+
+#[tauri::command]
+/// get view state of comparison with row to scroll to after it is diffed
+pub fn comparison_view_state(
+    open_comparisons: tauri::State<'_, OpenComparisons>,
+    id: u64,
+) -> Result<RestoredView, String> {
+    open_comparisons.restored_view(id)
+}
+
+#[tauri::command]
+/// close comparison open in tab
+pub fn close_comparison(open_comparisons: tauri::State<'_, OpenComparisons>, id: u64) {
+    open_comparisons.close(id)
+}
+
+#[tauri::command(async)]
+/// export comparison into `.forskscope` bundle with both files, options and annotations
+pub async fn export_bundle(
+    app_handle: tauri::AppHandle,
+    old: &str,
+    new: &str,
+    options: ComparisonOptions,
+    bundle_filepath: &str,
+) -> Result<(), String> {
+    bundle::export_bundle(&app_handle, old, new, options, bundle_filepath)
+}
+
+#[tauri::command(async)]
+/// import `.forskscope` bundle to open comparison as it was exported
+pub async fn import_bundle(
+    app_handle: tauri::AppHandle,
+    bundle_filepath: &str,
+) -> Result<ComparisonBundle, String> {
+    bundle::import_bundle(&app_handle, bundle_filepath)
+}
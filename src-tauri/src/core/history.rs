@@ -0,0 +1,80 @@
+use chrono::Local;
+
+use super::store;
+use super::types::{CompareMode, HistoryEntry, HistoryQuery, HistoryQueryResponse, HistoryStore};
+
+/// file name to persist comparison history
+const HISTORY_STORE_FILENAME: &str = "history.json";
+/// max entries kept. oldest ones are dropped
+const MAX_HISTORY_ENTRIES: usize = 10000;
+
+/// record completed comparison
+pub fn record(
+    app_handle: &tauri::AppHandle,
+    old: &str,
+    new: &str,
+    compare_mode: Option<CompareMode>,
+    similarity: Option<f32>,
+) -> Result<(), String> {
+    let mut store: HistoryStore = store::load(app_handle, HISTORY_STORE_FILENAME)?;
+    store.entries.push(HistoryEntry {
+        old: old.to_owned(),
+        new: new.to_owned(),
+        compared_at: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        compare_mode,
+        similarity,
+    });
+    let overflow = store.entries.len().saturating_sub(MAX_HISTORY_ENTRIES);
+    store.entries.drain(..overflow);
+    store::save(app_handle, HISTORY_STORE_FILENAME, &store)
+}
+
+/// search history by path substring and date range
+pub fn query(
+    app_handle: &tauri::AppHandle,
+    query: &HistoryQuery,
+) -> Result<HistoryQueryResponse, String> {
+    let store: HistoryStore = store::load(app_handle, HISTORY_STORE_FILENAME)?;
+
+    let path = query.path.as_deref().filter(|x| !x.is_empty());
+    let entries = store
+        .entries
+        .into_iter()
+        .rev()
+        .filter(|x| match path {
+            Some(path) => x.old.contains(path) || x.new.contains(path),
+            None => true,
+        })
+        .filter(|x| match &query.from {
+            Some(from) => from.as_str() <= x.compared_at.as_str(),
+            None => true,
+        })
+        // date only `to` includes whole day
+        .filter(|x| match &query.to {
+            Some(to) => &x.compared_at[..to.len().min(x.compared_at.len())] <= to.as_str(),
+            None => true,
+        })
+        .collect::<Vec<_>>();
+
+    let similarities = entries
+        .iter()
+        .filter_map(|x| x.similarity)
+        .collect::<Vec<_>>();
+    let average_similarity = if similarities.is_empty() {
+        None
+    } else {
+        Some(similarities.iter().sum::<f32>() / similarities.len() as f32)
+    };
+
+    Ok(HistoryQueryResponse {
+        compared_count: entries.len(),
+        last_compared_at: entries.first().map(|x| x.compared_at.to_owned()),
+        average_similarity,
+        entries,
+    })
+}
+
+/// remove all history
+pub fn clear(app_handle: &tauri::AppHandle) -> Result<(), String> {
+    store::save(app_handle, HISTORY_STORE_FILENAME, &HistoryStore::default())
+}
@@ -0,0 +1,165 @@
+use similar::DiffTag;
+
+use super::patch::selected_hunks_patch;
+use super::types::{HunkCopyFormat, LinesDiff};
+
+/// separator between old and new columns in side-by-side text
+const SIDE_BY_SIDE_SEPARATOR: &str = " | ";
+
+/// changed lines of hunk with 1-based line numbers, paired row by row
+struct HunkRows {
+    old_start: usize,
+    new_start: usize,
+    rows: Vec<(Option<String>, Option<String>)>,
+}
+
+/// text of hunk, or of all hunks when `diff_index` is none, in format for pasting into tickets and chat
+pub fn hunks_text(
+    old_label: &str,
+    new_label: &str,
+    diffs: &[LinesDiff],
+    diff_index: Option<usize>,
+    format: HunkCopyFormat,
+) -> String {
+    let included = |x: usize| diff_index.map_or(true, |i| i == x);
+    match format {
+        HunkCopyFormat::UnifiedDiff => selected_hunks_patch(old_label, new_label, diffs, included),
+        HunkCopyFormat::SideBySide => {
+            side_by_side_text(old_label, new_label, &hunk_rows(diffs, included))
+        }
+        HunkCopyFormat::MarkdownTable => {
+            markdown_table(old_label, new_label, &hunk_rows(diffs, included))
+        }
+    }
+}
+
+/// rows of hunks included, with line numbers counted through equal lines
+fn hunk_rows(diffs: &[LinesDiff], included: impl Fn(usize) -> bool) -> Vec<HunkRows> {
+    let mut ret = vec![];
+    let (mut old_line, mut new_line) = (1, 1);
+    for diff in diffs {
+        if diff.diff_kind != DiffTag::Equal && included(diff.diff_index) {
+            let count = diff.old_lines.len().max(diff.new_lines.len());
+            let line = |lines: &[String], i: usize| lines.get(i).map(|x| without_line_ending(x));
+            ret.push(HunkRows {
+                old_start: old_line,
+                new_start: new_line,
+                rows: (0..count)
+                    .map(|i| (line(&diff.old_lines, i), line(&diff.new_lines, i)))
+                    .collect(),
+            });
+        }
+        old_line += diff.old_lines.len();
+        new_line += diff.new_lines.len();
+    }
+    ret
+}
+
+/// old and new lines in columns with line numbers, hunks separated by blank line
+fn side_by_side_text(old_label: &str, new_label: &str, hunks: &[HunkRows]) -> String {
+    let old_width = hunks
+        .iter()
+        .flat_map(|x| x.rows.iter())
+        .filter_map(|x| x.0.as_ref())
+        .map(|x| x.chars().count())
+        .chain([old_label.chars().count()])
+        .max()
+        .unwrap_or_default();
+    let number_width = hunks
+        .iter()
+        .map(|x| (x.old_start + x.rows.len()).max(x.new_start + x.rows.len()))
+        .max()
+        .unwrap_or_default()
+        .to_string()
+        .len();
+
+    let mut ret = format!(
+        "{:number_width$} {:old_width$}{}{:number_width$} {}\n",
+        "", old_label, SIDE_BY_SIDE_SEPARATOR, "", new_label
+    );
+    for hunk in hunks {
+        ret.push('\n');
+        let mut old_line = hunk.old_start;
+        let mut new_line = hunk.new_start;
+        for (old, new) in &hunk.rows {
+            let old_number = old.as_ref().map_or(String::new(), |_| old_line.to_string());
+            let new_number = new.as_ref().map_or(String::new(), |_| new_line.to_string());
+            let old_text = old.as_deref().unwrap_or_default();
+            // padding of new column is trimmed so that lines have no trailing spaces
+            let row = format!(
+                "{:>number_width$} {:old_width$}{}{:>number_width$} {}",
+                old_number,
+                old_text,
+                SIDE_BY_SIDE_SEPARATOR,
+                new_number,
+                new.as_deref().unwrap_or_default()
+            );
+            ret.push_str(row.trim_end());
+            ret.push('\n');
+            old_line += old.is_some() as usize;
+            new_line += new.is_some() as usize;
+        }
+    }
+    ret
+}
+
+/// markdown table of old and new lines with line numbers, hunks separated by ellipsis row
+fn markdown_table(old_label: &str, new_label: &str, hunks: &[HunkRows]) -> String {
+    let mut ret = format!(
+        "| # | {} | # | {} |\n|--:|---|--:|---|\n",
+        markdown_cell(old_label),
+        markdown_cell(new_label)
+    );
+    for (i, hunk) in hunks.iter().enumerate() {
+        if 0 < i {
+            ret.push_str("| … | | … | |\n");
+        }
+        let mut old_line = hunk.old_start;
+        let mut new_line = hunk.new_start;
+        for (old, new) in &hunk.rows {
+            let cell = |line: &Option<String>, number: usize| match line {
+                Some(x) => (number.to_string(), markdown_code(x)),
+                None => (String::new(), String::new()),
+            };
+            let (old_number, old_text) = cell(old, old_line);
+            let (new_number, new_text) = cell(new, new_line);
+            ret.push_str(&format!(
+                "| {} | {} | {} | {} |\n",
+                old_number, old_text, new_number, new_text
+            ));
+            old_line += old.is_some() as usize;
+            new_line += new.is_some() as usize;
+        }
+    }
+    ret
+}
+
+/// line as inline code so that markdown syntax in it is shown as it is
+fn markdown_code(line: &str) -> String {
+    if line.trim().is_empty() {
+        return String::new();
+    }
+    // code span is delimited by backticks longer than any run of them in line
+    let longest_run = line
+        .split(|x| x != '`')
+        .map(|x| x.len())
+        .max()
+        .unwrap_or_default();
+    let fence = "`".repeat(longest_run + 1);
+    let padding = if line.starts_with('`') || line.ends_with('`') {
+        " "
+    } else {
+        ""
+    };
+    markdown_cell(&format!("{fence}{padding}{line}{padding}{fence}"))
+}
+
+/// text escaped for table cell
+fn markdown_cell(s: &str) -> String {
+    s.replace('|', "\\|")
+}
+
+/// line without its ending of `\n` or `\r\n`
+fn without_line_ending(line: &str) -> String {
+    line.trim_end_matches(['\r', '\n']).to_owned()
+}
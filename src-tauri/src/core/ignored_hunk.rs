@@ -0,0 +1,92 @@
+use similar::DiffTag;
+
+use super::archive::crc32;
+use super::store;
+use super::types::{ComparisonIgnoredHunks, IgnoredHunkStore, LinesDiff};
+
+/// file name to persist hunks marked as ignored
+const IGNORED_HUNKS_STORE_FILENAME: &str = "ignored_hunks.json";
+
+/// fingerprint of hunk content so that mark is kept while both sides of hunk stay the same
+pub fn hunk_fingerprint(diff: &LinesDiff) -> String {
+    let old = diff.old_lines.concat();
+    let new = diff.new_lines.concat();
+    let bytes = [old.as_bytes(), &[0], new.as_bytes()].concat();
+    format!("{:08x}-{:x}-{:x}", crc32(&bytes), old.len(), new.len())
+}
+
+/// mark hunk of comparison as reviewed and ignored, or unmark it
+pub fn set_hunk_ignored(
+    app_handle: &tauri::AppHandle,
+    old: &str,
+    new: &str,
+    diff: &LinesDiff,
+    ignored: bool,
+) -> Result<(), String> {
+    let mut store: IgnoredHunkStore = store::load(app_handle, IGNORED_HUNKS_STORE_FILENAME)?;
+    let fingerprint = hunk_fingerprint(diff);
+
+    let comparison = match store
+        .comparisons
+        .iter()
+        .position(|x| x.old == old && x.new == new)
+    {
+        Some(i) => &mut store.comparisons[i],
+        None if ignored => {
+            store.comparisons.push(ComparisonIgnoredHunks {
+                old: old.to_owned(),
+                new: new.to_owned(),
+                fingerprints: Default::default(),
+            });
+            store.comparisons.last_mut().unwrap()
+        }
+        None => return Ok(()),
+    };
+    if ignored {
+        comparison.fingerprints.insert(fingerprint);
+    } else {
+        comparison.fingerprints.remove(&fingerprint);
+    }
+    store.comparisons.retain(|x| !x.fingerprints.is_empty());
+
+    store::save(app_handle, IGNORED_HUNKS_STORE_FILENAME, &store)
+}
+
+/// diff indices of hunks marked as ignored in comparison
+/// marks whose hunks are not found any more because text changed again are dropped
+pub fn ignored_diff_indices(
+    app_handle: &tauri::AppHandle,
+    old: &str,
+    new: &str,
+    diffs: &[LinesDiff],
+) -> Result<Vec<usize>, String> {
+    let mut store: IgnoredHunkStore = store::load(app_handle, IGNORED_HUNKS_STORE_FILENAME)?;
+    let Some(comparison) = store
+        .comparisons
+        .iter_mut()
+        .find(|x| x.old == old && x.new == new)
+    else {
+        return Ok(vec![]);
+    };
+
+    let hunks = diffs
+        .iter()
+        .filter(|x| x.diff_kind != DiffTag::Equal)
+        .map(|x| (x.diff_index, hunk_fingerprint(x)))
+        .collect::<Vec<_>>();
+    let ret = hunks
+        .iter()
+        .filter(|(_, fingerprint)| comparison.fingerprints.contains(fingerprint))
+        .map(|(diff_index, _)| *diff_index)
+        .collect::<Vec<_>>();
+
+    let marks_count = comparison.fingerprints.len();
+    comparison
+        .fingerprints
+        .retain(|x| hunks.iter().any(|(_, fingerprint)| fingerprint == x));
+    if comparison.fingerprints.len() != marks_count {
+        store.comparisons.retain(|x| !x.fingerprints.is_empty());
+        store::save(app_handle, IGNORED_HUNKS_STORE_FILENAME, &store)?;
+    }
+    Ok(ret)
+}
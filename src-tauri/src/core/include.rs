@@ -0,0 +1,236 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use super::diff::lines_diff_response;
+use super::file::{bytes_content, filepaths_content_with_mode};
+use super::os_path::{os_path, path_string};
+use super::rules::glob_match;
+use super::shell::home_dir;
+use super::types::{CompareMode, ConcatenatedDiffResponse, ReadContent, SourceBoundary};
+
+/// max depth of nested includes
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+/// compare config files with includes expanded from disk into flattened documents
+/// so that changes in effective configuration are visible
+/// `include` of nginx, `Include` of ssh_config and `Include` / `IncludeOptional` of apache
+/// are followed. sources tell which file and line each range of lines comes from
+pub fn expanded_includes_diff(old: &str, new: &str) -> Result<ConcatenatedDiffResponse, String> {
+    let (old_read, old_sources) = expanded_content(old)?;
+    let (new_read, new_sources) = expanded_content(new)?;
+    Ok(ConcatenatedDiffResponse {
+        diff: lines_diff_response(old_read, new_read),
+        old_sources,
+        new_sources,
+    })
+}
+
+/// content with include directives followed by included contents
+fn expanded_content(filepath: &str) -> Result<(ReadContent, Vec<SourceBoundary>), String> {
+    if filepath.is_empty() {
+        return Ok((ReadContent::default(), vec![]));
+    }
+    let read = filepaths_content_with_mode(filepath, "", Some(CompareMode::Text))?
+        .0
+        .remove(0);
+    // relative include paths are resolved against main config dir
+    // when not found next to including file, as nginx prefix and apache server root are
+    let root_dir = os_path(filepath)
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+    let mut expansion = Expansion {
+        root_dir,
+        content: String::new(),
+        sources: vec![],
+        stack: vec![],
+    };
+    expansion.expand(&os_path(filepath), &read.content);
+    let read = ReadContent {
+        content: expansion.content,
+        ..read
+    };
+    Ok((read, expansion.sources))
+}
+
+/// flattened document being built
+struct Expansion {
+    root_dir: PathBuf,
+    content: String,
+    sources: Vec<SourceBoundary>,
+    /// files being expanded to detect cyclic includes
+    stack: Vec<PathBuf>,
+}
+
+impl Expansion {
+    /// append file content with includes expanded recursively
+    fn expand(&mut self, filepath: &Path, content: &str) {
+        let canonical = fs::canonicalize(filepath).unwrap_or(filepath.to_path_buf());
+        self.stack.push(canonical);
+        let dir = filepath.parent().map(Path::to_path_buf).unwrap_or_default();
+
+        let mut segment_start = 1;
+        let mut lines_count = 0;
+        for line in content.split_inclusive('\n') {
+            self.content.push_str(line);
+            if !line.ends_with('\n') {
+                self.content.push('\n');
+            }
+            lines_count += 1;
+
+            let Some((patterns, optional)) = include_patterns(line) else {
+                continue;
+            };
+            // directive line itself is kept followed by included contents
+            self.push_source(filepath, segment_start, lines_count + 1);
+            segment_start = lines_count + 1;
+            for pattern in patterns {
+                let included = self.included_filepaths(&pattern, &dir);
+                if included.is_empty() && !optional && !pattern.contains(['*', '?']) {
+                    tracing::warn!("Include not found: {}", pattern);
+                }
+                for x in included {
+                    self.expand_included(&x);
+                }
+            }
+        }
+        self.push_source(filepath, segment_start, lines_count + 1);
+        self.stack.pop();
+    }
+
+    /// expand included file unless it is too deep or cyclic
+    fn expand_included(&mut self, filepath: &Path) {
+        if MAX_INCLUDE_DEPTH <= self.stack.len() {
+            tracing::warn!("Include too deep: {}", path_string(filepath));
+            return;
+        }
+        let canonical = fs::canonicalize(filepath).unwrap_or(filepath.to_path_buf());
+        if self.stack.contains(&canonical) {
+            tracing::warn!("Cyclic include: {}", path_string(filepath));
+            return;
+        }
+        match fs::read(filepath) {
+            Ok(bytes) => self.expand(filepath, &bytes_content(&bytes).content),
+            Err(err) => {
+                tracing::warn!("Failed to read include {} ({})", path_string(filepath), err)
+            }
+        }
+    }
+
+    /// source boundary of lines in file from `source_start_line` (1-based) to `source_end_line`
+    /// (exclusive) appended at the end of the document. empty range is skipped
+    fn push_source(&mut self, filepath: &Path, source_start_line: usize, source_end_line: usize) {
+        if source_end_line <= source_start_line {
+            return;
+        }
+        let start_line = self.sources.last().map_or(1, |x| x.end_line);
+        self.sources.push(SourceBoundary {
+            filepath: path_string(filepath),
+            start_line,
+            end_line: start_line + source_end_line - source_start_line,
+            source_start_line,
+        });
+    }
+
+    /// files matching include pattern in name order
+    /// relative pattern is tried against including file dir and then main config dir
+    fn included_filepaths(&self, pattern: &str, dir: &Path) -> Vec<PathBuf> {
+        let path = match pattern.strip_prefix("~/") {
+            Some(x) => match home_dir() {
+                Ok(home) => home.join(x),
+                Err(_) => return vec![],
+            },
+            None => PathBuf::from(pattern),
+        };
+        if path.is_absolute() {
+            return glob_filepaths(&path);
+        }
+        for base in [dir, self.root_dir.as_path()] {
+            let ret = glob_filepaths(&base.join(&path));
+            if !ret.is_empty() {
+                return ret;
+            }
+        }
+        vec![]
+    }
+}
+
+/// include targets and whether they are optional when line is include directive
+fn include_patterns(line: &str) -> Option<(Vec<String>, bool)> {
+    let line = line.trim();
+    let (directive, args) = line.split_once(|x: char| x.is_ascii_whitespace())?;
+    let optional = if directive.eq_ignore_ascii_case("IncludeOptional") {
+        true
+    } else if directive.eq_ignore_ascii_case("include") {
+        false
+    } else {
+        return None;
+    };
+    // nginx directive ends with semicolon and may be followed by comment
+    let args = args.split('#').next().unwrap_or_default().trim();
+    let args = args.strip_suffix(';').unwrap_or(args);
+
+    let mut ret = vec![];
+    let mut rest = args.trim_start();
+    while !rest.is_empty() {
+        let (arg, next) = match rest.strip_prefix(['"', '\'']) {
+            Some(x) => {
+                let quote = rest.as_bytes()[0] as char;
+                x.split_once(quote).unwrap_or((x, ""))
+            }
+            None => rest
+                .split_once(|x: char| x.is_ascii_whitespace())
+                .unwrap_or((rest, "")),
+        };
+        if !arg.is_empty() {
+            ret.push(arg.to_owned());
+        }
+        rest = next.trim_start();
+    }
+    if ret.is_empty() {
+        None
+    } else {
+        Some((ret, optional))
+    }
+}
+
+/// files matching path where any component may contain `*` or `?`
+fn glob_filepaths(path: &Path) -> Vec<PathBuf> {
+    let mut candidates = vec![PathBuf::new()];
+    for component in path.components() {
+        let name = match component {
+            Component::Normal(x) => path_string(Path::new(x)),
+            _ => {
+                for x in candidates.iter_mut() {
+                    x.push(component);
+                }
+                continue;
+            }
+        };
+        if !name.contains(['*', '?']) {
+            for x in candidates.iter_mut() {
+                x.push(component);
+            }
+            continue;
+        }
+        let mut matched = BTreeSet::new();
+        for candidate in &candidates {
+            let Ok(entries) = fs::read_dir(candidate) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let entry_name = path_string(Path::new(&entry.file_name()));
+                // hidden files are not matched as shell glob does
+                if entry_name.starts_with('.') && !name.starts_with('.') {
+                    continue;
+                }
+                if glob_match(name.as_bytes(), entry_name.as_bytes()) {
+                    matched.insert(entry.path());
+                }
+            }
+        }
+        candidates = matched.into_iter().collect();
+    }
+    candidates.into_iter().filter(|x| x.is_file()).collect()
+}
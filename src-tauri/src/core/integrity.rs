@@ -0,0 +1,319 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use super::archive::crc32;
+use super::diff::collect_relative_filepaths;
+use super::os_path::os_path;
+use super::types::{DirEntryDiff, EntryStatus};
+
+/// bytes read at once to digest file
+const DIGEST_CHUNK_BYTES: usize = 1024 * 1024;
+
+/// sha-256 round constants
+const SHA256_K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// digest expected on file listed in manifest
+enum ExpectedDigest {
+    Sha256(String),
+    Crc32(u32),
+    // listed only with size or path
+    None,
+}
+
+/// file listed in manifest
+struct ManifestEntry {
+    digest: ExpectedDigest,
+    size: Option<u64>,
+}
+
+/// entry of custom json manifest
+#[derive(Deserialize)]
+struct JsonManifestEntry {
+    path: String,
+    sha256: Option<String>,
+    crc32: Option<String>,
+    size: Option<u64>,
+}
+
+/// custom json manifest as array of entries or object with them in `files`
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonManifest {
+    Entries(Vec<JsonManifestEntry>),
+    Files { files: Vec<JsonManifestEntry> },
+}
+
+/// verify files under root against manifest of sha256sums, sfv or custom json
+/// result is in folder comparison format with manifest as old side and root as new side:
+/// missing files are old only, extra files are new only and mismatched files are modified
+/// entries verified and total are reported to `on_progress`
+pub fn verify_manifest(
+    manifest_path: &str,
+    root: &str,
+    mut on_progress: impl FnMut(u64, u64),
+) -> Result<Vec<DirEntryDiff>, String> {
+    let content = fs::read_to_string(os_path(manifest_path))
+        .map_err(|err| format!("Failed to read {} ({})", manifest_path, err))?;
+    let manifest = parse_manifest(manifest_path, &content)?;
+
+    let mut filepaths = vec![];
+    collect_relative_filepaths(&os_path(root), "", &mut filepaths)?;
+    // manifest put under root is not extra file
+    let manifest_fullpath = os_path(manifest_path).canonicalize().ok();
+    filepaths.retain(|x| os_path(root).join(os_path(x)).canonicalize().ok() != manifest_fullpath);
+
+    let mut paths = manifest.keys().cloned().collect::<Vec<_>>();
+    paths.extend(filepaths);
+    paths.sort();
+    paths.dedup();
+
+    let total = paths.len() as u64;
+    paths
+        .into_iter()
+        .enumerate()
+        .map(|(i, path)| {
+            on_progress(i as u64, total);
+            let filepath = os_path(root).join(os_path(&path));
+            let new_size = fs::metadata(&filepath).ok().map(|x| x.len());
+            let Some(entry) = manifest.get(&path) else {
+                return Ok(DirEntryDiff {
+                    path,
+                    status: EntryStatus::NewOnly,
+                    old_size: None,
+                    new_size,
+                    stale: false,
+                });
+            };
+            let status = match new_size {
+                None => EntryStatus::OldOnly,
+                Some(size) if entry.size.is_some_and(|x| x != size) => EntryStatus::Modified,
+                Some(_) if digest_matches(&filepath, &entry.digest)? => EntryStatus::Equal,
+                Some(_) => EntryStatus::Modified,
+            };
+            Ok(DirEntryDiff {
+                path,
+                status,
+                old_size: entry.size,
+                new_size,
+                stale: false,
+            })
+        })
+        .collect()
+}
+
+/// files listed in manifest by path relative to root
+/// format is decided by extension: `.sfv`, `.json`, and sha256sums otherwise
+fn parse_manifest(
+    manifest_path: &str,
+    content: &str,
+) -> Result<BTreeMap<String, ManifestEntry>, String> {
+    let extension = Path::new(manifest_path)
+        .extension()
+        .map(|x| x.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let normalized_path = |x: &str| {
+        x.trim()
+            .replace('\\', "/")
+            .trim_start_matches("./")
+            .to_owned()
+    };
+    let invalid_line = |line: &str| format!("Invalid line in {}: {}", manifest_path, line);
+
+    let mut ret = BTreeMap::new();
+    match extension.as_str() {
+        "json" => {
+            let manifest: JsonManifest = serde_json::from_str(content)
+                .map_err(|err| format!("Failed to parse {} ({})", manifest_path, err))?;
+            let entries = match manifest {
+                JsonManifest::Entries(x) | JsonManifest::Files { files: x } => x,
+            };
+            for x in entries {
+                let digest = match (x.sha256, x.crc32) {
+                    (Some(sha256), _) => ExpectedDigest::Sha256(sha256.to_lowercase()),
+                    (None, Some(crc32)) => ExpectedDigest::Crc32(
+                        u32::from_str_radix(&crc32, 16)
+                            .map_err(|_| format!("Invalid crc32 of {}: {}", x.path, crc32))?,
+                    ),
+                    (None, None) => ExpectedDigest::None,
+                };
+                ret.insert(
+                    normalized_path(&x.path),
+                    ManifestEntry {
+                        digest,
+                        size: x.size,
+                    },
+                );
+            }
+        }
+        // <path> SP <crc32>, with comments starting with `;`
+        "sfv" => {
+            for line in content.lines() {
+                let line = line.trim();
+                if line.is_empty() || line.starts_with(';') {
+                    continue;
+                }
+                let (path, crc32) = line.rsplit_once(' ').ok_or(invalid_line(line))?;
+                let crc32 = u32::from_str_radix(crc32, 16).map_err(|_| invalid_line(line))?;
+                ret.insert(
+                    normalized_path(path),
+                    ManifestEntry {
+                        digest: ExpectedDigest::Crc32(crc32),
+                        size: None,
+                    },
+                );
+            }
+        }
+        // <sha256> SP SP <path>, or SP `*` <path> in binary mode
+        _ => {
+            for line in content.lines() {
+                let line = line.trim_end();
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+                let (digest, path) = line.split_once(' ').ok_or(invalid_line(line))?;
+                if digest.len() != 64 || !digest.chars().all(|x| x.is_ascii_hexdigit()) {
+                    return Err(invalid_line(line));
+                }
+                let path = path.strip_prefix([' ', '*']).unwrap_or(path);
+                ret.insert(
+                    normalized_path(path),
+                    ManifestEntry {
+                        digest: ExpectedDigest::Sha256(digest.to_lowercase()),
+                        size: None,
+                    },
+                );
+            }
+        }
+    }
+    Ok(ret)
+}
+
+/// check if file has expected digest. file is read in chunks on sha-256
+fn digest_matches(filepath: &Path, expected: &ExpectedDigest) -> Result<bool, String> {
+    let read_error =
+        |err: std::io::Error| format!("Failed to read {} ({})", filepath.display(), err);
+    match expected {
+        ExpectedDigest::Sha256(x) => {
+            let mut file = File::open(filepath).map_err(read_error)?;
+            let mut sha256 = Sha256::default();
+            let mut buffer = vec![0; DIGEST_CHUNK_BYTES];
+            loop {
+                let read_bytes = file.read(&mut buffer).map_err(read_error)?;
+                if read_bytes == 0 {
+                    break;
+                }
+                sha256.update(&buffer[..read_bytes]);
+            }
+            Ok(sha256.hex_digest() == *x)
+        }
+        ExpectedDigest::Crc32(x) => Ok(crc32(&fs::read(filepath).map_err(read_error)?) == *x),
+        ExpectedDigest::None => Ok(true),
+    }
+}
+
+/// sha-256 digest computed incrementally
+struct Sha256 {
+    state: [u32; 8],
+    block: Vec<u8>,
+    length: u64,
+}
+
+impl Default for Sha256 {
+    fn default() -> Self {
+        Sha256 {
+            state: [
+                0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
+                0x5be0cd19,
+            ],
+            block: Vec::with_capacity(64),
+            length: 0,
+        }
+    }
+}
+
+impl Sha256 {
+    fn update(&mut self, bytes: &[u8]) {
+        self.length += bytes.len() as u64;
+        let mut rest = bytes;
+        if !self.block.is_empty() {
+            let taken = (64 - self.block.len()).min(rest.len());
+            self.block.extend_from_slice(&rest[..taken]);
+            rest = &rest[taken..];
+            if self.block.len() < 64 {
+                return;
+            }
+            let block = std::mem::take(&mut self.block);
+            self.compress(&block);
+        }
+        let mut chunks = rest.chunks_exact(64);
+        for chunk in &mut chunks {
+            self.compress(chunk);
+        }
+        self.block.extend_from_slice(chunks.remainder());
+    }
+
+    fn hex_digest(mut self) -> String {
+        let bit_length = self.length.wrapping_mul(8);
+        let mut padding = vec![0x80];
+        padding.resize((55_usize.wrapping_sub(self.block.len()) % 64) + 1, 0);
+        padding.extend_from_slice(&bit_length.to_be_bytes());
+        // length is not counted for padding
+        let length = self.length;
+        self.update(&padding);
+        self.length = length;
+        self.state.iter().map(|x| format!("{:08x}", x)).collect()
+    }
+
+    fn compress(&mut self, block: &[u8]) {
+        let mut w = [0_u32; 64];
+        for (i, x) in block.chunks_exact(4).enumerate() {
+            w[i] = u32::from_be_bytes([x[0], x[1], x[2], x[3]]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = self.state;
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = h
+                .wrapping_add(s1)
+                .wrapping_add(ch)
+                .wrapping_add(SHA256_K[i])
+                .wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+        for (x, y) in self.state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *x = x.wrapping_add(y);
+        }
+    }
+}
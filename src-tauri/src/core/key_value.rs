@@ -0,0 +1,165 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use super::field_diff::field_diffs;
+use super::file::file_extension;
+use super::os_path::os_path;
+use super::types::MetadataFieldDiff;
+
+/// key patterns whose values are masked unless specified
+const DEFAULT_SECRET_KEY_PATTERNS: [&str; 8] = [
+    "password",
+    "passwd",
+    "secret",
+    "token",
+    "api_key",
+    "apikey",
+    "private_key",
+    "credential",
+];
+/// value shown instead of secret
+const MASKED_VALUE: &str = "********";
+
+/// compare flat config files such as `.properties`, `.ini` and `.env` aligned by key
+/// regardless of ordering. ini keys are prefixed with section such as `database.host`
+/// values of keys containing any of `secret_key_patterns` case-insensitively are masked
+/// while their changes are still reported
+pub fn key_value_diffs(
+    old: &str,
+    new: &str,
+    secret_key_patterns: Option<&[String]>,
+) -> Result<Vec<MetadataFieldDiff>, String> {
+    let old_entries = key_values(old)?;
+    let new_entries = key_values(new)?;
+    let patterns = match secret_key_patterns {
+        Some(x) => x.iter().map(|x| x.to_lowercase()).collect::<Vec<_>>(),
+        None => DEFAULT_SECRET_KEY_PATTERNS
+            .iter()
+            .map(|x| x.to_string())
+            .collect(),
+    };
+
+    let ret = field_diffs(&old_entries, &new_entries)
+        .into_iter()
+        .map(|x| {
+            let key = x.key.to_lowercase();
+            if !patterns
+                .iter()
+                .any(|pattern| key.contains(pattern.as_str()))
+            {
+                return x;
+            }
+            MetadataFieldDiff {
+                old: x.old.map(|_| MASKED_VALUE.to_owned()),
+                new: x.new.map(|_| MASKED_VALUE.to_owned()),
+                ..x
+            }
+        })
+        .collect();
+    Ok(ret)
+}
+
+/// read key-value entries. format is decided by file name
+/// empty file path is regarded as no entries
+fn key_values(filepath: &str) -> Result<BTreeMap<String, String>, String> {
+    if filepath.is_empty() {
+        return Ok(BTreeMap::new());
+    }
+    let content = fs::read_to_string(os_path(filepath))
+        .map_err(|err| format!("Failed to read {} ({})", filepath, err))?;
+    let filename = Path::new(filepath)
+        .file_name()
+        .map(|x| x.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let ret = match file_extension(filepath).as_str() {
+        "ini" | "cfg" | "conf" => ini_entries(&content),
+        _ if filename.starts_with(".env") || file_extension(filepath) == "env" => {
+            env_entries(&content)
+        }
+        _ => properties_entries(&content),
+    };
+    Ok(ret)
+}
+
+/// java properties with `=`, `:` or space separator and backslash line continuation
+fn properties_entries(content: &str) -> BTreeMap<String, String> {
+    let mut ret = BTreeMap::new();
+    let mut logical_line = String::new();
+    for line in content.lines() {
+        let line = line.trim_start();
+        if logical_line.is_empty() && (line.starts_with('#') || line.starts_with('!')) {
+            continue;
+        }
+        match line.strip_suffix('\\') {
+            Some(x) => {
+                logical_line.push_str(x);
+                continue;
+            }
+            None => logical_line.push_str(line),
+        }
+        let entry = std::mem::take(&mut logical_line);
+        if entry.trim().is_empty() {
+            continue;
+        }
+        let separator = entry.find(['=', ':', ' ', '\t']).unwrap_or(entry.len());
+        let key = entry[..separator].trim();
+        let value = entry[separator..]
+            .trim_start_matches([' ', '\t'])
+            .trim_start_matches(['=', ':'])
+            .trim();
+        ret.insert(key.to_owned(), value.to_owned());
+    }
+    ret
+}
+
+/// ini with `[section]` headers and `;` or `#` comments
+fn ini_entries(content: &str) -> BTreeMap<String, String> {
+    let mut ret = BTreeMap::new();
+    let mut section = String::new();
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+            continue;
+        }
+        if let Some(x) = line.strip_prefix('[').and_then(|x| x.strip_suffix(']')) {
+            section = x.trim().to_owned();
+            continue;
+        }
+        let (key, value) = line.split_once(['=', ':']).unwrap_or((line, ""));
+        let key = if section.is_empty() {
+            key.trim().to_owned()
+        } else {
+            format!("{}.{}", section, key.trim())
+        };
+        ret.insert(key, unquoted(value.trim()).to_owned());
+    }
+    ret
+}
+
+/// dotenv with optional `export` and quoted values
+fn env_entries(content: &str) -> BTreeMap<String, String> {
+    content
+        .lines()
+        .map(|x| x.trim())
+        .filter(|x| !x.is_empty() && !x.starts_with('#'))
+        .filter_map(|line| {
+            let line = line.strip_prefix("export ").unwrap_or(line);
+            let (key, value) = line.split_once('=')?;
+            Some((key.trim().to_owned(), unquoted(value.trim()).to_owned()))
+        })
+        .collect()
+}
+
+/// strip surrounding quotes
+fn unquoted(value: &str) -> &str {
+    for quote in ['"', '\''] {
+        if let Some(x) = value
+            .strip_prefix(quote)
+            .and_then(|x| x.strip_suffix(quote))
+        {
+            return x;
+        }
+    }
+    value
+}
@@ -0,0 +1,196 @@
+use std::fs;
+
+use super::file::bytes_content;
+use super::os_path::os_path;
+use super::types::ReadContent;
+
+/// built-in ignore profile of fields set by cluster and changing on every apply or read
+const VOLATILE_FIELDS: [&str; 8] = [
+    "resourceVersion",
+    "managedFields",
+    "creationTimestamp",
+    "uid",
+    "generation",
+    "selfLink",
+    "kubectl.kubernetes.io/last-applied-configuration",
+    "deployment.kubernetes.io/revision",
+];
+/// comment put by `helm template` on each document
+const HELM_SOURCE_COMMENT: &str = "# Source:";
+
+/// document in yaml stream identified by kind and name
+struct ManifestDocument {
+    id: String,
+    lines: Vec<String>,
+}
+
+/// read kubernetes or helm manifests in multi-document yaml stream
+/// documents are sorted by kind, namespace and name so that they are compared one by one
+/// and volatile fields such as `resourceVersion` and `managedFields` are dropped
+pub fn kube_manifest_content(filepath: &str) -> Result<ReadContent, String> {
+    if filepath.is_empty() {
+        return Ok(ReadContent::default());
+    }
+    let bytes = fs::read(os_path(filepath))
+        .map_err(|err| format!("Failed to read {} ({})", filepath, err))?;
+    let read = bytes_content(&bytes);
+    Ok(ReadContent {
+        content: normalized_manifests(&read.content),
+        ..read
+    })
+}
+
+/// documents with headers of their ids, in order of ids
+fn normalized_manifests(content: &str) -> String {
+    let mut documents = yaml_documents(content)
+        .into_iter()
+        .map(|lines| {
+            let lines = without_volatile_fields(&lines);
+            ManifestDocument {
+                id: document_id(&lines),
+                lines,
+            }
+        })
+        .filter(|x| x.lines.iter().any(|x| !is_blank_or_comment(x)))
+        .collect::<Vec<_>>();
+    // stable so that documents of the same id keep their order
+    documents.sort_by(|a, b| a.id.cmp(&b.id));
+
+    let mut ret = String::new();
+    for document in documents {
+        ret.push_str(&format!("--- # {}\n", document.id));
+        for line in document.lines {
+            ret.push_str(&line);
+            ret.push('\n');
+        }
+    }
+    ret
+}
+
+/// split yaml stream into lines of documents by `---` and `...`
+fn yaml_documents(content: &str) -> Vec<Vec<String>> {
+    let mut ret = vec![];
+    let mut lines = vec![];
+    for line in content.lines() {
+        let is_separator =
+            line == "..." || line == "---" || line.starts_with("--- ") || line.starts_with("---\t");
+        if is_separator {
+            ret.push(std::mem::take(&mut lines));
+            continue;
+        }
+        if line.trim_start().starts_with(HELM_SOURCE_COMMENT) {
+            continue;
+        }
+        lines.push(line.trim_end().to_owned());
+    }
+    ret.push(lines);
+    ret
+}
+
+/// lines without volatile fields and blocks nested in them
+fn without_volatile_fields(lines: &[String]) -> Vec<String> {
+    let mut ret = vec![];
+    let mut i = 0;
+    while i < lines.len() {
+        let line = &lines[i];
+        i += 1;
+        let indent = indent_width(line);
+        let key = mapping_key(line.trim_start());
+        if !key.is_some_and(|x| VOLATILE_FIELDS.contains(&x)) {
+            ret.push(line.to_owned());
+            continue;
+        }
+        // nested block, with sequence items which may be at the same indent as key
+        while i < lines.len() {
+            let next = &lines[i];
+            let next_indent = indent_width(next);
+            let continues = next.trim().is_empty()
+                || indent < next_indent
+                || (indent == next_indent && next.trim_start().starts_with("- "));
+            if !continues {
+                break;
+            }
+            i += 1;
+        }
+    }
+    ret
+}
+
+/// id of document such as `Deployment/default/web` by top-level kind and metadata
+fn document_id(lines: &[String]) -> String {
+    let mut kind = "";
+    let mut name = "";
+    let mut namespace = "";
+    let mut in_metadata = false;
+    let mut metadata_indent = None;
+    for line in lines {
+        if is_blank_or_comment(line) {
+            continue;
+        }
+        let indent = indent_width(line);
+        let trimmed = line.trim_start();
+        if indent == 0 {
+            in_metadata = mapping_key(trimmed) == Some("metadata");
+            if mapping_key(trimmed) == Some("kind") {
+                kind = mapping_value(trimmed);
+            }
+            continue;
+        }
+        if !in_metadata {
+            continue;
+        }
+        // direct children of metadata share indent of first one
+        let child_indent = *metadata_indent.get_or_insert(indent);
+        if indent != child_indent {
+            continue;
+        }
+        match mapping_key(trimmed) {
+            Some("name") => name = mapping_value(trimmed),
+            Some("namespace") => namespace = mapping_value(trimmed),
+            _ => (),
+        }
+    }
+    let kind = if kind.is_empty() { "(unknown)" } else { kind };
+    if namespace.is_empty() {
+        format!("{}/{}", kind, name)
+    } else {
+        format!("{}/{}/{}", kind, namespace, name)
+    }
+}
+
+/// key of mapping entry, unquoted
+fn mapping_key(trimmed_line: &str) -> Option<&str> {
+    if trimmed_line.starts_with('#') {
+        return None;
+    }
+    let (key, _) = trimmed_line.split_once(':')?;
+    // key is quoted when it has special chars such as annotations
+    let key = key.trim().trim_matches(['"', '\'']);
+    if key.is_empty() {
+        None
+    } else {
+        Some(key)
+    }
+}
+
+/// scalar value of mapping entry, unquoted and without comment
+fn mapping_value(trimmed_line: &str) -> &str {
+    let value = trimmed_line
+        .split_once(':')
+        .map_or("", |x| x.1)
+        .split(" #")
+        .next()
+        .unwrap_or_default();
+    value.trim().trim_matches(['"', '\''])
+}
+
+/// count of leading spaces
+fn indent_width(line: &str) -> usize {
+    line.len() - line.trim_start_matches(' ').len()
+}
+
+/// check if line has nothing to compare
+fn is_blank_or_comment(line: &str) -> bool {
+    let trimmed = line.trim();
+    trimmed.is_empty() || trimmed.starts_with('#')
+}
@@ -0,0 +1,42 @@
+use std::collections::BTreeSet;
+
+use super::diff::lines_diff_response;
+use super::types::{LineSetDiff, ReadContent};
+
+/// compare sides as sets of lines where order and duplicates are meaningless
+/// such as lists of ids, hosts or dependencies. lines are sorted and deduplicated
+/// before diff and lines only on either side are listed. blank lines are ignored
+pub fn line_set_diff(old_read: ReadContent, new_read: ReadContent) -> LineSetDiff {
+    let old_lines = line_set(&old_read.content);
+    let new_lines = line_set(&new_read.content);
+    let old_only = old_lines.difference(&new_lines).cloned().collect();
+    let new_only = new_lines.difference(&old_lines).cloned().collect();
+    let common_count = old_lines.intersection(&new_lines).count();
+
+    let joined = |lines: &BTreeSet<String>| lines.iter().map(|x| format!("{}\n", x)).collect();
+    let diff = lines_diff_response(
+        ReadContent {
+            content: joined(&old_lines),
+            ..old_read
+        },
+        ReadContent {
+            content: joined(&new_lines),
+            ..new_read
+        },
+    );
+    LineSetDiff {
+        diff,
+        old_only,
+        new_only,
+        common_count,
+    }
+}
+
+/// distinct lines without line endings and surrounding whitespace
+fn line_set(content: &str) -> BTreeSet<String> {
+    content
+        .lines()
+        .map(|x| x.trim().to_owned())
+        .filter(|x| !x.is_empty())
+        .collect()
+}
@@ -0,0 +1,425 @@
+use std::collections::BTreeMap;
+use std::fs;
+
+use regex::Regex;
+
+use super::file::{bytes_content, file_extension};
+use super::os_path::os_path;
+use super::protected::xml_unescape;
+use super::types::{LocalizationChange, LocalizationEntryDiff};
+
+/// translation unit in localization file
+struct LocalizationEntry {
+    // msgid on gettext, or comment for translators on others which describes source text
+    source: Option<String>,
+    translation: String,
+    // key before source changed, given by `#| msgid` on gettext
+    previous_key: Option<String>,
+    // marked as needing review on gettext
+    fuzzy: bool,
+}
+
+/// gettext entry being parsed
+#[derive(Default)]
+struct PoEntry {
+    msgctxt: Option<String>,
+    msgid: Option<String>,
+    msgid_plural: Option<String>,
+    msgstr: Vec<String>,
+    previous_msgid: Option<String>,
+    fuzzy: bool,
+}
+
+/// gettext field which continued string line is appended to
+#[derive(Clone, Copy)]
+enum PoField {
+    Msgctxt,
+    Msgid,
+    MsgidPlural,
+    Msgstr(usize),
+    PreviousMsgid,
+}
+
+/// compare localization files such as gettext `.po` / `.pot`, apple `.strings`
+/// and android `strings.xml` aligned by msgid or key regardless of ordering
+/// entries moved by source change are aligned by their previous msgid (`#| msgid`) on gettext
+/// and changes of source take precedence over ones of translation because translation needs review
+pub fn localization_diffs(old: &str, new: &str) -> Result<Vec<LocalizationEntryDiff>, String> {
+    let old_entries = localization_entries(old)?;
+    let mut new_entries = localization_entries(new)?;
+
+    let mut ret = vec![];
+    for (key, old_entry) in &old_entries {
+        let moved_key = new_entries
+            .iter()
+            .find(|(new_key, x)| {
+                x.previous_key.as_ref() == Some(key) && !old_entries.contains_key(new_key.as_str())
+            })
+            .map(|x| x.0.to_owned());
+        let new_entry = match &moved_key {
+            Some(moved_key) => new_entries.remove(moved_key),
+            None => new_entries.remove(key),
+        };
+        let status = match &new_entry {
+            None => LocalizationChange::Removed,
+            Some(new_entry) if moved_key.is_some() || new_entry.source != old_entry.source => {
+                LocalizationChange::ChangedSource
+            }
+            Some(new_entry) if new_entry.translation != old_entry.translation => {
+                LocalizationChange::ChangedTranslation
+            }
+            Some(_) => LocalizationChange::Equal,
+        };
+        ret.push(LocalizationEntryDiff {
+            key: moved_key.unwrap_or(key.to_owned()),
+            status,
+            old_source: old_entry.source.to_owned(),
+            new_source: new_entry.as_ref().and_then(|x| x.source.to_owned()),
+            old_translation: Some(old_entry.translation.to_owned()),
+            new_translation: new_entry.as_ref().map(|x| x.translation.to_owned()),
+            fuzzy: new_entry.is_some_and(|x| x.fuzzy),
+        });
+    }
+    for (key, new_entry) in new_entries {
+        ret.push(LocalizationEntryDiff {
+            key,
+            status: LocalizationChange::New,
+            old_source: None,
+            new_source: new_entry.source,
+            old_translation: None,
+            new_translation: Some(new_entry.translation),
+            fuzzy: new_entry.fuzzy,
+        });
+    }
+    ret.sort_by(|a, b| a.key.cmp(&b.key));
+    Ok(ret)
+}
+
+/// read entries by key. format is decided by file extension
+/// empty file path is regarded as no entries
+fn localization_entries(filepath: &str) -> Result<BTreeMap<String, LocalizationEntry>, String> {
+    if filepath.is_empty() {
+        return Ok(BTreeMap::new());
+    }
+    let bytes = fs::read(os_path(filepath))
+        .map_err(|err| format!("Failed to read {} ({})", filepath, err))?;
+    // apple strings are often in utf-16
+    let read = bytes_content(&bytes);
+    if read.charset.starts_with('(') {
+        return Err(format!("Not text file: {}", filepath));
+    }
+    match file_extension(filepath).as_str() {
+        "po" | "pot" => Ok(po_entries(&read.content)),
+        "strings" => Ok(apple_strings_entries(&read.content)),
+        "xml" => android_strings_entries(&read.content),
+        _ => Err(format!("Unsupported localization file: {}", filepath)),
+    }
+}
+
+/// gettext entries keyed by msgid, prefixed with msgctxt such as `[menu] Open`
+/// header entry and obsolete ones (`#~`) are skipped. plural forms are joined by line break
+fn po_entries(content: &str) -> BTreeMap<String, LocalizationEntry> {
+    let mut ret = BTreeMap::new();
+    let mut entry = PoEntry::default();
+    let mut field = None;
+    let mut flush = |entry: &mut PoEntry| {
+        let entry = std::mem::take(entry);
+        let Some(msgid) = entry.msgid else {
+            return;
+        };
+        if msgid.is_empty() && entry.msgctxt.is_none() {
+            return;
+        }
+        let key = |msgid: &str| match &entry.msgctxt {
+            Some(msgctxt) => format!("[{}] {}", msgctxt, msgid),
+            None => msgid.to_owned(),
+        };
+        let source = match &entry.msgid_plural {
+            Some(plural) => format!("{}\n{}", msgid, plural),
+            None => msgid.to_owned(),
+        };
+        ret.insert(
+            key(&msgid),
+            LocalizationEntry {
+                source: Some(source),
+                translation: entry.msgstr.join("\n"),
+                previous_key: entry.previous_msgid.as_deref().map(key),
+                fuzzy: entry.fuzzy,
+            },
+        );
+    };
+
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            flush(&mut entry);
+            field = None;
+            continue;
+        }
+        if line.starts_with("#~") {
+            continue;
+        }
+        // comments belong to entry following them
+        if line.starts_with('#') && entry.msgid.is_some() {
+            flush(&mut entry);
+            field = None;
+        }
+        if let Some(previous) = line.strip_prefix("#|") {
+            let previous = previous.trim();
+            if let Some(value) = previous.strip_prefix("msgid ") {
+                entry.previous_msgid = Some(po_string(value));
+                field = Some(PoField::PreviousMsgid);
+            } else if previous.starts_with('"') {
+                if let (Some(PoField::PreviousMsgid), Some(x)) =
+                    (field, entry.previous_msgid.as_mut())
+                {
+                    x.push_str(&po_string(previous));
+                }
+            }
+            continue;
+        }
+        if let Some(flags) = line.strip_prefix("#,") {
+            entry.fuzzy = flags.split(',').any(|x| x.trim() == "fuzzy");
+            continue;
+        }
+        if line.starts_with('#') {
+            continue;
+        }
+
+        if line.starts_with('"') {
+            let value = po_string(line);
+            let target = match field {
+                Some(PoField::Msgctxt) => entry.msgctxt.as_mut(),
+                Some(PoField::Msgid) => entry.msgid.as_mut(),
+                Some(PoField::MsgidPlural) => entry.msgid_plural.as_mut(),
+                Some(PoField::Msgstr(i)) => entry.msgstr.get_mut(i),
+                Some(PoField::PreviousMsgid) | None => None,
+            };
+            if let Some(target) = target {
+                target.push_str(&value);
+            }
+            continue;
+        }
+        let (keyword, value) = line.split_once(' ').unwrap_or((line, "\"\""));
+        let value = po_string(value);
+        match keyword {
+            "msgctxt" => {
+                if entry.msgid.is_some() {
+                    flush(&mut entry);
+                }
+                entry.msgctxt = Some(value);
+                field = Some(PoField::Msgctxt);
+            }
+            "msgid" => {
+                if entry.msgid.is_some() {
+                    flush(&mut entry);
+                }
+                entry.msgid = Some(value);
+                field = Some(PoField::Msgid);
+            }
+            "msgid_plural" => {
+                entry.msgid_plural = Some(value);
+                field = Some(PoField::MsgidPlural);
+            }
+            _ => {
+                // `msgstr` or `msgstr[n]`
+                let Some(index) = keyword.strip_prefix("msgstr").map(|x| {
+                    x.trim_start_matches('[')
+                        .trim_end_matches(']')
+                        .parse::<usize>()
+                        .unwrap_or_default()
+                }) else {
+                    continue;
+                };
+                if entry.msgstr.len() <= index {
+                    entry.msgstr.resize(index + 1, String::new());
+                }
+                entry.msgstr[index] = value;
+                field = Some(PoField::Msgstr(index));
+            }
+        }
+    }
+    flush(&mut entry);
+    ret
+}
+
+/// content of gettext quoted string with c escapes resolved
+fn po_string(quoted: &str) -> String {
+    let inner = quoted
+        .trim()
+        .strip_prefix('"')
+        .and_then(|x| x.strip_suffix('"'))
+        .unwrap_or_default();
+    unescaped(inner)
+}
+
+/// resolve backslash escapes such as `\n`, `\t` and `\"`
+fn unescaped(s: &str) -> String {
+    let mut ret = String::new();
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            ret.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => ret.push('\n'),
+            Some('t') => ret.push('\t'),
+            Some('r') => ret.push('\r'),
+            Some(x) => ret.push(x),
+            None => ret.push('\\'),
+        }
+    }
+    ret
+}
+
+/// apple `"key" = "value";` entries with comment before each taken as its source
+fn apple_strings_entries(content: &str) -> BTreeMap<String, LocalizationEntry> {
+    let mut ret = BTreeMap::new();
+    let mut comment = None;
+    let mut rest = content.trim_start_matches('\u{feff}');
+    loop {
+        rest = rest.trim_start();
+        if rest.is_empty() {
+            break;
+        }
+        if let Some(x) = rest.strip_prefix("/*") {
+            let end = x.find("*/").unwrap_or(x.len());
+            comment = Some(x[..end].trim().to_owned());
+            rest = x.get(end + 2..).unwrap_or_default();
+            continue;
+        }
+        if let Some(x) = rest.strip_prefix("//") {
+            let end = x.find('\n').unwrap_or(x.len());
+            comment = Some(x[..end].trim().to_owned());
+            rest = &x[end..];
+            continue;
+        }
+        let Some((key, after_key)) = strings_token(rest) else {
+            break;
+        };
+        let Some(after_equal) = after_key.trim_start().strip_prefix('=') else {
+            // skip to next statement on malformed one
+            rest = after_key.split_once(';').map_or("", |x| x.1);
+            continue;
+        };
+        let Some((value, after_value)) = strings_token(after_equal.trim_start()) else {
+            break;
+        };
+        ret.insert(
+            key,
+            LocalizationEntry {
+                source: comment.take(),
+                translation: value,
+                previous_key: None,
+                fuzzy: false,
+            },
+        );
+        rest = after_value.trim_start();
+        rest = rest.strip_prefix(';').unwrap_or(rest);
+    }
+    ret
+}
+
+/// quoted string with escapes resolved, or unquoted word, and rest after it
+fn strings_token(s: &str) -> Option<(String, &str)> {
+    let Some(quoted) = s.strip_prefix('"') else {
+        let end = s.find(|x: char| x.is_whitespace() || x == '=' || x == ';')?;
+        return (0 < end).then(|| (s[..end].to_owned(), &s[end..]));
+    };
+    let mut escaped = false;
+    for (i, c) in quoted.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return Some((unescaped(&quoted[..i]), &quoted[i + 1..])),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// android resources keyed by name, with `name:quantity` for plurals and `name[index]` for arrays
+/// comment before each is taken as its source
+fn android_strings_entries(content: &str) -> Result<BTreeMap<String, LocalizationEntry>, String> {
+    let regex_err = |err: regex::Error| format!("Failed to parse android resources ({})", err);
+    let resource = Regex::new(
+        r#"(?s)<!--(.*?)-->|<(string|plurals|string-array)\b([^>]*?)(?:/>|>(.*?)</(?:string|plurals|string-array)>)"#,
+    )
+    .map_err(regex_err)?;
+    let item = Regex::new(r#"(?s)<item\b([^>]*?)(?:/>|>(.*?)</item>)"#).map_err(regex_err)?;
+
+    let mut ret = BTreeMap::new();
+    let mut comment = None;
+    for captures in resource.captures_iter(content) {
+        if let Some(x) = captures.get(1) {
+            comment = Some(x.as_str().trim().to_owned());
+            continue;
+        }
+        let tag = captures.get(2).map(|x| x.as_str()).unwrap_or_default();
+        let attributes = captures.get(3).map(|x| x.as_str()).unwrap_or_default();
+        let body = captures.get(4).map(|x| x.as_str()).unwrap_or_default();
+        let Some(name) = attribute(attributes, "name") else {
+            continue;
+        };
+        let source = comment.take();
+        let mut insert = |key: String, text: &str| {
+            ret.insert(
+                key,
+                LocalizationEntry {
+                    source: source.to_owned(),
+                    translation: android_text(text),
+                    previous_key: None,
+                    fuzzy: false,
+                },
+            );
+        };
+        match tag {
+            "string" => insert(name, body),
+            _ => {
+                for (i, item) in item.captures_iter(body).enumerate() {
+                    let item_attributes = item.get(1).map(|x| x.as_str()).unwrap_or_default();
+                    let text = item.get(2).map(|x| x.as_str()).unwrap_or_default();
+                    let key = match attribute(item_attributes, "quantity") {
+                        Some(quantity) => format!("{}:{}", name, quantity),
+                        None => format!("{}[{}]", name, i),
+                    };
+                    insert(key, text);
+                }
+            }
+        }
+    }
+    Ok(ret)
+}
+
+/// attribute value in start tag, quoted by either double or single quotes
+fn attribute(attributes: &str, name: &str) -> Option<String> {
+    let pattern = format!("{}=", name);
+    let start = attributes
+        .match_indices(&pattern)
+        .find(|(i, _)| *i == 0 || attributes[..*i].ends_with(char::is_whitespace))?
+        .0
+        + pattern.len();
+    let rest = &attributes[start..];
+    let quote = rest.chars().next().filter(|x| *x == '"' || *x == '\'')?;
+    let value = rest[1..].split(quote).next()?;
+    Some(xml_unescape(value))
+}
+
+/// text of android string with xml entities, cdata, surrounding quotes and escapes resolved
+/// inline markup such as `<b>` is kept
+fn android_text(text: &str) -> String {
+    let text = text.trim();
+    let text = match text
+        .strip_prefix("<![CDATA[")
+        .and_then(|x| x.strip_suffix("]]>"))
+    {
+        Some(x) => x.to_owned(),
+        None => xml_unescape(text),
+    };
+    let text = text
+        .strip_prefix('"')
+        .and_then(|x| x.strip_suffix('"'))
+        .unwrap_or(&text);
+    unescaped(text)
+}
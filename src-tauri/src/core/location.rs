@@ -0,0 +1,77 @@
+use chrono::Local;
+
+use super::store;
+use super::types::{LocationStore, PinnedDir, RecentDir};
+
+/// file name to persist recent and pinned dirs
+const LOCATION_STORE_FILENAME: &str = "locations.json";
+/// max recent dirs kept. oldest ones are dropped
+const MAX_RECENT_DIRS: usize = 50;
+
+/// record dir visited in browser. visited again is moved to head
+pub fn record_visit(app_handle: &tauri::AppHandle, dir: &str) -> Result<(), String> {
+    if dir.is_empty() {
+        return Ok(());
+    }
+    let mut store: LocationStore = store::load(app_handle, LOCATION_STORE_FILENAME)?;
+    store.recent.retain(|x| x.path != dir);
+    store.recent.insert(
+        0,
+        RecentDir {
+            path: dir.to_owned(),
+            visited_at: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        },
+    );
+    store.recent.truncate(MAX_RECENT_DIRS);
+    store::save(app_handle, LOCATION_STORE_FILENAME, &store)
+}
+
+/// dirs visited recently, latest first
+pub fn recent_dirs(app_handle: &tauri::AppHandle) -> Result<Vec<RecentDir>, String> {
+    let store: LocationStore = store::load(app_handle, LOCATION_STORE_FILENAME)?;
+    Ok(store.recent)
+}
+
+/// remove dir from recent ones, or all of them when dir is not specified
+pub fn remove_recent_dir(app_handle: &tauri::AppHandle, dir: Option<&str>) -> Result<(), String> {
+    let mut store: LocationStore = store::load(app_handle, LOCATION_STORE_FILENAME)?;
+    match dir {
+        Some(dir) => store.recent.retain(|x| x.path != dir),
+        None => store.recent.clear(),
+    }
+    store::save(app_handle, LOCATION_STORE_FILENAME, &store)
+}
+
+/// dirs pinned by user in order of pinning
+pub fn pinned_dirs(app_handle: &tauri::AppHandle) -> Result<Vec<PinnedDir>, String> {
+    let store: LocationStore = store::load(app_handle, LOCATION_STORE_FILENAME)?;
+    Ok(store.pinned)
+}
+
+/// pin dir with label shown instead of path. pinned again is relabeled in place
+pub fn pin_dir(
+    app_handle: &tauri::AppHandle,
+    dir: &str,
+    label: Option<String>,
+) -> Result<(), String> {
+    if dir.is_empty() {
+        return Err("Dir to pin is not specified".to_owned());
+    }
+    let mut store: LocationStore = store::load(app_handle, LOCATION_STORE_FILENAME)?;
+    let label = label.filter(|x| !x.is_empty());
+    match store.pinned.iter_mut().find(|x| x.path == dir) {
+        Some(pinned) => pinned.label = label,
+        None => store.pinned.push(PinnedDir {
+            path: dir.to_owned(),
+            label,
+        }),
+    }
+    store::save(app_handle, LOCATION_STORE_FILENAME, &store)
+}
+
+/// unpin dir
+pub fn unpin_dir(app_handle: &tauri::AppHandle, dir: &str) -> Result<(), String> {
+    let mut store: LocationStore = store::load(app_handle, LOCATION_STORE_FILENAME)?;
+    store.pinned.retain(|x| x.path != dir);
+    store::save(app_handle, LOCATION_STORE_FILENAME, &store)
+}
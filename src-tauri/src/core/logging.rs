@@ -0,0 +1,103 @@
+use std::fs;
+
+use tauri::Manager;
+use tracing_appender::rolling::{Builder, Rotation};
+
+use super::types::LogEntry;
+
+/// prefix of log file names followed by date
+const LOG_FILENAME_PREFIX: &str = "forskscope";
+/// suffix of log file names
+const LOG_FILENAME_SUFFIX: &str = "log";
+/// daily log files kept on rotation
+const MAX_LOG_FILES: usize = 7;
+/// log entries returned at once when limit is not specified
+const DEFAULT_LOG_ENTRIES_LIMIT: usize = 200;
+
+/// start writing backend logs as json lines into log files rotated daily in app log dir
+pub fn init_logging(app_handle: &tauri::AppHandle) -> Result<(), String> {
+    let log_dir = app_handle
+        .path()
+        .app_log_dir()
+        .map_err(|err| format!("Failed to get app log dir ({})", err))?;
+    let appender = Builder::new()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(LOG_FILENAME_PREFIX)
+        .filename_suffix(LOG_FILENAME_SUFFIX)
+        .max_log_files(MAX_LOG_FILES)
+        .build(&log_dir)
+        .map_err(|err| {
+            format!(
+                "Failed to create log file in {} ({})",
+                log_dir.display(),
+                err
+            )
+        })?;
+    tracing_subscriber::fmt()
+        .json()
+        .with_ansi(false)
+        .with_max_level(tracing::Level::INFO)
+        .with_writer(appender)
+        .try_init()
+        .map_err(|err| format!("Failed to init logging ({})", err))
+}
+
+/// get recent log entries in ascending order of time
+/// so that users can report problems with context
+pub fn recent_log_entries(
+    app_handle: &tauri::AppHandle,
+    limit: Option<usize>,
+) -> Result<Vec<LogEntry>, String> {
+    let limit = limit.unwrap_or(DEFAULT_LOG_ENTRIES_LIMIT);
+    let log_dir = app_handle
+        .path()
+        .app_log_dir()
+        .map_err(|err| format!("Failed to get app log dir ({})", err))?;
+    if !log_dir.exists() {
+        return Ok(vec![]);
+    }
+
+    // dated file names sort in order of time
+    let mut log_files = fs::read_dir(&log_dir)
+        .map_err(|err| format!("Invalid path: {} ({})", log_dir.display(), err))?
+        .filter_map(|x| x.ok().map(|x| x.path()))
+        .filter(|x| {
+            x.file_name()
+                .is_some_and(|x| x.to_string_lossy().starts_with(LOG_FILENAME_PREFIX))
+        })
+        .collect::<Vec<_>>();
+    log_files.sort();
+
+    let mut ret = vec![];
+    for log_file in log_files.iter().rev() {
+        let content = fs::read_to_string(log_file)
+            .map_err(|err| format!("Failed to read {} ({})", log_file.display(), err))?;
+        for line in content.lines().rev() {
+            if limit <= ret.len() {
+                break;
+            }
+            if let Some(x) = log_entry(line) {
+                ret.push(x);
+            }
+        }
+    }
+    ret.reverse();
+    Ok(ret)
+}
+
+/// parse json line written by logging layer
+fn log_entry(line: &str) -> Option<LogEntry> {
+    let value = serde_json::from_str::<serde_json::Value>(line).ok()?;
+    let text = |key: &str| value.get(key).and_then(|x| x.as_str()).unwrap_or_default();
+    let message = value
+        .get("fields")
+        .and_then(|x| x.get("message"))
+        .and_then(|x| x.as_str())
+        .unwrap_or_default();
+    Some(LogEntry {
+        timestamp: text("timestamp").to_owned(),
+        level: text("level").to_owned(),
+        target: text("target").to_owned(),
+        message: message.to_owned(),
+    })
+}
@@ -0,0 +1,314 @@
+use std::fs;
+
+use super::file::bytes_content;
+use super::os_path::os_path;
+use super::types::{MarkupOptions, ReadContent};
+
+/// elements whose content is not markup
+const RAW_TEXT_ELEMENTS: [&str; 4] = ["script", "style", "textarea", "pre"];
+
+/// markup token
+enum MarkupToken<'a> {
+    /// start, end or self-closing tag with name and attributes
+    Tag {
+        name: &'a str,
+        closing: bool,
+        self_closing: bool,
+        attributes: Vec<(&'a str, Option<&'a str>)>,
+    },
+    /// comment, doctype, cdata, processing instruction or raw text kept as it is
+    Verbatim(&'a str),
+    Text(&'a str),
+}
+
+/// read html, xml, jsx or vue template with one tag or text per line
+/// so that lines are compared by markup tokens rather than by source formatting
+pub fn markup_content(filepath: &str, options: &MarkupOptions) -> Result<ReadContent, String> {
+    if filepath.is_empty() {
+        return Ok(ReadContent::default());
+    }
+    let bytes = fs::read(os_path(filepath))
+        .map_err(|err| format!("Failed to read {} ({})", filepath, err))?;
+    let read = bytes_content(&bytes);
+    Ok(ReadContent {
+        content: normalized_markup(&read.content, options),
+        ..read
+    })
+}
+
+/// markup with one token per line
+/// attributes are sorted by name when `ignore_attribute_order` is set and
+/// whitespace between tags is dropped and collapsed in text when `ignore_whitespace` is set
+pub fn normalized_markup(content: &str, options: &MarkupOptions) -> String {
+    let mut ret = String::new();
+    for token in markup_tokens(content) {
+        match token {
+            MarkupToken::Tag {
+                name,
+                closing,
+                self_closing,
+                mut attributes,
+            } => {
+                if options.ignore_attribute_order {
+                    attributes.sort_by(|a, b| a.0.cmp(b.0));
+                }
+                ret.push('<');
+                if closing {
+                    ret.push('/');
+                }
+                ret.push_str(name);
+                for (name, value) in attributes {
+                    ret.push(' ');
+                    ret.push_str(name);
+                    if let Some(value) = value {
+                        ret.push('=');
+                        ret.push_str(&normalized_attribute_value(value));
+                    }
+                }
+                ret.push_str(if self_closing { " />\n" } else { ">\n" });
+            }
+            MarkupToken::Verbatim(x) => {
+                ret.push_str(x);
+                if !x.ends_with('\n') {
+                    ret.push('\n');
+                }
+            }
+            MarkupToken::Text(x) => {
+                let text = if options.ignore_whitespace {
+                    x.split_whitespace().collect::<Vec<_>>().join(" ")
+                } else {
+                    x.to_owned()
+                };
+                if text.is_empty() {
+                    continue;
+                }
+                ret.push_str(&text);
+                if !text.ends_with('\n') {
+                    ret.push('\n');
+                }
+            }
+        }
+    }
+    ret
+}
+
+/// split markup into tokens. malformed tag is regarded as text
+fn markup_tokens(content: &str) -> Vec<MarkupToken<'_>> {
+    let mut ret = vec![];
+    let mut rest = content;
+    while !rest.is_empty() {
+        let Some(start) = rest.find('<') else {
+            ret.push(MarkupToken::Text(rest));
+            break;
+        };
+        if 0 < start {
+            ret.push(MarkupToken::Text(&rest[..start]));
+            rest = &rest[start..];
+        }
+
+        let verbatim_end = [
+            ("<!--", "-->"),
+            ("<![CDATA[", "]]>"),
+            ("<?", "?>"),
+            ("<!", ">"),
+        ]
+        .iter()
+        .find(|x| rest.starts_with(x.0))
+        .map(|(_, end)| rest.find(end).map_or(rest.len(), |x| x + end.len()));
+        if let Some(end) = verbatim_end {
+            ret.push(MarkupToken::Verbatim(&rest[..end]));
+            rest = &rest[end..];
+            continue;
+        }
+
+        match parse_tag(rest) {
+            Some((token, len)) => {
+                let raw_text_name = match &token {
+                    MarkupToken::Tag {
+                        name,
+                        closing: false,
+                        self_closing: false,
+                        ..
+                    } if RAW_TEXT_ELEMENTS.contains(&name.to_lowercase().as_str()) => {
+                        Some(name.to_lowercase())
+                    }
+                    _ => None,
+                };
+                ret.push(token);
+                rest = &rest[len..];
+                // content of script, style and pre is kept as it is up to its end tag
+                if let Some(name) = raw_text_name {
+                    // ascii lowercase keeps byte offsets
+                    let end = rest
+                        .to_ascii_lowercase()
+                        .find(&format!("</{}", name))
+                        .unwrap_or(rest.len());
+                    if 0 < end {
+                        ret.push(MarkupToken::Verbatim(&rest[..end]));
+                    }
+                    rest = &rest[end..];
+                }
+            }
+            None => {
+                // joined to preceding text such as `a < b`
+                let offset = content.len() - rest.len();
+                let end = offset + rest[1..].find('<').map_or(rest.len(), |x| x + 1);
+                let start = match ret.last() {
+                    Some(MarkupToken::Text(x)) => {
+                        let start = x.as_ptr() as usize - content.as_ptr() as usize;
+                        if start + x.len() == offset {
+                            ret.pop();
+                            start
+                        } else {
+                            offset
+                        }
+                    }
+                    _ => offset,
+                };
+                ret.push(MarkupToken::Text(&content[start..end]));
+                rest = &content[end..];
+            }
+        }
+    }
+    ret
+}
+
+/// parse tag at head of string and get it with its length
+/// attribute values may be quoted or jsx expressions in braces
+fn parse_tag(s: &str) -> Option<(MarkupToken<'_>, usize)> {
+    let bytes = s.as_bytes();
+    let mut pos = 1;
+    let closing = bytes.get(pos) == Some(&b'/');
+    if closing {
+        pos += 1;
+    }
+    let name_start = pos;
+    while pos < bytes.len() && !bytes[pos].is_ascii_whitespace() && !b"/>".contains(&bytes[pos]) {
+        pos += 1;
+    }
+    let name = &s[name_start..pos];
+    // jsx fragment `<>` has empty name
+    if name.contains(['<', '"', '\'', '=', '{']) || (name.is_empty() && !s[pos..].starts_with('>'))
+    {
+        return None;
+    }
+
+    let mut attributes = vec![];
+    loop {
+        while pos < bytes.len() && bytes[pos].is_ascii_whitespace() {
+            pos += 1;
+        }
+        match bytes.get(pos)? {
+            b'>' => {
+                let token = MarkupToken::Tag {
+                    name,
+                    closing,
+                    self_closing: false,
+                    attributes,
+                };
+                return Some((token, pos + 1));
+            }
+            b'/' if bytes.get(pos + 1) == Some(&b'>') => {
+                let token = MarkupToken::Tag {
+                    name,
+                    closing,
+                    self_closing: true,
+                    attributes,
+                };
+                return Some((token, pos + 2));
+            }
+            b'<' => return None,
+            _ => (),
+        }
+
+        let attribute_start = pos;
+        if bytes[pos] == b'{' {
+            // jsx spread attribute such as `{...props}`
+            pos = braces_end(bytes, pos)?;
+            attributes.push((&s[attribute_start..pos], None));
+            continue;
+        }
+        while pos < bytes.len()
+            && !bytes[pos].is_ascii_whitespace()
+            && !b"=/>".contains(&bytes[pos])
+        {
+            pos += 1;
+        }
+        if pos == attribute_start {
+            // stray slash
+            pos += 1;
+            continue;
+        }
+        let attribute_name = &s[attribute_start..pos];
+        while pos < bytes.len() && bytes[pos].is_ascii_whitespace() {
+            pos += 1;
+        }
+        if bytes.get(pos) != Some(&b'=') {
+            attributes.push((attribute_name, None));
+            continue;
+        }
+        pos += 1;
+        while pos < bytes.len() && bytes[pos].is_ascii_whitespace() {
+            pos += 1;
+        }
+        let value_start = pos;
+        match bytes.get(pos)? {
+            quote @ (b'"' | b'\'') => {
+                pos += 1 + s[pos + 1..].find(*quote as char)? + 1;
+            }
+            b'{' => pos = braces_end(bytes, pos)?,
+            _ => {
+                while pos < bytes.len() && !bytes[pos].is_ascii_whitespace() && bytes[pos] != b'>' {
+                    pos += 1;
+                }
+            }
+        }
+        attributes.push((attribute_name, Some(&s[value_start..pos])));
+    }
+}
+
+/// end position of balanced braces starting at `start`. strings in braces are skipped
+fn braces_end(bytes: &[u8], start: usize) -> Option<usize> {
+    let mut depth = 0;
+    let mut quote = None;
+    let mut pos = start;
+    while pos < bytes.len() {
+        let x = bytes[pos];
+        match quote {
+            Some(_) if x == b'\\' => pos += 1,
+            Some(q) if x == q => quote = None,
+            Some(_) => (),
+            None => match x {
+                b'"' | b'\'' | b'`' => quote = Some(x),
+                b'{' => depth += 1,
+                b'}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some(pos + 1);
+                    }
+                }
+                _ => (),
+            },
+        }
+        pos += 1;
+    }
+    None
+}
+
+/// attribute value in double quotes unless it is jsx expression or contains double quote
+fn normalized_attribute_value(value: &str) -> String {
+    let is_quoted =
+        |quote: char| 2 <= value.len() && value.starts_with(quote) && value.ends_with(quote);
+    let unquoted = if is_quoted('"') || is_quoted('\'') {
+        &value[1..value.len() - 1]
+    } else {
+        value
+    };
+    if value.starts_with('{') {
+        value.to_owned()
+    } else if unquoted.contains('"') {
+        format!("'{}'", unquoted)
+    } else {
+        format!("\"{}\"", unquoted)
+    }
+}
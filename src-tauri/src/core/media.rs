@@ -0,0 +1,535 @@
+use std::collections::BTreeMap;
+use std::fs;
+
+use super::field_diff::field_diffs;
+use super::os_path::os_path;
+use super::types::MetadataFieldDiff;
+
+/// max nesting depth to walk into mp4 boxes
+const MAX_MP4_BOX_DEPTH: usize = 8;
+
+/// extract metadata of image, audio or video file as key-value pairs
+/// keys are prefixed with metadata format such as `EXIF.Model` and `ID3.TIT2`
+pub fn media_metadata(filepath: &str) -> Result<BTreeMap<String, String>, String> {
+    let bytes = fs::read(os_path(filepath))
+        .map_err(|err| format!("Failed to read {} ({})", filepath, err))?;
+
+    let mut ret = BTreeMap::new();
+    if bytes.starts_with(&[0xff, 0xd8]) {
+        jpeg_metadata(&bytes, &mut ret);
+    } else if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        png_metadata(&bytes, &mut ret);
+    } else if bytes.starts_with(b"RIFF") && bytes.get(8..12) == Some(b"WAVE") {
+        wav_metadata(&bytes, &mut ret);
+    } else if bytes.get(4..8) == Some(b"ftyp") {
+        mp4_boxes_metadata(&bytes, 0, &mut ret);
+    } else if bytes.starts_with(b"II*\0") || bytes.starts_with(b"MM\0*") {
+        tiff_metadata(&bytes, "EXIF", &mut ret);
+    }
+    // id3 tags may be put on any audio file
+    if bytes.starts_with(b"ID3") {
+        id3v2_metadata(&bytes, &mut ret);
+    }
+    id3v1_metadata(&bytes, &mut ret);
+
+    Ok(ret)
+}
+
+/// compare metadata field by field
+pub fn metadata_diffs(old: &str, new: &str) -> Result<Vec<MetadataFieldDiff>, String> {
+    let old_metadata = media_metadata(old)?;
+    let new_metadata = media_metadata(new)?;
+    Ok(field_diffs(&old_metadata, &new_metadata))
+}
+
+/// walk jpeg segments to find dimensions and exif
+fn jpeg_metadata(bytes: &[u8], metadata: &mut BTreeMap<String, String>) {
+    let mut pos = 2;
+    while pos + 4 <= bytes.len() {
+        if bytes[pos] != 0xff {
+            return;
+        }
+        let marker = bytes[pos + 1];
+        // start of scan: image data follows
+        if marker == 0xda {
+            return;
+        }
+        let len = u16::from_be_bytes([bytes[pos + 2], bytes[pos + 3]]) as usize;
+        let segment = match bytes.get(pos + 4..pos + 2 + len) {
+            Some(x) => x,
+            None => return,
+        };
+        match marker {
+            // app1
+            0xe1 if segment.starts_with(b"Exif\0\0") => {
+                tiff_metadata(&segment[6..], "EXIF", metadata)
+            }
+            // start of frame except dht, jpg and dac
+            0xc0..=0xcf
+                if marker != 0xc4 && marker != 0xc8 && marker != 0xcc && 5 <= segment.len() =>
+            {
+                let height = u16::from_be_bytes([segment[1], segment[2]]);
+                let width = u16::from_be_bytes([segment[3], segment[4]]);
+                metadata.insert("JPEG.Width".to_owned(), width.to_string());
+                metadata.insert("JPEG.Height".to_owned(), height.to_string());
+            }
+            _ => {}
+        }
+        pos += 2 + len;
+    }
+}
+
+/// read ifds in tiff structure used by exif
+fn tiff_metadata(tiff: &[u8], prefix: &str, metadata: &mut BTreeMap<String, String>) {
+    let little_endian = match tiff.get(0..2) {
+        Some(b"II") => true,
+        Some(b"MM") => false,
+        _ => return,
+    };
+    let reader = TiffReader {
+        tiff,
+        little_endian,
+    };
+    let ifd_offset = match reader.u32(4) {
+        Some(x) => x as usize,
+        None => return,
+    };
+    reader.read_ifd(ifd_offset, prefix, metadata, 0);
+}
+
+/// reader on tiff bytes with byte order
+struct TiffReader<'a> {
+    tiff: &'a [u8],
+    little_endian: bool,
+}
+
+impl TiffReader<'_> {
+    fn u16(&self, pos: usize) -> Option<u16> {
+        let x: [u8; 2] = self.tiff.get(pos..pos + 2)?.try_into().ok()?;
+        Some(if self.little_endian {
+            u16::from_le_bytes(x)
+        } else {
+            u16::from_be_bytes(x)
+        })
+    }
+
+    fn u32(&self, pos: usize) -> Option<u32> {
+        let x: [u8; 4] = self.tiff.get(pos..pos + 4)?.try_into().ok()?;
+        Some(if self.little_endian {
+            u32::from_le_bytes(x)
+        } else {
+            u32::from_be_bytes(x)
+        })
+    }
+
+    /// read entries in ifd and sub ifds for exif and gps
+    fn read_ifd(
+        &self,
+        offset: usize,
+        prefix: &str,
+        metadata: &mut BTreeMap<String, String>,
+        depth: usize,
+    ) {
+        if 4 < depth {
+            return;
+        }
+        let entries_count = match self.u16(offset) {
+            Some(x) => x as usize,
+            None => return,
+        };
+        for i in 0..entries_count {
+            let entry = offset + 2 + i * 12;
+            let (tag, value_type, count) =
+                match (self.u16(entry), self.u16(entry + 2), self.u32(entry + 4)) {
+                    (Some(tag), Some(value_type), Some(count)) => (tag, value_type, count as usize),
+                    _ => return,
+                };
+
+            // sub ifd pointers
+            if tag == 0x8769 || tag == 0x8825 {
+                if let Some(sub_offset) = self.u32(entry + 8) {
+                    let sub_prefix = if tag == 0x8825 { "GPS" } else { prefix };
+                    self.read_ifd(sub_offset as usize, sub_prefix, metadata, depth + 1);
+                }
+                continue;
+            }
+
+            if let Some(value) = self.value(entry, value_type, count) {
+                let name = match exif_tag_name(tag) {
+                    Some(x) => x.to_owned(),
+                    None => format!("Tag0x{:04X}", tag),
+                };
+                metadata.insert(format!("{}.{}", prefix, name), value);
+            }
+        }
+    }
+
+    /// format entry value
+    fn value(&self, entry: usize, value_type: u16, count: usize) -> Option<String> {
+        let unit_size = match value_type {
+            1 | 2 | 6 | 7 => 1,
+            3 | 8 => 2,
+            4 | 9 | 11 => 4,
+            5 | 10 | 12 => 8,
+            _ => return None,
+        };
+        let total_size = unit_size * count;
+        let pos = if total_size <= 4 {
+            entry + 8
+        } else {
+            self.u32(entry + 8)? as usize
+        };
+        let data = self.tiff.get(pos..pos + total_size)?;
+
+        let values = match value_type {
+            // ascii
+            2 => {
+                return Some(
+                    String::from_utf8_lossy(data)
+                        .trim_end_matches('\0')
+                        .trim()
+                        .to_owned(),
+                )
+            }
+            // undefined: bytes as hex unless short printable text
+            7 => {
+                if data.iter().all(|x| x.is_ascii_graphic() || *x == b' ') {
+                    return Some(String::from_utf8_lossy(data).to_string());
+                }
+                return Some(
+                    data.iter()
+                        .take(32)
+                        .map(|x| format!("{:02x}", x))
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                );
+            }
+            1 | 6 => data.iter().map(|x| x.to_string()).collect::<Vec<_>>(),
+            3 | 8 => (0..count)
+                .filter_map(|i| self.u16(pos + i * 2).map(|x| x.to_string()))
+                .collect(),
+            4 | 9 => (0..count)
+                .filter_map(|i| self.u32(pos + i * 4).map(|x| x.to_string()))
+                .collect(),
+            5 | 10 => (0..count)
+                .filter_map(|i| {
+                    let numerator = self.u32(pos + i * 8)?;
+                    let denominator = self.u32(pos + i * 8 + 4)?;
+                    Some(format!("{}/{}", numerator, denominator))
+                })
+                .collect(),
+            _ => return None,
+        };
+        Some(values.join(", "))
+    }
+}
+
+/// name of well-known exif tag
+fn exif_tag_name(tag: u16) -> Option<&'static str> {
+    let name = match tag {
+        0x010e => "ImageDescription",
+        0x010f => "Make",
+        0x0110 => "Model",
+        0x0112 => "Orientation",
+        0x011a => "XResolution",
+        0x011b => "YResolution",
+        0x0128 => "ResolutionUnit",
+        0x0131 => "Software",
+        0x0132 => "DateTime",
+        0x013b => "Artist",
+        0x8298 => "Copyright",
+        0x829a => "ExposureTime",
+        0x829d => "FNumber",
+        0x8827 => "ISOSpeedRatings",
+        0x9003 => "DateTimeOriginal",
+        0x9004 => "DateTimeDigitized",
+        0x9201 => "ShutterSpeedValue",
+        0x9202 => "ApertureValue",
+        0x9209 => "Flash",
+        0x920a => "FocalLength",
+        0xa002 => "PixelXDimension",
+        0xa003 => "PixelYDimension",
+        0xa405 => "FocalLengthIn35mmFilm",
+        0xa433 => "LensMake",
+        0xa434 => "LensModel",
+        _ => return None,
+    };
+    Some(name)
+}
+
+/// walk png chunks to find header and text
+fn png_metadata(bytes: &[u8], metadata: &mut BTreeMap<String, String>) {
+    let mut pos = 8;
+    while pos + 8 <= bytes.len() {
+        let len = u32::from_be_bytes(bytes[pos..pos + 4].try_into().unwrap()) as usize;
+        let chunk_type = &bytes[pos + 4..pos + 8];
+        let data = match bytes.get(pos + 8..pos + 8 + len) {
+            Some(x) => x,
+            None => return,
+        };
+        match chunk_type {
+            b"IHDR" if 10 <= data.len() => {
+                let width = u32::from_be_bytes(data[0..4].try_into().unwrap());
+                let height = u32::from_be_bytes(data[4..8].try_into().unwrap());
+                metadata.insert("PNG.Width".to_owned(), width.to_string());
+                metadata.insert("PNG.Height".to_owned(), height.to_string());
+                metadata.insert("PNG.BitDepth".to_owned(), data[8].to_string());
+                metadata.insert("PNG.ColorType".to_owned(), data[9].to_string());
+            }
+            b"tEXt" => {
+                if let Some(sep) = data.iter().position(|x| *x == 0) {
+                    let keyword = latin1(&data[..sep]);
+                    let text = latin1(&data[sep + 1..]);
+                    metadata.insert(format!("PNG.{}", keyword), text);
+                }
+            }
+            b"iTXt" => {
+                // keyword \0 compression flag, method, language \0 translated keyword \0 text
+                if let Some(sep) = data.iter().position(|x| *x == 0) {
+                    let keyword = latin1(&data[..sep]);
+                    let compressed = data.get(sep + 1) == Some(&1);
+                    let rest = data.get(sep + 3..).unwrap_or_default();
+                    let mut parts = rest.splitn(3, |x| *x == 0);
+                    let text = parts.nth(2).unwrap_or_default();
+                    if !compressed {
+                        metadata.insert(
+                            format!("PNG.{}", keyword),
+                            String::from_utf8_lossy(text).to_string(),
+                        );
+                    }
+                }
+            }
+            b"tIME" if 7 <= data.len() => {
+                let year = u16::from_be_bytes([data[0], data[1]]);
+                metadata.insert(
+                    "PNG.LastModified".to_owned(),
+                    format!(
+                        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+                        year, data[2], data[3], data[4], data[5], data[6]
+                    ),
+                );
+            }
+            b"eXIf" => tiff_metadata(data, "EXIF", metadata),
+            b"IEND" => return,
+            _ => {}
+        }
+        // length, type, data and crc
+        pos += 12 + len;
+    }
+}
+
+/// walk riff chunks of wav to find format and info
+fn wav_metadata(bytes: &[u8], metadata: &mut BTreeMap<String, String>) {
+    let mut pos = 12;
+    while pos + 8 <= bytes.len() {
+        let chunk_id = &bytes[pos..pos + 4];
+        let len = u32::from_le_bytes(bytes[pos + 4..pos + 8].try_into().unwrap()) as usize;
+        let data = match bytes.get(pos + 8..pos + 8 + len) {
+            Some(x) => x,
+            None => return,
+        };
+        match chunk_id {
+            b"fmt " if 16 <= data.len() => {
+                let channels = u16::from_le_bytes([data[2], data[3]]);
+                let sample_rate = u32::from_le_bytes(data[4..8].try_into().unwrap());
+                let bits_per_sample = u16::from_le_bytes([data[14], data[15]]);
+                metadata.insert("WAV.Channels".to_owned(), channels.to_string());
+                metadata.insert("WAV.SampleRate".to_owned(), sample_rate.to_string());
+                metadata.insert("WAV.BitsPerSample".to_owned(), bits_per_sample.to_string());
+            }
+            b"LIST" if data.starts_with(b"INFO") => {
+                let mut info_pos = 4;
+                while info_pos + 8 <= data.len() {
+                    let info_id = latin1(&data[info_pos..info_pos + 4]);
+                    let info_len =
+                        u32::from_le_bytes(data[info_pos + 4..info_pos + 8].try_into().unwrap())
+                            as usize;
+                    let info = match data.get(info_pos + 8..info_pos + 8 + info_len) {
+                        Some(x) => x,
+                        None => break,
+                    };
+                    metadata.insert(
+                        format!("RIFF.{}", info_id),
+                        String::from_utf8_lossy(info)
+                            .trim_end_matches('\0')
+                            .to_owned(),
+                    );
+                    // chunks are word aligned
+                    info_pos += 8 + info_len + info_len % 2;
+                }
+            }
+            _ => {}
+        }
+        pos += 8 + len + len % 2;
+    }
+}
+
+/// walk mp4 / mov boxes to find brand and duration
+fn mp4_boxes_metadata(bytes: &[u8], depth: usize, metadata: &mut BTreeMap<String, String>) {
+    if MAX_MP4_BOX_DEPTH < depth {
+        return;
+    }
+    let mut pos = 0;
+    while pos + 8 <= bytes.len() {
+        let size = u32::from_be_bytes(bytes[pos..pos + 4].try_into().unwrap()) as usize;
+        let box_type = &bytes[pos + 4..pos + 8];
+        // size 0 means box extends to end, 1 means 64-bit size which is skipped here
+        let end = match size {
+            0 => bytes.len(),
+            1 => return,
+            _ if size < 8 => return,
+            _ => (pos + size).min(bytes.len()),
+        };
+        let data = &bytes[pos + 8..end];
+        match box_type {
+            b"ftyp" if 4 <= data.len() => {
+                metadata.insert("MP4.MajorBrand".to_owned(), latin1(&data[..4]));
+            }
+            b"moov" | b"trak" | b"mdia" => mp4_boxes_metadata(data, depth + 1, metadata),
+            b"mvhd" if !data.is_empty() => {
+                // version 1 has 64-bit times
+                let (timescale, duration) = if data[0] == 1 {
+                    (
+                        data.get(20..24)
+                            .map(|x| u32::from_be_bytes(x.try_into().unwrap()) as u64),
+                        data.get(24..32)
+                            .map(|x| u64::from_be_bytes(x.try_into().unwrap())),
+                    )
+                } else {
+                    (
+                        data.get(12..16)
+                            .map(|x| u32::from_be_bytes(x.try_into().unwrap()) as u64),
+                        data.get(16..20)
+                            .map(|x| u32::from_be_bytes(x.try_into().unwrap()) as u64),
+                    )
+                };
+                if let (Some(timescale), Some(duration)) = (timescale, duration) {
+                    if 0 < timescale {
+                        metadata.insert(
+                            "MP4.Duration".to_owned(),
+                            format!("{:.3} s", duration as f64 / timescale as f64),
+                        );
+                    }
+                }
+            }
+            _ => {}
+        }
+        pos = end;
+    }
+}
+
+/// read id3v2 text frames
+fn id3v2_metadata(bytes: &[u8], metadata: &mut BTreeMap<String, String>) {
+    if bytes.len() < 10 {
+        return;
+    }
+    let major_version = bytes[3];
+    let tag_size = syncsafe(&bytes[6..10]);
+    let tag = match bytes.get(10..10 + tag_size) {
+        Some(x) => x,
+        None => return,
+    };
+    metadata.insert("ID3.Version".to_owned(), format!("2.{}", major_version));
+    // frames of v2.2 have 3 chars id and are not supported
+    if major_version < 3 {
+        return;
+    }
+
+    let mut pos = 0;
+    while pos + 10 <= tag.len() {
+        let frame_id = &tag[pos..pos + 4];
+        // padding
+        if frame_id[0] == 0 {
+            return;
+        }
+        let frame_size = if major_version == 4 {
+            syncsafe(&tag[pos + 4..pos + 8])
+        } else {
+            u32::from_be_bytes(tag[pos + 4..pos + 8].try_into().unwrap()) as usize
+        };
+        let data = match tag.get(pos + 10..pos + 10 + frame_size) {
+            Some(x) => x,
+            None => return,
+        };
+        if frame_id[0] == b'T' && frame_id != b"TXXX" && !data.is_empty() {
+            metadata.insert(
+                format!("ID3.{}", latin1(frame_id)),
+                id3_text(data[0], &data[1..]),
+            );
+        }
+        pos += 10 + frame_size;
+    }
+}
+
+/// read id3v1 tag at end of file
+fn id3v1_metadata(bytes: &[u8], metadata: &mut BTreeMap<String, String>) {
+    const ID3V1_SIZE: usize = 128;
+
+    if bytes.len() < ID3V1_SIZE {
+        return;
+    }
+    let tag = &bytes[bytes.len() - ID3V1_SIZE..];
+    if !tag.starts_with(b"TAG") {
+        return;
+    }
+    for (key, range) in [
+        ("Title", 3..33),
+        ("Artist", 33..63),
+        ("Album", 63..93),
+        ("Year", 93..97),
+        ("Comment", 97..127),
+    ] {
+        let value = latin1(&tag[range]).trim_end_matches('\0').trim().to_owned();
+        if !value.is_empty() {
+            metadata.insert(format!("ID3v1.{}", key), value);
+        }
+    }
+    metadata.insert("ID3v1.Genre".to_owned(), tag[127].to_string());
+}
+
+/// decode id3 text with its encoding byte
+fn id3_text(encoding: u8, data: &[u8]) -> String {
+    let text = match encoding {
+        // utf-16 with bom
+        1 => {
+            let little_endian = data.starts_with(&[0xff, 0xfe]);
+            let data = if data.starts_with(&[0xff, 0xfe]) || data.starts_with(&[0xfe, 0xff]) {
+                &data[2..]
+            } else {
+                data
+            };
+            utf16(data, little_endian)
+        }
+        // utf-16be
+        2 => utf16(data, false),
+        // utf-8
+        3 => String::from_utf8_lossy(data).to_string(),
+        _ => latin1(data),
+    };
+    text.trim_end_matches('\0').replace('\0', " / ")
+}
+
+/// decode utf-16 bytes
+fn utf16(data: &[u8], little_endian: bool) -> String {
+    let units = data.chunks_exact(2).map(|x| {
+        if little_endian {
+            u16::from_le_bytes([x[0], x[1]])
+        } else {
+            u16::from_be_bytes([x[0], x[1]])
+        }
+    });
+    char::decode_utf16(units)
+        .map(|x| x.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect()
+}
+
+/// decode latin-1 bytes
+fn latin1(data: &[u8]) -> String {
+    data.iter().map(|x| *x as char).collect()
+}
+
+/// decode syncsafe integer used in id3v2
+fn syncsafe(data: &[u8]) -> usize {
+    data.iter()
+        .fold(0, |acc, x| (acc << 7) | (*x & 0x7f) as usize)
+}
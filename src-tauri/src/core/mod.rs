@@ -1,5 +1,86 @@
+mod agent;
+mod annotation;
+mod archive;
+mod audio;
+mod binary_template;
+mod bundle;
+pub mod cli;
+mod clipboard;
+mod compare_mode;
+mod comparison;
+mod compressed;
+mod concat;
+pub mod credentials;
 mod diff;
+mod diff_rows;
+pub mod dir_diff;
+mod document;
+mod equality;
+pub mod external_tool;
+mod field_diff;
 mod file;
+mod file_lock;
+mod frontmatter;
+mod ftp;
 pub mod handlers;
+mod history;
+mod hunk_copy;
+mod ignored_hunk;
+mod include;
+mod integrity;
+mod key_value;
+mod kube_manifest;
+mod line_set;
+mod localization;
+mod location;
+pub mod logging;
+mod markup;
+mod media;
+pub mod navigation;
+mod netfs;
+mod os_path;
+mod package;
+mod pairing;
+mod palette;
+mod patch;
+pub mod path_policy;
+mod path_remap;
+mod permission;
+mod preview;
+mod process;
+mod profile;
+pub mod progress;
+mod protected;
+mod protobuf;
+mod range;
+mod records;
+mod registry;
+mod replace;
+mod round_trip;
+mod rules;
+pub mod saved;
+pub mod schedule;
+pub mod scratch;
+mod settings;
+pub mod shell;
+mod similarity;
+mod sniff;
+mod snippet;
+pub mod spool;
+mod spreadsheet;
+mod sql_dump;
+mod store;
 mod str;
+mod sync;
+mod temp_file;
+mod template;
+mod text_stats;
+mod transform;
 mod types;
+mod vcs;
+mod view_state;
+pub mod watch;
+mod web;
+mod wide_text;
+pub mod workspace;
+mod xlsx_export;
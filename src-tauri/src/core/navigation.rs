@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use tauri::Emitter;
+
+use super::types::{DiffSide, NavigationDirection, NavigationEvent, ViewNavigation};
+
+/// event emitted when navigation state is changed
+/// so that view follows changes driven by shortcuts or scripts
+pub const NAVIGATION_EVENT: &str = "navigation-changed";
+
+/// app state to keep navigation state of each view
+#[derive(Default)]
+pub struct NavigationStates(Mutex<HashMap<String, ViewNavigation>>);
+
+impl NavigationStates {
+    /// get navigation state of view
+    pub fn get(&self, view_id: &str) -> ViewNavigation {
+        self.0
+            .lock()
+            .unwrap()
+            .get(view_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// set hunks of newly built diff. current hunk and collapsed regions are reset
+    pub fn set_hunks(&self, view_id: &str, hunk_diff_indices: Vec<usize>) -> ViewNavigation {
+        self.update(view_id, |navigation| {
+            navigation.hunk_diff_indices = hunk_diff_indices;
+            navigation.current_hunk = None;
+            navigation.collapsed.clear();
+            Ok(())
+        })
+        .unwrap()
+    }
+
+    /// move current hunk
+    /// stays at both ends instead of wrapping around
+    pub fn navigate(&self, view_id: &str, direction: NavigationDirection) -> ViewNavigation {
+        self.update(view_id, |navigation| {
+            let last = match navigation.hunk_diff_indices.len().checked_sub(1) {
+                Some(x) => x,
+                None => return Ok(()),
+            };
+            navigation.current_hunk = Some(match (direction, navigation.current_hunk) {
+                (NavigationDirection::First, _) | (NavigationDirection::Next, None) => 0,
+                (NavigationDirection::Last, _) | (NavigationDirection::Previous, None) => last,
+                (NavigationDirection::Previous, Some(x)) => x.saturating_sub(1),
+                (NavigationDirection::Next, Some(x)) => (x + 1).min(last),
+            });
+            Ok(())
+        })
+        .unwrap()
+    }
+
+    /// jump to hunk at position
+    pub fn go_to_hunk(&self, view_id: &str, hunk: usize) -> Result<ViewNavigation, String> {
+        self.update(view_id, |navigation| {
+            if navigation.hunk_diff_indices.len() <= hunk {
+                return Err(format!("Hunk out of range: {}", hunk));
+            }
+            navigation.current_hunk = Some(hunk);
+            Ok(())
+        })
+    }
+
+    /// focus pane
+    pub fn focus_pane(&self, view_id: &str, side: DiffSide) -> ViewNavigation {
+        self.update(view_id, |navigation| {
+            navigation.focused_pane = side;
+            Ok(())
+        })
+        .unwrap()
+    }
+
+    /// collapse or expand region
+    pub fn set_collapsed(
+        &self,
+        view_id: &str,
+        diff_index: usize,
+        collapsed: bool,
+    ) -> ViewNavigation {
+        self.update(view_id, |navigation| {
+            navigation.collapsed.retain(|x| *x != diff_index);
+            if collapsed {
+                navigation.collapsed.push(diff_index);
+                navigation.collapsed.sort();
+            }
+            Ok(())
+        })
+        .unwrap()
+    }
+
+    /// forget navigation state of closed view
+    pub fn release(&self, view_id: &str) {
+        self.0.lock().unwrap().remove(view_id);
+    }
+
+    /// apply change to navigation state of view
+    fn update(
+        &self,
+        view_id: &str,
+        f: impl FnOnce(&mut ViewNavigation) -> Result<(), String>,
+    ) -> Result<ViewNavigation, String> {
+        let mut states = self.0.lock().unwrap();
+        let navigation = states.entry(view_id.to_owned()).or_default();
+        f(navigation)?;
+        Ok(navigation.clone())
+    }
+}
+
+/// notify view of navigation state
+pub fn emit_navigation(app_handle: &tauri::AppHandle, view_id: &str, navigation: &ViewNavigation) {
+    let event = NavigationEvent {
+        view_id: view_id.to_owned(),
+        navigation: navigation.clone(),
+    };
+    if let Err(err) = app_handle.emit(NAVIGATION_EVENT, event) {
+        tracing::warn!("Failed to emit navigation ({})", err);
+    }
+}
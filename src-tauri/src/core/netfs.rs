@@ -0,0 +1,166 @@
+use std::fs::{self, Metadata};
+use std::path::Path;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use super::file;
+use super::os_path::{os_path, os_str_string};
+use super::types::{FileAttr, ListDirResponse, NumberFormat, SortKey, SortOrder};
+
+/// timeout of each filesystem operation on network path
+const NETWORK_OPERATION_TIMEOUT: Duration = Duration::from_secs(10);
+/// attempts of filesystem operation on network path
+const NETWORK_RETRY_ATTEMPTS: u32 = 3;
+/// wait before second attempt. doubled on each retry
+const NETWORK_RETRY_BACKOFF: Duration = Duration::from_millis(500);
+
+/// check if path is on network share such as unc path `\\server\share`
+/// or mount point of cifs / nfs / sshfs on linux
+pub fn is_network_path(path: &str) -> bool {
+    if path.starts_with("\\\\") || path.starts_with("//") {
+        // `\\?\C:\` is local path in extended-length form
+        return !path.starts_with("\\\\?\\") || path.starts_with("\\\\?\\UNC\\");
+    }
+    network_mount_points()
+        .iter()
+        .any(|x| Path::new(path).starts_with(x))
+}
+
+/// list directory with timeout and retries on network path
+/// names only are listed as partial result when metadata is not fetched in time
+pub fn list_dir(
+    current_dir: &str,
+    sort_key: SortKey,
+    sort_order: SortOrder,
+    number_format: &NumberFormat,
+) -> Result<ListDirResponse, String> {
+    if !is_network_path(current_dir) {
+        return file::list_dir(current_dir, sort_key, sort_order, number_format);
+    }
+
+    let listed = {
+        let current_dir = current_dir.to_owned();
+        let number_format = number_format.clone();
+        with_retry(move || {
+            let current_dir = current_dir.clone();
+            let number_format = number_format.clone();
+            with_timeout(move || file::list_dir(&current_dir, sort_key, sort_order, &number_format))
+        })
+    };
+    match listed {
+        Ok(x) => Ok(x),
+        Err(err) => {
+            tracing::warn!("Failed to list {} with metadata ({})", current_dir, err);
+            names_only_list_dir(current_dir)
+        }
+    }
+}
+
+/// get metadata with timeout and retries on network path
+pub fn metadata(filepath: &str) -> Result<Metadata, String> {
+    let read = |filepath: String| {
+        fs::metadata(&filepath)
+            .map_err(|err| format!("Failed to get metadata of {} ({})", filepath, err))
+    };
+    if !is_network_path(filepath) {
+        return read(filepath.to_owned());
+    }
+    let filepath = filepath.to_owned();
+    with_retry(move || {
+        let filepath = filepath.clone();
+        with_timeout(move || read(filepath))
+    })
+}
+
+/// list only names without metadata. entries are all treated as files
+fn names_only_list_dir(current_dir: &str) -> Result<ListDirResponse, String> {
+    let dirpath = current_dir.to_owned();
+    let mut names = with_timeout(move || {
+        fs::read_dir(os_path(&dirpath))
+            .map_err(|err| format!("Invalid path: {} ({})", dirpath, err))
+            .map(|entries| {
+                entries
+                    .filter_map(|x| x.ok())
+                    .map(|x| os_str_string(&x.file_name()))
+                    .collect::<Vec<_>>()
+            })
+    })?;
+    names.sort();
+
+    Ok(ListDirResponse {
+        current_dir: current_dir.to_owned(),
+        dirs: vec![],
+        files: names
+            .into_iter()
+            .map(|name| FileAttr {
+                name,
+                bytes_size: String::new(),
+                human_readable_size: String::new(),
+                last_modified: String::new(),
+                binary_comparison_only: false,
+                mime_type: None,
+            })
+            .collect(),
+        partial: true,
+    })
+}
+
+/// run operation on another thread and give up waiting on timeout
+/// the thread is left behind because blocked filesystem call cannot be cancelled
+fn with_timeout<T: Send + 'static>(
+    f: impl FnOnce() -> Result<T, String> + Send + 'static,
+) -> Result<T, String> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let _ = sender.send(f());
+    });
+    receiver
+        .recv_timeout(NETWORK_OPERATION_TIMEOUT)
+        .map_err(|_| {
+            format!(
+                "Timed out after {} secs",
+                NETWORK_OPERATION_TIMEOUT.as_secs()
+            )
+        })?
+}
+
+/// retry operation with exponential backoff
+fn with_retry<T>(f: impl Fn() -> Result<T, String>) -> Result<T, String> {
+    let mut backoff = NETWORK_RETRY_BACKOFF;
+    let mut attempt = 1;
+    loop {
+        match f() {
+            Ok(x) => return Ok(x),
+            Err(err) if NETWORK_RETRY_ATTEMPTS <= attempt => return Err(err),
+            Err(_) => {
+                thread::sleep(backoff);
+                backoff *= 2;
+                attempt += 1;
+            }
+        }
+    }
+}
+
+/// mount points of network filesystems
+fn network_mount_points() -> Vec<String> {
+    const NETWORK_FS_TYPES: &[&str] = &["cifs", "smb3", "smbfs", "nfs", "nfs4", "fuse.sshfs"];
+    if !cfg!(target_os = "linux") {
+        return vec![];
+    }
+    // reading /proc never blocks on network
+    let mounts = fs::read_to_string("/proc/mounts").unwrap_or_default();
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            match (fields.get(1), fields.get(2)) {
+                (Some(mount_point), Some(fs_type)) if NETWORK_FS_TYPES.contains(fs_type) => {
+                    // spaces are escaped as octal in mounts
+                    Some(mount_point.replace("\\040", " "))
+                }
+                _ => None,
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,138 @@
+use std::ffi::{OsStr, OsString};
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+
+/// first char of private use area where bytes invalid in utf-8 are mapped on unix
+/// such as byte `0xe9` in latin-1 file name to `U+10FFE9`
+#[cfg(not(target_os = "windows"))]
+const INVALID_BYTE_CHAR_BASE: u32 = 0x10_ff00;
+/// chars invalid bytes `0x80` - `0xff` are mapped to
+/// the same chars in path itself are escaped as mapped bytes of their utf-8
+#[cfg(not(target_os = "windows"))]
+const MAPPED_CHARS: RangeInclusive<u32> = 0x10_ff80..=0x10_ffff;
+/// first char of private use area where unpaired surrogates are mapped on windows
+/// such as `0xd800` to `U+10E000`
+#[cfg(target_os = "windows")]
+const UNPAIRED_SURROGATE_CHAR_BASE: u32 = 0x10_e000;
+/// chars surrogates `0xd800` - `0xdfff` are mapped to
+/// the same chars in path itself are escaped as mapped surrogate pairs of their utf-16
+#[cfg(target_os = "windows")]
+const MAPPED_CHARS: RangeInclusive<u32> = 0x10_e000..=0x10_e7ff;
+
+/// path as string passed over ipc without loss
+/// chars which cannot be represented in utf-8 are mapped into private use area
+/// so that string can be joined and split as usual and decoded back by `os_path`
+pub fn path_string(path: &Path) -> String {
+    os_str_string(path.as_os_str())
+}
+
+/// os string as string passed over ipc without loss. see `path_string`
+pub fn os_str_string(s: &OsStr) -> String {
+    if let Some(x) = s.to_str().filter(|x| !x.chars().any(is_mapped)) {
+        return x.to_owned();
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        use std::os::unix::ffi::OsStrExt;
+
+        let mut ret = String::new();
+        let mut bytes = s.as_bytes();
+        while !bytes.is_empty() {
+            match std::str::from_utf8(bytes) {
+                Ok(x) => {
+                    push_escaped(&mut ret, x);
+                    break;
+                }
+                Err(err) => {
+                    let (valid, rest) = bytes.split_at(err.valid_up_to());
+                    push_escaped(&mut ret, std::str::from_utf8(valid).unwrap_or_default());
+                    let invalid_len = err.error_len().unwrap_or(rest.len());
+                    for x in &rest[..invalid_len] {
+                        ret.extend(char::from_u32(INVALID_BYTE_CHAR_BASE + *x as u32));
+                    }
+                    bytes = &rest[invalid_len..];
+                }
+            }
+        }
+        ret
+    }
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::ffi::OsStrExt;
+
+        let mapped = |x: u16| {
+            char::from_u32(UNPAIRED_SURROGATE_CHAR_BASE + (x - 0xd800) as u32)
+                .unwrap_or(char::REPLACEMENT_CHARACTER)
+        };
+        let mut ret = String::new();
+        for x in char::decode_utf16(s.encode_wide()) {
+            match x {
+                Ok(c) if is_mapped(c) => {
+                    ret.extend(c.encode_utf16(&mut [0; 2]).iter().map(|x| mapped(*x)))
+                }
+                Ok(c) => ret.push(c),
+                Err(err) => ret.push(mapped(err.unpaired_surrogate())),
+            }
+        }
+        ret
+    }
+}
+
+/// push str escaping chars which would be taken as mapped from invalid bytes
+#[cfg(not(target_os = "windows"))]
+fn push_escaped(ret: &mut String, s: &str) {
+    for c in s.chars() {
+        if !is_mapped(c) {
+            ret.push(c);
+            continue;
+        }
+        for x in c.encode_utf8(&mut [0; 4]).as_bytes() {
+            ret.extend(char::from_u32(INVALID_BYTE_CHAR_BASE + *x as u32));
+        }
+    }
+}
+
+/// whether char is in private use area chars not representable in utf-8 are mapped to
+fn is_mapped(c: char) -> bool {
+    MAPPED_CHARS.contains(&(c as u32))
+}
+
+/// path on os decoded from string made by `path_string`
+/// strings of ordinary paths are returned as they are
+pub fn os_path(s: &str) -> PathBuf {
+    #[cfg(not(target_os = "windows"))]
+    {
+        use std::os::unix::ffi::OsStringExt;
+
+        if !s.chars().any(is_mapped) {
+            return PathBuf::from(s);
+        }
+        let mut bytes = vec![];
+        for c in s.chars() {
+            if is_mapped(c) {
+                bytes.push((c as u32 - INVALID_BYTE_CHAR_BASE) as u8);
+            } else {
+                bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+            }
+        }
+        PathBuf::from(OsString::from_vec(bytes))
+    }
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::ffi::OsStringExt;
+
+        if !s.chars().any(is_mapped) {
+            return PathBuf::from(s);
+        }
+        let mut wide = vec![];
+        for c in s.chars() {
+            if is_mapped(c) {
+                wide.push((c as u32 - UNPAIRED_SURROGATE_CHAR_BASE + 0xd800) as u16);
+            } else {
+                wide.extend_from_slice(c.encode_utf16(&mut [0; 2]));
+            }
+        }
+        PathBuf::from(OsString::from_wide(&wide))
+    }
+}
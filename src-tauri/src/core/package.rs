@@ -0,0 +1,167 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use super::file::file_extension;
+use super::os_path::os_str_string;
+use super::types::TreeEntryDiff;
+use super::vcs::tree_blobs_diffs;
+
+/// compare file manifests of two packages
+/// each side is package archive (`.deb`, `.rpm` or `.msi`) or name of installed package
+pub fn package_diffs(old: &str, new: &str) -> Result<Vec<TreeEntryDiff>, String> {
+    let old_files = package_files(old)?;
+    let new_files = package_files(new)?;
+    Ok(tree_blobs_diffs(&old_files, &new_files, None))
+}
+
+/// list files in package with fingerprint such as size and digest, keyed by installed path
+pub fn package_files(package: &str) -> Result<BTreeMap<String, String>, String> {
+    if Path::new(package).is_file() {
+        return match file_extension(package).as_str() {
+            "deb" => deb_archive_files(package),
+            "rpm" => rpm_dump_files(&["-qlp", "--dump", package]),
+            "msi" => msi_archive_files(package),
+            _ => Err(format!("Not supported package archive: {}", package)),
+        };
+    }
+    match dpkg_installed_files(package) {
+        Ok(x) => Ok(x),
+        Err(dpkg_err) => rpm_dump_files(&["-ql", "--dump", package]).map_err(|rpm_err| {
+            format!(
+                "Package not found: {} ({} / {})",
+                package, dpkg_err, rpm_err
+            )
+        }),
+    }
+}
+
+/// run package tool and get its stdout
+fn command_stdout(program: &str, args: &[&str]) -> Result<String, String> {
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .map_err(|err| format!("Failed to run {} ({})", program, err))?;
+    if !output.status.success() {
+        return Err(format!(
+            "{} {} failed: {}",
+            program,
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// files of installed debian package with md5 digests recorded by dpkg
+fn dpkg_installed_files(package: &str) -> Result<BTreeMap<String, String>, String> {
+    let listed = command_stdout("dpkg-query", &["-L", package])?;
+
+    // md5sums file is suffixed with architecture on multi-arch packages
+    let info_dir = Path::new("/var/lib/dpkg/info");
+    let md5sums = fs::read_dir(info_dir)
+        .ok()
+        .into_iter()
+        .flatten()
+        .filter_map(|x| x.ok())
+        .find(|x| {
+            let name = os_str_string(&x.file_name());
+            name == format!("{}.md5sums", package)
+                || (name.starts_with(&format!("{}:", package)) && name.ends_with(".md5sums"))
+        })
+        .and_then(|x| fs::read_to_string(x.path()).ok())
+        .unwrap_or_default();
+    let digests = md5sums
+        .lines()
+        .filter_map(|line| line.split_once("  "))
+        .map(|(digest, path)| (format!("/{}", path), digest.to_owned()))
+        .collect::<BTreeMap<_, _>>();
+
+    let ret = listed
+        .lines()
+        .filter(|x| Path::new(x).is_file())
+        .map(|path| {
+            let fingerprint = match digests.get(path) {
+                Some(digest) => format!("md5 {}", digest),
+                None => fs::metadata(path)
+                    .map(|x| format!("{} bytes", x.len()))
+                    .unwrap_or_default(),
+            };
+            (path.to_owned(), fingerprint)
+        })
+        .collect();
+    Ok(ret)
+}
+
+/// files of debian package archive with sizes
+fn deb_archive_files(filepath: &str) -> Result<BTreeMap<String, String>, String> {
+    let listed = command_stdout("dpkg-deb", &["-c", filepath])?;
+
+    // such as `-rw-r--r-- root/root 1234 2024-01-02 03:04 ./usr/bin/x`
+    let ret = listed
+        .lines()
+        .filter(|x| !x.starts_with('d'))
+        .filter_map(|line| {
+            let size = line.split_whitespace().nth(2)?;
+            let path = line[line.find(" ./")? + 2..].trim();
+            let path = path.split(" -> ").next()?;
+            Some((path.to_owned(), format!("{} bytes", size)))
+        })
+        .collect();
+    Ok(ret)
+}
+
+/// files of rpm package installed or archived with sizes and digests
+fn rpm_dump_files(args: &[&str]) -> Result<BTreeMap<String, String>, String> {
+    let listed = command_stdout("rpm", args)?;
+
+    // path size mtime digest mode owner group isconfig isdoc rdev symlink
+    const DUMP_FIELDS_AFTER_PATH: usize = 10;
+    let ret = listed
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line
+                .rsplitn(DUMP_FIELDS_AFTER_PATH + 1, ' ')
+                .collect::<Vec<_>>();
+            if fields.len() != DUMP_FIELDS_AFTER_PATH + 1 {
+                return None;
+            }
+            fields.reverse();
+            let mode = u32::from_str_radix(fields[4], 8).ok()?;
+            // directory
+            if mode & 0o170000 == 0o040000 {
+                return None;
+            }
+            Some((
+                fields[0].to_owned(),
+                format!("{} bytes {}", fields[1], fields[3]),
+            ))
+        })
+        .collect();
+    Ok(ret)
+}
+
+/// files in msi file table with sizes and versions via msitools
+fn msi_archive_files(filepath: &str) -> Result<BTreeMap<String, String>, String> {
+    let exported = command_stdout("msiinfo", &["export", filepath, "File"])?;
+
+    // header lines are column names, column types and table name
+    let ret = exported
+        .lines()
+        .skip(3)
+        .filter_map(|line| {
+            let columns = line.split('\t').collect::<Vec<_>>();
+            let component = columns.get(1)?;
+            // short and long names are separated by `|`
+            let name = columns.get(2)?.rsplit('|').next()?;
+            let size = columns.get(3)?;
+            let version = columns.get(4).unwrap_or(&"");
+            Some((
+                format!("{}/{}", component, name),
+                format!("{} bytes {}", size, version).trim().to_owned(),
+            ))
+        })
+        .collect();
+    Ok(ret)
+}
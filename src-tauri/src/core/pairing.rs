@@ -0,0 +1,133 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use super::diff::{collect_relative_filepaths, filepaths_digest_diff};
+use super::types::{FilePairing, FilePairingResult, PairingReason};
+
+/// words in file name regarded as revision markers such as `report_final.xlsx`
+const REVISION_WORDS: [&str; 7] = ["old", "new", "copy", "final", "latest", "backup", "bak"];
+
+/// propose pairs of files in directories to be compared
+/// files are paired by the same relative path first, and then by the same name
+/// after version numbers, timestamps and revision words are removed
+/// (`report_v1.xlsx` and `report_v2.xlsx`, `app-20240101.log` and `app-20240102.log`)
+pub fn propose_file_pairings(old_dir: &str, new_dir: &str) -> Result<Vec<FilePairing>, String> {
+    let mut old_files = vec![];
+    collect_relative_filepaths(Path::new(old_dir), "", &mut old_files)?;
+    let mut new_files = vec![];
+    collect_relative_filepaths(Path::new(new_dir), "", &mut new_files)?;
+    old_files.sort();
+    new_files.sort();
+
+    let mut ret = vec![];
+    for path in old_files
+        .iter()
+        .filter(|x| new_files.binary_search(x).is_ok())
+    {
+        ret.push(FilePairing {
+            old_path: path.to_owned(),
+            new_path: path.to_owned(),
+            reason: PairingReason::SamePath,
+            confidence: 1.0,
+        });
+    }
+
+    let mut groups: BTreeMap<String, (Vec<&String>, Vec<&String>)> = BTreeMap::new();
+    let old_rest = old_files
+        .iter()
+        .filter(|x| new_files.binary_search(x).is_err());
+    let new_rest = new_files
+        .iter()
+        .filter(|x| old_files.binary_search(x).is_err());
+    for path in old_rest {
+        groups.entry(pairing_key(path)).or_default().0.push(path);
+    }
+    for path in new_rest {
+        groups.entry(pairing_key(path)).or_default().1.push(path);
+    }
+    for (olds, news) in groups.values() {
+        // the latest ones in name order are paired when ambiguous
+        // because versions and timestamps usually sort ascending
+        let (Some(old_path), Some(new_path)) = (olds.last(), news.last()) else {
+            continue;
+        };
+        let ambiguous = 1 < olds.len() || 1 < news.len();
+        ret.push(FilePairing {
+            old_path: (*old_path).to_owned(),
+            new_path: (*new_path).to_owned(),
+            reason: PairingReason::NamePattern,
+            confidence: if ambiguous { 0.5 } else { 0.8 },
+        });
+    }
+
+    ret.sort_by(|a, b| a.old_path.cmp(&b.old_path));
+    Ok(ret)
+}
+
+/// compare confirmed pairs of files by digest
+pub fn compare_file_pairings(
+    old_dir: &str,
+    new_dir: &str,
+    pairings: &[FilePairing],
+) -> Vec<FilePairingResult> {
+    pairings
+        .iter()
+        .map(|x| {
+            let old_filepath = Path::new(old_dir).join(&x.old_path);
+            let new_filepath = Path::new(new_dir).join(&x.new_path);
+            let (equal, error) = if old_filepath.is_file() && new_filepath.is_file() {
+                match filepaths_digest_diff(&old_filepath, &new_filepath) {
+                    Ok(equal) => (Some(equal), None),
+                    Err(err) => (None, Some(err)),
+                }
+            } else {
+                (
+                    None,
+                    Some(format!("File not found: {} or {}", x.old_path, x.new_path)),
+                )
+            };
+            FilePairingResult {
+                old_path: x.old_path.to_owned(),
+                new_path: x.new_path.to_owned(),
+                equal,
+                error,
+            }
+        })
+        .collect()
+}
+
+/// relative path without version numbers, timestamps and revision words in file name
+fn pairing_key(path: &str) -> String {
+    let (parent, filename) = match path.rsplit_once('/') {
+        Some((parent, filename)) => (parent, filename),
+        None => ("", path),
+    };
+    let (stem, extension) = match filename.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => (stem, extension),
+        _ => (filename, ""),
+    };
+    let words = stem
+        .to_lowercase()
+        .split(['-', '_', ' ', '.', '(', ')', '[', ']'])
+        .filter(|x| !x.is_empty() && !is_version_word(x))
+        .map(|x| x.to_owned())
+        .collect::<Vec<_>>();
+    format!(
+        "{}/{}.{}",
+        parent,
+        words.join("_"),
+        extension.to_lowercase()
+    )
+}
+
+/// check if word is version number, timestamp or revision word
+/// such as `v2`, `rev3`, `20240101`, `1` or `final`
+fn is_version_word(word: &str) -> bool {
+    let digits = word
+        .strip_prefix("rev")
+        .or_else(|| word.strip_prefix('v'))
+        .or_else(|| word.strip_prefix('r'))
+        .unwrap_or(word);
+    (!digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()))
+        || REVISION_WORDS.contains(&word)
+}
@@ -0,0 +1,93 @@
+use super::types::{ChangeLevel, Palette, PaletteColor, PaletteKind};
+
+/// change levels colored on palette
+const CHANGE_LEVELS: [ChangeLevel; 5] = [
+    ChangeLevel::Added,
+    ChangeLevel::Removed,
+    ChangeLevel::Modified,
+    ChangeLevel::Moved,
+    ChangeLevel::Conflict,
+];
+
+/// colors of change levels on palette
+/// color-blind palettes are based on okabe-ito colors distinguishable on each type
+pub fn palette(kind: PaletteKind) -> Palette {
+    let colors = CHANGE_LEVELS
+        .iter()
+        .map(|level| {
+            let (foreground, background) = level_colors(kind, *level);
+            PaletteColor {
+                change_level: *level,
+                foreground: foreground.to_owned(),
+                background: background.to_owned(),
+            }
+        })
+        .collect::<Vec<_>>();
+    let css = palette_css(&colors);
+    Palette { kind, colors, css }
+}
+
+/// foreground and background colors of change level
+fn level_colors(kind: PaletteKind, level: ChangeLevel) -> (&'static str, &'static str) {
+    match (kind, level) {
+        (PaletteKind::Default, ChangeLevel::Added) => ("#1a7f37", "#e6ffec"),
+        (PaletteKind::Default, ChangeLevel::Removed) => ("#cf222e", "#ffebe9"),
+        (PaletteKind::Default, ChangeLevel::Modified) => ("#9a6700", "#fff8c5"),
+        (PaletteKind::Default, ChangeLevel::Moved) => ("#8250df", "#fbefff"),
+        (PaletteKind::Default, ChangeLevel::Conflict) => ("#bc4c00", "#fff1e5"),
+
+        // blue and orange instead of green and red
+        (PaletteKind::Deuteranopia | PaletteKind::Protanopia, ChangeLevel::Added) => {
+            ("#0072b2", "#ddeeff")
+        }
+        (PaletteKind::Deuteranopia | PaletteKind::Protanopia, ChangeLevel::Removed) => {
+            ("#d55e00", "#ffe8d9")
+        }
+        (PaletteKind::Deuteranopia | PaletteKind::Protanopia, ChangeLevel::Modified) => {
+            ("#806000", "#fdf4c8")
+        }
+        (PaletteKind::Deuteranopia | PaletteKind::Protanopia, ChangeLevel::Moved) => {
+            ("#cc79a7", "#f9e8f1")
+        }
+        (PaletteKind::Deuteranopia | PaletteKind::Protanopia, ChangeLevel::Conflict) => {
+            ("#000000", "#f0e442")
+        }
+
+        // bluish green and vermillion avoiding blue-yellow contrast
+        (PaletteKind::Tritanopia, ChangeLevel::Added) => ("#009e73", "#dcf5ec"),
+        (PaletteKind::Tritanopia, ChangeLevel::Removed) => ("#d55e00", "#ffe8d9"),
+        (PaletteKind::Tritanopia, ChangeLevel::Modified) => ("#cc79a7", "#f9e8f1"),
+        (PaletteKind::Tritanopia, ChangeLevel::Moved) => ("#555555", "#eeeeee"),
+        (PaletteKind::Tritanopia, ChangeLevel::Conflict) => ("#ffffff", "#a00000"),
+
+        (PaletteKind::HighContrast, ChangeLevel::Added) => ("#ffffff", "#005a9e"),
+        (PaletteKind::HighContrast, ChangeLevel::Removed) => ("#ffffff", "#b00020"),
+        (PaletteKind::HighContrast, ChangeLevel::Modified) => ("#000000", "#ffd700"),
+        (PaletteKind::HighContrast, ChangeLevel::Moved) => ("#ffffff", "#6a1b9a"),
+        (PaletteKind::HighContrast, ChangeLevel::Conflict) => ("#000000", "#ff8c00"),
+
+        (_, ChangeLevel::Unchanged) => ("inherit", "transparent"),
+    }
+}
+
+/// css custom properties such as `--diff-added-fg` and `--diff-added-bg`
+fn palette_css(colors: &[PaletteColor]) -> String {
+    let properties = colors
+        .iter()
+        .map(|x| {
+            let name = match x.change_level {
+                ChangeLevel::Unchanged => "unchanged",
+                ChangeLevel::Added => "added",
+                ChangeLevel::Removed => "removed",
+                ChangeLevel::Modified => "modified",
+                ChangeLevel::Moved => "moved",
+                ChangeLevel::Conflict => "conflict",
+            };
+            format!(
+                "  --diff-{}-fg: {};\n  --diff-{}-bg: {};\n",
+                name, x.foreground, name, x.background
+            )
+        })
+        .collect::<String>();
+    format!(":root {{\n{}}}\n", properties)
+}
@@ -0,0 +1,135 @@
+use std::fs;
+
+use similar::DiffTag;
+
+use super::diff::lines_diff_ops;
+use super::os_path::os_path;
+use super::str::split_lines_with_endings;
+use super::types::LinesDiff;
+
+/// equal lines around changes in patch
+const PATCH_CONTEXT_LINES: usize = 3;
+
+/// line in patch
+enum PatchLine<'a> {
+    Context(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// unified patch containing only hunks included
+/// excluded hunks are kept as they are on old side so that patch applies to old file
+/// lines in diffs may be segments of long lines, so texts are joined and compared again
+/// on whole lines to be patched
+pub fn selected_hunks_patch(
+    old_label: &str,
+    new_label: &str,
+    diffs: &[LinesDiff],
+    included: impl Fn(usize) -> bool,
+) -> String {
+    let mut old_text = String::new();
+    let mut new_text = String::new();
+    for diff in diffs {
+        old_text.extend(diff.old_lines.iter().map(|x| x.as_str()));
+        if diff.diff_kind == DiffTag::Equal || !included(diff.diff_index) {
+            new_text.extend(diff.old_lines.iter().map(|x| x.as_str()));
+        } else {
+            new_text.extend(diff.new_lines.iter().map(|x| x.as_str()));
+        }
+    }
+    let old_lines = split_lines_with_endings(&old_text);
+    let new_lines = split_lines_with_endings(&new_text);
+
+    let mut lines = vec![];
+    for op in lines_diff_ops(&old_lines, &new_lines) {
+        let old_range = &old_lines[op.old_range()];
+        let new_range = &new_lines[op.new_range()];
+        if op.tag() == DiffTag::Equal {
+            lines.extend(old_range.iter().map(|x| PatchLine::Context(x)));
+            continue;
+        }
+        lines.extend(old_range.iter().map(|x| PatchLine::Removed(x)));
+        lines.extend(new_range.iter().map(|x| PatchLine::Added(x)));
+    }
+
+    let changes = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, x)| !matches!(x, PatchLine::Context(_)))
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+    if changes.is_empty() {
+        return String::new();
+    }
+
+    // changes close to each other are joined into one hunk
+    let mut ranges: Vec<(usize, usize)> = vec![];
+    for i in changes {
+        let start = i.saturating_sub(PATCH_CONTEXT_LINES);
+        let end = (i + PATCH_CONTEXT_LINES + 1).min(lines.len());
+        match ranges.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => ranges.push((start, end)),
+        }
+    }
+
+    let mut ret = format!("--- {}\n+++ {}\n", old_label, new_label);
+    // 1-based line numbers at start of next range
+    let (mut old_line, mut new_line) = (1, 1);
+    let mut position = 0;
+    for (start, end) in ranges {
+        for x in &lines[position..start] {
+            match x {
+                PatchLine::Context(_) => {
+                    old_line += 1;
+                    new_line += 1;
+                }
+                PatchLine::Removed(_) => old_line += 1,
+                PatchLine::Added(_) => new_line += 1,
+            }
+        }
+        let old_count = lines[start..end]
+            .iter()
+            .filter(|x| !matches!(x, PatchLine::Added(_)))
+            .count();
+        let new_count = lines[start..end]
+            .iter()
+            .filter(|x| !matches!(x, PatchLine::Removed(_)))
+            .count();
+        // empty range is denoted by line before it
+        let header_line = |line: usize, count: usize| {
+            if count == 0 {
+                format!("{},0", line - 1)
+            } else {
+                format!("{},{}", line, count)
+            }
+        };
+        ret.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            header_line(old_line, old_count),
+            header_line(new_line, new_count)
+        ));
+        for x in &lines[start..end] {
+            let (prefix, text) = match x {
+                PatchLine::Context(x) => (' ', x),
+                PatchLine::Removed(x) => ('-', x),
+                PatchLine::Added(x) => ('+', x),
+            };
+            ret.push(prefix);
+            ret.push_str(text);
+            if !text.ends_with('\n') {
+                ret.push_str("\n\\ No newline at end of file\n");
+            }
+        }
+        old_line += old_count;
+        new_line += new_count;
+        position = end;
+    }
+    ret
+}
+
+/// write patch into file
+pub fn write_patch(filepath: &str, patch: &str) -> Result<(), String> {
+    fs::write(os_path(filepath), patch)
+        .map_err(|err| format!("Failed to write {} ({})", filepath, err))
+}
@@ -0,0 +1,255 @@
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use super::agent::is_agent_url;
+use super::ftp::is_ftp_url;
+use super::netfs::is_network_path;
+use super::os_path::{os_path, path_string};
+use super::scratch::SCRATCH_URL_PREFIX;
+use super::settings;
+use super::types::{PathCheck, PathPolicy, PathWarning};
+use super::web::is_web_url;
+
+/// system directories warned before reading from them
+#[cfg(not(target_os = "windows"))]
+const SYSTEM_DIRS: &[&str] = &[
+    "/bin", "/boot", "/dev", "/etc", "/lib", "/lib64", "/proc", "/sbin", "/sys", "/usr", "/var",
+    "/Library", "/System", "/private",
+];
+#[cfg(target_os = "windows")]
+const SYSTEM_DIRS: &[&str] = &[
+    r"C:\Windows",
+    r"C:\Program Files",
+    r"C:\Program Files (x86)",
+    r"C:\ProgramData",
+];
+/// command args in ipc payload which carry texts rather than paths, by command and arg names.
+/// empty command name matches every command
+const TEXT_ARGS: &[(&str, &str)] = &[
+    ("", "charset"),
+    ("", "content"),
+    ("", "gitRef"),
+    ("", "key"),
+    ("", "keyColumns"),
+    ("", "label"),
+    ("", "locale"),
+    ("", "messageType"),
+    ("", "newCharset"),
+    ("", "newRef"),
+    ("", "oldCharset"),
+    ("", "oldRef"),
+    ("", "secretKeyPatterns"),
+    ("", "viewId"),
+    ("check_path_policy", "path"),
+    ("diff_single_lines", "new"),
+    ("diff_single_lines", "old"),
+    ("launch_external_tool", "name"),
+    ("similarity_texts", "new"),
+    ("similarity_texts", "old"),
+];
+/// command args in ipc payload which carry paths relative to folder or repository in other args
+const RELATIVE_PATH_ARGS: &[(&str, &str)] = &[
+    ("", "dirname"),
+    ("", "filename"),
+    ("copy_file_metadata", "paths"),
+    ("diff_refs_file", "path"),
+    ("diff_refs_tree", "dir"),
+    ("diff_working_tree", "dir"),
+    ("diff_working_tree_file", "path"),
+    ("permission_diff", "name"),
+];
+
+/// check path against policy in settings before reading from it
+/// path is resolved with symlinks and `..` so that it cannot escape roots by them
+/// paths under blocked roots are refused, and so are ones outside allowed roots when any is set.
+/// warnings on system directories, network mounts and mount roots need confirmation only
+pub fn check_path(app_handle: &tauri::AppHandle, path: &str) -> PathCheck {
+    check_path_with(&settings::path_policy(app_handle), path)
+}
+
+/// check path against policy given
+pub fn check_path_with(policy: &PathPolicy, path: &str) -> PathCheck {
+    let resolved = resolved_path(path);
+    let is_under = |roots: &[String]| {
+        roots
+            .iter()
+            .filter(|x| !x.is_empty())
+            .any(|x| resolved.starts_with(resolved_path(x)))
+    };
+
+    let refusal = if is_under(&policy.blocked_roots) {
+        Some(format!("Path is under blocked root: {}", path))
+    } else if !policy.allowed_roots.is_empty() && !is_under(&policy.allowed_roots) {
+        Some(format!("Path is outside allowed roots: {}", path))
+    } else {
+        None
+    };
+
+    let mut warnings = vec![];
+    if !policy.ignore_system_dir_warnings
+        && SYSTEM_DIRS
+            .iter()
+            .any(|x| resolved.starts_with(resolved_path(x)))
+    {
+        warnings.push(PathWarning::SystemDirectory);
+    }
+    if is_network_path(path) {
+        warnings.push(PathWarning::NetworkMount);
+    }
+    if is_mount_root(&resolved) {
+        warnings.push(PathWarning::MountRoot);
+    }
+
+    PathCheck {
+        path: path_string(&resolved),
+        allowed: refusal.is_none(),
+        refusal,
+        warnings,
+    }
+}
+
+/// refuse path not permitted by policy such as one passed by another process
+pub fn ensure_permitted(app_handle: &tauri::AppHandle, path: &str) -> Result<(), String> {
+    ensure_permitted_with(&settings::path_policy(app_handle), path)
+}
+
+/// refuse path not permitted by policy given
+/// urls of remote files and scratch buffers are out of policy
+pub fn ensure_permitted_with(policy: &PathPolicy, path: &str) -> Result<(), String> {
+    if path.is_empty() || is_out_of_policy_url(path) {
+        return Ok(());
+    }
+    match check_path_with(policy, path).refusal {
+        Some(refusal) => {
+            tracing::warn!("{}", refusal);
+            Err(refusal)
+        }
+        None => Ok(()),
+    }
+}
+
+/// refuse command whose args in ipc payload carry path not permitted by policy
+/// called on every command before it runs so that no reader or writer is left unguarded.
+/// every string arg is taken as path unless it is known as text,
+/// and relative ones must stay inside the folder they are joined to
+pub fn ensure_payload_permitted(
+    app_handle: &tauri::AppHandle,
+    command: &str,
+    payload: &serde_json::Value,
+) -> Result<(), String> {
+    let Some(args) = payload.as_object() else {
+        return Ok(());
+    };
+    let policy = settings::path_policy(app_handle);
+    if policy.allowed_roots.is_empty() && policy.blocked_roots.is_empty() {
+        return Ok(());
+    }
+    let is_listed = |list: &[(&str, &str)], name: &str| {
+        list.iter()
+            .any(|(x, y)| (x.is_empty() || *x == command) && *y == name)
+    };
+    for (name, value) in args {
+        if is_listed(TEXT_ARGS, name) {
+            continue;
+        }
+        let paths = match value {
+            serde_json::Value::String(x) => vec![x.as_str()],
+            serde_json::Value::Array(x) => x.iter().filter_map(|x| x.as_str()).collect(),
+            _ => continue,
+        };
+        for path in paths {
+            if is_listed(RELATIVE_PATH_ARGS, name) {
+                ensure_relative(path)?;
+            } else {
+                ensure_permitted_with(&policy, path)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// refuse path which escapes folder it is joined to by being absolute or by `..`
+pub fn ensure_relative(path: &str) -> Result<(), String> {
+    if os_path(path)
+        .components()
+        .all(|x| matches!(x, Component::Normal(_) | Component::CurDir))
+    {
+        return Ok(());
+    }
+    let refusal = format!("Path escapes its folder: {}", path);
+    tracing::warn!("{}", refusal);
+    Err(refusal)
+}
+
+/// check if path is url of remote file or scratch buffer which has no local path to check
+fn is_out_of_policy_url(path: &str) -> bool {
+    is_web_url(path)
+        || is_ftp_url(path)
+        || is_agent_url(path)
+        || path.starts_with(SCRATCH_URL_PREFIX)
+}
+
+/// canonical path with symlinks and `..` resolved.
+/// when path does not exist yet such as save target, its nearest existing ancestor is resolved
+/// and the rest is appended so that symlinked parents cannot escape roots either
+fn resolved_path(path: &str) -> PathBuf {
+    let path = os_path(path);
+    let path = match std::env::current_dir() {
+        Ok(x) if path.is_relative() => x.join(path),
+        _ => path,
+    };
+    let mut existing = path.as_path();
+    let mut rest = vec![];
+    loop {
+        if let Ok(mut ret) = fs::canonicalize(existing) {
+            for component in rest.iter().rev() {
+                match component {
+                    Component::CurDir => {}
+                    Component::ParentDir => {
+                        ret.pop();
+                    }
+                    x => ret.push(x),
+                }
+            }
+            return ret;
+        }
+        let mut components = existing.components();
+        match components.next_back() {
+            Some(x @ (Component::Normal(_) | Component::CurDir | Component::ParentDir)) => {
+                rest.push(x);
+                existing = components.as_path();
+            }
+            _ => break,
+        }
+    }
+    let mut ret = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                ret.pop();
+            }
+            x => ret.push(x),
+        }
+    }
+    ret
+}
+
+/// check if path is filesystem root such as `/` or `C:\`, or mount point
+/// whose whole tree may be too large to read
+fn is_mount_root(path: &Path) -> bool {
+    if path.parent().is_none() {
+        return true;
+    }
+    if !cfg!(target_os = "linux") {
+        return false;
+    }
+    // reading /proc never blocks on network
+    let mounts = fs::read_to_string("/proc/mounts").unwrap_or_default();
+    mounts.lines().any(|line| {
+        line.split_whitespace()
+            .nth(1)
+            // spaces are escaped as octal in mounts
+            .is_some_and(|x| Path::new(&x.replace("\\040", " ")) == path)
+    })
+}
@@ -0,0 +1,82 @@
+use std::fs;
+use std::path::Path;
+
+use super::file::bytes_content;
+use super::types::{DiffSide, PathRemapping};
+
+/// max file size in bytes compared again with paths remapped on folder comparison
+const MAX_REMAPPED_FILE_BYTES: u64 = 16 * 1024 * 1024;
+
+/// check if remapping changes anything
+pub fn remapping_applies(remapping: &PathRemapping) -> bool {
+    remapping.ignore_drive_letters || remapping.rules.iter().any(|x| x.old != x.new)
+}
+
+/// normalize paths embedded in text so that equivalent roots are equal
+/// roots of rules on old side are rewritten into new side ones, with backslash separators too,
+/// and drive letters are dropped on both sides when ignored
+pub fn remapped_content(content: &str, remapping: &PathRemapping, side: DiffSide) -> String {
+    let mut ret = content.to_owned();
+    if side == DiffSide::Old {
+        // longer roots first so that nested roots are not rewritten by their parents
+        let mut rules = remapping
+            .rules
+            .iter()
+            .filter(|x| !x.old.is_empty() && x.old != x.new)
+            .collect::<Vec<_>>();
+        rules.sort_by_key(|x| std::cmp::Reverse(x.old.len()));
+        for rule in rules {
+            ret = ret.replace(&rule.old, &rule.new);
+            let (old, new) = (rule.old.replace('/', "\\"), rule.new.replace('/', "\\"));
+            if old != rule.old {
+                ret = ret.replace(&old, &new);
+            }
+        }
+    }
+    if remapping.ignore_drive_letters {
+        ret = without_drive_letters(&ret);
+    }
+    ret
+}
+
+/// check if text files are equal with paths in them remapped
+/// false when either is not text or too large
+pub fn remapped_equal(old_filepath: &Path, new_filepath: &Path, remapping: &PathRemapping) -> bool {
+    let read = |filepath: &Path| {
+        let size = fs::metadata(filepath).ok()?.len();
+        if MAX_REMAPPED_FILE_BYTES < size {
+            return None;
+        }
+        let bytes = fs::read(filepath).ok()?;
+        if bytes.contains(&0) {
+            return None;
+        }
+        Some(bytes_content(&bytes).content)
+    };
+    let (Some(old), Some(new)) = (read(old_filepath), read(new_filepath)) else {
+        return false;
+    };
+    remapped_content(&old, remapping, DiffSide::Old)
+        == remapped_content(&new, remapping, DiffSide::New)
+}
+
+/// drop drive letters such as `C:` followed by path separator
+/// letter preceded by word char is not drive such as in url scheme
+fn without_drive_letters(content: &str) -> String {
+    let chars = content.chars().collect::<Vec<_>>();
+    let mut ret = String::with_capacity(content.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let is_drive = chars[i].is_ascii_alphabetic()
+            && chars.get(i + 1) == Some(&':')
+            && matches!(chars.get(i + 2), Some('\\' | '/'))
+            && (i == 0 || !(chars[i - 1].is_alphanumeric() || chars[i - 1] == '_'));
+        if is_drive {
+            i += 2;
+            continue;
+        }
+        ret.push(chars[i]);
+        i += 1;
+    }
+    ret
+}
@@ -0,0 +1,140 @@
+use std::fs::{read_dir, symlink_metadata};
+use std::path::Path;
+
+use super::diff::file_digest_diff;
+use super::os_path::{os_path, os_str_string, path_string};
+use super::types::{PermissionAttr, PermissionDiff};
+
+/// get permission, ownership and acl of path
+pub fn permission_attr(path: &Path) -> Result<PermissionAttr, String> {
+    let metadata = symlink_metadata(path)
+        .map_err(|err| format!("Failed to get metadata of {} ({})", path.display(), err))?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+
+        Ok(PermissionAttr {
+            mode: format!("{:04o}", metadata.mode() & 0o7777),
+            readonly: metadata.permissions().readonly(),
+            uid: Some(metadata.uid()),
+            gid: Some(metadata.gid()),
+            acl: acl(path),
+        })
+    }
+    #[cfg(not(unix))]
+    {
+        Ok(PermissionAttr {
+            mode: String::new(),
+            readonly: metadata.permissions().readonly(),
+            uid: None,
+            gid: None,
+            acl: acl(path),
+        })
+    }
+}
+
+/// compare permission of entry in old dir and new dir
+pub fn permission_diff(name: &str, old_dir: &str, new_dir: &str) -> Result<PermissionDiff, String> {
+    let old_path = os_path(old_dir).join(os_path(name));
+    let new_path = os_path(new_dir).join(os_path(name));
+
+    let old = permission_attr(&old_path)?;
+    let new = permission_attr(&new_path)?;
+    let content_equal = if old_path.is_file() && new_path.is_file() {
+        Some(file_digest_diff(name, old_dir, new_dir)?)
+    } else {
+        None
+    };
+
+    Ok(PermissionDiff {
+        name: name.to_owned(),
+        permission_equal: old == new,
+        content_equal,
+        old,
+        new,
+    })
+}
+
+/// collect entries whose permissions differ under directories recursively
+/// only entries existing on both sides are compared
+pub fn permission_diffs(old_dir: &str, new_dir: &str) -> Result<Vec<PermissionDiff>, String> {
+    let mut ret = vec![];
+    collect_permission_diffs(&os_path(old_dir), &os_path(new_dir), "", &mut ret)?;
+    Ok(ret)
+}
+
+/// check if permissions of entries under directories are all equal
+pub fn permissions_equal(old_path: &Path, new_path: &Path) -> Result<bool, String> {
+    if permission_attr(old_path)? != permission_attr(new_path)? {
+        return Ok(false);
+    }
+    if old_path.is_dir() && new_path.is_dir() {
+        let mut diffs = vec![];
+        collect_permission_diffs(old_path, new_path, "", &mut diffs)?;
+        return Ok(diffs.is_empty());
+    }
+    Ok(true)
+}
+
+/// collect permission diffs in directory into `diffs`
+fn collect_permission_diffs(
+    old_root: &Path,
+    new_root: &Path,
+    relative_dir: &str,
+    diffs: &mut Vec<PermissionDiff>,
+) -> Result<(), String> {
+    let old_dir = old_root.join(os_path(relative_dir));
+    let entries = read_dir(&old_dir)
+        .map_err(|err| format!("Invalid path: {} ({})", old_dir.display(), err))?;
+
+    for entry in entries {
+        let entry = entry.map_err(|err| format!("Failed to get entry ({})", err))?;
+        let name = os_str_string(&entry.file_name());
+        let relative_path = if relative_dir.is_empty() {
+            name
+        } else {
+            path_string(&os_path(relative_dir).join(os_path(&name)))
+        };
+        if !new_root.join(os_path(&relative_path)).exists() {
+            continue;
+        }
+
+        let diff = permission_diff(
+            &relative_path,
+            &path_string(old_root),
+            &path_string(new_root),
+        )?;
+        let is_dir = entry.path().is_dir() && new_root.join(&relative_path).is_dir();
+        if !diff.permission_equal {
+            diffs.push(diff);
+        }
+        if is_dir {
+            collect_permission_diffs(old_root, new_root, &relative_path, diffs)?;
+        }
+    }
+    Ok(())
+}
+
+/// get acl entries in text if platform tool is available
+fn acl(path: &Path) -> Option<String> {
+    #[cfg(target_os = "linux")]
+    {
+        use std::process::Command;
+
+        let output = Command::new("getfacl")
+            .args(["--omit-header", "--absolute-names", "--skip-base"])
+            .arg(path)
+            .output()
+            .ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        Some(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = path;
+        None
+    }
+}
@@ -0,0 +1,111 @@
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Read};
+
+use super::archive::{archive_bytes, archive_entries};
+use super::compare_mode::file_compare_mode;
+use super::file::bytes_content;
+use super::media::media_metadata;
+use super::os_path::os_path;
+use super::str::{base64_encode, bytes_to_hex_dump};
+use super::types::{CompareMode, FilePreview};
+
+/// lines shown in text preview unless specified
+const DEFAULT_PREVIEW_LINES: usize = 20;
+/// max image size embedded in preview as data url
+const MAX_PREVIEW_IMAGE_BYTES: u64 = 512 * 1024;
+/// bytes shown in binary preview as hex dump
+const PREVIEW_HEX_BYTES: usize = 256;
+/// archive entry names shown in preview
+const PREVIEW_ARCHIVE_ENTRIES: usize = 20;
+
+/// lightweight preview of single file to confirm it before comparing
+/// first lines of text, image with metadata, archive entries or head of binary
+/// only the part needed is read except for archives
+pub fn file_preview(filepath: &str, lines: Option<usize>) -> Result<FilePreview, String> {
+    let path = &os_path(filepath);
+    let metadata =
+        fs::metadata(path).map_err(|err| format!("Failed to read {} ({})", filepath, err))?;
+    let mut ret = FilePreview {
+        size: metadata.len(),
+        ..Default::default()
+    };
+    if metadata.is_dir() {
+        let entries_count = fs::read_dir(path)
+            .map_err(|err| format!("Invalid path: {} ({})", filepath, err))?
+            .count();
+        ret.entries_count = Some(entries_count);
+        return Ok(ret);
+    }
+
+    let compare_mode = file_compare_mode(filepath);
+    ret.compare_mode = compare_mode;
+    match compare_mode {
+        Some(CompareMode::Text) | Some(CompareMode::Structured) => {
+            let file =
+                File::open(path).map_err(|err| format!("Failed to open {} ({})", filepath, err))?;
+            let mut reader = BufReader::new(file);
+            let mut head = vec![];
+            for _ in 0..lines.unwrap_or(DEFAULT_PREVIEW_LINES) {
+                let read = reader
+                    .read_until(b'\n', &mut head)
+                    .map_err(|err| format!("Failed to read {} ({})", filepath, err))?;
+                if read == 0 {
+                    break;
+                }
+            }
+            let read = bytes_content(&head);
+            ret.charset = Some(read.charset);
+            ret.text = Some(read.content);
+        }
+        Some(CompareMode::Image) => {
+            ret.metadata = media_metadata(filepath)?;
+            if metadata.len() <= MAX_PREVIEW_IMAGE_BYTES {
+                let bytes = fs::read(path)
+                    .map_err(|err| format!("Failed to read {} ({})", filepath, err))?;
+                ret.image_data_url = Some(format!(
+                    "data:{};base64,{}",
+                    image_mime_type(&bytes),
+                    base64_encode(&bytes)
+                ));
+            }
+        }
+        Some(CompareMode::Archive) | Some(CompareMode::Spreadsheet) => {
+            let entries = archive_entries(&archive_bytes(filepath)?).unwrap_or_default();
+            ret.entries_count = Some(entries.len());
+            ret.text = Some(
+                entries
+                    .iter()
+                    .take(PREVIEW_ARCHIVE_ENTRIES)
+                    .map(|x| format!("{}\n", x.name))
+                    .collect(),
+            );
+        }
+        _ => {
+            let file =
+                File::open(path).map_err(|err| format!("Failed to open {} ({})", filepath, err))?;
+            let mut head = vec![];
+            file.take(PREVIEW_HEX_BYTES as u64)
+                .read_to_end(&mut head)
+                .map_err(|err| format!("Failed to read {} ({})", filepath, err))?;
+            ret.text = Some(bytes_to_hex_dump(&head));
+        }
+    }
+    Ok(ret)
+}
+
+/// mime type of image by magic number
+fn image_mime_type(bytes: &[u8]) -> &'static str {
+    if bytes.starts_with(b"\x89PNG") {
+        "image/png"
+    } else if bytes.starts_with(&[0xff, 0xd8]) {
+        "image/jpeg"
+    } else if bytes.starts_with(b"GIF8") {
+        "image/gif"
+    } else if bytes.starts_with(b"BM") {
+        "image/bmp"
+    } else if bytes.get(8..12) == Some(b"WEBP") {
+        "image/webp"
+    } else {
+        "application/octet-stream"
+    }
+}
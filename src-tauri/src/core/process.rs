@@ -0,0 +1,66 @@
+use std::io::Read;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::file::bytes_content;
+use super::types::{CommandSpec, ReadContent};
+
+/// default timeout to wait for command to exit
+const DEFAULT_COMMAND_TIMEOUT_SECS: u64 = 30;
+/// interval to check if command has exited
+const COMMAND_POLL_INTERVAL_MILLIS: u64 = 50;
+
+/// run command and get its stdout as content
+pub fn command_output_content(spec: &CommandSpec) -> Result<ReadContent, String> {
+    let mut command = Command::new(&spec.program);
+    command
+        .args(&spec.args)
+        .envs(spec.env.iter().map(|x| (&x.key, &x.value)))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null());
+    if let Some(cwd) = &spec.cwd {
+        if !cwd.is_empty() {
+            command.current_dir(cwd);
+        }
+    }
+
+    let mut child = command
+        .spawn()
+        .map_err(|err| format!("Failed to run {} ({})", spec.program, err))?;
+
+    // read stdout in another thread so that child is not blocked by full pipe
+    let mut stdout = child.stdout.take().expect("Failed to get stdout");
+    let reader = thread::spawn(move || {
+        let mut buffer = Vec::new();
+        stdout.read_to_end(&mut buffer).map(|_| buffer)
+    });
+
+    let timeout = Duration::from_secs(spec.timeout_secs.unwrap_or(DEFAULT_COMMAND_TIMEOUT_SECS));
+    let started = Instant::now();
+    loop {
+        match child.try_wait() {
+            Ok(Some(_)) => break,
+            Ok(None) => {
+                if timeout <= started.elapsed() {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return Err(format!(
+                        "{} timed out after {} secs",
+                        spec.program,
+                        timeout.as_secs()
+                    ));
+                }
+                thread::sleep(Duration::from_millis(COMMAND_POLL_INTERVAL_MILLIS));
+            }
+            Err(err) => return Err(format!("Failed to wait {} ({})", spec.program, err)),
+        }
+    }
+
+    let buffer = reader
+        .join()
+        .map_err(|_| format!("Failed to read stdout of {}", spec.program))?
+        .map_err(|err| format!("Failed to read stdout of {} ({})", spec.program, err))?;
+    Ok(bytes_content(&buffer))
+}
@@ -0,0 +1,189 @@
+use std::collections::HashMap;
+use std::fs;
+
+use super::file::{bytes_content, file_extension};
+use super::os_path::os_path;
+use super::types::{FileProfile, Indentation, ProfileComparison};
+
+/// languages by file extension
+const LANGUAGES: [(&str, &[&str]); 32] = [
+    ("C", &["c", "h"]),
+    ("C++", &["cc", "cpp", "cxx", "hpp", "hh"]),
+    ("C#", &["cs"]),
+    ("CSS", &["css", "scss", "less"]),
+    ("CSV", &["csv", "tsv"]),
+    ("Dart", &["dart"]),
+    ("Go", &["go"]),
+    ("HTML", &["html", "htm", "xhtml"]),
+    ("INI", &["ini", "cfg", "conf", "properties"]),
+    ("Java", &["java"]),
+    ("JavaScript", &["js", "mjs", "cjs", "jsx"]),
+    ("JSON", &["json", "jsonl", "ndjson"]),
+    ("Kotlin", &["kt", "kts"]),
+    ("Lua", &["lua"]),
+    ("Markdown", &["md", "markdown"]),
+    ("Perl", &["pl", "pm"]),
+    ("PHP", &["php"]),
+    ("PowerShell", &["ps1", "psm1"]),
+    ("Python", &["py", "pyi"]),
+    ("R", &["r"]),
+    ("Ruby", &["rb"]),
+    ("Rust", &["rs"]),
+    ("Scala", &["scala"]),
+    ("Shell", &["sh", "bash", "zsh"]),
+    ("SQL", &["sql"]),
+    ("Svelte", &["svelte"]),
+    ("Swift", &["swift"]),
+    ("TOML", &["toml"]),
+    ("TypeScript", &["ts", "tsx"]),
+    ("Vue", &["vue"]),
+    ("XML", &["xml", "svg", "xsd", "xsl"]),
+    ("YAML", &["yaml", "yml"]),
+];
+/// languages by interpreter in shebang
+const SHEBANG_LANGUAGES: [(&str, &str); 8] = [
+    ("python", "Python"),
+    ("node", "JavaScript"),
+    ("deno", "TypeScript"),
+    ("ruby", "Ruby"),
+    ("perl", "Perl"),
+    ("php", "PHP"),
+    ("pwsh", "PowerShell"),
+    ("sh", "Shell"),
+];
+
+/// profile both sides with structural stats to see at a glance how they differ
+/// before comparing them line by line
+pub fn profile_comparison(old: &str, new: &str) -> Result<ProfileComparison, String> {
+    Ok(ProfileComparison {
+        old: file_profile(old)?,
+        new: file_profile(new)?,
+    })
+}
+
+/// structural stats of file. text stats are left empty on binary file
+fn file_profile(filepath: &str) -> Result<FileProfile, String> {
+    if filepath.is_empty() {
+        return Ok(FileProfile::default());
+    }
+    let bytes = fs::read(os_path(filepath))
+        .map_err(|err| format!("Failed to read {} ({})", filepath, err))?;
+
+    let mut byte_histogram = vec![0; 256];
+    for x in &bytes {
+        byte_histogram[*x as usize] += 1;
+    }
+    let is_binary = bytes.windows(2).any(|window| window[0] == 0x00);
+    let mut ret = FileProfile {
+        size: bytes.len() as u64,
+        byte_histogram,
+        is_binary,
+        ..Default::default()
+    };
+    if is_binary {
+        return Ok(ret);
+    }
+
+    let content = bytes_content(&bytes).content;
+    let line_lengths = content
+        .lines()
+        .map(|x| x.chars().count())
+        .collect::<Vec<_>>();
+    ret.line_count = line_lengths.len();
+    ret.max_line_length = line_lengths.iter().max().copied().unwrap_or_default();
+    if !line_lengths.is_empty() {
+        ret.average_line_length =
+            line_lengths.iter().sum::<usize>() as f32 / line_lengths.len() as f32;
+    }
+    (ret.indentation, ret.indent_width) = indentation(&content);
+    ret.language = language(filepath, &content);
+    Ok(ret)
+}
+
+/// indentation style of lines and most common step of space indents
+fn indentation(content: &str) -> (Indentation, Option<usize>) {
+    let mut tabs = 0;
+    let mut spaces = 0;
+    let mut steps = HashMap::<usize, usize>::new();
+    let mut previous_width = 0;
+    for line in content.lines() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let indent = &line[..line.len() - line.trim_start_matches([' ', '\t']).len()];
+        if indent.starts_with('\t') {
+            tabs += 1;
+        } else if indent.starts_with(' ') {
+            spaces += 1;
+        }
+        // step between nested lines rather than absolute width
+        // so that alignment such as continuation lines is less counted
+        let width = if indent.contains('\t') {
+            0
+        } else {
+            indent.len()
+        };
+        if previous_width < width {
+            *steps.entry(width - previous_width).or_default() += 1;
+        }
+        previous_width = width;
+    }
+    let style = match (tabs, spaces) {
+        (0, 0) => Indentation::None,
+        (_, 0) => Indentation::Tabs,
+        (0, _) => Indentation::Spaces,
+        // a few exceptions such as alignment are tolerated
+        (t, s) if s <= t / 20 => Indentation::Tabs,
+        (t, s) if t <= s / 20 => Indentation::Spaces,
+        _ => Indentation::Mixed,
+    };
+    let width = steps
+        .into_iter()
+        .max_by_key(|(step, count)| (*count, std::cmp::Reverse(*step)))
+        .map(|x| x.0)
+        .filter(|_| matches!(style, Indentation::Spaces | Indentation::Mixed));
+    (style, width)
+}
+
+/// guess language by file extension, shebang and then leading content
+fn language(filepath: &str, content: &str) -> Option<String> {
+    let extension = file_extension(filepath);
+    if let Some((name, _)) = LANGUAGES.iter().find(|x| x.1.contains(&extension.as_str())) {
+        return Some(name.to_string());
+    }
+
+    let head = content.trim_start_matches('\u{feff}').trim_start();
+    if let Some(shebang) = head.strip_prefix("#!") {
+        let command = shebang.lines().next().unwrap_or_default();
+        // `/usr/bin/env python3` or `/bin/bash`
+        let interpreter = command
+            .split_whitespace()
+            .map(|x| x.rsplit('/').next().unwrap_or(x))
+            .find(|x| *x != "env" && !x.starts_with('-'))
+            .unwrap_or_default();
+        return SHEBANG_LANGUAGES
+            .iter()
+            .find(|x| interpreter.starts_with(x.0) || interpreter.ends_with(x.0))
+            .map(|x| x.1.to_owned());
+    }
+    let lowercase_head = head
+        .chars()
+        .take(64)
+        .collect::<String>()
+        .to_ascii_lowercase();
+    let ret = if lowercase_head.starts_with("<!doctype html") || lowercase_head.starts_with("<html")
+    {
+        "HTML"
+    } else if lowercase_head.starts_with("<?xml") {
+        "XML"
+    } else if lowercase_head.starts_with("<?php") {
+        "PHP"
+    } else if head.starts_with('{') || head.starts_with('[') {
+        "JSON"
+    } else if head.starts_with("---\n") || head.starts_with("---\r\n") {
+        "YAML"
+    } else {
+        return None;
+    };
+    Some(ret.to_owned())
+}
@@ -0,0 +1,113 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use tauri::{Emitter, Manager};
+
+use super::types::{OperationKind, OperationProgressEvent, ProgressPhase, ProgressUnit};
+
+/// event emitted by long operations in uniform schema
+pub const OPERATION_PROGRESS_EVENT: &str = "operation-progress";
+/// min interval between running events of operation
+const PROGRESS_EVENT_INTERVAL: Duration = Duration::from_millis(200);
+
+/// app state to number operations reporting progress
+#[derive(Default)]
+pub struct Operations {
+    next_id: AtomicU64,
+}
+
+/// progress of long operation emitted as events so that ui shows consistent progress bars
+/// started and finished events are always emitted, and running ones at limited rate
+pub struct Progress {
+    app_handle: tauri::AppHandle,
+    event: OperationProgressEvent,
+    started_at: Instant,
+    last_emitted: Instant,
+}
+
+impl Progress {
+    /// start operation on target such as file path, url or directory
+    pub fn start(
+        app_handle: &tauri::AppHandle,
+        operation: OperationKind,
+        target: &str,
+        unit: ProgressUnit,
+    ) -> Self {
+        let operation_id = app_handle
+            .state::<Operations>()
+            .next_id
+            .fetch_add(1, Ordering::SeqCst);
+        let ret = Self {
+            app_handle: app_handle.clone(),
+            event: OperationProgressEvent {
+                operation_id,
+                operation,
+                target: target.to_owned(),
+                phase: ProgressPhase::Started,
+                unit,
+                processed: 0,
+                total: None,
+                eta_millis: None,
+                error: None,
+            },
+            started_at: Instant::now(),
+            last_emitted: Instant::now(),
+        };
+        ret.emit();
+        ret
+    }
+
+    /// update bytes or entries processed, and total when known
+    pub fn update(&mut self, processed: u64, total: Option<u64>) {
+        self.event.phase = ProgressPhase::Running;
+        self.event.processed = processed;
+        self.event.total = total;
+        if self.last_emitted.elapsed() < PROGRESS_EVENT_INTERVAL {
+            return;
+        }
+        self.last_emitted = Instant::now();
+        self.event.eta_millis = self.eta_millis();
+        self.emit();
+    }
+
+    /// finish operation with its result which is passed through
+    pub fn finish<T>(mut self, result: Result<T, String>) -> Result<T, String> {
+        match &result {
+            Ok(_) => {
+                self.event.phase = ProgressPhase::Finished;
+                // total may not be known such as on ftp download
+                if let Some(total) = self.event.total {
+                    self.event.processed = total;
+                }
+            }
+            Err(err) => {
+                self.event.phase = ProgressPhase::Failed;
+                self.event.error = Some(err.to_owned());
+            }
+        }
+        self.event.eta_millis = None;
+        self.emit();
+        result
+    }
+
+    /// estimated time to finish at average rate so far
+    fn eta_millis(&self) -> Option<u64> {
+        let total = self.event.total?;
+        let processed = self.event.processed;
+        if processed == 0 {
+            return None;
+        }
+        let elapsed = self.started_at.elapsed().as_millis() as f64;
+        let remaining = total.saturating_sub(processed) as f64;
+        Some((elapsed * remaining / processed as f64) as u64)
+    }
+
+    fn emit(&self) {
+        if let Err(err) = self
+            .app_handle
+            .emit(OPERATION_PROGRESS_EVENT, self.event.clone())
+        {
+            tracing::warn!("Failed to emit operation progress ({})", err);
+        }
+    }
+}
@@ -0,0 +1,256 @@
+use std::fs;
+use std::io::{Cursor, Read};
+use std::sync::atomic::{compiler_fence, Ordering};
+
+use serde::Deserialize;
+use zip::result::ZipError;
+use zip::ZipArchive;
+
+use super::archive::{archive_entries, is_zip};
+use super::file::{file_extension, fnv1a_digest};
+use super::os_path::os_path;
+use super::types::ReadContent;
+
+/// compound file binary signature which encrypted office files are stored in
+const CFB_SIGNATURE: [u8; 8] = [0xd0, 0xcf, 0x11, 0xe0, 0xa1, 0xb1, 0x1a, 0xe1];
+/// office open xml extensions which can be password-protected
+const OOXML_EXTENSIONS: [&str; 3] = ["docx", "xlsx", "pptx"];
+
+/// password passed from prompt
+/// never serialized nor logged, and its bytes are cleared on drop
+#[derive(Deserialize)]
+#[serde(transparent)]
+pub struct Password(String);
+
+impl Drop for Password {
+    fn drop(&mut self) {
+        let mut bytes = std::mem::take(&mut self.0).into_bytes();
+        bytes.fill(0);
+        // keep clearing from being optimized away
+        compiler_fence(Ordering::SeqCst);
+    }
+}
+
+/// check if file requires password to be compared
+/// zip with encrypted entries or office open xml encrypted in compound file
+pub fn is_password_protected(filepath: &str) -> Result<bool, String> {
+    let bytes = fs::read(os_path(filepath))
+        .map_err(|err| format!("Failed to read {} ({})", filepath, err))?;
+    if is_zip(&bytes) {
+        let encrypted = archive_entries(&bytes).is_some_and(|x| x.iter().any(|x| x.encrypted));
+        return Ok(encrypted);
+    }
+    Ok(bytes.starts_with(&CFB_SIGNATURE)
+        && OOXML_EXTENSIONS.contains(&file_extension(filepath).as_str()))
+}
+
+/// read content of file decrypted in memory with password
+/// office files are read as text and other archives are read as entries listing
+/// with digests of decrypted entries. decrypted bytes are never written to disk
+pub fn protected_content(
+    filepath: &str,
+    password: Option<&Password>,
+) -> Result<ReadContent, String> {
+    let bytes = fs::read(os_path(filepath))
+        .map_err(|err| format!("Failed to read {} ({})", filepath, err))?;
+    let is_ooxml = OOXML_EXTENSIONS.contains(&file_extension(filepath).as_str());
+    let bytes = if bytes.starts_with(&CFB_SIGNATURE) && is_ooxml {
+        let password = password.ok_or(format!("Password required: {}", filepath))?;
+        office_crypto::decrypt_from_bytes(bytes, &password.0)
+            .map_err(|err| format!("Failed to decrypt {} ({})", filepath, err))?
+    } else {
+        bytes
+    };
+
+    let mut archive = ZipArchive::new(Cursor::new(bytes))
+        .map_err(|err| format!("Not supported archive: {} ({})", filepath, err))?;
+    let content = if is_ooxml {
+        ooxml_text(&mut archive, &file_extension(filepath))
+    } else {
+        decrypted_entries_listing(&mut archive, password)
+    }
+    .map_err(|err| format!("Failed to read {} ({})", filepath, err))?;
+    Ok(ReadContent {
+        charset: "(decrypted)".to_owned(),
+        content,
+        ..Default::default()
+    })
+}
+
+/// read zip entry decrypted with password when encrypted
+fn zip_entry_bytes(
+    archive: &mut ZipArchive<Cursor<Vec<u8>>>,
+    index: usize,
+    password: Option<&Password>,
+) -> Result<(String, Vec<u8>), String> {
+    let encrypted = archive
+        .by_index_raw(index)
+        .map_err(|err| err.to_string())?
+        .encrypted();
+    let entry = match (encrypted, password) {
+        (true, Some(password)) => archive.by_index_decrypt(index, password.0.as_bytes()),
+        (true, None) => return Err("Password required".to_owned()),
+        (false, _) => archive.by_index(index),
+    };
+    let mut entry = entry.map_err(|err| match err {
+        ZipError::InvalidPassword => "Invalid password".to_owned(),
+        err => err.to_string(),
+    })?;
+    let mut bytes = vec![];
+    entry
+        .read_to_end(&mut bytes)
+        .map_err(|err| err.to_string())?;
+    Ok((entry.name().to_owned(), bytes))
+}
+
+/// entries listing with sizes and digests of decrypted entries
+fn decrypted_entries_listing(
+    archive: &mut ZipArchive<Cursor<Vec<u8>>>,
+    password: Option<&Password>,
+) -> Result<String, String> {
+    let mut ret = String::new();
+    for i in 0..archive.len() {
+        let (name, bytes) = zip_entry_bytes(archive, i, password)?;
+        if name.ends_with('/') {
+            continue;
+        }
+        ret.push_str(&format!(
+            "{}\t{} bytes\tfnv1a {:016x}\n",
+            name,
+            bytes.len(),
+            fnv1a_digest(&bytes)
+        ));
+    }
+    Ok(ret)
+}
+
+/// text in office open xml
+/// paragraphs in document and slides, and cell values in sheets
+fn ooxml_text(
+    archive: &mut ZipArchive<Cursor<Vec<u8>>>,
+    extension: &str,
+) -> Result<String, String> {
+    let mut parts = vec![];
+    for i in 0..archive.len() {
+        let (name, bytes) = zip_entry_bytes(archive, i, None)?;
+        if name.ends_with(".xml") {
+            parts.push((name, String::from_utf8_lossy(&bytes).to_string()));
+        }
+    }
+    parts.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let ret = match extension {
+        "xlsx" => {
+            let shared_strings = parts
+                .iter()
+                .find(|x| x.0 == "xl/sharedStrings.xml")
+                .map(|x| shared_strings(&x.1))
+                .unwrap_or_default();
+            parts
+                .iter()
+                .filter(|x| x.0.starts_with("xl/worksheets/"))
+                .map(|(name, xml)| sheet_cells(name, xml, &shared_strings))
+                .collect()
+        }
+        "pptx" => parts
+            .iter()
+            .filter(|x| x.0.starts_with("ppt/slides/"))
+            .map(|(name, xml)| format!("[{}]\n{}", name, xml_text(xml, "</a:p>")))
+            .collect(),
+        _ => parts
+            .iter()
+            .filter(|x| x.0 == "word/document.xml")
+            .map(|x| xml_text(&x.1, "</w:p>"))
+            .collect(),
+    };
+    Ok(ret)
+}
+
+/// shared strings in workbook referred by index from cells
+pub fn shared_strings(xml: &str) -> Vec<String> {
+    xml.split("<si>")
+        .skip(1)
+        .map(|x| {
+            let item = x.split("</si>").next().unwrap_or_default();
+            xml_text(item, "").trim_end().to_owned()
+        })
+        .collect()
+}
+
+/// cell values in worksheet such as `sheet1!A1\tvalue`
+fn sheet_cells(name: &str, xml: &str, shared_strings: &[String]) -> String {
+    let sheet = name
+        .trim_start_matches("xl/worksheets/")
+        .trim_end_matches(".xml");
+    sheet_cell_values(xml, shared_strings)
+        .into_iter()
+        .map(|(reference, value)| format!("{}!{}\t{}\n", sheet, reference, value))
+        .collect()
+}
+
+/// cell references such as `A1` and values in worksheet xml
+/// shared strings are resolved and formulas are represented by their cached values
+pub fn sheet_cell_values(xml: &str, shared_strings: &[String]) -> Vec<(String, String)> {
+    xml.split("<c ")
+        .skip(1)
+        .filter_map(|cell| {
+            let (attributes, body) = cell.split_once('>')?;
+            let reference = xml_attribute(attributes, "r")?;
+            let value = if attributes.ends_with('/') {
+                String::new()
+            } else {
+                let body = body.split("</c>").next().unwrap_or_default();
+                match xml_attribute(attributes, "t").as_deref() {
+                    Some("s") => {
+                        let index = xml_text(body, "").trim().parse::<usize>().ok()?;
+                        shared_strings.get(index)?.to_owned()
+                    }
+                    // formula is not evaluated but its cached value is used
+                    _ => {
+                        let value = body.split("<v>").nth(1).unwrap_or(body);
+                        xml_text(value, "").trim().to_owned()
+                    }
+                }
+            };
+            Some((reference, value))
+        })
+        .collect()
+}
+
+/// attribute value in xml start tag
+pub fn xml_attribute(attributes: &str, name: &str) -> Option<String> {
+    let pattern = format!(" {}=\"", name);
+    let start = format!(" {}", attributes).find(&pattern)? + pattern.len() - 1;
+    let value = attributes.get(start..)?.split('"').next()?;
+    Some(value.to_owned())
+}
+
+/// text between xml tags. line break is put after `paragraph_end` tag
+fn xml_text(xml: &str, paragraph_end: &str) -> String {
+    let mut ret = String::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find('<') {
+        ret.push_str(&xml_unescape(&rest[..start]));
+        let end = rest[start..]
+            .find('>')
+            .map_or(rest.len(), |x| start + x + 1);
+        let tag = &rest[start..end];
+        if !paragraph_end.is_empty() && tag == paragraph_end {
+            ret.push('\n');
+        } else if tag == "<w:tab/>" {
+            ret.push('\t');
+        }
+        rest = &rest[end..];
+    }
+    ret.push_str(&xml_unescape(rest));
+    ret
+}
+
+/// unescape predefined xml entities
+pub fn xml_unescape(s: &str) -> String {
+    s.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
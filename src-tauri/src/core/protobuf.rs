@@ -0,0 +1,454 @@
+use std::collections::HashMap;
+use std::fs;
+
+use super::os_path::os_path;
+use super::types::ReadContent;
+
+/// max nesting depth to try to decode length-delimited field as message
+const MAX_MESSAGE_DEPTH: usize = 32;
+/// indent per nesting level in decoded text tree
+const INDENT: &str = "  ";
+
+/// message definition in .proto schema
+#[derive(Clone, Default)]
+struct ProtoMessage {
+    fields: HashMap<u64, ProtoField>,
+}
+
+/// field definition in .proto schema
+#[derive(Clone)]
+struct ProtoField {
+    name: String,
+    type_name: String,
+}
+
+/// field decoded from wire format
+enum WireValue<'a> {
+    Varint(u64),
+    Fixed64(u64),
+    LengthDelimited(&'a [u8]),
+    Fixed32(u32),
+}
+
+/// decode protobuf-encoded file into text tree
+/// field names and types are resolved with .proto schema when `message_type` is found in it
+pub fn protobuf_content(
+    filepath: &str,
+    proto_filepath: Option<&str>,
+    message_type: Option<&str>,
+) -> Result<ReadContent, String> {
+    if filepath.is_empty() {
+        return Ok(ReadContent::default());
+    }
+
+    let bytes = fs::read(os_path(filepath))
+        .map_err(|err| format!("Failed to read {} ({})", filepath, err))?;
+
+    let schema = match proto_filepath {
+        Some(proto_filepath) if !proto_filepath.is_empty() => {
+            let src = fs::read_to_string(os_path(proto_filepath))
+                .map_err(|err| format!("Failed to read {} ({})", proto_filepath, err))?;
+            parse_proto(&src)?
+        }
+        _ => HashMap::new(),
+    };
+    let message = match message_type {
+        Some(message_type) if !message_type.is_empty() => match schema.get(message_type) {
+            Some(x) => Some(x),
+            None => {
+                return Err(format!(
+                    "Message type not found in schema: {}",
+                    message_type
+                ))
+            }
+        },
+        _ => None,
+    };
+
+    let mut content = String::new();
+    if !write_message(&bytes, message, &schema, 0, &mut content) {
+        return Err(format!("Not protobuf wire format: {}", filepath));
+    }
+    Ok(ReadContent {
+        charset: "(protobuf)".to_owned(),
+        content,
+        ..Default::default()
+    })
+}
+
+/// write decoded message fields into output
+/// returns false when bytes are not valid wire format
+fn write_message(
+    bytes: &[u8],
+    message: Option<&ProtoMessage>,
+    schema: &HashMap<String, ProtoMessage>,
+    depth: usize,
+    output: &mut String,
+) -> bool {
+    let fields = match decode_wire(bytes) {
+        Some(x) => x,
+        None => return false,
+    };
+
+    let indent = INDENT.repeat(depth);
+    for (field_number, value) in fields {
+        let field = message.and_then(|x| x.fields.get(&field_number));
+        let label = match field {
+            Some(field) => format!("{} ({})", field.name, field_number),
+            None => field_number.to_string(),
+        };
+        let type_name = field.map(|x| x.type_name.as_str()).unwrap_or_default();
+
+        match value {
+            WireValue::Varint(x) => output.push_str(&format!(
+                "{}{}: {}\n",
+                indent,
+                label,
+                varint_text(x, type_name)
+            )),
+            WireValue::Fixed64(x) => {
+                let text = match type_name {
+                    "double" => f64::from_bits(x).to_string(),
+                    "sfixed64" => (x as i64).to_string(),
+                    _ => x.to_string(),
+                };
+                output.push_str(&format!("{}{}: {}\n", indent, label, text));
+            }
+            WireValue::Fixed32(x) => {
+                let text = match type_name {
+                    "float" => f32::from_bits(x).to_string(),
+                    "sfixed32" => (x as i32).to_string(),
+                    _ => x.to_string(),
+                };
+                output.push_str(&format!("{}{}: {}\n", indent, label, text));
+            }
+            WireValue::LengthDelimited(x) => {
+                write_length_delimited(x, &label, type_name, schema, depth, output)
+            }
+        }
+    }
+    true
+}
+
+/// write length-delimited field as string, nested message or bytes
+fn write_length_delimited(
+    bytes: &[u8],
+    label: &str,
+    type_name: &str,
+    schema: &HashMap<String, ProtoMessage>,
+    depth: usize,
+    output: &mut String,
+) {
+    let indent = INDENT.repeat(depth);
+
+    match type_name {
+        "string" => {
+            let text = String::from_utf8_lossy(bytes);
+            output.push_str(&format!("{}{}: {:?}\n", indent, label, text));
+            return;
+        }
+        "bytes" => {
+            output.push_str(&format!("{}{}: {}\n", indent, label, hex_text(bytes)));
+            return;
+        }
+        _ => {}
+    }
+
+    // nested message defined in schema or guessed by wire format heuristics
+    let nested = schema.get(type_name);
+    let tries_message = nested.is_some()
+        || (type_name.is_empty() && !bytes.is_empty() && !is_printable_text(bytes));
+    if tries_message && depth < MAX_MESSAGE_DEPTH {
+        let mut nested_output = String::new();
+        if write_message(bytes, nested, schema, depth + 1, &mut nested_output) {
+            output.push_str(&format!("{}{} {{\n", indent, label));
+            output.push_str(&nested_output);
+            output.push_str(&format!("{}}}\n", indent));
+            return;
+        }
+    }
+
+    if is_printable_text(bytes) {
+        let text = String::from_utf8_lossy(bytes);
+        output.push_str(&format!("{}{}: {:?}\n", indent, label, text));
+    } else {
+        output.push_str(&format!("{}{}: {}\n", indent, label, hex_text(bytes)));
+    }
+}
+
+/// decode all fields in bytes
+/// returns none when bytes are not consumed exactly as wire format
+fn decode_wire(bytes: &[u8]) -> Option<Vec<(u64, WireValue<'_>)>> {
+    let mut ret = vec![];
+    let mut pos = 0;
+    while pos < bytes.len() {
+        let key = read_varint(bytes, &mut pos)?;
+        let field_number = key >> 3;
+        if field_number == 0 {
+            return None;
+        }
+        let value = match key & 0x07 {
+            0 => WireValue::Varint(read_varint(bytes, &mut pos)?),
+            1 => {
+                let end = pos.checked_add(8).filter(|x| *x <= bytes.len())?;
+                let x = u64::from_le_bytes(bytes[pos..end].try_into().ok()?);
+                pos = end;
+                WireValue::Fixed64(x)
+            }
+            2 => {
+                let len = usize::try_from(read_varint(bytes, &mut pos)?).ok()?;
+                let end = pos.checked_add(len).filter(|x| *x <= bytes.len())?;
+                let x = &bytes[pos..end];
+                pos = end;
+                WireValue::LengthDelimited(x)
+            }
+            5 => {
+                let end = pos.checked_add(4).filter(|x| *x <= bytes.len())?;
+                let x = u32::from_le_bytes(bytes[pos..end].try_into().ok()?);
+                pos = end;
+                WireValue::Fixed32(x)
+            }
+            // groups are deprecated and others are invalid
+            _ => return None,
+        };
+        ret.push((field_number, value));
+    }
+    Some(ret)
+}
+
+/// read base 128 varint
+fn read_varint(bytes: &[u8], pos: &mut usize) -> Option<u64> {
+    let mut ret = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = *bytes.get(*pos)?;
+        *pos += 1;
+        ret |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Some(ret);
+        }
+    }
+    None
+}
+
+/// format varint according to field type
+fn varint_text(x: u64, type_name: &str) -> String {
+    match type_name {
+        "sint32" | "sint64" => (((x >> 1) as i64) ^ -((x & 1) as i64)).to_string(),
+        "int32" | "int64" => (x as i64).to_string(),
+        "bool" => (x != 0).to_string(),
+        _ => x.to_string(),
+    }
+}
+
+/// format bytes as hex
+fn hex_text(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|x| format!("{:02x}", x))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// check if bytes are utf-8 text without control chars
+fn is_printable_text(bytes: &[u8]) -> bool {
+    match std::str::from_utf8(bytes) {
+        Ok(x) => x.chars().all(|c| !c.is_control() || c.is_whitespace()),
+        Err(_) => false,
+    }
+}
+
+/// parse messages in .proto schema
+/// messages are keyed by both simple name and full name with package and parents
+fn parse_proto(src: &str) -> Result<HashMap<String, ProtoMessage>, String> {
+    let tokens = tokenize_proto(src);
+    let mut messages = vec![];
+    let mut package = String::new();
+    let mut pos = 0;
+    while pos < tokens.len() {
+        match tokens[pos].as_str() {
+            "package" => {
+                package = tokens.get(pos + 1).cloned().unwrap_or_default();
+                skip_statement(&tokens, &mut pos);
+            }
+            "message" => {
+                pos += 1;
+                parse_message(&tokens, &mut pos, &package, &mut messages)?;
+            }
+            "enum" | "service" | "extend" => skip_block(&tokens, &mut pos),
+            _ => skip_statement(&tokens, &mut pos),
+        }
+    }
+
+    let mut ret = HashMap::new();
+    for (full_name, message) in messages {
+        let simple_name = full_name.rsplit('.').next().unwrap_or_default().to_owned();
+        ret.insert(simple_name, message.clone());
+        ret.insert(full_name, message);
+    }
+    Ok(ret)
+}
+
+/// parse message block starting at its name
+fn parse_message(
+    tokens: &[String],
+    pos: &mut usize,
+    parent: &str,
+    messages: &mut Vec<(String, ProtoMessage)>,
+) -> Result<(), String> {
+    let name = tokens
+        .get(*pos)
+        .ok_or("Unexpected end of schema after message")?;
+    let full_name = if parent.is_empty() {
+        name.to_owned()
+    } else {
+        format!("{}.{}", parent, name)
+    };
+    *pos += 1;
+    if tokens.get(*pos).map(|x| x.as_str()) != Some("{") {
+        return Err(format!("Expected {{ after message {}", name));
+    }
+    *pos += 1;
+
+    let mut message = ProtoMessage::default();
+    parse_message_body(tokens, pos, &full_name, &mut message, messages)?;
+    messages.push((full_name, message));
+    Ok(())
+}
+
+/// parse statements in message block until its closing brace
+fn parse_message_body(
+    tokens: &[String],
+    pos: &mut usize,
+    full_name: &str,
+    message: &mut ProtoMessage,
+    messages: &mut Vec<(String, ProtoMessage)>,
+) -> Result<(), String> {
+    while *pos < tokens.len() {
+        match tokens[*pos].as_str() {
+            "}" => {
+                *pos += 1;
+                return Ok(());
+            }
+            "message" => {
+                *pos += 1;
+                parse_message(tokens, pos, full_name, messages)?;
+            }
+            "oneof" => {
+                // fields in oneof belong to message itself
+                *pos += 3;
+                parse_message_body(tokens, pos, full_name, message, messages)?;
+            }
+            "enum" | "extend" => skip_block(tokens, pos),
+            "option" | "reserved" | "extensions" | ";" => skip_statement(tokens, pos),
+            _ => {
+                if let Some((number, field)) = parse_field(tokens, pos) {
+                    message.fields.insert(number, field);
+                }
+            }
+        }
+    }
+    Err(format!("Unexpected end of schema in message {}", full_name))
+}
+
+/// parse field statement such as `repeated string name = 1;`
+fn parse_field(tokens: &[String], pos: &mut usize) -> Option<(u64, ProtoField)> {
+    let start = *pos;
+    skip_statement(tokens, pos);
+    let statement = &tokens[start..*pos];
+
+    let eq = statement.iter().position(|x| x == "=")?;
+    let number = statement.get(eq + 1)?.parse::<u64>().ok()?;
+    let name = statement.get(eq.checked_sub(1)?)?.to_owned();
+    let type_name = if statement[0] == "map" {
+        "map".to_owned()
+    } else {
+        let type_pos = match statement[0].as_str() {
+            "repeated" | "optional" | "required" => 1,
+            _ => 0,
+        };
+        let type_name = statement.get(type_pos)?;
+        // resolve by simple name
+        type_name.rsplit('.').next()?.to_owned()
+    };
+    Some((number, ProtoField { name, type_name }))
+}
+
+/// skip tokens until end of statement
+fn skip_statement(tokens: &[String], pos: &mut usize) {
+    while *pos < tokens.len() {
+        let token = tokens[*pos].as_str();
+        *pos += 1;
+        if token == ";" {
+            return;
+        }
+    }
+}
+
+/// skip tokens until end of braced block
+fn skip_block(tokens: &[String], pos: &mut usize) {
+    let mut depth = 0;
+    while *pos < tokens.len() {
+        let token = tokens[*pos].as_str();
+        *pos += 1;
+        match token {
+            "{" => depth += 1,
+            "}" => {
+                depth -= 1;
+                if depth == 0 {
+                    return;
+                }
+            }
+            ";" if depth == 0 => return,
+            _ => {}
+        }
+    }
+}
+
+/// split .proto schema into tokens, dropping comments
+fn tokenize_proto(src: &str) -> Vec<String> {
+    let mut ret = vec![];
+    let chars = src.chars().collect::<Vec<_>>();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '/' && chars.get(i + 1) == Some(&'/') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c == '/' && chars.get(i + 1) == Some(&'*') {
+            i += 2;
+            while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                i += 1;
+            }
+            i += 2;
+        } else if c == '"' || c == '\'' {
+            let start = i;
+            i += 1;
+            while i < chars.len() && chars[i] != c {
+                if chars[i] == '\\' {
+                    i += 1;
+                }
+                i += 1;
+            }
+            i += 1;
+            ret.push(chars[start..i.min(chars.len())].iter().collect());
+        } else if c.is_alphanumeric() || c == '_' || c == '.' || c == '-' {
+            let start = i;
+            while i < chars.len()
+                && (chars[i].is_alphanumeric()
+                    || chars[i] == '_'
+                    || chars[i] == '.'
+                    || chars[i] == '-')
+            {
+                i += 1;
+            }
+            ret.push(chars[start..i].iter().collect());
+        } else {
+            ret.push(c.to_string());
+            i += 1;
+        }
+    }
+    ret
+}
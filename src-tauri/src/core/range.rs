@@ -0,0 +1,50 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+
+use super::file::bytes_content;
+use super::os_path::os_path;
+use super::str::bytes_to_hex_dump_at;
+use super::types::{ContentRange, RangeUnit, ReadContent};
+
+/// read only range of file so that huge file is not read entirely
+/// lines are 1-based and inclusive, and bytes are 0-based and end-exclusive
+/// byte range is shown as hex dump whose addresses are offsets in file
+pub fn range_content(filepath: &str, range: &ContentRange) -> Result<ReadContent, String> {
+    if range.end < range.start {
+        return Err(format!("Invalid range: {} - {}", range.start, range.end));
+    }
+    let file = File::open(os_path(filepath))
+        .map_err(|err| format!("Failed to open {} ({})", filepath, err))?;
+    let read_err = |err: std::io::Error| format!("Failed to read {} ({})", filepath, err);
+
+    match range.unit {
+        RangeUnit::Lines => {
+            let mut reader = BufReader::new(file);
+            let mut buffer = vec![];
+            let mut line = vec![];
+            for line_number in 1..=range.end {
+                line.clear();
+                if reader.read_until(b'\n', &mut line).map_err(read_err)? == 0 {
+                    break;
+                }
+                if range.start <= line_number {
+                    buffer.extend_from_slice(&line);
+                }
+            }
+            Ok(bytes_content(&buffer))
+        }
+        RangeUnit::Bytes => {
+            let mut file = file;
+            file.seek(SeekFrom::Start(range.start)).map_err(read_err)?;
+            let mut buffer = vec![];
+            file.take(range.end - range.start)
+                .read_to_end(&mut buffer)
+                .map_err(read_err)?;
+            Ok(ReadContent {
+                charset: "(binary)".to_owned(),
+                content: bytes_to_hex_dump_at(&buffer, range.start as usize),
+                ..Default::default()
+            })
+        }
+    }
+}
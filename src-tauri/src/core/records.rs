@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+
+use super::file::filepaths_content_with_mode;
+use super::similarity::chars_similarity;
+use super::types::{CompareMode, EntryStatus, RecordDiff};
+
+/// min similarity in percentage to align records as modified
+const MIN_RECORD_SIMILARITY: f32 = 50.0;
+/// records around the same position compared on similarity to bound cost on large files
+const MAX_SIMILARITY_CANDIDATES: usize = 200;
+
+/// compare files as sets of records such as json lines and sql dumps, one record per line
+/// records are aligned by content rather than position so that reordering is not reported.
+/// identical records are aligned first, then json records by `key` field when specified
+/// such as `id` or `user.id`, and the rest by similarity.
+/// result is in order of new records followed by records only in old one
+pub fn record_diffs(old: &str, new: &str, key: Option<&str>) -> Result<Vec<RecordDiff>, String> {
+    let (read_contents, _) = filepaths_content_with_mode(old, new, Some(CompareMode::Text))?;
+    let old_records = records(&read_contents[0].content);
+    let new_records = records(&read_contents[1].content);
+
+    // index of old record aligned to each new record
+    let mut aligned: Vec<Option<usize>> = vec![None; new_records.len()];
+    let mut old_used = vec![false; old_records.len()];
+
+    let mut old_by_content: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (i, x) in old_records.iter().enumerate().rev() {
+        old_by_content.entry(x.1).or_default().push(i);
+    }
+    for (j, x) in new_records.iter().enumerate() {
+        if let Some(i) = old_by_content.get_mut(x.1).and_then(|x| x.pop()) {
+            aligned[j] = Some(i);
+            old_used[i] = true;
+        }
+    }
+
+    if let Some(key) = key {
+        let mut old_by_key: HashMap<String, usize> = HashMap::new();
+        for (i, x) in old_records.iter().enumerate() {
+            if let Some(value) = record_key(x.1, key).filter(|_| !old_used[i]) {
+                old_by_key.entry(value).or_insert(i);
+            }
+        }
+        for (j, x) in new_records.iter().enumerate() {
+            if aligned[j].is_some() {
+                continue;
+            }
+            if let Some(i) = record_key(x.1, key).and_then(|x| old_by_key.remove(&x)) {
+                aligned[j] = Some(i);
+                old_used[i] = true;
+            }
+        }
+    }
+
+    let mut similarities = vec![None; new_records.len()];
+    for (j, x) in new_records.iter().enumerate() {
+        if aligned[j].is_some() {
+            continue;
+        }
+        let best = old_records
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !old_used[*i])
+            .skip(
+                j.saturating_sub(MAX_SIMILARITY_CANDIDATES / 2)
+                    .min(old_records.len()),
+            )
+            .take(MAX_SIMILARITY_CANDIDATES)
+            .map(|(i, old)| (i, chars_similarity(old.1, x.1)))
+            .filter(|x| MIN_RECORD_SIMILARITY <= x.1)
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some((i, similarity)) = best {
+            aligned[j] = Some(i);
+            old_used[i] = true;
+            similarities[j] = Some(similarity);
+        }
+    }
+
+    let mut ret = new_records
+        .iter()
+        .enumerate()
+        .map(|(j, new_record)| {
+            let old_record = aligned[j].map(|i| old_records[i]);
+            let similarity = match old_record {
+                Some(old_record) => {
+                    similarities[j].unwrap_or_else(|| chars_similarity(old_record.1, new_record.1))
+                }
+                None => 0.0,
+            };
+            let status = match old_record {
+                Some(x) if x.1 == new_record.1 => EntryStatus::Equal,
+                Some(_) => EntryStatus::Modified,
+                None => EntryStatus::NewOnly,
+            };
+            RecordDiff {
+                status,
+                old_line: old_record.map(|x| x.0),
+                new_line: Some(new_record.0),
+                old_record: old_record.map(|x| x.1.to_owned()),
+                new_record: Some(new_record.1.to_owned()),
+                similarity,
+            }
+        })
+        .collect::<Vec<_>>();
+    ret.extend(
+        old_records
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !old_used[*i])
+            .map(|(_, x)| RecordDiff {
+                status: EntryStatus::OldOnly,
+                old_line: Some(x.0),
+                new_line: None,
+                old_record: Some(x.1.to_owned()),
+                new_record: None,
+                similarity: 0.0,
+            }),
+    );
+    Ok(ret)
+}
+
+/// non-empty lines with 1-based line numbers
+fn records(content: &str) -> Vec<(usize, &str)> {
+    content
+        .lines()
+        .enumerate()
+        .map(|(i, x)| (i + 1, x.trim_end()))
+        .filter(|x| !x.1.is_empty())
+        .collect()
+}
+
+/// value of field in json record by dot-separated path
+fn record_key(record: &str, key: &str) -> Option<String> {
+    let value = serde_json::from_str::<serde_json::Value>(record).ok()?;
+    let field = key
+        .split('.')
+        .try_fold(&value, |value, name| value.get(name))?;
+    Some(field.to_string())
+}
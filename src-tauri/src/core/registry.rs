@@ -0,0 +1,150 @@
+use std::collections::BTreeMap;
+use std::fs;
+
+use encoding_rs::{UTF_16BE, UTF_16LE, UTF_8};
+
+use super::field_diff::field_diffs;
+use super::os_path::os_path;
+use super::types::MetadataFieldDiff;
+
+/// value shown for key itself
+const KEY_VALUE: &str = "(key)";
+/// value shown for key or value removed by `[-key]` or `"name"=-`
+const DELETED_VALUE: &str = "(deleted)";
+/// abbreviated hive names expanded so that both notations are aligned
+const HIVE_ABBREVIATIONS: [(&str, &str); 5] = [
+    ("HKLM", "HKEY_LOCAL_MACHINE"),
+    ("HKCU", "HKEY_CURRENT_USER"),
+    ("HKCR", "HKEY_CLASSES_ROOT"),
+    ("HKU", "HKEY_USERS"),
+    ("HKCC", "HKEY_CURRENT_CONFIG"),
+];
+
+/// compare windows registry exports (`.reg`) by key and value regardless of ordering
+/// keys are full hive paths such as `HKEY_LOCAL_MACHINE\Software\App`
+/// and values are under them such as `HKEY_LOCAL_MACHINE\Software\App\"Version"`
+/// with `@` for default value. comment lines are ignored
+pub fn registry_diffs(old: &str, new: &str) -> Result<Vec<MetadataFieldDiff>, String> {
+    Ok(field_diffs(
+        &registry_entries(old)?,
+        &registry_entries(new)?,
+    ))
+}
+
+/// entries in registry export. utf-16 export by regedit is decoded by its bom
+fn registry_entries(filepath: &str) -> Result<BTreeMap<String, String>, String> {
+    if filepath.is_empty() {
+        return Ok(BTreeMap::new());
+    }
+    let bytes = fs::read(os_path(filepath))
+        .map_err(|err| format!("Failed to read {} ({})", filepath, err))?;
+    let encoding = if bytes.starts_with(&[0xff, 0xfe]) {
+        UTF_16LE
+    } else if bytes.starts_with(&[0xfe, 0xff]) {
+        UTF_16BE
+    } else {
+        UTF_8
+    };
+    let (content, _, _) = encoding.decode(&bytes);
+
+    let mut ret = BTreeMap::new();
+    let mut key = String::new();
+    let mut logical_line = String::new();
+    for line in content.lines() {
+        let line = line.trim();
+        if logical_line.is_empty() && (line.is_empty() || line.starts_with(';')) {
+            continue;
+        }
+        // hex data continues on next lines
+        match line.strip_suffix('\\') {
+            Some(x) => {
+                logical_line.push_str(x.trim());
+                continue;
+            }
+            None => logical_line.push_str(line),
+        }
+        let entry = std::mem::take(&mut logical_line);
+
+        if let Some(path) = entry.strip_prefix('[').and_then(|x| x.strip_suffix(']')) {
+            let (path, deleted) = match path.strip_prefix('-') {
+                Some(x) => (x, true),
+                None => (path, false),
+            };
+            key = expanded_hive(path.trim_end_matches('\\'));
+            let value = if deleted { DELETED_VALUE } else { KEY_VALUE };
+            ret.insert(key.clone(), value.to_owned());
+            continue;
+        }
+        // version header such as `Windows Registry Editor Version 5.00`
+        if key.is_empty() {
+            continue;
+        }
+        let Some((name, data)) = value_name_and_data(&entry) else {
+            continue;
+        };
+        ret.insert(format!("{}\\{}", key, name), readable_data(data));
+    }
+    Ok(ret)
+}
+
+/// split `"name"=data` or `@=data` with name kept in quotes
+fn value_name_and_data(entry: &str) -> Option<(&str, &str)> {
+    if let Some(data) = entry.strip_prefix("@=") {
+        return Some(("@", data));
+    }
+    if !entry.starts_with('"') {
+        return None;
+    }
+    // quotes in name are escaped with backslash
+    let bytes = entry.as_bytes();
+    let mut pos = 1;
+    while pos < bytes.len() && bytes[pos] != b'"' {
+        pos += if bytes[pos] == b'\\' { 2 } else { 1 };
+    }
+    let name = entry.get(..pos + 1)?;
+    let data = entry.get(pos + 1..)?.trim_start().strip_prefix('=')?;
+    Some((name, data.trim()))
+}
+
+/// data with utf-16 strings in `hex(2)` (expandable) and `hex(7)` (multi-string) decoded
+fn readable_data(data: &str) -> String {
+    if data == "-" {
+        return DELETED_VALUE.to_owned();
+    }
+    let (kind, hex) = match data.split_once(':') {
+        Some((kind, hex)) if kind == "hex(2)" || kind == "hex(7)" => (kind, hex),
+        _ => return data.to_owned(),
+    };
+    let bytes = hex
+        .split(',')
+        .map(|x| u8::from_str_radix(x.trim(), 16))
+        .collect::<Result<Vec<_>, _>>();
+    let Ok(bytes) = bytes else {
+        return data.to_owned();
+    };
+    let (decoded, _, _) = UTF_16LE.decode(&bytes);
+    let strings = decoded
+        .split('\0')
+        .filter(|x| !x.is_empty())
+        .map(|x| format!("{:?}", x))
+        .collect::<Vec<_>>();
+    if kind == "hex(2)" {
+        format!("expand_sz:{}", strings.join(""))
+    } else {
+        format!("multi_sz:[{}]", strings.join(", "))
+    }
+}
+
+/// key path with abbreviated hive name expanded
+fn expanded_hive(path: &str) -> String {
+    let (hive, rest) = path.split_once('\\').unwrap_or((path, ""));
+    let hive = HIVE_ABBREVIATIONS
+        .iter()
+        .find(|x| x.0.eq_ignore_ascii_case(hive))
+        .map_or(hive.to_uppercase(), |x| x.1.to_owned());
+    if rest.is_empty() {
+        hive
+    } else {
+        format!("{}\\{}", hive, rest)
+    }
+}
@@ -0,0 +1,49 @@
+use regex::{NoExpand, Regex, RegexBuilder};
+
+use super::diff::lines_diff_response;
+use super::types::{ReadContent, ReplacePreview, ReplaceQuery};
+
+/// replace matches in pane content and get result with diff against original as preview
+/// pattern is plain text unless `regex` is set, where replacement may refer to groups as `$1`
+pub fn replace_preview(content: &str, query: &ReplaceQuery) -> Result<ReplacePreview, String> {
+    let pattern = query_regex(query)?;
+    let replaced_count = pattern.find_iter(content).count();
+    let replaced = if query.regex {
+        pattern.replace_all(content, query.replacement.as_str())
+    } else {
+        pattern.replace_all(content, NoExpand(&query.replacement))
+    }
+    .to_string();
+
+    let read = |content: &str| ReadContent {
+        content: content.to_owned(),
+        ..Default::default()
+    };
+    Ok(ReplacePreview {
+        diff: lines_diff_response(read(content), read(&replaced)),
+        content: replaced,
+        replaced_count,
+    })
+}
+
+/// compile query into regex. `^` and `$` match at line boundaries
+fn query_regex(query: &ReplaceQuery) -> Result<Regex, String> {
+    if query.pattern.is_empty() {
+        return Err("Search pattern is empty".to_owned());
+    }
+    let pattern = if query.regex {
+        query.pattern.to_owned()
+    } else {
+        regex::escape(&query.pattern)
+    };
+    let pattern = if query.whole_word {
+        format!(r"\b(?:{})\b", pattern)
+    } else {
+        pattern
+    };
+    RegexBuilder::new(&pattern)
+        .case_insensitive(!query.case_sensitive)
+        .multi_line(true)
+        .build()
+        .map_err(|err| format!("Invalid pattern {} ({})", query.pattern, err))
+}
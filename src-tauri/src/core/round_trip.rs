@@ -0,0 +1,133 @@
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE};
+use similar::{capture_diff_slices, Algorithm, DiffTag};
+
+/// line of file as stored, mapped to its byte range including line ending
+struct StoredLine {
+    text: String,
+    // length of text part in bytes as stored
+    text_length: usize,
+    start: usize,
+    end: usize,
+}
+
+/// encode edited content into bytes to be saved over original bytes of file
+/// lines untouched in edit are written as their original bytes so that bom is kept
+/// and saving without edit changes nothing. lines are matched with their line endings,
+/// so that edits only on them such as trailing newline or crlf to lf are saved too
+pub fn round_trip_bytes(original: &[u8], content: &str, encoding: &'static Encoding) -> Vec<u8> {
+    let encode = |s: &str| encoding.encode(s).0.into_owned();
+    // utf-16 is not split by line feed bytes and encoder of encoding_rs writes utf-8 for it
+    if original.is_empty() || encoding == UTF_16LE || encoding == UTF_16BE {
+        return encode(content);
+    }
+    let Some((bom, stored_lines)) = stored_lines(original, encoding) else {
+        return encode(content);
+    };
+    // bom may be kept in content as char when it is decoded as utf-8
+    let content = if bom.is_empty() {
+        content
+    } else {
+        content.strip_prefix('\u{FEFF}').unwrap_or(content)
+    };
+    let edited_lines = edited_lines(content);
+
+    // line endings are ascii bytes in encodings supported here
+    let old_lines = stored_lines
+        .iter()
+        .map(|x| {
+            let eol = &original[x.start + x.text_length..x.end];
+            (
+                x.text.as_str(),
+                std::str::from_utf8(eol).unwrap_or_default(),
+            )
+        })
+        .collect::<Vec<_>>();
+    let ops = capture_diff_slices(Algorithm::Myers, &old_lines, &edited_lines);
+
+    let mut ret = bom.to_vec();
+    for op in ops {
+        if op.tag() == DiffTag::Equal {
+            for stored in &stored_lines[op.old_range()] {
+                ret.extend_from_slice(&original[stored.start..stored.end]);
+            }
+            continue;
+        }
+        for (text, eol) in &edited_lines[op.new_range()] {
+            ret.extend(encode(text));
+            ret.extend(encode(eol));
+        }
+    }
+    ret
+}
+
+/// split original bytes into bom and lines decoded one by one
+/// none when lines decoded separately do not match whole text such as stateful encoding
+fn stored_lines<'a>(
+    original: &'a [u8],
+    encoding: &'static Encoding,
+) -> Option<(&'a [u8], Vec<StoredLine>)> {
+    let bom_length = Encoding::for_bom(original).map_or(0, |x| x.1);
+    let (bom, body) = original.split_at(bom_length);
+
+    let mut lines = vec![];
+    let mut start = 0;
+    let mut i = 0;
+    while i < body.len() {
+        let end = match body[i] {
+            b'\r' if body.get(i + 1) == Some(&b'\n') => i + 2,
+            b'\r' | b'\n' => i + 1,
+            _ => {
+                i += 1;
+                continue;
+            }
+        };
+        lines.push((start, i, end));
+        start = end;
+        i = end;
+    }
+    if start < body.len() {
+        lines.push((start, body.len(), body.len()));
+    }
+
+    let ret = lines
+        .into_iter()
+        .map(|(start, text_end, end)| StoredLine {
+            text: encoding
+                .decode_without_bom_handling(&body[start..text_end])
+                .0
+                .into_owned(),
+            text_length: text_end - start,
+            start: bom_length + start,
+            end: bom_length + end,
+        })
+        .collect::<Vec<_>>();
+    // line endings are ascii bytes in encodings supported here
+    let joined = ret
+        .iter()
+        .map(|x| {
+            let eol = &original[x.start + x.text_length..x.end];
+            format!("{}{}", x.text, String::from_utf8_lossy(eol))
+        })
+        .collect::<String>();
+    if joined == encoding.decode_without_bom_handling(body).0 {
+        Some((bom, ret))
+    } else {
+        None
+    }
+}
+
+/// lines of edited content without line endings, with line ending following each
+/// which is empty on last line without it
+fn edited_lines(content: &str) -> Vec<(&str, &str)> {
+    let mut ret = vec![];
+    let mut rest = content;
+    while let Some(i) = rest.find(['\r', '\n']) {
+        let eol_length = if rest[i..].starts_with("\r\n") { 2 } else { 1 };
+        ret.push((&rest[..i], &rest[i..i + eol_length]));
+        rest = &rest[i + eol_length..];
+    }
+    if !rest.is_empty() {
+        ret.push((rest, ""));
+    }
+    ret
+}
@@ -0,0 +1,61 @@
+use super::os_path::{os_path, os_str_string};
+use super::types::{ComparisonRule, ReadContent};
+
+/// first rule matching new file path, or old one when new side is empty
+pub fn matching_rule<'a>(
+    rules: &'a [ComparisonRule],
+    old: &str,
+    new: &str,
+) -> Option<&'a ComparisonRule> {
+    let filepath = if new.is_empty() { old } else { new };
+    rules.iter().find(|x| rule_matches(&x.pattern, filepath))
+}
+
+/// check if pattern matches file path
+/// pattern is extension such as `csv` or `.csv`, glob on file name such as `*.log.?`,
+/// or glob on path such as `**/logs/*.txt` when including `/`
+fn rule_matches(pattern: &str, filepath: &str) -> bool {
+    let filepath = filepath.replace('\\', "/");
+    if !pattern.contains(['*', '?', '/']) {
+        let extension = pattern.trim_start_matches('.').to_lowercase();
+        return filepath
+            .to_lowercase()
+            .ends_with(&format!(".{}", extension));
+    }
+    if pattern.contains('/') {
+        return glob_match(pattern.as_bytes(), filepath.as_bytes());
+    }
+    let filename = os_path(&filepath)
+        .file_name()
+        .map(os_str_string)
+        .unwrap_or_default();
+    glob_match(pattern.as_bytes(), filename.as_bytes())
+}
+
+/// glob match where `*` and `?` do not cross `/` and `**` does
+pub fn glob_match(pattern: &[u8], s: &[u8]) -> bool {
+    match pattern {
+        [] => s.is_empty(),
+        [b'*', b'*', rest @ ..] => {
+            let rest = rest.strip_prefix(b"/").unwrap_or(rest);
+            (0..=s.len()).any(|i| glob_match(rest, &s[i..]))
+        }
+        [b'*', rest @ ..] => (0..=s.len())
+            .take_while(|i| *i == 0 || s[i - 1] != b'/')
+            .any(|i| glob_match(rest, &s[i..])),
+        [b'?', rest @ ..] => matches!(s, [c, tail @ ..] if *c != b'/' && glob_match(rest, tail)),
+        [c, rest @ ..] => matches!(s, [x, tail @ ..] if x == c && glob_match(rest, tail)),
+    }
+}
+
+/// remove lines containing any of ignored texts such as timestamps
+pub fn apply_ignored_texts(read: &mut ReadContent, rule: &ComparisonRule) {
+    if rule.ignored_texts.is_empty() {
+        return;
+    }
+    read.content = read
+        .content
+        .split_inclusive('\n')
+        .filter(|line| !rule.ignored_texts.iter().any(|x| line.contains(x.as_str())))
+        .collect();
+}
@@ -0,0 +1,29 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use super::types::ReadContent;
+
+/// app state to keep content as read from or saved to file
+/// so that edits in pane can be reviewed against it
+#[derive(Default)]
+pub struct SavedContents(Mutex<HashMap<String, ReadContent>>);
+
+impl SavedContents {
+    /// remember content of file
+    /// non-text content such as hex dump is labeled with charset in parentheses and not tracked
+    /// decompressed content is not tracked either because it differs from file
+    pub fn track(&self, filepath: &str, read: &ReadContent) {
+        if filepath.is_empty() || read.charset.starts_with('(') || read.compression.is_some() {
+            return;
+        }
+        self.0
+            .lock()
+            .unwrap()
+            .insert(filepath.to_owned(), read.clone());
+    }
+
+    /// get content of file remembered
+    pub fn get(&self, filepath: &str) -> Option<ReadContent> {
+        self.0.lock().unwrap().get(filepath).cloned()
+    }
+}
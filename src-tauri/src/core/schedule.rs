@@ -0,0 +1,197 @@
+use std::collections::HashMap;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chrono::Local;
+use similar::DiffTag;
+use tauri::Emitter;
+
+use super::diff::{dir_tree_diffs, lines_diffs};
+use super::file::{filepaths_content, fnv1a_digest};
+use super::store;
+use super::types::{
+    EntryStatus, RunSummary, Schedule, ScheduleInput, ScheduleKind, ScheduleRunEvent, ScheduleStore,
+};
+
+/// file name to persist schedules
+const SCHEDULES_STORE_FILENAME: &str = "schedules.json";
+/// event emitted when scheduled comparison is run in background
+pub const SCHEDULE_RUN_EVENT: &str = "schedule-run";
+/// max run summaries kept per schedule
+const MAX_HISTORY_LENGTH: usize = 20;
+/// interval to check if schedules are due
+/// shorter intervals than this are rounded up
+const SCHEDULER_TICK_SECS: u64 = 30;
+
+/// get all schedules
+pub fn schedules(app_handle: &tauri::AppHandle) -> Result<Vec<Schedule>, String> {
+    let store: ScheduleStore = store::load(app_handle, SCHEDULES_STORE_FILENAME)?;
+    Ok(store.schedules)
+}
+
+/// save comparison session to be re-run
+pub fn add_schedule(
+    app_handle: &tauri::AppHandle,
+    input: ScheduleInput,
+) -> Result<Schedule, String> {
+    let mut store: ScheduleStore = store::load(app_handle, SCHEDULES_STORE_FILENAME)?;
+    let id = store
+        .schedules
+        .iter()
+        .map(|x| x.id + 1)
+        .max()
+        .unwrap_or_default();
+    let schedule = Schedule {
+        id,
+        kind: input.kind,
+        old: input.old,
+        new: input.new,
+        interval_secs: input.interval_secs.filter(|x| 0 < *x),
+        at_launch: input.at_launch,
+        history: vec![],
+    };
+    store.schedules.push(schedule.clone());
+    store::save(app_handle, SCHEDULES_STORE_FILENAME, &store)?;
+    Ok(schedule)
+}
+
+/// remove saved comparison session
+pub fn remove_schedule(app_handle: &tauri::AppHandle, id: u64) -> Result<(), String> {
+    let mut store: ScheduleStore = store::load(app_handle, SCHEDULES_STORE_FILENAME)?;
+    let schedules_count = store.schedules.len();
+    store.schedules.retain(|x| x.id != id);
+    if store.schedules.len() == schedules_count {
+        return Err(format!("Schedule not found: {}", id));
+    }
+    store::save(app_handle, SCHEDULES_STORE_FILENAME, &store)
+}
+
+/// run saved comparison and append its summary to history
+pub fn run_schedule(app_handle: &tauri::AppHandle, id: u64) -> Result<RunSummary, String> {
+    let store: ScheduleStore = store::load(app_handle, SCHEDULES_STORE_FILENAME)?;
+    let schedule = store
+        .schedules
+        .iter()
+        .find(|x| x.id == id)
+        .ok_or(format!("Schedule not found: {}", id))?;
+    let summary = run_summary(schedule);
+
+    // reload because comparison may take long
+    let mut store: ScheduleStore = store::load(app_handle, SCHEDULES_STORE_FILENAME)?;
+    if let Some(schedule) = store.schedules.iter_mut().find(|x| x.id == id) {
+        schedule.history.push(summary.clone());
+        let overflow = schedule.history.len().saturating_sub(MAX_HISTORY_LENGTH);
+        schedule.history.drain(..overflow);
+        store::save(app_handle, SCHEDULES_STORE_FILENAME, &store)?;
+    }
+    Ok(summary)
+}
+
+/// start background thread to run schedules at launch and at intervals
+pub fn start_scheduler(app_handle: tauri::AppHandle) {
+    thread::spawn(move || {
+        let started_at = Instant::now();
+        let mut last_runs: HashMap<u64, Instant> = HashMap::new();
+
+        match schedules(&app_handle) {
+            Ok(schedules) => {
+                for schedule in schedules.iter().filter(|x| x.at_launch) {
+                    run_and_emit(&app_handle, schedule.id);
+                    last_runs.insert(schedule.id, Instant::now());
+                }
+            }
+            Err(err) => tracing::warn!("Failed to load schedules ({})", err),
+        }
+
+        loop {
+            thread::sleep(Duration::from_secs(SCHEDULER_TICK_SECS));
+            let schedules = match schedules(&app_handle) {
+                Ok(x) => x,
+                Err(err) => {
+                    tracing::warn!("Failed to load schedules ({})", err);
+                    continue;
+                }
+            };
+            for schedule in schedules.iter() {
+                let interval_secs = match schedule.interval_secs {
+                    Some(x) => x,
+                    None => continue,
+                };
+                let last_run = last_runs.get(&schedule.id).unwrap_or(&started_at);
+                if last_run.elapsed() < Duration::from_secs(interval_secs) {
+                    continue;
+                }
+                run_and_emit(&app_handle, schedule.id);
+                last_runs.insert(schedule.id, Instant::now());
+            }
+        }
+    });
+}
+
+/// run schedule and notify frontend of its summary
+fn run_and_emit(app_handle: &tauri::AppHandle, id: u64) {
+    match run_schedule(app_handle, id) {
+        Ok(summary) => {
+            let event = ScheduleRunEvent { id, summary };
+            if let Err(err) = app_handle.emit(SCHEDULE_RUN_EVENT, event) {
+                tracing::warn!("Failed to emit schedule run ({})", err);
+            }
+        }
+        Err(err) => tracing::warn!("Failed to run schedule {} ({})", id, err),
+    }
+}
+
+/// compare session and summarize result
+/// errors such as missing file are kept in summary instead of failing
+fn run_summary(schedule: &Schedule) -> RunSummary {
+    let result = match schedule.kind {
+        ScheduleKind::Files => files_result(&schedule.old, &schedule.new),
+        ScheduleKind::Dirs => dirs_result(&schedule.old, &schedule.new),
+    };
+    let (diffs_count, fingerprint, error) = match result {
+        Ok((diffs_count, fingerprint)) => (diffs_count, fingerprint, None),
+        Err(err) => (0, String::new(), Some(err)),
+    };
+    let changed = match schedule.history.last() {
+        Some(last) => last.fingerprint != fingerprint || last.error != error,
+        None => false,
+    };
+    RunSummary {
+        ran_at: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        diffs_count,
+        fingerprint,
+        changed,
+        error,
+    }
+}
+
+/// count hunks on files and digest them
+fn files_result(old: &str, new: &str) -> Result<(usize, String), String> {
+    let read_contents = filepaths_content(old, new)?;
+    let diffs = lines_diffs(&read_contents[0].content, &read_contents[1].content);
+    let diffs_count = diffs
+        .iter()
+        .filter(|x| x.diff_kind != DiffTag::Equal)
+        .count();
+    let serialized = serde_json::to_string(&diffs)
+        .map_err(|err| format!("Failed to serialize diffs ({})", err))?;
+    Ok((
+        diffs_count,
+        format!("{:016x}", fnv1a_digest(serialized.as_bytes())),
+    ))
+}
+
+/// count entries not equal on dirs and digest them
+fn dirs_result(old: &str, new: &str) -> Result<(usize, String), String> {
+    let entry_diffs = dir_tree_diffs(old, new, |_, _| ())?;
+    let not_equal = entry_diffs
+        .iter()
+        .filter(|x| x.status != EntryStatus::Equal)
+        .collect::<Vec<_>>();
+    let serialized = serde_json::to_string(&not_equal)
+        .map_err(|err| format!("Failed to serialize diffs ({})", err))?;
+    Ok((
+        not_equal.len(),
+        format!("{:016x}", fnv1a_digest(serialized.as_bytes())),
+    ))
+}
@@ -0,0 +1,64 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use super::types::{ReadContent, ScratchBuffer};
+
+/// prefix of path to scratch buffer on side
+pub const SCRATCH_URL_PREFIX: &str = "scratch://";
+/// charset of scratch buffer content
+const SCRATCH_CHARSET: &str = "UTF-8";
+
+/// app state to keep text edited in panes without backing file
+#[derive(Default)]
+pub struct ScratchBuffers {
+    buffers: Mutex<HashMap<u64, String>>,
+    next_id: AtomicU64,
+}
+
+impl ScratchBuffers {
+    /// create scratch buffer
+    pub fn create(&self, content: String) -> ScratchBuffer {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        self.buffers.lock().unwrap().insert(id, content);
+        ScratchBuffer {
+            id,
+            url: format!("{}{}", SCRATCH_URL_PREFIX, id),
+            name: format!("untitled-{}", id + 1),
+        }
+    }
+
+    /// replace content of scratch buffer
+    pub fn update(&self, id: u64, content: String) -> Result<(), String> {
+        match self.buffers.lock().unwrap().get_mut(&id) {
+            Some(x) => {
+                *x = content;
+                Ok(())
+            }
+            None => Err(format!("Scratch buffer not found: {}", id)),
+        }
+    }
+
+    /// get content of scratch buffer as read from file
+    pub fn content(&self, id: u64) -> Result<ReadContent, String> {
+        match self.buffers.lock().unwrap().get(&id) {
+            Some(x) => Ok(ReadContent {
+                charset: SCRATCH_CHARSET.to_owned(),
+                content: x.to_owned(),
+                ..Default::default()
+            }),
+            None => Err(format!("Scratch buffer not found: {}", id)),
+        }
+    }
+
+    /// discard scratch buffer
+    pub fn release(&self, id: u64) {
+        self.buffers.lock().unwrap().remove(&id);
+    }
+}
+
+/// get scratch buffer id from path such as `scratch://0`
+/// none when path is file path
+pub fn scratch_id(path: &str) -> Option<u64> {
+    path.strip_prefix(SCRATCH_URL_PREFIX)?.parse().ok()
+}
@@ -0,0 +1,196 @@
+use std::time::Duration;
+
+use super::diff::DEFAULT_DIFF_TIMEOUT_MILLIS;
+use super::store;
+use super::types::{
+    ComparisonRule, ExternalTool, NumberFormat, PaletteKind, PathPolicy, PathRemapping, Settings,
+    SizeUnit,
+};
+
+/// file name to persist settings
+const SETTINGS_STORE_FILENAME: &str = "settings.json";
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        NumberFormat {
+            decimal_separator: ".".to_owned(),
+            grouping_separator: ",".to_owned(),
+            grouping_size: 3,
+            size_unit: SizeUnit::Binary,
+        }
+    }
+}
+
+/// get settings persisted in backend
+pub fn settings(app_handle: &tauri::AppHandle) -> Result<Settings, String> {
+    store::load(app_handle, SETTINGS_STORE_FILENAME)
+}
+
+/// get number format
+/// default is used when settings are broken so that listing does not fail
+pub fn number_format(app_handle: &tauri::AppHandle) -> NumberFormat {
+    match settings(app_handle) {
+        Ok(x) => x.number_format,
+        Err(err) => {
+            tracing::warn!("Failed to load settings ({})", err);
+            NumberFormat::default()
+        }
+    }
+}
+
+/// update number format
+pub fn set_number_format(
+    app_handle: &tauri::AppHandle,
+    number_format: NumberFormat,
+) -> Result<(), String> {
+    let mut settings = settings(app_handle)?;
+    settings.number_format = number_format;
+    store::save(app_handle, SETTINGS_STORE_FILENAME, &settings)
+}
+
+/// update palette kind on diff colors
+pub fn set_palette_kind(
+    app_handle: &tauri::AppHandle,
+    palette_kind: PaletteKind,
+) -> Result<(), String> {
+    let mut settings = settings(app_handle)?;
+    settings.palette_kind = palette_kind;
+    store::save(app_handle, SETTINGS_STORE_FILENAME, &settings)
+}
+
+/// get rules of default comparison options per extension
+/// no rule is applied when settings are broken so that comparison does not fail
+pub fn comparison_rules(app_handle: &tauri::AppHandle) -> Vec<ComparisonRule> {
+    match settings(app_handle) {
+        Ok(x) => x.comparison_rules,
+        Err(err) => {
+            tracing::warn!("Failed to load settings ({})", err);
+            vec![]
+        }
+    }
+}
+
+/// update rules of default comparison options. former rules take precedence
+pub fn set_comparison_rules(
+    app_handle: &tauri::AppHandle,
+    comparison_rules: Vec<ComparisonRule>,
+) -> Result<(), String> {
+    let mut settings = settings(app_handle)?;
+    settings.comparison_rules = comparison_rules;
+    store::save(app_handle, SETTINGS_STORE_FILENAME, &settings)
+}
+
+/// get external diff tools
+pub fn external_tools(app_handle: &tauri::AppHandle) -> Result<Vec<ExternalTool>, String> {
+    Ok(settings(app_handle)?.external_tools)
+}
+
+/// update external diff tools
+pub fn set_external_tools(
+    app_handle: &tauri::AppHandle,
+    external_tools: Vec<ExternalTool>,
+) -> Result<(), String> {
+    let mut settings = settings(app_handle)?;
+    settings.external_tools = external_tools;
+    store::save(app_handle, SETTINGS_STORE_FILENAME, &settings)
+}
+
+/// get time budget of lines diff
+/// default is used when settings are broken so that comparison does not fail
+pub fn diff_timeout(app_handle: &tauri::AppHandle) -> Duration {
+    let millis = match settings(app_handle) {
+        Ok(x) => x.diff_timeout_millis,
+        Err(err) => {
+            tracing::warn!("Failed to load settings ({})", err);
+            None
+        }
+    };
+    Duration::from_millis(millis.unwrap_or(DEFAULT_DIFF_TIMEOUT_MILLIS))
+}
+
+/// update time budget of lines diff. none resets it to default
+pub fn set_diff_timeout(
+    app_handle: &tauri::AppHandle,
+    diff_timeout_millis: Option<u64>,
+) -> Result<(), String> {
+    let mut settings = settings(app_handle)?;
+    settings.diff_timeout_millis = diff_timeout_millis;
+    store::save(app_handle, SETTINGS_STORE_FILENAME, &settings)
+}
+
+/// get rules to treat roots and drive letters of paths as equivalent
+/// nothing is remapped when settings are broken so that comparison does not fail
+pub fn path_remapping(app_handle: &tauri::AppHandle) -> PathRemapping {
+    match settings(app_handle) {
+        Ok(x) => x.path_remapping,
+        Err(err) => {
+            tracing::warn!("Failed to load settings ({})", err);
+            PathRemapping::default()
+        }
+    }
+}
+
+/// update rules to treat roots and drive letters of paths as equivalent
+pub fn set_path_remapping(
+    app_handle: &tauri::AppHandle,
+    path_remapping: PathRemapping,
+) -> Result<(), String> {
+    let mut settings = settings(app_handle)?;
+    settings.path_remapping = path_remapping;
+    store::save(app_handle, SETTINGS_STORE_FILENAME, &settings)
+}
+
+/// get allowed and blocked roots of paths read
+/// nothing is restricted when settings are broken so that files can still be compared
+pub fn path_policy(app_handle: &tauri::AppHandle) -> PathPolicy {
+    match settings(app_handle) {
+        Ok(x) => x.path_policy,
+        Err(err) => {
+            tracing::warn!("Failed to load settings ({})", err);
+            PathPolicy::default()
+        }
+    }
+}
+
+/// get policy on paths read without app such as on running as agent
+pub fn headless_path_policy() -> PathPolicy {
+    match store::load_headless::<Settings>(SETTINGS_STORE_FILENAME) {
+        Ok(x) => x.path_policy,
+        Err(err) => {
+            tracing::warn!("Failed to load settings ({})", err);
+            PathPolicy::default()
+        }
+    }
+}
+
+/// update allowed and blocked roots of paths read
+pub fn set_path_policy(
+    app_handle: &tauri::AppHandle,
+    path_policy: PathPolicy,
+) -> Result<(), String> {
+    let mut settings = settings(app_handle)?;
+    settings.path_policy = path_policy;
+    store::save(app_handle, SETTINGS_STORE_FILENAME, &settings)
+}
+
+/// get conventional number format of locale such as `en`, `de-DE` or `fr_FR`
+pub fn locale_number_format(locale: &str) -> NumberFormat {
+    let language = locale
+        .split(['-', '_'])
+        .next()
+        .unwrap_or_default()
+        .to_lowercase();
+    let (decimal_separator, grouping_separator) = match language.as_str() {
+        "de" | "es" | "it" | "nl" | "pt" | "id" | "tr" | "da" | "el" => (",", "."),
+        // narrow no-break space
+        "fr" => (",", "\u{202f}"),
+        // no-break space
+        "ru" | "pl" | "cs" | "sk" | "uk" | "sv" | "fi" | "nb" | "no" | "hu" => (",", "\u{a0}"),
+        _ => (".", ","),
+    };
+    NumberFormat {
+        decimal_separator: decimal_separator.to_owned(),
+        grouping_separator: grouping_separator.to_owned(),
+        ..Default::default()
+    }
+}
@@ -0,0 +1,334 @@
+use std::path::{Path, PathBuf};
+
+use chrono::Local;
+use tauri::{Emitter, Manager};
+
+use super::diff::startup_compare_set_item;
+use super::os_path::{os_path, path_string};
+use super::path_policy::ensure_permitted;
+use super::store;
+use super::types::{CompareSet, ShellSelection};
+
+/// executable arg to remember file as left side of next comparison
+pub const SELECT_LEFT_ARG: &str = "--select-left";
+/// executable arg to compare file with one selected as left side
+pub const COMPARE_WITH_SELECTED_ARG: &str = "--compare-with-selected";
+/// event emitted when another launch asks running app to compare
+pub const COMPARE_SET_EVENT: &str = "compare-set";
+/// event emitted when file is selected as left side
+pub const LEFT_SELECTED_EVENT: &str = "left-selected";
+/// error on registering context menu on os whose file manager is not supported such as macos
+/// so that ui tells it apart from failure on registering
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+const CONTEXT_MENU_UNSUPPORTED_ERROR: &str =
+    "Context menu registration is not supported on this os";
+
+/// file name to persist file selected via context menu
+const SHELL_SELECTION_STORE_FILENAME: &str = "shell_selection.json";
+/// context menu label to select left side
+const SELECT_LEFT_LABEL: &str = "Select left for compare";
+/// context menu label to compare with left side selected
+const COMPARE_WITH_SELECTED_LABEL: &str = "Compare with selected";
+
+/// check if arg is passed by context menu entries
+pub fn is_shell_arg(arg: &str) -> bool {
+    arg == SELECT_LEFT_ARG || arg == COMPARE_WITH_SELECTED_ARG
+}
+
+/// handle args passed by context menu entries
+/// returns compare set to open when comparison is requested
+/// file path not permitted by path policy is refused because it is passed by another process
+pub fn shell_args_compare_set(
+    app_handle: &tauri::AppHandle,
+    args: &[String],
+) -> Result<Option<CompareSet>, String> {
+    let (flag, filepath) = match args {
+        [flag, filepath, ..] if is_shell_arg(flag) => (flag.as_str(), filepath.as_str()),
+        _ => return Ok(None),
+    };
+    ensure_permitted(app_handle, filepath)?;
+
+    match flag {
+        SELECT_LEFT_ARG => {
+            select_left(app_handle, filepath)?;
+            Ok(None)
+        }
+        COMPARE_WITH_SELECTED_ARG => compare_with_selected(app_handle, filepath).map(Some),
+        _ => Ok(None),
+    }
+}
+
+/// remember file as left side until right side is chosen, even across launches
+/// running app is notified so that it can show left side pending
+pub fn select_left(
+    app_handle: &tauri::AppHandle,
+    filepath: &str,
+) -> Result<ShellSelection, String> {
+    let selection = ShellSelection {
+        left: filepath.to_owned(),
+        selected_at: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+    };
+    store::save(app_handle, SHELL_SELECTION_STORE_FILENAME, &selection)?;
+    if let Err(err) = app_handle.emit(LEFT_SELECTED_EVENT, &selection) {
+        tracing::warn!("Failed to emit left side selected ({})", err);
+    }
+    Ok(selection)
+}
+
+/// left side selected and waiting for right side
+/// selection of file removed since then is dropped
+pub fn left_selection(app_handle: &tauri::AppHandle) -> Result<Option<ShellSelection>, String> {
+    let selection: ShellSelection = store::load(app_handle, SHELL_SELECTION_STORE_FILENAME)?;
+    if selection.left.is_empty() {
+        return Ok(None);
+    }
+    if !Path::new(&os_path(&selection.left)).exists() {
+        clear_left_selection(app_handle)?;
+        return Ok(None);
+    }
+    Ok(Some(selection))
+}
+
+/// pair file as right side with left side selected
+/// selection is cleared so that next pair starts from left side again
+pub fn compare_with_selected(
+    app_handle: &tauri::AppHandle,
+    filepath: &str,
+) -> Result<CompareSet, String> {
+    let Some(selection) = left_selection(app_handle)? else {
+        return Err("Left side is not selected yet".to_owned());
+    };
+    // policy may have been changed since left side was selected
+    ensure_permitted(app_handle, &selection.left)?;
+    ensure_permitted(app_handle, filepath)?;
+    clear_left_selection(app_handle)?;
+    Ok(CompareSet {
+        old: startup_compare_set_item(&Some(selection.left.into())),
+        new: startup_compare_set_item(&Some(filepath.into())),
+        tool: None,
+    })
+}
+
+/// forget left side selected
+pub fn clear_left_selection(app_handle: &tauri::AppHandle) -> Result<(), String> {
+    store::save(
+        app_handle,
+        SHELL_SELECTION_STORE_FILENAME,
+        &ShellSelection::default(),
+    )
+}
+
+/// handle args forwarded from second launch through single instance channel
+pub fn handle_second_instance_args(app_handle: &tauri::AppHandle, args: Vec<String>, cwd: &str) {
+    // first arg is executable themself.
+    // relative paths such as `%f` given by file manager are of dir second launch ran in
+    let args = args
+        .into_iter()
+        .skip(1)
+        .map(|x| {
+            if is_shell_arg(&x) || cwd.is_empty() || os_path(&x).is_absolute() {
+                x
+            } else {
+                path_string(&Path::new(cwd).join(os_path(&x)))
+            }
+        })
+        .collect::<Vec<_>>();
+    match shell_args_compare_set(app_handle, &args) {
+        Ok(Some(compare_set)) => {
+            if let Err(err) = app_handle.emit(COMPARE_SET_EVENT, compare_set) {
+                tracing::warn!("Failed to emit compare set ({})", err);
+            }
+        }
+        Ok(None) => {}
+        Err(err) => tracing::warn!("Failed to handle args ({})", err),
+    }
+    if let Some(window) = app_handle.get_webview_window("main") {
+        let _ = window.unminimize();
+        let _ = window.set_focus();
+    }
+}
+
+/// check if context menu entries can be registered in os file manager
+pub fn is_context_menu_supported() -> bool {
+    cfg!(any(target_os = "windows", target_os = "linux"))
+}
+
+/// register context menu entries in os file manager
+/// fails with `CONTEXT_MENU_UNSUPPORTED_ERROR` on os not supported
+pub fn register_context_menu() -> Result<(), String> {
+    let exe = std::env::current_exe()
+        .map_err(|err| format!("Failed to get executable path ({})", err))?;
+    let exe = exe.to_string_lossy();
+
+    #[cfg(target_os = "windows")]
+    {
+        for (key, label, arg) in windows_context_menu_entries() {
+            reg(&["add", &key, "/ve", "/d", label, "/f"])?;
+            reg(&[
+                "add",
+                &format!(r"{}\command", key),
+                "/ve",
+                "/d",
+                &format!(r#""{}" {} "%1""#, exe, arg),
+                "/f",
+            ])?;
+        }
+        Ok(())
+    }
+    #[cfg(target_os = "linux")]
+    {
+        use std::fs;
+        use std::os::unix::fs::PermissionsExt;
+
+        // nautilus scripts
+        let scripts_dir = nautilus_scripts_dir()?;
+        fs::create_dir_all(&scripts_dir)
+            .map_err(|err| format!("Failed to create {} ({})", scripts_dir.display(), err))?;
+        for (label, arg) in [
+            (SELECT_LEFT_LABEL, SELECT_LEFT_ARG),
+            (COMPARE_WITH_SELECTED_LABEL, COMPARE_WITH_SELECTED_ARG),
+        ] {
+            let script_path = scripts_dir.join(label);
+            let script = format!("#!/bin/sh\nexec {} {} \"$1\"\n", shell_quoted(&exe), arg);
+            fs::write(&script_path, script)
+                .map_err(|err| format!("Failed to write {} ({})", script_path.display(), err))?;
+            fs::set_permissions(&script_path, fs::Permissions::from_mode(0o755))
+                .map_err(|err| format!("Failed to chmod {} ({})", script_path.display(), err))?;
+        }
+
+        // dolphin service menu
+        let service_menu_path = dolphin_service_menu_path()?;
+        if let Some(parent) = service_menu_path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|err| format!("Failed to create {} ({})", parent.display(), err))?;
+        }
+        let service_menu = format!(
+            "[Desktop Entry]\nType=Service\nMimeType=all/allfiles;\nActions=selectLeft;compareWithSelected;\n\n\
+             [Desktop Action selectLeft]\nName={}\nExec={} {} %f\n\n\
+             [Desktop Action compareWithSelected]\nName={}\nExec={} {} %f\n",
+            SELECT_LEFT_LABEL,
+            desktop_exec_quoted(&exe),
+            SELECT_LEFT_ARG,
+            COMPARE_WITH_SELECTED_LABEL,
+            desktop_exec_quoted(&exe),
+            COMPARE_WITH_SELECTED_ARG
+        );
+        fs::write(&service_menu_path, service_menu)
+            .map_err(|err| format!("Failed to write {} ({})", service_menu_path.display(), err))?;
+        fs::set_permissions(&service_menu_path, fs::Permissions::from_mode(0o755))
+            .map_err(|err| format!("Failed to chmod {} ({})", service_menu_path.display(), err))?;
+        Ok(())
+    }
+    #[cfg(not(any(target_os = "windows", target_os = "linux")))]
+    {
+        let _ = exe;
+        Err(CONTEXT_MENU_UNSUPPORTED_ERROR.to_owned())
+    }
+}
+
+/// remove context menu entries registered
+pub fn unregister_context_menu() -> Result<(), String> {
+    #[cfg(target_os = "windows")]
+    {
+        for (key, _, _) in windows_context_menu_entries() {
+            reg(&["delete", &key, "/f"])?;
+        }
+        Ok(())
+    }
+    #[cfg(target_os = "linux")]
+    {
+        use std::fs;
+
+        let scripts_dir = nautilus_scripts_dir()?;
+        for label in [SELECT_LEFT_LABEL, COMPARE_WITH_SELECTED_LABEL] {
+            let script_path = scripts_dir.join(label);
+            if script_path.exists() {
+                fs::remove_file(&script_path).map_err(|err| {
+                    format!("Failed to remove {} ({})", script_path.display(), err)
+                })?;
+            }
+        }
+        let service_menu_path = dolphin_service_menu_path()?;
+        if service_menu_path.exists() {
+            fs::remove_file(&service_menu_path).map_err(|err| {
+                format!("Failed to remove {} ({})", service_menu_path.display(), err)
+            })?;
+        }
+        Ok(())
+    }
+    #[cfg(not(any(target_os = "windows", target_os = "linux")))]
+    {
+        Err(CONTEXT_MENU_UNSUPPORTED_ERROR.to_owned())
+    }
+}
+
+/// registry keys, labels and args of context menu entries
+#[cfg(target_os = "windows")]
+fn windows_context_menu_entries() -> [(String, &'static str, &'static str); 2] {
+    const SHELL_KEY: &str = r"HKCU\Software\Classes\*\shell";
+    [
+        (
+            format!(r"{}\ForskScopeSelectLeft", SHELL_KEY),
+            SELECT_LEFT_LABEL,
+            SELECT_LEFT_ARG,
+        ),
+        (
+            format!(r"{}\ForskScopeCompareWithSelected", SHELL_KEY),
+            COMPARE_WITH_SELECTED_LABEL,
+            COMPARE_WITH_SELECTED_ARG,
+        ),
+    ]
+}
+
+/// run reg command
+#[cfg(target_os = "windows")]
+fn reg(args: &[&str]) -> Result<(), String> {
+    let output = std::process::Command::new("reg")
+        .args(args)
+        .output()
+        .map_err(|err| format!("Failed to run reg ({})", err))?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_owned());
+    }
+    Ok(())
+}
+
+/// home dir
+pub fn home_dir() -> Result<PathBuf, String> {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(PathBuf::from)
+        .ok_or("Failed to get home dir".to_owned())
+}
+
+/// directory for nautilus scripts
+fn nautilus_scripts_dir() -> Result<PathBuf, String> {
+    Ok(home_dir()?.join(".local/share/nautilus/scripts"))
+}
+
+/// file path of dolphin service menu
+fn dolphin_service_menu_path() -> Result<PathBuf, String> {
+    Ok(home_dir()?.join(".local/share/kio/servicemenus/forskscope.desktop"))
+}
+
+/// arg quoted for posix shell, where nothing is special inside single quotes but quote itself
+#[cfg(target_os = "linux")]
+fn shell_quoted(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
+
+/// arg quoted for `Exec` key of desktop entry, where `"`, `` ` ``, `$` and `\` are escaped
+/// inside double quotes and `%` is doubled not to be taken as field code
+#[cfg(target_os = "linux")]
+fn desktop_exec_quoted(arg: &str) -> String {
+    let escaped = arg
+        .chars()
+        .map(|x| match x {
+            '"' | '`' | '$' | '\\' => format!("\\{}", x),
+            '%' => "%%".to_owned(),
+            x => x.to_string(),
+        })
+        .collect::<String>();
+    // desktop entry value itself unescapes backslash once more
+    format!("\"{}\"", escaped.replace('\\', "\\\\"))
+}
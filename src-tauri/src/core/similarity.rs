@@ -0,0 +1,115 @@
+use std::collections::HashSet;
+use std::fs::read_dir;
+
+use similar::{DiffTag, TextDiff};
+
+use super::file::filepaths_content;
+use super::os_path::{os_path, os_str_string, path_string};
+use super::types::{LinesDiff, SimilarPair};
+
+/// similarity in percentage (0 - 100) between texts on lines
+pub fn similarity(old: &str, new: &str) -> f32 {
+    if old.is_empty() && new.is_empty() {
+        return 100.0;
+    }
+    TextDiff::configure().diff_lines(old, new).ratio() * 100.0
+}
+
+/// similarity in percentage (0 - 100) between short texts such as records on chars
+pub fn chars_similarity(old: &str, new: &str) -> f32 {
+    if old.is_empty() && new.is_empty() {
+        return 100.0;
+    }
+    TextDiff::configure().diff_chars(old, new).ratio() * 100.0
+}
+
+/// similarity in percentage (0 - 100) from lines diffs already built
+pub fn diffs_similarity(diffs: &[LinesDiff]) -> f32 {
+    let lines_count = diffs
+        .iter()
+        .map(|x| x.old_lines.len() + x.new_lines.len())
+        .sum::<usize>();
+    if lines_count == 0 {
+        return 100.0;
+    }
+    let equal_lines_count = diffs
+        .iter()
+        .filter(|x| x.diff_kind == DiffTag::Equal)
+        .map(|x| x.old_lines.len() + x.new_lines.len())
+        .sum::<usize>();
+    equal_lines_count as f32 / lines_count as f32 * 100.0
+}
+
+/// similarity in percentage (0 - 100) between files
+pub fn filepaths_similarity(old: &str, new: &str) -> Result<f32, String> {
+    let read_contents = filepaths_content(old, new)?;
+    Ok(similarity(
+        read_contents[0].content.as_str(),
+        read_contents[1].content.as_str(),
+    ))
+}
+
+/// pair files existing only in old dir with ones only in new dir by similarity
+/// in order to detect renamed files
+pub fn similar_pairs(
+    old_dir: &str,
+    new_dir: &str,
+    threshold: f32,
+) -> Result<Vec<SimilarPair>, String> {
+    let old_names = file_names(old_dir)?;
+    let new_names = file_names(new_dir)?;
+
+    let old_only = old_names
+        .iter()
+        .filter(|x| !new_names.contains(*x))
+        .collect::<Vec<_>>();
+    let new_only = new_names
+        .iter()
+        .filter(|x| !old_names.contains(*x))
+        .collect::<Vec<_>>();
+
+    let mut candidates = vec![];
+    for old_name in old_only.iter() {
+        let old_filepath = os_path(old_dir).join(os_path(old_name));
+        for new_name in new_only.iter() {
+            let new_filepath = os_path(new_dir).join(os_path(new_name));
+            let similarity =
+                filepaths_similarity(&path_string(&old_filepath), &path_string(&new_filepath))?;
+            if threshold <= similarity {
+                candidates.push(SimilarPair {
+                    old_name: old_name.to_string(),
+                    new_name: new_name.to_string(),
+                    similarity,
+                });
+            }
+        }
+    }
+
+    // pair greedily from the most similar
+    candidates.sort_by(|a, b| b.similarity.total_cmp(&a.similarity));
+    let mut paired_old = HashSet::new();
+    let mut paired_new = HashSet::new();
+    let ret = candidates
+        .into_iter()
+        .filter(|x| {
+            if paired_old.contains(&x.old_name) || paired_new.contains(&x.new_name) {
+                return false;
+            }
+            paired_old.insert(x.old_name.to_owned());
+            paired_new.insert(x.new_name.to_owned());
+            true
+        })
+        .collect();
+    Ok(ret)
+}
+
+/// names of files directly in directory
+fn file_names(dirpath: &str) -> Result<HashSet<String>, String> {
+    let entries =
+        read_dir(os_path(dirpath)).map_err(|err| format!("Invalid path: {} ({})", dirpath, err))?;
+    Ok(entries
+        .filter_map(|x| x.ok())
+        .filter(|x| x.path().is_file())
+        .map(|x| os_str_string(&x.file_name()))
+        .collect())
+}
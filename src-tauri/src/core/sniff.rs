@@ -0,0 +1,164 @@
+use std::fs::File;
+use std::io::Read;
+
+use encoding_rs::{Encoding, UTF_8};
+
+use super::os_path::os_path;
+use super::wide_text::{utf32_without_bom, wide_encoding_for_bom, WideEncoding};
+
+/// bytes read from head of file to identify its type
+const SNIFF_SAMPLE_BYTES: usize = 8192;
+/// mime type of text not identified further
+const TEXT_MIME_TYPE: &str = "text/plain";
+/// mime type of binary not identified
+const BINARY_MIME_TYPE: &str = "application/octet-stream";
+/// ratio of code units with zero high byte on which bom-less utf-16 is assumed
+const MIN_UTF16_ZERO_RATIO: f32 = 0.3;
+
+/// magic numbers at offset with mime types
+const MAGIC_NUMBERS: [(usize, &[u8], &str); 31] = [
+    (0, b"\x89PNG\r\n\x1a\n", "image/png"),
+    (0, b"\xff\xd8\xff", "image/jpeg"),
+    (0, b"GIF87a", "image/gif"),
+    (0, b"GIF89a", "image/gif"),
+    (0, b"II*\x00", "image/tiff"),
+    (0, b"MM\x00*", "image/tiff"),
+    (0, b"\x00\x00\x01\x00", "image/vnd.microsoft.icon"),
+    (0, b"%PDF-", "application/pdf"),
+    (0, b"PK\x03\x04", "application/zip"),
+    (0, b"PK\x05\x06", "application/zip"),
+    (0, b"\x1f\x8b", "application/gzip"),
+    (0, b"BZh", "application/x-bzip2"),
+    (0, b"\xfd7zXZ\x00", "application/x-xz"),
+    (0, b"\x28\xb5\x2f\xfd", "application/zstd"),
+    (0, b"7z\xbc\xaf\x27\x1c", "application/x-7z-compressed"),
+    (0, b"Rar!\x1a\x07", "application/vnd.rar"),
+    (257, b"ustar", "application/x-tar"),
+    (
+        0,
+        b"\xd0\xcf\x11\xe0\xa1\xb1\x1a\xe1",
+        "application/x-ole-storage",
+    ),
+    (0, b"SQLite format 3\x00", "application/vnd.sqlite3"),
+    (0, b"\x7fELF", "application/x-elf"),
+    (0, b"MZ", "application/vnd.microsoft.portable-executable"),
+    (0, b"\xcf\xfa\xed\xfe", "application/x-mach-binary"),
+    (0, b"\xfe\xed\xfa\xcf", "application/x-mach-binary"),
+    (0, b"\xca\xfe\xba\xbe", "application/java-vm"),
+    (0, b"\x00asm", "application/wasm"),
+    (0, b"ID3", "audio/mpeg"),
+    (0, b"OggS", "audio/ogg"),
+    (0, b"fLaC", "audio/flac"),
+    (4, b"ftyp", "video/mp4"),
+    (0, b"\x1a\x45\xdf\xa3", "video/webm"),
+    (0, b"{\\rtf", "application/rtf"),
+];
+
+/// type of content identified by sniffing its head
+pub struct SniffedType {
+    pub mime_type: &'static str,
+    pub is_text: bool,
+    // set when text is found in utf-16 or utf-32 by bom or by zero bytes
+    pub encoding: Option<WideEncoding>,
+}
+
+/// identify type of file by sniffing its head
+/// empty or unreadable file is regarded as text
+pub fn sniff_file(filepath: &str) -> SniffedType {
+    let mut head = vec![];
+    if let Ok(file) = File::open(os_path(filepath)) {
+        let _ = file.take(SNIFF_SAMPLE_BYTES as u64).read_to_end(&mut head);
+    }
+    sniff_bytes(&head)
+}
+
+/// identify type of bytes by bom, magic numbers and distribution of zero bytes
+/// only head of bytes is sampled
+pub fn sniff_bytes(bytes: &[u8]) -> SniffedType {
+    let head = &bytes[..bytes.len().min(SNIFF_SAMPLE_BYTES)];
+    let text = |mime_type, encoding| SniffedType {
+        mime_type,
+        is_text: true,
+        encoding,
+    };
+    let binary = |mime_type| SniffedType {
+        mime_type,
+        is_text: false,
+        encoding: None,
+    };
+
+    if let Some((encoding, _)) = wide_encoding_for_bom(head) {
+        return text(TEXT_MIME_TYPE, Some(encoding));
+    }
+    if Encoding::for_bom(head).is_some_and(|x| x.0 == UTF_8) {
+        return text(TEXT_MIME_TYPE, None);
+    }
+    // riff container is identified by its form type
+    if head.starts_with(b"RIFF") {
+        return match head.get(8..12) {
+            Some(b"WEBP") => binary("image/webp"),
+            Some(b"WAVE") => binary("audio/wav"),
+            Some(b"AVI ") => binary("video/x-msvideo"),
+            _ => binary(BINARY_MIME_TYPE),
+        };
+    }
+    // short ascii magic numbers such as `MZ` may be head of text as well
+    let looks_binary =
+        head.contains(&0) || std::str::from_utf8(head).is_err_and(|x| x.error_len().is_some());
+    if let Some((_, _, mime_type)) = MAGIC_NUMBERS.iter().find(|(offset, magic, _)| {
+        let is_weak = magic.len() < 4 && magic.is_ascii();
+        head.get(*offset..*offset + magic.len()) == Some(*magic) && (!is_weak || looks_binary)
+    }) {
+        // rtf is text though it is compared as document
+        return if *mime_type == "application/rtf" {
+            text(mime_type, None)
+        } else {
+            binary(mime_type)
+        };
+    }
+
+    if head.contains(&0) {
+        return match utf32_without_bom(head).or(utf16_without_bom(head)) {
+            Some(encoding) => text(TEXT_MIME_TYPE, Some(encoding)),
+            None => binary(BINARY_MIME_TYPE),
+        };
+    }
+    text(text_mime_type(head), None)
+}
+
+/// utf-16 guessed by zero high bytes of code units on either side as in ascii text
+fn utf16_without_bom(head: &[u8]) -> Option<WideEncoding> {
+    let units = head.chunks_exact(2).collect::<Vec<_>>();
+    if units.is_empty() || units.iter().any(|x| x == &[0, 0]) {
+        return None;
+    }
+    let ratio = |zero_index: usize| {
+        units.iter().filter(|x| x[zero_index] == 0).count() as f32 / units.len() as f32
+    };
+    let (le_ratio, be_ratio) = (ratio(1), ratio(0));
+    // zero bytes are mostly on one side
+    if MIN_UTF16_ZERO_RATIO <= le_ratio && be_ratio * 4.0 < le_ratio {
+        Some(WideEncoding::Utf16Le)
+    } else if MIN_UTF16_ZERO_RATIO <= be_ratio && le_ratio * 4.0 < be_ratio {
+        Some(WideEncoding::Utf16Be)
+    } else {
+        None
+    }
+}
+
+/// mime type of text by its head such as json and xml
+fn text_mime_type(head: &[u8]) -> &'static str {
+    let head = String::from_utf8_lossy(&head[..head.len().min(256)]).to_lowercase();
+    let head = head.trim_start();
+    if head.starts_with('{') || head.starts_with('[') {
+        "application/json"
+    } else if head.starts_with("<!doctype html") || head.starts_with("<html") {
+        "text/html"
+    } else if head.starts_with("<?xml") {
+        "application/xml"
+    } else if head.starts_with("#!") {
+        "text/x-script"
+    } else {
+        TEXT_MIME_TYPE
+    }
+}
@@ -0,0 +1,75 @@
+use super::diff::lines_diff_response;
+use super::str::split_lines_with_endings;
+use super::types::{LineRange, ReadContent, SnippetDiff};
+
+/// compare two line ranges in the same content such as near-duplicated blocks
+/// common leading whitespace of each snippet is removed when `dedent` is true
+/// so that block copied into deeper nesting is compared by its code only
+pub fn snippet_diff(
+    read: ReadContent,
+    old_range: &LineRange,
+    new_range: &LineRange,
+    dedent: bool,
+) -> Result<SnippetDiff, String> {
+    let lines = split_lines_with_endings(&read.content);
+    // charset and others are kept from content read
+    let template = ReadContent {
+        content: String::new(),
+        ..read
+    };
+    let snippet = |range: &LineRange| -> Result<(ReadContent, usize), String> {
+        if range.start == 0 || range.end < range.start || lines.len() < range.end {
+            return Err(format!(
+                "Invalid range: {} - {} (lines: {})",
+                range.start,
+                range.end,
+                lines.len()
+            ));
+        }
+        let snippet_lines = &lines[range.start - 1..range.end];
+        let (content, indent_width) = if dedent {
+            dedented(snippet_lines)
+        } else {
+            (snippet_lines.concat(), 0)
+        };
+        Ok((
+            ReadContent {
+                content,
+                ..template.clone()
+            },
+            indent_width,
+        ))
+    };
+    let (old_read, old_indent_width) = snippet(old_range)?;
+    let (new_read, new_indent_width) = snippet(new_range)?;
+    Ok(SnippetDiff {
+        diff: lines_diff_response(old_read, new_read),
+        old_start_line: old_range.start,
+        new_start_line: new_range.start,
+        old_indent_width,
+        new_indent_width,
+    })
+}
+
+/// lines with leading whitespace common to non-blank lines removed, and its width in chars
+fn dedented(lines: &[String]) -> (String, usize) {
+    let leading_whitespace = |line: &str| line.len() - line.trim_start_matches([' ', '\t']).len();
+    let indent = lines
+        .iter()
+        .filter(|x| !x.trim().is_empty())
+        .map(|x| &x[..leading_whitespace(x)])
+        .reduce(|a, b| {
+            let common = a.bytes().zip(b.bytes()).take_while(|(a, b)| a == b).count();
+            &a[..common]
+        })
+        .unwrap_or_default();
+    let content = lines
+        .iter()
+        .map(|x| match x.strip_prefix(indent) {
+            Some(x) => x,
+            // blank line shorter than indent
+            None => x.trim_start_matches([' ', '\t']),
+        })
+        .collect::<String>();
+    (content, indent.chars().count())
+}
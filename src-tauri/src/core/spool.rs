@@ -0,0 +1,290 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use chardetng::EncodingDetector;
+use encoding_rs::{CoderResult, UTF_8};
+
+use super::os_path::os_path;
+use super::str::{bytes_to_hex_dump_at, HEX_DUMP_BYTES_PER_ROW};
+use super::temp_file::{create_temp_file, temp_dir};
+use super::types::{LowMemoryMode, SpoolInfo};
+
+/// file size from which low-memory mode is selected automatically (64 MB)
+const LOW_MEMORY_FILE_SIZE_THRESHOLD: u64 = 64 * 1024 * 1024;
+/// available memory under which low-memory mode is selected automatically (512 MB)
+const LOW_MEMORY_AVAILABLE_THRESHOLD: u64 = 512 * 1024 * 1024;
+/// bytes read at once on spooling (multiple of hex dump row)
+const SPOOL_CHUNK_SIZE: usize = 8192 * HEX_DUMP_BYTES_PER_ROW;
+/// bytes fed to charset detector
+const CHARSET_DETECTION_BYTES: usize = 1024 * 1024;
+
+/// content spooled to temporary file
+struct Spool {
+    filepath: PathBuf,
+    // byte offset of each line start in spooled file
+    line_offsets: Vec<u64>,
+}
+
+impl Drop for Spool {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.filepath);
+    }
+}
+
+/// app state to keep spooled contents
+#[derive(Default)]
+pub struct SpoolStore {
+    mode: Mutex<LowMemoryMode>,
+    spools: Mutex<HashMap<u64, Spool>>,
+    next_id: AtomicU64,
+}
+
+impl SpoolStore {
+    /// current low-memory mode
+    pub fn mode(&self) -> LowMemoryMode {
+        *self.mode.lock().unwrap()
+    }
+
+    /// switch low-memory mode
+    pub fn set_mode(&self, mode: LowMemoryMode) {
+        *self.mode.lock().unwrap() = mode;
+    }
+
+    /// check if file should be spooled instead of read at once
+    pub fn low_memory_required(&self, filepath: &str) -> Result<bool, String> {
+        match self.mode() {
+            LowMemoryMode::Always => Ok(true),
+            LowMemoryMode::Never => Ok(false),
+            LowMemoryMode::Auto => {
+                let file_size = fs::metadata(filepath)
+                    .map_err(|err| format!("Failed to get metadata of {} ({})", filepath, err))?
+                    .len();
+                if LOW_MEMORY_FILE_SIZE_THRESHOLD <= file_size {
+                    return Ok(true);
+                }
+                Ok(match available_memory() {
+                    Some(available) => {
+                        available < LOW_MEMORY_AVAILABLE_THRESHOLD || available < file_size * 4
+                    }
+                    None => false,
+                })
+            }
+        }
+    }
+
+    /// spool file content (text or hex dump) into temporary file
+    /// bytes read and file size are reported to `on_progress` chunk by chunk
+    pub fn spool(
+        &self,
+        filepath: &str,
+        binary: bool,
+        mut on_progress: impl FnMut(u64, u64),
+    ) -> Result<SpoolInfo, String> {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let spool_filepath =
+            temp_dir("spool")?.join(format!("{}-{}.spool", std::process::id(), id));
+
+        let charset = if binary {
+            spool_hex_dump(&os_path(filepath), &spool_filepath, &mut on_progress)?
+        } else {
+            spool_text(&os_path(filepath), &spool_filepath, &mut on_progress)?
+        };
+        let line_offsets = line_offsets(&spool_filepath)?;
+        let lines_count = line_offsets.len();
+
+        self.spools.lock().unwrap().insert(
+            id,
+            Spool {
+                filepath: spool_filepath,
+                line_offsets,
+            },
+        );
+
+        Ok(SpoolInfo {
+            id,
+            charset,
+            lines_count,
+        })
+    }
+
+    /// read lines in range from spooled content
+    pub fn lines(&self, id: u64, start: usize, count: usize) -> Result<Vec<String>, String> {
+        let spools = self.spools.lock().unwrap();
+        let spool = match spools.get(&id) {
+            Some(x) => x,
+            None => return Err(format!("Spool not found: {}", id)),
+        };
+        if spool.line_offsets.len() <= start {
+            return Ok(vec![]);
+        }
+
+        let mut file =
+            File::open(&spool.filepath).map_err(|err| format!("Failed to open spool ({})", err))?;
+        file.seek(SeekFrom::Start(spool.line_offsets[start]))
+            .map_err(|err| format!("Failed to seek spool ({})", err))?;
+        let mut reader = BufReader::new(file);
+
+        let end = (start + count).min(spool.line_offsets.len());
+        let mut ret = Vec::with_capacity(end - start);
+        for _ in start..end {
+            let mut line = String::new();
+            reader
+                .read_line(&mut line)
+                .map_err(|err| format!("Failed to read spool ({})", err))?;
+            ret.push(line);
+        }
+        Ok(ret)
+    }
+
+    /// remove spooled content
+    pub fn release(&self, id: u64) {
+        self.spools.lock().unwrap().remove(&id);
+    }
+}
+
+/// write hex dump of file into spool file chunk by chunk
+fn spool_hex_dump(
+    filepath: &Path,
+    spool_filepath: &Path,
+    on_progress: &mut impl FnMut(u64, u64),
+) -> Result<String, String> {
+    let file = File::open(filepath)
+        .map_err(|err| format!("Failed to open {} ({})", filepath.display(), err))?;
+    let total_bytes = file.metadata().map(|x| x.len()).unwrap_or_default();
+    let mut reader = BufReader::new(file);
+    let mut writer = BufWriter::new(create_temp_file(spool_filepath)?);
+
+    let mut buffer = vec![0; SPOOL_CHUNK_SIZE];
+    let mut offset = 0;
+    loop {
+        let read_bytes = read_chunk(&mut reader, &mut buffer)
+            .map_err(|err| format!("Failed to read {} ({})", filepath.display(), err))?;
+        if read_bytes == 0 {
+            break;
+        }
+        writer
+            .write_all(bytes_to_hex_dump_at(&buffer[..read_bytes], offset).as_bytes())
+            .map_err(|err| format!("Failed to write spool ({})", err))?;
+        offset += read_bytes;
+        on_progress(offset as u64, total_bytes);
+    }
+    writer
+        .flush()
+        .map_err(|err| format!("Failed to write spool ({})", err))?;
+
+    Ok("(binary)".to_owned())
+}
+
+/// write text of file decoded as utf-8 into spool file chunk by chunk
+fn spool_text(
+    filepath: &Path,
+    spool_filepath: &Path,
+    on_progress: &mut impl FnMut(u64, u64),
+) -> Result<String, String> {
+    let file = File::open(filepath)
+        .map_err(|err| format!("Failed to open {} ({})", filepath.display(), err))?;
+    let total_bytes = file.metadata().map(|x| x.len()).unwrap_or_default();
+    let mut reader = BufReader::new(file);
+    let mut writer = BufWriter::new(create_temp_file(spool_filepath)?);
+
+    // detect charset with head of file
+    let mut head = vec![0; CHARSET_DETECTION_BYTES];
+    let head_bytes = read_chunk(&mut reader, &mut head)
+        .map_err(|err| format!("Failed to read {} ({})", filepath.display(), err))?;
+    head.truncate(head_bytes);
+    let encoding = if std::str::from_utf8(&head).is_ok() {
+        UTF_8
+    } else {
+        let mut detector = EncodingDetector::new();
+        detector.feed(&head, head_bytes < CHARSET_DETECTION_BYTES);
+        detector.guess(None, false)
+    };
+
+    let mut decoder = encoding.new_decoder();
+    let mut buffer = vec![0; SPOOL_CHUNK_SIZE];
+    let mut read_total = head_bytes as u64;
+    let mut pending = head;
+    loop {
+        on_progress(read_total, total_bytes);
+        let last = pending.is_empty();
+        let mut decoded = String::with_capacity(
+            decoder
+                .max_utf8_buffer_length(pending.len())
+                .unwrap_or(pending.len() * 3),
+        );
+        let (result, _, _) = decoder.decode_to_string(&pending, &mut decoded, last);
+        debug_assert_eq!(result, CoderResult::InputEmpty);
+        writer
+            .write_all(decoded.as_bytes())
+            .map_err(|err| format!("Failed to write spool ({})", err))?;
+        if last {
+            break;
+        }
+
+        let read_bytes = read_chunk(&mut reader, &mut buffer)
+            .map_err(|err| format!("Failed to read {} ({})", filepath.display(), err))?;
+        read_total += read_bytes as u64;
+        pending = buffer[..read_bytes].to_vec();
+    }
+    writer
+        .flush()
+        .map_err(|err| format!("Failed to write spool ({})", err))?;
+
+    Ok(encoding.name().to_owned())
+}
+
+/// fill buffer as much as possible and return read bytes
+fn read_chunk(reader: &mut impl Read, buffer: &mut [u8]) -> std::io::Result<usize> {
+    let mut read_bytes = 0;
+    while read_bytes < buffer.len() {
+        match reader.read(&mut buffer[read_bytes..])? {
+            0 => break,
+            n => read_bytes += n,
+        }
+    }
+    Ok(read_bytes)
+}
+
+/// collect byte offset of each line start
+fn line_offsets(spool_filepath: &Path) -> Result<Vec<u64>, String> {
+    let mut reader = BufReader::new(
+        File::open(spool_filepath).map_err(|err| format!("Failed to open spool ({})", err))?,
+    );
+    let mut ret = vec![];
+    let mut offset = 0;
+    let mut line = vec![];
+    loop {
+        line.clear();
+        let read_bytes = reader
+            .read_until(b'\n', &mut line)
+            .map_err(|err| format!("Failed to read spool ({})", err))?;
+        if read_bytes == 0 {
+            break;
+        }
+        ret.push(offset);
+        offset += read_bytes as u64;
+    }
+    Ok(ret)
+}
+
+/// get available memory size in bytes if os tells it
+fn available_memory() -> Option<u64> {
+    #[cfg(target_os = "linux")]
+    {
+        let meminfo = fs::read_to_string("/proc/meminfo").ok()?;
+        meminfo
+            .lines()
+            .find(|x| x.starts_with("MemAvailable:"))
+            .and_then(|x| x.split_whitespace().nth(1))
+            .and_then(|x| x.parse::<u64>().ok())
+            .map(|kb| kb * 1024)
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        None
+    }
+}
@@ -0,0 +1,281 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fs;
+use std::io::{Cursor, Read};
+
+use zip::ZipArchive;
+
+use super::field_diff::field_diff;
+use super::os_path::os_path;
+use super::protected::{shared_strings, sheet_cell_values, xml_attribute};
+use super::types::{EntryStatus, SheetRowDiff};
+
+/// rows in sheet as 1-based row number and cell values by 0-based column index
+pub type SheetRows = Vec<(usize, Vec<String>)>;
+
+/// compare spreadsheets sheet by sheet with rows aligned by values of key columns
+/// instead of position, like database merge, so that inserted rows do not shift the rest
+/// key columns are column letters such as `A`, or header names when first row is header
+/// rows with the same key are paired in order of appearance
+pub fn keyed_sheet_row_diffs(
+    old: &str,
+    new: &str,
+    key_columns: &[String],
+    has_header: bool,
+) -> Result<Vec<SheetRowDiff>, String> {
+    if key_columns.is_empty() {
+        return Err("Key columns are required".to_owned());
+    }
+    let old_sheets = workbook_sheets(old)?;
+    let new_sheets = workbook_sheets(new)?;
+
+    let mut sheet_names = old_sheets.iter().map(|x| x.0.clone()).collect::<Vec<_>>();
+    for (name, _) in &new_sheets {
+        if !sheet_names.contains(name) {
+            sheet_names.push(name.to_owned());
+        }
+    }
+
+    let mut ret = vec![];
+    for sheet in sheet_names {
+        let rows = |sheets: &[(String, SheetRows)]| {
+            sheets
+                .iter()
+                .find(|x| x.0 == sheet)
+                .map(|x| x.1.clone())
+                .unwrap_or_default()
+        };
+        let mut old_rows = rows(&old_sheets);
+        let mut new_rows = rows(&new_sheets);
+
+        let (old_header, new_header) = if has_header {
+            (take_header(&mut old_rows), take_header(&mut new_rows))
+        } else {
+            (None, None)
+        };
+        let header = new_header.as_ref().or(old_header.as_ref());
+        let key_indices = key_columns
+            .iter()
+            .map(|x| key_column_index(x, header))
+            .collect::<Result<Vec<_>, _>>()?;
+        let columns_count = old_rows
+            .iter()
+            .chain(&new_rows)
+            .map(|x| x.1.len())
+            .chain(header.map(|x| x.len()))
+            .max()
+            .unwrap_or_default();
+        let labels = (0..columns_count)
+            .map(|i| {
+                header
+                    .and_then(|x| x.get(i))
+                    .filter(|x| !x.is_empty())
+                    .cloned()
+                    .unwrap_or_else(|| column_letters(i))
+            })
+            .collect::<Vec<_>>();
+
+        let key = |row: &[String]| {
+            key_indices
+                .iter()
+                .map(|i| row.get(*i).cloned().unwrap_or_default())
+                .collect::<Vec<_>>()
+        };
+        let mut old_by_key = HashMap::<Vec<String>, VecDeque<usize>>::new();
+        for (i, row) in old_rows.iter().enumerate() {
+            old_by_key.entry(key(&row.1)).or_default().push_back(i);
+        }
+
+        // new rows in order with old-only rows put before the next matched row
+        let mut old_done = vec![false; old_rows.len()];
+        let mut old_next = 0;
+        for (new_row, new_cells) in &new_rows {
+            let row_key = key(new_cells);
+            let matched = old_by_key.get_mut(&row_key).and_then(|x| x.pop_front());
+            if let Some(j) = matched {
+                while old_next < j {
+                    if !old_done[old_next] {
+                        old_done[old_next] = true;
+                        let (old_row, old_cells) = &old_rows[old_next];
+                        ret.push(sheet_row_diff(
+                            &sheet,
+                            key(old_cells),
+                            (Some(*old_row), Some(old_cells)),
+                            (None, None),
+                            &labels,
+                        ));
+                    }
+                    old_next += 1;
+                }
+                old_done[j] = true;
+            }
+            let old = matched.map(|j| (Some(old_rows[j].0), Some(&old_rows[j].1)));
+            ret.push(sheet_row_diff(
+                &sheet,
+                row_key,
+                old.unwrap_or((None, None)),
+                (Some(*new_row), Some(new_cells)),
+                &labels,
+            ));
+        }
+        for (i, (old_row, old_cells)) in old_rows.iter().enumerate() {
+            if !old_done[i] {
+                ret.push(sheet_row_diff(
+                    &sheet,
+                    key(old_cells),
+                    (Some(*old_row), Some(old_cells)),
+                    (None, None),
+                    &labels,
+                ));
+            }
+        }
+    }
+    Ok(ret)
+}
+
+/// row diff with cells compared column by column. empty cell is regarded as missing
+fn sheet_row_diff(
+    sheet: &str,
+    key: Vec<String>,
+    old: (Option<usize>, Option<&Vec<String>>),
+    new: (Option<usize>, Option<&Vec<String>>),
+    labels: &[String],
+) -> SheetRowDiff {
+    let value = |cells: Option<&Vec<String>>, i: usize| {
+        cells
+            .and_then(|x| x.get(i))
+            .filter(|x| !x.is_empty())
+            .cloned()
+    };
+    let cells = labels
+        .iter()
+        .enumerate()
+        .filter_map(|(i, label)| field_diff(label, value(old.1, i), value(new.1, i)))
+        .collect::<Vec<_>>();
+    let status = match (old.1, new.1) {
+        (Some(_), None) => EntryStatus::OldOnly,
+        (None, Some(_)) => EntryStatus::NewOnly,
+        _ if cells.iter().all(|x| x.status == EntryStatus::Equal) => EntryStatus::Equal,
+        _ => EntryStatus::Modified,
+    };
+    SheetRowDiff {
+        sheet: sheet.to_owned(),
+        key,
+        status,
+        old_row: old.0,
+        new_row: new.0,
+        cells,
+    }
+}
+
+/// remove first row as header
+fn take_header(rows: &mut SheetRows) -> Option<Vec<String>> {
+    if rows.is_empty() {
+        None
+    } else {
+        Some(rows.remove(0).1)
+    }
+}
+
+/// column index of key column given as header name or column letters
+fn key_column_index(key_column: &str, header: Option<&Vec<String>>) -> Result<usize, String> {
+    if let Some(i) = header.and_then(|x| x.iter().position(|x| x == key_column)) {
+        return Ok(i);
+    }
+    column_index(key_column).ok_or(format!("Key column not found: {}", key_column))
+}
+
+/// 0-based column index from column letters such as `A` and `AB`
+fn column_index(letters: &str) -> Option<usize> {
+    if letters.is_empty() || !letters.chars().all(|x| x.is_ascii_alphabetic()) {
+        return None;
+    }
+    let ret = letters
+        .to_ascii_uppercase()
+        .bytes()
+        .fold(0, |acc, x| acc * 26 + (x - b'A' + 1) as usize);
+    Some(ret - 1)
+}
+
+/// column letters from 0-based column index
+pub fn column_letters(index: usize) -> String {
+    let mut ret = vec![];
+    let mut n = index + 1;
+    while 0 < n {
+        ret.insert(0, (b'A' + ((n - 1) % 26) as u8) as char);
+        n = (n - 1) / 26;
+    }
+    ret.into_iter().collect()
+}
+
+/// sheets in workbook order with their rows
+pub fn workbook_sheets(filepath: &str) -> Result<Vec<(String, SheetRows)>, String> {
+    let bytes = fs::read(os_path(filepath))
+        .map_err(|err| format!("Failed to read {} ({})", filepath, err))?;
+    let mut archive = ZipArchive::new(Cursor::new(bytes))
+        .map_err(|err| format!("Failed to open {} as spreadsheet ({})", filepath, err))?;
+    let mut entry_text = |name: &str| -> Option<String> {
+        let mut entry = archive.by_name(name).ok()?;
+        let mut ret = String::new();
+        entry.read_to_string(&mut ret).ok()?;
+        Some(ret)
+    };
+
+    let workbook = entry_text("xl/workbook.xml").unwrap_or_default();
+    let relationships = entry_text("xl/_rels/workbook.xml.rels").unwrap_or_default();
+    let shared_strings = entry_text("xl/sharedStrings.xml")
+        .map(|x| shared_strings(&x))
+        .unwrap_or_default();
+    let targets = relationships
+        .split("<Relationship ")
+        .skip(1)
+        .filter_map(|x| {
+            let attributes = x.split('>').next()?;
+            let target = xml_attribute(attributes, "Target")?;
+            let target = match target.strip_prefix('/') {
+                Some(x) => x.to_owned(),
+                None => format!("xl/{}", target),
+            };
+            Some((xml_attribute(attributes, "Id")?, target))
+        })
+        .collect::<HashMap<_, _>>();
+
+    let mut ret = vec![];
+    for sheet in workbook.split("<sheet ").skip(1) {
+        let attributes = sheet.split('>').next().unwrap_or_default();
+        let (Some(name), Some(target)) = (
+            xml_attribute(attributes, "name"),
+            xml_attribute(attributes, "r:id").and_then(|x| targets.get(&x)),
+        ) else {
+            continue;
+        };
+        let xml = match entry_text(target) {
+            Some(x) => x,
+            None => continue,
+        };
+        ret.push((name, sheet_rows(&xml, &shared_strings)));
+    }
+    Ok(ret)
+}
+
+/// rows in worksheet xml. empty rows are skipped
+fn sheet_rows(xml: &str, shared_strings: &[String]) -> SheetRows {
+    let mut rows = BTreeMap::<usize, Vec<String>>::new();
+    for (reference, value) in sheet_cell_values(xml, shared_strings) {
+        let split_at = reference
+            .find(|x: char| x.is_ascii_digit())
+            .unwrap_or(reference.len());
+        let (letters, digits) = reference.split_at(split_at);
+        let (Some(column), Ok(row)) = (column_index(letters), digits.parse::<usize>()) else {
+            continue;
+        };
+        if value.is_empty() {
+            continue;
+        }
+        let cells = rows.entry(row).or_default();
+        if cells.len() <= column {
+            cells.resize(column + 1, String::new());
+        }
+        cells[column] = value;
+    }
+    rows.into_iter().collect()
+}
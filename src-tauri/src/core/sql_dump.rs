@@ -0,0 +1,471 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fs;
+
+use super::file::bytes_content;
+use super::os_path::os_path;
+use super::types::ReadContent;
+
+/// statements of session settings and locks which are noise on comparison
+const SKIPPED_STATEMENT_PREFIXES: [&str; 5] = [
+    "SET ",
+    "LOCK TABLES",
+    "UNLOCK TABLES",
+    "SELECT pg_catalog.set_config",
+    "\\connect",
+];
+/// section of statements not related to any table
+const OTHER_SECTION: &str = "(other)";
+
+/// column names when statement lists them, and values of row
+type DumpRow = (Option<Vec<String>>, Vec<String>);
+
+/// statement in dump, or data block of pg_dump copy
+enum DumpStatement {
+    Sql(String),
+    // copy statement and tab-separated rows following it
+    Copy(String, Vec<String>),
+}
+
+/// table section in dump
+#[derive(Default)]
+struct TableSection {
+    columns: Vec<String>,
+    primary_key: Vec<String>,
+    statements: Vec<String>,
+    rows: Vec<DumpRow>,
+}
+
+/// read mysqldump or pg_dump output normalized into per-table sections
+/// comments and session settings such as dump timestamps are dropped
+/// and inserted rows are put one per line ordered by primary key
+/// so that dumps are compared by their tables and rows
+pub fn sql_dump_content(filepath: &str) -> Result<ReadContent, String> {
+    if filepath.is_empty() {
+        return Ok(ReadContent::default());
+    }
+    let bytes = fs::read(os_path(filepath))
+        .map_err(|err| format!("Failed to read {} ({})", filepath, err))?;
+    let read = bytes_content(&bytes);
+    Ok(ReadContent {
+        content: normalized_dump(&read.content),
+        ..read
+    })
+}
+
+/// dump with sections of tables sorted by name
+fn normalized_dump(content: &str) -> String {
+    let mut sections: BTreeMap<String, TableSection> = BTreeMap::new();
+    for statement in dump_statements(content) {
+        match statement {
+            DumpStatement::Sql(sql) => {
+                if SKIPPED_STATEMENT_PREFIXES
+                    .iter()
+                    .any(|x| sql.to_uppercase().starts_with(&x.to_uppercase()))
+                {
+                    continue;
+                }
+                let table = statement_table(&sql).unwrap_or(OTHER_SECTION.to_owned());
+                let section = sections.entry(table).or_default();
+                if let Some(rows) = insert_rows(&sql) {
+                    section.rows.extend(rows);
+                    continue;
+                }
+                let upper = sql.to_uppercase();
+                if upper.starts_with("CREATE TABLE") {
+                    let (columns, primary_key) = table_definition(&sql);
+                    section.columns = columns;
+                    if !primary_key.is_empty() {
+                        section.primary_key = primary_key;
+                    }
+                } else if upper.starts_with("ALTER TABLE") && upper.contains("PRIMARY KEY") {
+                    section.primary_key = parenthesized_names(&sql, "PRIMARY KEY");
+                }
+                section.statements.push(sql);
+            }
+            DumpStatement::Copy(sql, data_rows) => {
+                let table = statement_table(&sql).unwrap_or(OTHER_SECTION.to_owned());
+                let columns = outer_parenthesized(&sql)
+                    .map(|x| x.split(',').map(|x| unquoted(x.trim())).collect::<Vec<_>>());
+                let section = sections.entry(table).or_default();
+                section.rows.extend(data_rows.into_iter().map(|x| {
+                    let values = x.split('\t').map(|x| x.to_owned()).collect();
+                    (columns.clone(), values)
+                }));
+            }
+        }
+    }
+
+    let mut ret = String::new();
+    for (table, section) in sections {
+        ret.push_str(&format!("[{}]\n", table));
+        for statement in &section.statements {
+            ret.push_str(&formatted_statement(statement));
+        }
+        let key_columns = if section.primary_key.is_empty() {
+            section.columns.iter().take(1).cloned().collect::<Vec<_>>()
+        } else {
+            section.primary_key.to_owned()
+        };
+        let mut rows = section
+            .rows
+            .iter()
+            .map(|(columns, values)| {
+                let columns = columns.as_ref().unwrap_or(&section.columns);
+                let key = key_columns
+                    .iter()
+                    .map(|name| {
+                        let value = match columns.iter().position(|x| x == name) {
+                            Some(i) => values.get(i).cloned().unwrap_or_default(),
+                            // first value is regarded as key when columns are unknown
+                            None => values.first().cloned().unwrap_or_default(),
+                        };
+                        (name.to_owned(), value)
+                    })
+                    .collect::<Vec<_>>();
+                (key, values.join(", "))
+            })
+            .collect::<Vec<_>>();
+        rows.sort_by(|a, b| compare_keys(&a.0, &b.0).then_with(|| a.1.cmp(&b.1)));
+        for (key, values) in rows {
+            let key = key
+                .iter()
+                .map(|(name, value)| format!("{}={}", name, value))
+                .collect::<Vec<_>>()
+                .join(", ");
+            ret.push_str(&format!("  row {}: ({})\n", key, values));
+        }
+    }
+    ret
+}
+
+/// split dump into statements with comments dropped and whitespace collapsed
+/// rows of pg_dump `COPY ... FROM stdin` are taken until `\.`
+fn dump_statements(content: &str) -> Vec<DumpStatement> {
+    let chars = content.chars().collect::<Vec<_>>();
+    let mut ret = vec![];
+    let mut statement = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied().unwrap_or_default();
+        match c {
+            '-' if next == '-' => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+                statement.push(' ');
+            }
+            '#' if statement.trim().is_empty() => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            }
+            // mysql conditional comments such as `/*!40101 SET ... */` are dropped as well
+            '/' if next == '*' => {
+                i += 2;
+                while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                    i += 1;
+                }
+                i += 2;
+                statement.push(' ');
+                continue;
+            }
+            '\'' | '"' | '`' => {
+                let start = i;
+                i += 1;
+                while i < chars.len() {
+                    if chars[i] == '\\' && c == '\'' {
+                        i += 2;
+                        continue;
+                    }
+                    if chars[i] == c {
+                        // quote is escaped by doubling
+                        if chars.get(i + 1) == Some(&c) {
+                            i += 2;
+                            continue;
+                        }
+                        break;
+                    }
+                    i += 1;
+                }
+                let end = (i + 1).min(chars.len());
+                statement.extend(&chars[start..end]);
+                i = end;
+                continue;
+            }
+            ';' => {
+                let sql = collapsed_whitespace(&statement);
+                statement.clear();
+                i += 1;
+                if sql.is_empty() {
+                    continue;
+                }
+                let upper = sql.to_uppercase();
+                if upper.starts_with("COPY ") && upper.ends_with("FROM STDIN") {
+                    // data rows start at next line
+                    while i < chars.len() && chars[i] != '\n' {
+                        i += 1;
+                    }
+                    i += 1;
+                    let mut rows = vec![];
+                    while i < chars.len() {
+                        let line_end = (i..chars.len())
+                            .find(|x| chars[*x] == '\n')
+                            .unwrap_or(chars.len());
+                        let line = chars[i..line_end].iter().collect::<String>();
+                        i = line_end + 1;
+                        let line = line.trim_end_matches('\r');
+                        if line == "\\." {
+                            break;
+                        }
+                        rows.push(line.to_owned());
+                    }
+                    ret.push(DumpStatement::Copy(sql, rows));
+                } else {
+                    ret.push(DumpStatement::Sql(sql));
+                }
+                continue;
+            }
+            _ => {
+                statement.push(c);
+                i += 1;
+                continue;
+            }
+        }
+        i += 1;
+    }
+    let sql = collapsed_whitespace(&statement);
+    if !sql.is_empty() {
+        ret.push(DumpStatement::Sql(sql));
+    }
+    ret
+}
+
+/// whitespace out of quotes collapsed into single space
+fn collapsed_whitespace(sql: &str) -> String {
+    let mut ret = String::new();
+    let mut quote = None;
+    for c in sql.trim().chars() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => (),
+            None if matches!(c, '\'' | '"' | '`') => quote = Some(c),
+            None if c.is_whitespace() => {
+                if !ret.ends_with(' ') {
+                    ret.push(' ');
+                }
+                continue;
+            }
+            None => (),
+        }
+        ret.push(c);
+    }
+    ret
+}
+
+/// table which statement is about, unquoted
+fn statement_table(sql: &str) -> Option<String> {
+    let words = sql.split(' ').collect::<Vec<_>>();
+    let upper = words.iter().map(|x| x.to_uppercase()).collect::<Vec<_>>();
+    let position = |keywords: &[&str]| {
+        (0..upper.len()).find(|i| {
+            keywords
+                .iter()
+                .enumerate()
+                .all(|(j, x)| upper.get(i + j).is_some_and(|y| y == x))
+        })
+    };
+    let index = if upper.first().is_some_and(|x| x == "INSERT") {
+        position(&["INTO"])? + 1
+    } else if upper.first().is_some_and(|x| x == "COPY") {
+        1
+    } else if let Some(i) = position(&["TABLE"]) {
+        // such as `ALTER TABLE ONLY x` and `DROP TABLE IF EXISTS x`
+        let mut i = i + 1;
+        while upper
+            .get(i)
+            .is_some_and(|x| matches!(x.as_str(), "ONLY" | "IF" | "NOT" | "EXISTS"))
+        {
+            i += 1;
+        }
+        i
+    } else if upper.first().is_some_and(|x| x == "CREATE") {
+        // index on table
+        position(&["ON"])? + 1
+    } else {
+        return None;
+    };
+    let name = words.get(index)?;
+    let name = name.split('(').next().unwrap_or_default();
+    Some(unquoted(name)).filter(|x| !x.is_empty())
+}
+
+/// identifier without quotes
+fn unquoted(name: &str) -> String {
+    name.split('.')
+        .map(|x| x.trim_matches(['`', '"']))
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// column names and primary key in create table statement
+fn table_definition(sql: &str) -> (Vec<String>, Vec<String>) {
+    let Some(body) = outer_parenthesized(sql) else {
+        return (vec![], vec![]);
+    };
+    let mut columns = vec![];
+    let mut primary_key = vec![];
+    for definition in top_level_split(body) {
+        let definition = definition.trim();
+        let upper = definition.to_uppercase();
+        if upper.starts_with("PRIMARY KEY") || upper.starts_with("CONSTRAINT") {
+            if upper.contains("PRIMARY KEY") {
+                primary_key = parenthesized_names(definition, "PRIMARY KEY");
+            }
+            continue;
+        }
+        if ["KEY ", "UNIQUE ", "INDEX ", "FOREIGN ", "CHECK "]
+            .iter()
+            .any(|x| upper.starts_with(x))
+        {
+            continue;
+        }
+        let name = unquoted(definition.split(' ').next().unwrap_or_default());
+        if upper.contains(" PRIMARY KEY") {
+            primary_key = vec![name.to_owned()];
+        }
+        columns.push(name);
+    }
+    (columns, primary_key)
+}
+
+/// names in parentheses after keyword, such as key columns of `PRIMARY KEY (a, b)`
+fn parenthesized_names(sql: &str, keyword: &str) -> Vec<String> {
+    let Some(start) = sql.to_uppercase().find(&keyword.to_uppercase()) else {
+        return vec![];
+    };
+    let rest = &sql[start + keyword.len()..];
+    let Some(open) = rest.find('(') else {
+        return vec![];
+    };
+    let close = rest[open..].find(')').map_or(rest.len(), |x| open + x);
+    rest[open + 1..close]
+        .split(',')
+        .map(|x| unquoted(x.trim()))
+        .filter(|x| !x.is_empty())
+        .collect()
+}
+
+/// text between first opening parenthesis and its closing one
+fn outer_parenthesized(sql: &str) -> Option<&str> {
+    let start = sql.find('(')?;
+    let mut depth = 0;
+    let mut quote = None;
+    for (i, c) in sql[start..].char_indices() {
+        match (quote, c) {
+            (Some(q), _) if c == q => quote = None,
+            (Some(_), _) => (),
+            (None, '\'' | '"' | '`') => quote = Some(c),
+            (None, '(') => depth += 1,
+            (None, ')') => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(&sql[start + 1..start + i]);
+                }
+            }
+            _ => (),
+        }
+    }
+    None
+}
+
+/// split by commas out of quotes and parentheses
+fn top_level_split(s: &str) -> Vec<String> {
+    let mut ret = vec![];
+    let mut current = String::new();
+    let mut depth = 0;
+    let mut quote = None;
+    let mut escaped = false;
+    for c in s.chars() {
+        if escaped {
+            escaped = false;
+            current.push(c);
+            continue;
+        }
+        match (quote, c) {
+            (Some('\''), '\\') => escaped = true,
+            (Some(q), _) if c == q => quote = None,
+            (Some(_), _) => (),
+            (None, '\'' | '"' | '`') => quote = Some(c),
+            (None, '(') => depth += 1,
+            (None, ')') => depth -= 1,
+            (None, ',') if depth == 0 => {
+                ret.push(current.trim().to_owned());
+                current.clear();
+                continue;
+            }
+            _ => (),
+        }
+        current.push(c);
+    }
+    if !current.trim().is_empty() {
+        ret.push(current.trim().to_owned());
+    }
+    ret
+}
+
+/// rows in insert statement with column names if listed
+fn insert_rows(sql: &str) -> Option<Vec<DumpRow>> {
+    if !sql.to_uppercase().starts_with("INSERT ") {
+        return None;
+    }
+    let values_start = sql.to_uppercase().find(" VALUES")?;
+    let head = &sql[..values_start];
+    let columns = head
+        .find('(')
+        .and_then(|_| outer_parenthesized(head))
+        .map(|x| x.split(',').map(|x| unquoted(x.trim())).collect::<Vec<_>>());
+    let rows = top_level_split(&sql[values_start + " VALUES".len()..])
+        .into_iter()
+        .map(|x| {
+            let tuple = x.trim();
+            let tuple = tuple.strip_prefix('(').unwrap_or(tuple);
+            let tuple = tuple.strip_suffix(')').unwrap_or(tuple);
+            (columns.clone(), top_level_split(tuple))
+        })
+        .collect();
+    Some(rows)
+}
+
+/// statement with definitions in its parentheses put on lines
+/// such as columns in create table
+fn formatted_statement(sql: &str) -> String {
+    let upper = sql.to_uppercase();
+    let body = outer_parenthesized(sql).filter(|_| upper.starts_with("CREATE TABLE"));
+    let Some(body) = body else {
+        return format!("  {};\n", sql);
+    };
+    let start = body.as_ptr() as usize - sql.as_ptr() as usize;
+    let end = start + body.len();
+    let mut ret = format!("  {}(\n", &sql[..start - 1]);
+    for definition in top_level_split(body) {
+        ret.push_str(&format!("    {},\n", definition));
+    }
+    ret.push_str(&format!("  ){};\n", &sql[end + 1..]));
+    ret
+}
+
+/// compare key values numerically when both are numbers
+fn compare_keys(a: &[(String, String)], b: &[(String, String)]) -> Ordering {
+    for ((_, a), (_, b)) in a.iter().zip(b) {
+        let ordering = match (a.parse::<f64>(), b.parse::<f64>()) {
+            (Ok(a), Ok(b)) => a.total_cmp(&b),
+            _ => a.cmp(b),
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    a.len().cmp(&b.len())
+}
@@ -0,0 +1,76 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{de::DeserializeOwned, Serialize};
+use tauri::Manager;
+
+/// app identifier in tauri config, which names app data dir
+const APP_IDENTIFIER: &str = "com.forskscope.app";
+
+/// get file path in app data dir to persist store
+pub fn store_filepath(app_handle: &tauri::AppHandle, filename: &str) -> Result<PathBuf, String> {
+    let dirpath = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|err| format!("Failed to get app data dir ({})", err))?;
+    if !dirpath.exists() {
+        fs::create_dir_all(&dirpath)
+            .map_err(|err| format!("Failed to create app data dir ({})", err))?;
+    }
+    Ok(dirpath.join(filename))
+}
+
+/// load store from app data dir
+/// returns default when store has not been saved yet
+pub fn load<T: DeserializeOwned + Default>(
+    app_handle: &tauri::AppHandle,
+    filename: &str,
+) -> Result<T, String> {
+    load_from(&store_filepath(app_handle, filename)?)
+}
+
+/// load store from app data dir without app such as on running as agent
+/// returns default when store has not been saved yet
+pub fn load_headless<T: DeserializeOwned + Default>(filename: &str) -> Result<T, String> {
+    let dirpath = headless_app_data_dir().ok_or("Failed to get app data dir".to_owned())?;
+    load_from(&dirpath.join(filename))
+}
+
+fn load_from<T: DeserializeOwned + Default>(filepath: &Path) -> Result<T, String> {
+    if !filepath.exists() {
+        return Ok(T::default());
+    }
+    let read = fs::read_to_string(filepath)
+        .map_err(|err| format!("Failed to read {} ({})", filepath.display(), err))?;
+    serde_json::from_str(&read)
+        .map_err(|err| format!("Failed to parse {} ({})", filepath.display(), err))
+}
+
+/// app data dir resolved in the same way as tauri does, without app
+fn headless_app_data_dir() -> Option<PathBuf> {
+    let home = || std::env::var_os("HOME").map(PathBuf::from);
+    let data_dir = if cfg!(target_os = "windows") {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        home().map(|x| x.join("Library").join("Application Support"))
+    } else {
+        std::env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .filter(|x| x.is_absolute())
+            .or_else(|| home().map(|x| x.join(".local").join("share")))
+    };
+    data_dir.map(|x| x.join(APP_IDENTIFIER))
+}
+
+/// save store into app data dir
+pub fn save<T: Serialize>(
+    app_handle: &tauri::AppHandle,
+    filename: &str,
+    value: &T,
+) -> Result<(), String> {
+    let filepath = store_filepath(app_handle, filename)?;
+    let serialized = serde_json::to_string_pretty(value)
+        .map_err(|err| format!("Failed to serialize {} ({})", filename, err))?;
+    fs::write(&filepath, serialized)
+        .map_err(|err| format!("Failed to write {} ({})", filepath.display(), err))
+}
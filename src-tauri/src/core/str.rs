@@ -41,6 +41,38 @@ pub fn split_lines_with_endings(input: &str) -> Vec<String> {
     lines
 }
 
+/// split lines longer than `max_length` chars into segments
+/// segments except last one have no line ending so that joining them restores original text
+/// and they are counted as one line. `\r\n` is never split so that it stays one line ending
+pub fn split_long_lines(lines: Vec<String>, max_length: usize) -> Vec<String> {
+    if lines.iter().all(|x| x.len() <= max_length) {
+        return lines;
+    }
+
+    let mut ret = Vec::with_capacity(lines.len());
+    for line in lines {
+        if line.len() <= max_length {
+            ret.push(line);
+            continue;
+        }
+        let mut segment = String::with_capacity(max_length);
+        let mut segment_chars = 0;
+        let mut chars = line.chars().peekable();
+        while let Some(c) = chars.next() {
+            segment.push(c);
+            segment_chars += 1;
+            if max_length <= segment_chars && !(c == '\r' && chars.peek() == Some(&'\n')) {
+                ret.push(std::mem::take(&mut segment));
+                segment_chars = 0;
+            }
+        }
+        if !segment.is_empty() {
+            ret.push(segment);
+        }
+    }
+    ret
+}
+
 /// get byte start and end of multi byte string
 pub fn multibyte_str_byte_indices(
     text: &str,
@@ -64,40 +96,154 @@ pub fn multibyte_str_byte_indices(
     Some((byte_start, byte_end))
 }
 
+/// bytes shown in each row of hex dump
+pub const HEX_DUMP_BYTES_PER_ROW: usize = 8;
+
 /// convert bytes array to hex chars string
 pub fn bytes_to_hex_dump(bytes: &[u8]) -> String {
-    const BYTES_PER_ROW: usize = 8;
+    bytes_to_hex_dump_at(bytes, 0)
+}
+
+/// convert bytes array to hex chars string
+/// whose address offset starts at `offset` (for bytes read in chunks)
+/// output is built into buffer allocated at once without formatting per byte
+/// because this is the bottleneck on large binaries
+pub fn bytes_to_hex_dump_at(bytes: &[u8], offset: usize) -> String {
+    const BYTES_PER_ROW: usize = HEX_DUMP_BYTES_PER_ROW;
+    const HEX_CHARS: &[u8; 16] = b"0123456789abcdef";
+    // address, hex section padded to its width, ascii section between bars and line break
+    const ROW_LENGTH: usize = 10 + (BYTES_PER_ROW * 3) + (BYTES_PER_ROW / 4) + BYTES_PER_ROW + 3;
 
-    let mut output = String::new();
+    let mut output: Vec<u8> = Vec::with_capacity(bytes.len().div_ceil(BYTES_PER_ROW) * ROW_LENGTH);
 
     for (i, chunk) in bytes.chunks(BYTES_PER_ROW).enumerate() {
-        // address offset
-        output.push_str(&format!("{:08x}  ", i * BYTES_PER_ROW));
+        // address offset in 8 digits at least
+        let address = offset + i * BYTES_PER_ROW;
+        let digits = (usize::BITS - address.leading_zeros()).div_ceil(4).max(8);
+        for k in (0..digits).rev() {
+            output.push(HEX_CHARS[(address >> (k * 4)) & 0xf]);
+        }
+        output.extend_from_slice(b"  ");
 
         // hex bytes
         for (j, byte) in chunk.iter().enumerate() {
             if j > 0 && j % 4 == 0 {
-                output.push(' '); // Extra spacing every 4 bytes
+                output.push(b' '); // Extra spacing every 4 bytes
             }
-            output.push_str(&format!("{:02x} ", byte));
+            output.push(HEX_CHARS[(byte >> 4) as usize]);
+            output.push(HEX_CHARS[(byte & 0xf) as usize]);
+            output.push(b' ');
         }
 
         // ascii section
         let hex_width = (BYTES_PER_ROW * 3) + (BYTES_PER_ROW / 4); // Account for spaces
-        output.push_str(&" ".repeat(hex_width - chunk.len() * 3 - (chunk.len() / 4)));
+        let padding = hex_width - chunk.len() * 3 - (chunk.len() / 4);
+        output.resize(output.len() + padding, b' ');
 
         // ascii representation
-        output.push('|');
-        for &byte in chunk {
-            output.push(if byte.is_ascii_graphic() || byte.is_ascii_whitespace() {
-                byte as char
+        output.push(b'|');
+        output.extend(chunk.iter().map(|&byte| {
+            if byte.is_ascii_graphic() || byte.is_ascii_whitespace() {
+                byte
             } else {
-                '.'
-            });
-        }
-        output.push('|');
-        output.push('\n');
+                b'.'
+            }
+        }));
+        output.push(b'|');
+        output.push(b'\n');
     }
 
+    // only ascii bytes are written
+    String::from_utf8(output).unwrap_or_default()
+}
+
+/// encode bytes in base64 with padding such as for data urls
+pub fn base64_encode(bytes: &[u8]) -> String {
+    const BASE64_CHARS: &[u8; 64] =
+        b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut output = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0_u32, |n, (i, x)| n | (*x as u32) << (16 - i * 8));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                output.push(BASE64_CHARS[(n >> (18 - i * 6)) as usize & 0x3f] as char);
+            } else {
+                output.push('=');
+            }
+        }
+    }
     output
 }
+
+/// decode base64 with or without padding. whitespace such as line breaks is skipped
+/// url-safe alphabet is accepted as well
+pub fn base64_decode(s: &str) -> Result<Vec<u8>, String> {
+    let mut output = Vec::with_capacity(s.len() / 4 * 3);
+    let mut n = 0_u32;
+    let mut bits = 0;
+    for c in s.chars().filter(|x| !x.is_whitespace()) {
+        let value = match c {
+            'A'..='Z' => c as u32 - 'A' as u32,
+            'a'..='z' => c as u32 - 'a' as u32 + 26,
+            '0'..='9' => c as u32 - '0' as u32 + 52,
+            '+' | '-' => 62,
+            '/' | '_' => 63,
+            '=' => break,
+            _ => return Err(format!("Invalid base64 char: {}", c)),
+        };
+        n = n << 6 | value;
+        bits += 6;
+        if 8 <= bits {
+            bits -= 8;
+            output.push((n >> bits) as u8);
+        }
+    }
+    Ok(output)
+}
+
+/// chars looking like nothing or like other chars, with their short names
+const INVISIBLE_CHARS: [(char, &str); 22] = [
+    ('\u{00a0}', "NBSP"),
+    ('\u{00ad}', "SHY"),
+    ('\u{034f}', "CGJ"),
+    ('\u{061c}', "ALM"),
+    ('\u{180e}', "MVS"),
+    ('\u{200b}', "ZWSP"),
+    ('\u{200c}', "ZWNJ"),
+    ('\u{200d}', "ZWJ"),
+    ('\u{200e}', "LRM"),
+    ('\u{200f}', "RLM"),
+    ('\u{202a}', "LRE"),
+    ('\u{202b}', "RLE"),
+    ('\u{202c}', "PDF"),
+    ('\u{202d}', "LRO"),
+    ('\u{202e}', "RLO"),
+    ('\u{202f}', "NNBSP"),
+    ('\u{2060}', "WJ"),
+    ('\u{2066}', "LRI"),
+    ('\u{2067}', "RLI"),
+    ('\u{2068}', "FSI"),
+    ('\u{2069}', "PDI"),
+    ('\u{feff}', "BOM"),
+];
+
+/// replace invisible chars such as zero-width space, nbsp, bidi controls and bom
+/// with markers like `⟨U+200B ZWSP⟩` so that identical-looking lines show how they differ
+/// other control chars than tab and line endings are marked only with code point
+pub fn reveal_invisible_chars(s: &str) -> String {
+    let mut ret = String::with_capacity(s.len());
+    for c in s.chars() {
+        if let Some((_, name)) = INVISIBLE_CHARS.iter().find(|x| x.0 == c) {
+            ret.push_str(&format!("⟨U+{:04X} {}⟩", c as u32, name));
+        } else if c.is_control() && !matches!(c, '\t' | '\n' | '\r') {
+            ret.push_str(&format!("⟨U+{:04X}⟩", c as u32));
+        } else {
+            ret.push(c);
+        }
+    }
+    ret
+}
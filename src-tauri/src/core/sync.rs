@@ -0,0 +1,424 @@
+use std::fs::{self, metadata};
+use std::path::{Component, Path};
+use std::time::SystemTime;
+
+use chrono::Local;
+use filetime::FileTime;
+use tauri::Emitter;
+
+use super::diff::filepaths_digest_diff;
+use super::os_path::{os_path, path_string};
+use super::progress::Progress;
+use super::store::{self, store_filepath};
+use super::types::{
+    DiffSide, DirEntryDiff, EntryStatus, MetadataCopy, OperationKind, ProgressUnit, SyncDirection,
+    SyncJournal, SyncOperation, SyncOperationKind, SyncPlan, SyncProgressEvent, SyncRecord,
+    SyncRun, SyncScriptKind,
+};
+
+/// file name to persist operation journal
+const SYNC_JOURNAL_STORE_FILENAME: &str = "sync_journal.json";
+/// dir name in app data dir where deleted files are moved instead of trash
+const SYNC_TRASH_DIRNAME: &str = "sync_trash";
+/// event emitted on each operation in synchronization
+pub const SYNC_PROGRESS_EVENT: &str = "sync-progress";
+/// max runs kept in journal
+const MAX_JOURNAL_LENGTH: usize = 50;
+
+/// list copy and delete operations to synchronize folders without executing them
+/// `OldToNew` and `NewToOld` make target tree match source tree including deletions.
+/// `Mirror` copies entries on one side only to the other and modified entries from newer one
+pub fn sync_plan(
+    old_dir: &str,
+    new_dir: &str,
+    entries: &[DirEntryDiff],
+    direction: SyncDirection,
+) -> SyncPlan {
+    let operations = entries
+        .iter()
+        .filter_map(|entry| planned_operation(old_dir, new_dir, entry, direction))
+        .collect::<Vec<_>>();
+    let copy_bytes = operations.iter().filter_map(|x| x.size).sum();
+    SyncPlan {
+        old_dir: old_dir.to_owned(),
+        new_dir: new_dir.to_owned(),
+        direction,
+        operations,
+        copy_bytes,
+    }
+}
+
+/// operation to synchronize entry in direction. none on equal entry
+fn planned_operation(
+    old_dir: &str,
+    new_dir: &str,
+    entry: &DirEntryDiff,
+    direction: SyncDirection,
+) -> Option<SyncOperation> {
+    let (kind, source, target) = match (direction, entry.status) {
+        (_, EntryStatus::Equal) => return None,
+        (SyncDirection::OldToNew, EntryStatus::NewOnly) => {
+            (SyncOperationKind::Delete, None, DiffSide::New)
+        }
+        (SyncDirection::OldToNew, _) => {
+            (SyncOperationKind::Copy, Some(DiffSide::Old), DiffSide::New)
+        }
+        (SyncDirection::NewToOld, EntryStatus::OldOnly) => {
+            (SyncOperationKind::Delete, None, DiffSide::Old)
+        }
+        (SyncDirection::NewToOld, _) => {
+            (SyncOperationKind::Copy, Some(DiffSide::New), DiffSide::Old)
+        }
+        (SyncDirection::Mirror, EntryStatus::OldOnly) => {
+            (SyncOperationKind::Copy, Some(DiffSide::Old), DiffSide::New)
+        }
+        (SyncDirection::Mirror, EntryStatus::NewOnly) => {
+            (SyncOperationKind::Copy, Some(DiffSide::New), DiffSide::Old)
+        }
+        (SyncDirection::Mirror, EntryStatus::Modified) => {
+            let old_modified = modified(old_dir, &entry.path);
+            let new_modified = modified(new_dir, &entry.path);
+            if new_modified < old_modified {
+                (SyncOperationKind::Copy, Some(DiffSide::Old), DiffSide::New)
+            } else {
+                (SyncOperationKind::Copy, Some(DiffSide::New), DiffSide::Old)
+            }
+        }
+    };
+    let size = match (kind, source) {
+        (SyncOperationKind::Copy, Some(DiffSide::Old)) => entry.old_size,
+        (SyncOperationKind::Copy, Some(DiffSide::New)) => entry.new_size,
+        _ => None,
+    };
+    Some(SyncOperation {
+        kind,
+        path: entry.path.to_owned(),
+        source,
+        target,
+        size,
+    })
+}
+
+/// export sync plan as shell script to be reviewed and run by user
+pub fn sync_plan_script(plan: &SyncPlan, script_kind: SyncScriptKind) -> String {
+    let side_path = |side: DiffSide, path: &str| {
+        let dir = match side {
+            DiffSide::Old => &plan.old_dir,
+            DiffSide::New => &plan.new_dir,
+        };
+        path_string(&os_path(dir).join(os_path(path)))
+    };
+
+    let mut lines = match script_kind {
+        SyncScriptKind::Sh => vec!["#!/bin/sh".to_owned(), "set -e".to_owned()],
+        SyncScriptKind::PowerShell => vec!["$ErrorActionPreference = 'Stop'".to_owned()],
+    };
+    for x in plan.operations.iter() {
+        let target = side_path(x.target, &x.path);
+        let line = match (script_kind, x.kind, x.source) {
+            (SyncScriptKind::Sh, SyncOperationKind::Copy, Some(source)) => {
+                let parent = os_path(&target)
+                    .parent()
+                    .map(path_string)
+                    .unwrap_or_default();
+                format!(
+                    "mkdir -p {} && cp -p {} {}",
+                    sh_quote(&parent),
+                    sh_quote(&side_path(source, &x.path)),
+                    sh_quote(&target)
+                )
+            }
+            (SyncScriptKind::Sh, _, _) => format!("rm -f {}", sh_quote(&target)),
+            (SyncScriptKind::PowerShell, SyncOperationKind::Copy, Some(source)) => format!(
+                "New-Item -ItemType Directory -Force -Path (Split-Path -Parent {}) | Out-Null; Copy-Item -LiteralPath {} -Destination {} -Force",
+                powershell_quote(&target),
+                powershell_quote(&side_path(source, &x.path)),
+                powershell_quote(&target)
+            ),
+            (SyncScriptKind::PowerShell, _, _) => {
+                format!("Remove-Item -LiteralPath {} -Force", powershell_quote(&target))
+            }
+        };
+        lines.push(line);
+    }
+    lines.push(String::new());
+    lines.join("\n")
+}
+
+/// execute sync plan confirmed by user operation by operation
+/// paths escaping roots are refused, and so are entries changed since plan was made
+/// such as target created or modified meanwhile, because user has not reviewed them.
+/// failed operations are recorded and the rest continue. deleted files are moved
+/// into app data dir when `use_trash` is true so that they can be restored
+/// every run is recorded in journal for auditing
+pub fn execute_sync(
+    app_handle: &tauri::AppHandle,
+    plan: &SyncPlan,
+    use_trash: bool,
+) -> Result<SyncRun, String> {
+    let mut journal: SyncJournal = store::load(app_handle, SYNC_JOURNAL_STORE_FILENAME)?;
+    let id = journal
+        .runs
+        .iter()
+        .map(|x| x.id + 1)
+        .max()
+        .unwrap_or_default();
+    let trash_dir = store_filepath(app_handle, SYNC_TRASH_DIRNAME)?.join(id.to_string());
+    let side_path = |side: DiffSide, path: &str| match side {
+        DiffSide::Old => os_path(&plan.old_dir).join(os_path(path)),
+        DiffSide::New => os_path(&plan.new_dir).join(os_path(path)),
+    };
+
+    let started_at = Local::now().to_rfc3339();
+    let total = plan.operations.len();
+    let mut progress = Progress::start(
+        app_handle,
+        OperationKind::Sync,
+        &plan.old_dir,
+        ProgressUnit::Entries,
+    );
+    let mut records = Vec::with_capacity(total);
+    for (i, x) in plan.operations.iter().enumerate() {
+        let target = side_path(x.target, &x.path);
+        let verified = if !is_relative_path(&x.path) {
+            Err(format!("Path out of compared roots: {}", x.path))
+        } else {
+            current_entry(&plan.old_dir, &plan.new_dir, &x.path).and_then(|entry| {
+                let current =
+                    planned_operation(&plan.old_dir, &plan.new_dir, &entry, plan.direction);
+                if current.as_ref() == Some(x) {
+                    Ok(())
+                } else {
+                    Err(format!("Entry changed since plan was made: {}", x.path))
+                }
+            })
+        };
+        let result = verified.and_then(|_| match (x.kind, x.source) {
+            (SyncOperationKind::Copy, Some(source)) => {
+                copy_file(&side_path(source, &x.path), &target).map(|_| None)
+            }
+            (SyncOperationKind::Copy, None) => Err("Copy source is not specified".to_owned()),
+            (SyncOperationKind::Delete, _) if use_trash => {
+                let side = match x.target {
+                    DiffSide::Old => "old",
+                    DiffSide::New => "new",
+                };
+                let trashed = trash_dir.join(side).join(&x.path);
+                move_file(&target, &trashed).map(|_| Some(path_string(&trashed)))
+            }
+            (SyncOperationKind::Delete, _) => fs::remove_file(&target)
+                .map(|_| None)
+                .map_err(|err| format!("Failed to delete {} ({})", target.display(), err)),
+        });
+        let (trashed_to, error) = match result {
+            Ok(x) => (x, None),
+            Err(err) => (None, Some(err)),
+        };
+
+        let event = SyncProgressEvent {
+            run_id: id,
+            completed: i + 1,
+            total,
+            path: x.path.to_owned(),
+            error: error.to_owned(),
+        };
+        if let Err(err) = app_handle.emit(SYNC_PROGRESS_EVENT, event) {
+            tracing::warn!("Failed to emit sync progress ({})", err);
+        }
+        progress.update((i + 1) as u64, Some(total as u64));
+        records.push(SyncRecord {
+            operation: x.clone(),
+            trashed_to,
+            error,
+        });
+    }
+
+    let run = SyncRun {
+        id,
+        started_at,
+        finished_at: Local::now().to_rfc3339(),
+        old_dir: plan.old_dir.to_owned(),
+        new_dir: plan.new_dir.to_owned(),
+        direction: plan.direction,
+        records,
+    };
+    journal.runs.push(run.clone());
+    if MAX_JOURNAL_LENGTH < journal.runs.len() {
+        journal.runs.remove(0);
+    }
+    // failed operations are in records and do not fail run
+    let saved = store::save(app_handle, SYNC_JOURNAL_STORE_FILENAME, &journal);
+    progress.finish(saved)?;
+    Ok(run)
+}
+
+/// get runs in journal
+pub fn sync_journal(app_handle: &tauri::AppHandle) -> Result<Vec<SyncRun>, String> {
+    let journal: SyncJournal = store::load(app_handle, SYNC_JOURNAL_STORE_FILENAME)?;
+    Ok(journal.runs)
+}
+
+/// copy modified and accessed times, and permissions when `with_permissions` is true,
+/// from files on source side to the same paths on the other side
+/// so that metadata-only differences are quiet. files whose contents differ are skipped
+pub fn copy_metadata(
+    old_dir: &str,
+    new_dir: &str,
+    paths: &[String],
+    source: DiffSide,
+    with_permissions: bool,
+) -> Vec<MetadataCopy> {
+    let (source_dir, target_dir) = match source {
+        DiffSide::Old => (old_dir, new_dir),
+        DiffSide::New => (new_dir, old_dir),
+    };
+    paths
+        .iter()
+        .map(|path| {
+            let source = os_path(source_dir).join(os_path(path));
+            let target = os_path(target_dir).join(os_path(path));
+            let (copied, error) = match copy_file_metadata(&source, &target, with_permissions) {
+                Ok(x) => (x, None),
+                Err(err) => (false, Some(err)),
+            };
+            MetadataCopy {
+                path: path.to_owned(),
+                copied,
+                error,
+            }
+        })
+        .collect()
+}
+
+/// copy metadata of file confirmed identical in content
+/// false without change when contents differ
+fn copy_file_metadata(
+    source: &Path,
+    target: &Path,
+    with_permissions: bool,
+) -> Result<bool, String> {
+    let source_metadata = metadata(source)
+        .map_err(|err| format!("Failed to get metadata of {} ({})", source.display(), err))?;
+    if !source.is_file() || !target.is_file() {
+        return Err(format!(
+            "Not files on both sides: {}, {}",
+            source.display(),
+            target.display()
+        ));
+    }
+    if !filepaths_digest_diff(&source.to_path_buf(), &target.to_path_buf())? {
+        return Ok(false);
+    }
+
+    let write_error = |err: std::io::Error| {
+        format!(
+            "Failed to update metadata of {} ({})",
+            target.display(),
+            err
+        )
+    };
+    // set without opening file to write so that read-only file is updated too
+    filetime::set_file_times(
+        target,
+        FileTime::from_last_access_time(&source_metadata),
+        FileTime::from_last_modification_time(&source_metadata),
+    )
+    .map_err(write_error)?;
+    if with_permissions {
+        fs::set_permissions(target, source_metadata.permissions()).map_err(write_error)?;
+    }
+    Ok(true)
+}
+
+/// check if path is relative one staying under root, without `..` or drive prefix
+fn is_relative_path(path: &str) -> bool {
+    !path.is_empty()
+        && os_path(path)
+            .components()
+            .all(|x| matches!(x, Component::Normal(_) | Component::CurDir))
+}
+
+/// compare entry on both sides as it is now
+fn current_entry(old_dir: &str, new_dir: &str, path: &str) -> Result<DirEntryDiff, String> {
+    let old_filepath = os_path(old_dir).join(os_path(path));
+    let new_filepath = os_path(new_dir).join(os_path(path));
+    let file_size = |filepath: &Path| {
+        metadata(filepath)
+            .ok()
+            .filter(|x| x.is_file())
+            .map(|x| x.len())
+    };
+    let old_size = file_size(&old_filepath);
+    let new_size = file_size(&new_filepath);
+    let status = match (old_size, new_size) {
+        (Some(_), Some(_)) => {
+            if filepaths_digest_diff(&old_filepath, &new_filepath)? {
+                EntryStatus::Equal
+            } else {
+                EntryStatus::Modified
+            }
+        }
+        (Some(_), None) => EntryStatus::OldOnly,
+        (None, Some(_)) => EntryStatus::NewOnly,
+        (None, None) => return Err(format!("File not found on either side: {}", path)),
+    };
+    Ok(DirEntryDiff {
+        path: path.to_owned(),
+        status,
+        old_size,
+        new_size,
+        stale: false,
+    })
+}
+
+/// copy file with parent directories created
+fn copy_file(source: &Path, target: &Path) -> Result<(), String> {
+    create_parent_dir(target)?;
+    fs::copy(source, target).map_err(|err| {
+        format!(
+            "Failed to copy {} to {} ({})",
+            source.display(),
+            target.display(),
+            err
+        )
+    })?;
+    Ok(())
+}
+
+/// move file with parent directories created
+/// falls back to copy and delete across file systems
+fn move_file(source: &Path, target: &Path) -> Result<(), String> {
+    create_parent_dir(target)?;
+    if fs::rename(source, target).is_ok() {
+        return Ok(());
+    }
+    copy_file(source, target)?;
+    fs::remove_file(source)
+        .map_err(|err| format!("Failed to delete {} ({})", source.display(), err))
+}
+
+/// create parent directories of file path
+fn create_parent_dir(filepath: &Path) -> Result<(), String> {
+    match filepath.parent() {
+        Some(x) => fs::create_dir_all(x)
+            .map_err(|err| format!("Failed to create {} ({})", x.display(), err)),
+        None => Ok(()),
+    }
+}
+
+/// modified time of entry in directory
+fn modified(dir: &str, path: &str) -> Option<SystemTime> {
+    metadata(os_path(dir).join(os_path(path)))
+        .and_then(|x| x.modified())
+        .ok()
+}
+
+/// quote in posix shell
+fn sh_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+/// quote in powershell
+fn powershell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "''"))
+}
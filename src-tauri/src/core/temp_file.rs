@@ -0,0 +1,84 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+#[cfg(unix)]
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt, PermissionsExt};
+
+/// prefix of app dir under system temp dir, followed by user name
+const TEMP_DIR_PREFIX: &str = "forskscope-";
+
+/// private dir of current user for temporary files of app, such as `/tmp/forskscope-user/web`
+/// created only accessible by the user so that other users cannot read files or plant symlinks
+/// in it, and refused when one already there is symlink or accessible by others
+pub fn temp_dir(name: &str) -> Result<PathBuf, String> {
+    let user = std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_default();
+    let app_dirpath = std::env::temp_dir().join(format!(
+        "{}{}",
+        TEMP_DIR_PREFIX,
+        user.replace(['/', '\\', ':'], "_")
+    ));
+    let dirpath = app_dirpath.join(name);
+    for x in [&app_dirpath, &dirpath] {
+        create_private_dir(x)?;
+    }
+    Ok(dirpath)
+}
+
+/// create new file only accessible by current user, never following symlink at its path.
+/// stale file left by the same name such as by previous process of the same pid is replaced
+/// which is safe because dir is private
+pub fn create_temp_file(filepath: &Path) -> Result<File, String> {
+    let create = || {
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        options.mode(0o600);
+        options.open(filepath)
+    };
+    let ret = match create() {
+        Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {
+            fs::remove_file(filepath).and_then(|_| create())
+        }
+        x => x,
+    };
+    ret.map_err(|err| format!("Failed to create {} ({})", filepath.display(), err))
+}
+
+/// write bytes into new temporary file, which is not left behind when failed halfway
+pub fn write_temp_file(filepath: &Path, bytes: &[u8]) -> Result<(), String> {
+    let mut file = create_temp_file(filepath)?;
+    if let Err(err) = file.write_all(bytes) {
+        let _ = fs::remove_file(filepath);
+        return Err(format!("Failed to write {} ({})", filepath.display(), err));
+    }
+    Ok(())
+}
+
+/// create dir only accessible by current user, or check one already there
+fn create_private_dir(dirpath: &Path) -> Result<(), String> {
+    let mut builder = fs::DirBuilder::new();
+    #[cfg(unix)]
+    builder.mode(0o700);
+    match builder.create(dirpath) {
+        Ok(_) => return Ok(()),
+        Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {}
+        Err(err) => return Err(format!("Failed to create {} ({})", dirpath.display(), err)),
+    }
+
+    let metadata = fs::symlink_metadata(dirpath)
+        .map_err(|err| format!("Failed to read {} ({})", dirpath.display(), err))?;
+    if !metadata.is_dir() {
+        return Err(format!("Temp dir is not directory: {}", dirpath.display()));
+    }
+    #[cfg(unix)]
+    if metadata.permissions().mode() & 0o077 != 0 {
+        return Err(format!(
+            "Temp dir is accessible by other users: {}",
+            dirpath.display()
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,121 @@
+use regex::Regex;
+
+use super::diff::{diffs_response, keyed_lines_diffs};
+use super::types::{LinesDiffResponse, ReadContent};
+
+/// placeholder matching any text in line
+const ANY_PLACEHOLDER: &str = "ANY";
+/// placeholder matching integer or decimal number
+const NUMBER_PLACEHOLDER: &str = "NUMBER";
+/// template lines searched ahead of last matched one for line to match
+const MAX_TEMPLATE_LOOKAHEAD: usize = 256;
+
+/// template line compiled into regex when it has placeholders
+enum TemplateLine {
+    Literal(String),
+    Pattern(Regex),
+}
+
+impl TemplateLine {
+    fn is_match(&self, line: &str) -> bool {
+        match self {
+            TemplateLine::Literal(x) => x == line,
+            TemplateLine::Pattern(x) => x.is_match(line),
+        }
+    }
+}
+
+/// compare old side as template with new side as actual content like golden file
+/// template may have placeholders `{{ANY}}`, `{{NUMBER}}` and `{{/regex/}}`
+/// and lines matching template lines are equal while each side keeps its text
+pub fn template_diff_response(
+    template_read: ReadContent,
+    actual_read: ReadContent,
+) -> Result<LinesDiffResponse, String> {
+    let mut error = None;
+    let diffs = keyed_lines_diffs(
+        &template_read.content,
+        &actual_read.content,
+        |old_lines, new_lines| {
+            let without_eol = |x: &String| x.trim_end_matches(['\r', '\n']).to_owned();
+            let old_keys = old_lines.iter().map(without_eol).collect::<Vec<_>>();
+            let template_lines = match old_keys
+                .iter()
+                .map(|x| template_line(x))
+                .collect::<Result<Vec<_>, _>>()
+            {
+                Ok(x) => x,
+                Err(err) => {
+                    error = Some(err);
+                    vec![]
+                }
+            };
+            // lines are matched to template in order so that generic lines do not take all
+            let mut cursor = 0;
+            let new_keys = new_lines
+                .iter()
+                .map(|line| {
+                    let line = without_eol(line);
+                    let end = (cursor + MAX_TEMPLATE_LOOKAHEAD).min(template_lines.len());
+                    match (cursor..end).find(|i| template_lines[*i].is_match(&line)) {
+                        Some(i) => {
+                            cursor = i + 1;
+                            old_keys[i].to_owned()
+                        }
+                        None => line,
+                    }
+                })
+                .collect::<Vec<_>>();
+            (old_keys, new_keys)
+        },
+    );
+    if let Some(err) = error {
+        return Err(err);
+    }
+    Ok(diffs_response(diffs, template_read, actual_read))
+}
+
+/// compile template line. text out of placeholders is matched literally
+fn template_line(line: &str) -> Result<TemplateLine, String> {
+    if !line.contains("{{") {
+        return Ok(TemplateLine::Literal(line.to_owned()));
+    }
+
+    let mut pattern = String::from("^");
+    let mut has_placeholder = false;
+    let mut rest = line;
+    while let Some(start) = rest.find("{{") {
+        let Some(length) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        let name = &rest[start + 2..start + 2 + length];
+        let placeholder = match name {
+            ANY_PLACEHOLDER => Some(".*?".to_owned()),
+            NUMBER_PLACEHOLDER => Some(r"[-+]?\d+(?:\.\d+)?".to_owned()),
+            _ if 2 <= name.len() && name.starts_with('/') && name.ends_with('/') => {
+                Some(format!("({})", &name[1..name.len() - 1]))
+            }
+            // unknown placeholder is kept as text
+            _ => None,
+        };
+        let end = start + 2 + length + 2;
+        match placeholder {
+            Some(x) => {
+                pattern.push_str(&regex::escape(&rest[..start]));
+                pattern.push_str(&x);
+                has_placeholder = true;
+            }
+            None => pattern.push_str(&regex::escape(&rest[..end])),
+        }
+        rest = &rest[end..];
+    }
+    pattern.push_str(&regex::escape(rest));
+    pattern.push('$');
+
+    if !has_placeholder {
+        return Ok(TemplateLine::Literal(line.to_owned()));
+    }
+    Regex::new(&pattern)
+        .map(TemplateLine::Pattern)
+        .map_err(|err| format!("Invalid placeholder in template line {} ({})", line, err))
+}
@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+
+use similar::DiffTag;
+
+use super::types::{HunkTextStats, LinesDiff, TextScript};
+
+/// default percentage of width change beyond which hunk is flagged
+const DEFAULT_WIDTH_CHANGE_THRESHOLD_PERCENT: u32 = 50;
+/// code point ranges of east asian wide and fullwidth chars, shown in two columns
+const WIDE_RANGES: [(u32, u32); 14] = [
+    (0x1100, 0x115F),
+    (0x2E80, 0x303E),
+    (0x3041, 0x33FF),
+    (0x3400, 0x4DBF),
+    (0x4E00, 0x9FFF),
+    (0xA000, 0xA4CF),
+    (0xAC00, 0xD7A3),
+    (0xF900, 0xFAFF),
+    (0xFE30, 0xFE4F),
+    (0xFF00, 0xFF60),
+    (0xFFE0, 0xFFE6),
+    (0x1F300, 0x1F64F),
+    (0x1F900, 0x1F9FF),
+    (0x20000, 0x3FFFD),
+];
+/// code point ranges of combining marks and zero width chars, shown in no column
+const ZERO_WIDTH_RANGES: [(u32, u32); 6] = [
+    (0x0300, 0x036F),
+    (0x1AB0, 0x1AFF),
+    (0x1DC0, 0x1DFF),
+    (0x200B, 0x200F),
+    (0x20D0, 0x20FF),
+    (0xFE20, 0xFE2F),
+];
+
+/// char counts, display widths and scripts of both sides per changed hunk
+/// for localization reviews to find translations which got much longer or shorter
+/// hunk is flagged when width changes beyond threshold percent or its script changes.
+/// lines are flagged one by one too when both sides have the same number of lines
+pub fn hunk_text_stats(diffs: &[LinesDiff], threshold_percent: Option<u32>) -> Vec<HunkTextStats> {
+    let threshold_percent = threshold_percent.unwrap_or(DEFAULT_WIDTH_CHANGE_THRESHOLD_PERCENT);
+    diffs
+        .iter()
+        .filter(|x| x.diff_kind != DiffTag::Equal)
+        .map(|diff| {
+            let old_text = diff.old_lines.concat();
+            let new_text = diff.new_lines.concat();
+            let old_width = text_width(&old_text);
+            let new_width = text_width(&new_text);
+            let width_ratio = width_ratio(old_width, new_width);
+            let old_script = dominant_script(&old_text);
+            let new_script = dominant_script(&new_text);
+            let script_changed =
+                old_script.is_some() && new_script.is_some() && old_script != new_script;
+
+            let flagged_lines = if diff.old_lines.len() == diff.new_lines.len() {
+                diff.old_lines
+                    .iter()
+                    .zip(&diff.new_lines)
+                    .enumerate()
+                    .filter(|(_, (old, new))| {
+                        beyond_threshold(width_ratio_of(old, new), threshold_percent)
+                    })
+                    .map(|x| x.0)
+                    .collect()
+            } else {
+                vec![]
+            };
+
+            HunkTextStats {
+                diff_index: diff.diff_index,
+                diff_kind: diff.diff_kind,
+                old_chars: text_chars(&old_text),
+                new_chars: text_chars(&new_text),
+                old_width,
+                new_width,
+                width_delta: new_width as i64 - old_width as i64,
+                width_ratio,
+                old_script,
+                new_script,
+                script_changed,
+                flagged: script_changed || beyond_threshold(width_ratio, threshold_percent),
+                flagged_lines,
+            }
+        })
+        .collect()
+}
+
+/// chars without line endings
+fn text_chars(text: &str) -> usize {
+    text.chars().filter(|x| *x != '\n' && *x != '\r').count()
+}
+
+/// columns taken on display, where wide chars take two and marks none
+fn text_width(text: &str) -> usize {
+    text.chars().map(char_width).sum()
+}
+
+fn char_width(c: char) -> usize {
+    let code = c as u32;
+    let in_ranges = |ranges: &[(u32, u32)]| ranges.iter().any(|x| x.0 <= code && code <= x.1);
+    if c.is_control() || in_ranges(&ZERO_WIDTH_RANGES) {
+        0
+    } else if in_ranges(&WIDE_RANGES) {
+        2
+    } else {
+        1
+    }
+}
+
+/// percentage of new width to old one. none when old side is empty
+fn width_ratio(old_width: usize, new_width: usize) -> Option<f32> {
+    (0 < old_width).then(|| new_width as f32 * 100.0 / old_width as f32)
+}
+
+fn width_ratio_of(old: &str, new: &str) -> Option<f32> {
+    width_ratio(text_width(old), text_width(new))
+}
+
+fn beyond_threshold(width_ratio: Option<f32>, threshold_percent: u32) -> bool {
+    width_ratio.is_some_and(|x| (threshold_percent as f32) < (x - 100.0).abs())
+}
+
+/// script most letters are written in. kana with kanji is taken as japanese
+fn dominant_script(text: &str) -> Option<TextScript> {
+    let mut counts = HashMap::new();
+    for c in text.chars().filter(|x| x.is_alphabetic()) {
+        *counts.entry(char_script(c)).or_insert(0usize) += 1;
+    }
+    if let Some(kana) = counts.get(&TextScript::Japanese).copied() {
+        let han = counts.remove(&TextScript::Han).unwrap_or_default();
+        counts.insert(TextScript::Japanese, kana + han);
+    }
+    counts
+        .into_iter()
+        .max_by_key(|(script, count)| (*count, std::cmp::Reverse(*script)))
+        .map(|x| x.0)
+}
+
+fn char_script(c: char) -> TextScript {
+    match c as u32 {
+        0x0041..=0x024F | 0x1E00..=0x1EFF => TextScript::Latin,
+        0x0370..=0x03FF | 0x1F00..=0x1FFF => TextScript::Greek,
+        0x0400..=0x052F => TextScript::Cyrillic,
+        0x0590..=0x05FF => TextScript::Hebrew,
+        0x0600..=0x06FF | 0x0750..=0x077F => TextScript::Arabic,
+        0x0900..=0x097F => TextScript::Devanagari,
+        0x0E00..=0x0E7F => TextScript::Thai,
+        0x1100..=0x11FF | 0x3130..=0x318F | 0xAC00..=0xD7A3 => TextScript::Hangul,
+        0x3040..=0x30FF | 0x31F0..=0x31FF | 0xFF66..=0xFF9F => TextScript::Japanese,
+        0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xF900..=0xFAFF | 0x20000..=0x3FFFD => TextScript::Han,
+        _ => TextScript::Other,
+    }
+}
@@ -0,0 +1,79 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::thread;
+
+use super::file::bytes_content;
+use super::str::{base64_decode, split_lines_with_endings};
+use super::types::{ReadContent, Transform};
+
+/// program run by jq transform
+const JQ_PROGRAM: &str = "jq";
+
+/// transform content of side in order such as to compare file against its rendered form
+/// charset is kept as read from file
+pub fn apply_transforms(read: &mut ReadContent, transforms: &[Transform]) -> Result<(), String> {
+    for transform in transforms {
+        read.content = transformed(&read.content, transform)?;
+    }
+    Ok(())
+}
+
+/// content transformed by step
+fn transformed(content: &str, transform: &Transform) -> Result<String, String> {
+    match transform {
+        Transform::Jq { filter } => jq_output(content, filter),
+        Transform::SortLines => {
+            let mut lines = content_lines(content);
+            lines.sort();
+            Ok(joined_lines(&lines))
+        }
+        Transform::Uniq => {
+            let mut lines = content_lines(content);
+            lines.dedup();
+            Ok(joined_lines(&lines))
+        }
+        Transform::Base64Decode => Ok(bytes_content(&base64_decode(content)?).content),
+    }
+}
+
+/// lines without line endings so that last line without one is sorted as others
+fn content_lines(content: &str) -> Vec<String> {
+    split_lines_with_endings(content)
+        .into_iter()
+        .map(|x| x.trim_end_matches(['\r', '\n']).to_owned())
+        .collect()
+}
+
+fn joined_lines(lines: &[String]) -> String {
+    lines.iter().map(|x| format!("{}\n", x)).collect()
+}
+
+/// run jq with filter on content given to stdin
+fn jq_output(content: &str, filter: &str) -> Result<String, String> {
+    let mut child = Command::new(JQ_PROGRAM)
+        .arg(filter)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| format!("Failed to run {} ({})", JQ_PROGRAM, err))?;
+
+    // written in another thread so that child is not blocked by full stdout pipe
+    let mut stdin = child.stdin.take().expect("Failed to get stdin");
+    let input = content.to_owned();
+    let writer = thread::spawn(move || stdin.write_all(input.as_bytes()));
+    let output = child
+        .wait_with_output()
+        .map_err(|err| format!("Failed to wait {} ({})", JQ_PROGRAM, err))?;
+    if let Ok(Err(err)) = writer.join() {
+        tracing::warn!("Failed to write stdin of {} ({})", JQ_PROGRAM, err);
+    }
+    if !output.status.success() {
+        return Err(format!(
+            "Failed to apply jq filter {} ({})",
+            filter,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(bytes_content(&output.stdout).content)
+}
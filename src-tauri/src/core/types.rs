@@ -1,31 +1,58 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::time::SystemTime;
+
 use similar::DiffTag;
 
 // use serde::{Deserialize, Serialize};
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize)]
+#[derive(Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct CompareSet {
     pub old: CompareSetItem,
     pub new: CompareSetItem,
+    // set on launch by git as difftool or mergetool
+    pub tool: Option<ToolInvocation>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct CompareSetItem {
     pub filepath: String,
     pub binary_comparison_only: bool,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct LinesDiffResponse {
     pub old_charset: String,
     pub new_charset: String,
+    pub old_charset_candidates: Vec<CharsetCandidate>,
+    pub new_charset_candidates: Vec<CharsetCandidate>,
+    // set on file comparison
+    pub compare_mode: Option<CompareMode>,
     pub diffs: Vec<LinesDiff>,
+    // set when view rows are requested so that view can be switched without comparing again
+    pub split_rows: Option<Vec<DiffRow>>,
+    pub inline_rows: Option<Vec<DiffRow>>,
+    pub old_compression: Option<CompressionInfo>,
+    pub new_compression: Option<CompressionInfo>,
+    pub minimap: Minimap,
+    // set when both sides are single lines
+    pub single_line_diff: Option<Vec<CharsGroup>>,
+    // per-extension rule applied by default
+    pub comparison_rule: Option<ComparisonRule>,
+    // diff indices of hunks marked as ignored on file comparison
+    pub ignored_hunks: Vec<usize>,
+    // diff ran out of time budget and hunks are coarse
+    pub timed_out: bool,
+    // set when both sides are text decoded with different charsets
+    pub charset_mismatch: Option<CharsetMismatch>,
+    pub old_mime_type: Option<String>,
+    pub new_mime_type: Option<String>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct LinesDiff {
     pub diff_index: usize,
@@ -33,6 +60,35 @@ pub struct LinesDiff {
     pub lines_count: usize,
     pub old_lines: Vec<String>,
     pub new_lines: Vec<String>,
+    #[serde(default)]
+    pub classification: HunkClassification,
+    #[serde(default)]
+    pub change_level: ChangeLevel,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum ChangeLevel {
+    #[default]
+    Unchanged,
+    Added,
+    Removed,
+    Modified,
+    // removed and added elsewhere with the same lines
+    Moved,
+    // including merge conflict markers
+    Conflict,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct HunkClassification {
+    // all flags are false on equal hunks
+    pub whitespace_only: bool,
+    pub case_only: bool,
+    // set only when language is known from file extension
+    pub comment_only: bool,
+    pub eol_only: bool,
 }
 
 #[derive(Serialize)]
@@ -62,6 +118,9 @@ pub struct ListDirResponse {
     pub current_dir: String,
     pub dirs: Vec<String>,
     pub files: Vec<FileAttr>,
+    // true when metadata could not be fetched in time on network filesystem
+    // and only names are listed as files
+    pub partial: bool,
 }
 
 #[derive(Serialize, Eq, PartialEq, Ord, PartialOrd)]
@@ -72,10 +131,1490 @@ pub struct FileAttr {
     pub human_readable_size: String,
     pub last_modified: String,
     pub binary_comparison_only: bool,
+    // identified by content sniffing. none when not read such as on remote listing
+    pub mime_type: Option<String>,
 }
 
 #[derive(Clone, Default)]
 pub struct ReadContent {
     pub charset: String,
     pub content: String,
+    // likely charsets in descending order of confidence. empty on non text
+    pub charset_candidates: Vec<CharsetCandidate>,
+    // set when file was decompressed transparently
+    pub compression: Option<CompressionInfo>,
+    // identified by content sniffing when content is read from bytes
+    pub mime_type: Option<String>,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CharsetCandidate {
+    pub charset: String,
+    // ratio in percentage of chars decoded without malformed sequences
+    pub confidence: f32,
+    // chosen by detector
+    pub detected: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum DiffSide {
+    #[default]
+    Old,
+    New,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum AnnotationKind {
+    Bookmark,
+    Comment,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Annotation {
+    pub id: u64,
+    pub kind: AnnotationKind,
+    pub side: DiffSide,
+    pub diff_index: usize,
+    // none means annotation is attached to whole hunk
+    pub line_index: Option<usize>,
+    pub text: String,
+    pub created_at: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnnotationInput {
+    pub kind: AnnotationKind,
+    pub side: DiffSide,
+    pub diff_index: usize,
+    pub line_index: Option<usize>,
+    #[serde(default)]
+    pub text: String,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct AnnotationStore {
+    pub comparisons: Vec<ComparisonAnnotations>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ComparisonAnnotations {
+    pub old: String,
+    pub new: String,
+    pub annotations: Vec<Annotation>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandSpec {
+    pub program: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    pub cwd: Option<String>,
+    #[serde(default)]
+    pub env: Vec<CommandEnv>,
+    pub timeout_secs: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandEnv {
+    pub key: String,
+    pub value: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum LowMemoryMode {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpoolInfo {
+    pub id: u64,
+    pub charset: String,
+    pub lines_count: usize,
+}
+
+#[derive(Serialize)]
+#[serde(
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase",
+    tag = "event",
+    content = "data"
+)]
+pub enum LinesDiffStreamEvent {
+    Started {
+        old_charset: String,
+        new_charset: String,
+    },
+    Hunks {
+        diffs: Vec<LinesDiff>,
+    },
+    Finished {
+        diffs_count: usize,
+        // hunks streamed as removed or added and found moved once all hunks are built
+        moved_diff_indices: Vec<usize>,
+    },
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum SortKey {
+    #[default]
+    Name,
+    Size,
+    Modified,
+    Extension,
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimilarPair {
+    pub old_name: String,
+    pub new_name: String,
+    pub similarity: f32,
+}
+
+#[derive(Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PermissionAttr {
+    // octal on unix-like os and empty on others
+    pub mode: String,
+    pub readonly: bool,
+    pub uid: Option<u32>,
+    pub gid: Option<u32>,
+    // extended entries only. none when acl is not supported
+    pub acl: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PermissionDiff {
+    pub name: String,
+    pub permission_equal: bool,
+    // none on directories
+    pub content_equal: Option<bool>,
+    pub old: PermissionAttr,
+    pub new: PermissionAttr,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ShellSelection {
+    pub left: String,
+    // local time when left side was selected, empty in selection saved by older versions
+    #[serde(default)]
+    pub selected_at: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum EntryStatus {
+    Equal,
+    Modified,
+    OldOnly,
+    NewOnly,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TreeEntryDiff {
+    pub path: String,
+    pub status: EntryStatus,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MetadataFieldDiff {
+    pub key: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+    pub status: EntryStatus,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum CompareMode {
+    Text,
+    Structured,
+    Spreadsheet,
+    Archive,
+    Image,
+    // opendocument text or rich text compared by text extracted
+    Document,
+    Binary,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveEntry {
+    pub name: String,
+    pub is_dir: bool,
+    pub size: u64,
+    pub compressed_size: u64,
+    // none on tar
+    pub crc32: Option<u32>,
+    pub encrypted: bool,
+    // zip compression method. 0 means stored
+    pub method: u16,
+    // offset of local header on zip and of data on tar
+    pub data_offset: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum ScheduleKind {
+    Files,
+    Dirs,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduleInput {
+    pub kind: ScheduleKind,
+    pub old: String,
+    pub new: String,
+    // none means comparison is not re-run periodically
+    pub interval_secs: Option<u64>,
+    pub at_launch: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RunSummary {
+    pub ran_at: String,
+    // hunks on files and entries not equal on dirs
+    pub diffs_count: usize,
+    // digest of result to tell if it changed since the last run
+    pub fingerprint: String,
+    pub changed: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Schedule {
+    pub id: u64,
+    pub kind: ScheduleKind,
+    pub old: String,
+    pub new: String,
+    pub interval_secs: Option<u64>,
+    pub at_launch: bool,
+    // latest last
+    pub history: Vec<RunSummary>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduleStore {
+    pub schedules: Vec<Schedule>,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduleRunEvent {
+    pub id: u64,
+    pub summary: RunSummary,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DirEntryDiff {
+    pub path: String,
+    pub status: EntryStatus,
+    // none when entry does not exist on the side
+    pub old_size: Option<u64>,
+    pub new_size: Option<u64>,
+    // restored from result of last launch and changed since then. to be compared again
+    #[serde(default)]
+    pub stale: bool,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct DirDiffFilter {
+    // empty means all statuses
+    pub statuses: Vec<EntryStatus>,
+    // extensions without dot. empty include means all extensions
+    pub include_extensions: Vec<String>,
+    pub exclude_extensions: Vec<String>,
+    // compared with larger size of both sides
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DirDiffPage {
+    pub entries: Vec<DirEntryDiff>,
+    // count of all entries passing filter
+    pub total_count: usize,
+    pub offset: usize,
+    // count of all entries to be compared again
+    pub stale_count: usize,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LineAuthorship {
+    pub commit: String,
+    pub author: String,
+    // date in local time zone
+    pub authored_at: String,
+    pub summary: String,
+    // false on lines modified in working tree
+    pub committed: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum SizeUnit {
+    // 1 KB = 1024 bytes
+    #[default]
+    Binary,
+    // 1 kB = 1000 bytes
+    Si,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct NumberFormat {
+    pub decimal_separator: String,
+    pub grouping_separator: String,
+    // digits in group. no grouping when zero
+    pub grouping_size: usize,
+    pub size_unit: SizeUnit,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Settings {
+    pub number_format: NumberFormat,
+    pub palette_kind: PaletteKind,
+    pub comparison_rules: Vec<ComparisonRule>,
+    pub external_tools: Vec<ExternalTool>,
+    // time budget of lines diff. default is used when none
+    pub diff_timeout_millis: Option<u64>,
+    pub path_remapping: PathRemapping,
+    pub path_policy: PathPolicy,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BinaryField {
+    // byte range to be annotated on hex view
+    pub offset: u64,
+    pub length: u64,
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryEntry {
+    pub old: String,
+    pub new: String,
+    pub compared_at: String,
+    pub compare_mode: Option<CompareMode>,
+    // percentage (0 - 100). none when not calculated such as on streaming
+    pub similarity: Option<f32>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryStore {
+    // oldest first
+    pub entries: Vec<HistoryEntry>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryQuery {
+    // substring of either old or new path
+    pub path: Option<String>,
+    // dates such as `2024-01-02` or datetimes. both inclusive
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryQueryResponse {
+    // newest first
+    pub entries: Vec<HistoryEntry>,
+    pub compared_count: usize,
+    pub last_compared_at: Option<String>,
+    pub average_similarity: Option<f32>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum NavigationDirection {
+    First,
+    Previous,
+    Next,
+    Last,
+}
+
+#[derive(Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ViewNavigation {
+    // diff indices of hunks not equal, which are navigation targets
+    pub hunk_diff_indices: Vec<usize>,
+    // position in `hunk_diff_indices`. none before first navigation
+    pub current_hunk: Option<usize>,
+    pub focused_pane: DiffSide,
+    // diff indices of collapsed regions
+    pub collapsed: Vec<usize>,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct NavigationEvent {
+    pub view_id: String,
+    pub navigation: ViewNavigation,
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum DiffView {
+    Split,
+    Inline,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum DiffRowKind {
+    Equal,
+    Removed,
+    Added,
+    // old and new lines aligned side by side. split view only
+    Changed,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffRow {
+    pub diff_index: usize,
+    pub kind: DiffRowKind,
+    // 1-based. segments of long line share line number
+    pub old_line_number: Option<usize>,
+    pub new_line_number: Option<usize>,
+    // true on segments of long line after first one, which carry line number of first one
+    pub old_continuation: bool,
+    pub new_continuation: bool,
+    pub old_text: Option<String>,
+    pub new_text: Option<String>,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ScratchBuffer {
+    pub id: u64,
+    // used as file path of side such as `scratch://0`
+    pub url: String,
+    // label such as `untitled-1`
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum PaletteKind {
+    #[default]
+    Default,
+    // red-green color blindness
+    Deuteranopia,
+    Protanopia,
+    // blue-yellow color blindness
+    Tritanopia,
+    HighContrast,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PaletteColor {
+    pub change_level: ChangeLevel,
+    // css colors such as `#0072b2`
+    pub foreground: String,
+    pub background: String,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Palette {
+    pub kind: PaletteKind,
+    pub colors: Vec<PaletteColor>,
+    // css custom properties such as `--diff-added-fg` to be embedded in exports
+    pub css: String,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum PairingReason {
+    SamePath,
+    NamePattern,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FilePairing {
+    pub old_path: String,
+    pub new_path: String,
+    pub reason: PairingReason,
+    pub confidence: f32,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FilePairingResult {
+    pub old_path: String,
+    pub new_path: String,
+    pub equal: Option<bool>,
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LineMapSegment {
+    pub diff_index: usize,
+    pub diff_kind: DiffTag,
+    pub old_start: usize,
+    pub old_end: usize,
+    pub new_start: usize,
+    pub new_end: usize,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DirEntryChange {
+    pub path: String,
+    // none when entry newly appeared
+    pub previous_status: Option<EntryStatus>,
+    // none when entry disappeared on both sides
+    pub entry: Option<DirEntryDiff>,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DirWatchEvent {
+    pub old_dir: String,
+    pub new_dir: String,
+    pub changes: Vec<DirEntryChange>,
+    // files looking truncated or being written, compared after they stabilize
+    pub unsettled: Vec<UnsettledFile>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ComparisonRule {
+    // extension such as `csv` or glob such as `*.log.?` and `**/logs/*.txt`
+    pub pattern: String,
+    pub compare_mode: Option<CompareMode>,
+    pub charset: Option<String>,
+    // lines containing any of them are excluded from comparison
+    #[serde(default)]
+    pub ignored_texts: Vec<String>,
+    // equal lines shown around hunks. none shows all
+    pub context_lines: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum SyncDirection {
+    OldToNew,
+    NewToOld,
+    Mirror,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum SyncOperationKind {
+    Copy,
+    Delete,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncOperation {
+    pub kind: SyncOperationKind,
+    pub path: String,
+    // none on delete
+    pub source: Option<DiffSide>,
+    pub target: DiffSide,
+    // bytes to be copied
+    pub size: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncPlan {
+    pub old_dir: String,
+    pub new_dir: String,
+    pub direction: SyncDirection,
+    pub operations: Vec<SyncOperation>,
+    pub copy_bytes: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum SyncScriptKind {
+    Sh,
+    PowerShell,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncRecord {
+    pub operation: SyncOperation,
+    // where deleted file was moved
+    pub trashed_to: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncRun {
+    pub id: u64,
+    pub started_at: String,
+    pub finished_at: String,
+    pub old_dir: String,
+    pub new_dir: String,
+    pub direction: SyncDirection,
+    pub records: Vec<SyncRecord>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncJournal {
+    pub runs: Vec<SyncRun>,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncProgressEvent {
+    pub run_id: u64,
+    pub completed: usize,
+    pub total: usize,
+    pub path: String,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum RangeUnit {
+    Lines,
+    Bytes,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ContentRange {
+    pub unit: RangeUnit,
+    // lines are 1-based and inclusive. bytes are 0-based and end-exclusive
+    pub start: u64,
+    pub end: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum CompressionFormat {
+    Gzip,
+    Bzip2,
+    Xz,
+    Zstd,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CompressionInfo {
+    pub format: CompressionFormat,
+    pub compressed_size: u64,
+    pub decompressed_size: u64,
+}
+
+#[derive(Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Minimap {
+    pub lines_per_bucket: usize,
+    // ratio of changed rows per bucket
+    pub densities: Vec<f32>,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CharsGroup {
+    pub diff_kind: DiffTag,
+    pub old_chars: String,
+    pub new_chars: String,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LogEntry {
+    pub timestamp: String,
+    pub level: String,
+    pub target: String,
+    pub message: String,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct FileUseStatus {
+    // opening file failed on lock or sharing violation
+    pub locked: bool,
+    // lock file of app which has file open
+    pub lock_file: Option<String>,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceBoundary {
+    pub filepath: String,
+    // 1-based and end-exclusive lines in concatenated document
+    pub start_line: usize,
+    pub end_line: usize,
+    // 1-based line in source file where boundary starts
+    pub source_start_line: usize,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConcatenatedDiffResponse {
+    pub diff: LinesDiffResponse,
+    pub old_sources: Vec<SourceBoundary>,
+    pub new_sources: Vec<SourceBoundary>,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordDiff {
+    pub status: EntryStatus,
+    // 1-based line numbers of records
+    pub old_line: Option<usize>,
+    pub new_line: Option<usize>,
+    pub old_record: Option<String>,
+    pub new_record: Option<String>,
+    // percentage (0 - 100) between aligned records
+    pub similarity: f32,
+}
+
+#[derive(Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct FilePreview {
+    pub size: u64,
+    // none on directory
+    pub compare_mode: Option<CompareMode>,
+    pub charset: Option<String>,
+    // first lines, archive entry names or hex dump of head
+    pub text: Option<String>,
+    pub image_data_url: Option<String>,
+    pub metadata: BTreeMap<String, String>,
+    // entries in archive or directory
+    pub entries_count: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PaneEdit {
+    pub side: DiffSide,
+    // 1-based line numbers before edit. end-exclusive so that insertion has start equal to end
+    pub start_line: usize,
+    pub end_line: usize,
+    // text replacing the lines including line endings
+    pub text: String,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PartialLinesDiff {
+    // changed diffs, which replace previous diffs[start_index..start_index + replaced_count]
+    // diffs after them are kept with diff indices shifted by changed_count - replaced_count
+    pub diffs: Vec<LinesDiff>,
+    pub start_index: usize,
+    pub changed_count: usize,
+    pub replaced_count: usize,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SheetRowDiff {
+    pub sheet: String,
+    // values of key columns
+    pub key: Vec<String>,
+    pub status: EntryStatus,
+    // 1-based row numbers in sheets
+    pub old_row: Option<usize>,
+    pub new_row: Option<usize>,
+    // cells keyed by header name or column letters. empty on both sides are omitted
+    pub cells: Vec<MetadataFieldDiff>,
+}
+
+/// file size and modified time to detect changes without reading files
+pub type FileStamp = (u64, Option<SystemTime>);
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CachedDirDiff {
+    pub old_dir: String,
+    pub new_dir: String,
+    // stamps of files by relative path when compared
+    pub old_stamps: HashMap<String, FileStamp>,
+    pub new_stamps: HashMap<String, FileStamp>,
+    pub entries: Vec<DirEntryDiff>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct DirDiffCache {
+    // most recent first
+    pub items: Vec<CachedDirDiff>,
+}
+
+#[derive(Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct MarkupOptions {
+    pub ignore_attribute_order: bool,
+    // whitespace between tags and runs of whitespace in text
+    pub ignore_whitespace: bool,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BinaryEquality {
+    pub identical: bool,
+    // none when identical. size of shorter file when it is prefix of the other
+    pub first_difference_offset: Option<u64>,
+    pub old_size: u64,
+    pub new_size: u64,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BinaryEqualityProgressEvent {
+    pub old: String,
+    pub new: String,
+    pub compared_bytes: u64,
+    // size of shorter file
+    pub total_bytes: u64,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct FileRevision {
+    pub commit: String,
+    // path relative to repository root at the commit, which differs after rename
+    pub path: String,
+    pub author: String,
+    // date in local time zone
+    pub authored_at: String,
+    pub summary: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileTimelineStep {
+    // 0 for the newest revision
+    pub index: usize,
+    pub revisions_count: usize,
+    pub revision: FileRevision,
+    // revision as old side and working copy as new side
+    pub diff: LinesDiffResponse,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ComparisonOptions {
+    pub old_charset: Option<String>,
+    pub new_charset: Option<String>,
+    pub compare_mode: Option<CompareMode>,
+    pub with_rows: Option<bool>,
+    pub reveal_invisible: Option<bool>,
+    pub ignore_case: Option<bool>,
+    // applied in order to each side before comparison
+    #[serde(default)]
+    pub old_transforms: Vec<Transform>,
+    #[serde(default)]
+    pub new_transforms: Vec<Transform>,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenComparison {
+    pub id: u64,
+    pub old: String,
+    pub new: String,
+    pub options: ComparisonOptions,
+    // text edited but not saved yet
+    pub old_buffer: Option<String>,
+    pub new_buffer: Option<String>,
+    // last diff, discarded when options or buffers change
+    pub diff: Option<LinesDiffResponse>,
+    // diff indices of hunks excluded from patch export
+    pub excluded_hunks: BTreeSet<usize>,
+    // scroll position and collapsed hunks, restored on reopen
+    pub view_state: ViewState,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ComparisonSummary {
+    pub id: u64,
+    pub old: String,
+    pub new: String,
+    // true when either side has unsaved buffer
+    pub dirty: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum ToolInvocationKind {
+    Diff,
+    Merge,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolInvocation {
+    pub kind: ToolInvocationKind,
+    // common ancestor on merge
+    pub base: Option<String>,
+    // path in work tree shown as label on diff, and file to save result into on merge
+    pub merged: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ExternalTool {
+    pub name: String,
+    pub program: String,
+    // `{old}` and `{new}` are replaced with file paths
+    pub args: Vec<String>,
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum Indentation {
+    #[default]
+    None,
+    Tabs,
+    Spaces,
+    Mixed,
+}
+
+#[derive(Serialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct FileProfile {
+    pub size: u64,
+    // counts of bytes by value, 256 elements
+    pub byte_histogram: Vec<u64>,
+    pub is_binary: bool,
+    // text stats below are empty on binary file
+    pub line_count: usize,
+    // in chars
+    pub max_line_length: usize,
+    pub average_line_length: f32,
+    pub indentation: Indentation,
+    // most common step of space indents
+    pub indent_width: Option<usize>,
+    // guessed by file extension, shebang or leading content
+    pub language: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileComparison {
+    pub old: FileProfile,
+    pub new: FileProfile,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleManifest {
+    pub version: u32,
+    // file names of entries under `old/` and `new/` in bundle. empty when side is empty
+    pub old_name: String,
+    pub new_name: String,
+    pub options: ComparisonOptions,
+    pub annotations: Vec<Annotation>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ComparisonBundle {
+    // paths of files extracted from bundle
+    pub old: String,
+    pub new: String,
+    pub options: ComparisonOptions,
+    pub annotations: Vec<Annotation>,
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum UnsettledReason {
+    // size dropped since previous read
+    Truncated,
+    // text file ends with nul bytes such as space preallocated by writer
+    NulPadded,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct UnsettledFile {
+    pub path: String,
+    pub side: DiffSide,
+    pub reason: UnsettledReason,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplaceQuery {
+    pub pattern: String,
+    // may refer to groups such as `$1` when pattern is regex
+    pub replacement: String,
+    #[serde(default)]
+    pub regex: bool,
+    #[serde(default)]
+    pub case_sensitive: bool,
+    #[serde(default)]
+    pub whole_word: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplacePreview {
+    // content after replacement
+    pub content: String,
+    pub replaced_count: usize,
+    // content before replacement as old side and after as new side
+    pub diff: LinesDiffResponse,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AudioComparison {
+    // format, duration, sample rate, channels, bitrate and bits per sample
+    pub fields: Vec<MetadataFieldDiff>,
+    pub old_duration_secs: f64,
+    pub new_duration_secs: f64,
+    // 0.0 to 1.0 by coarse waveform envelopes. set only when both are pcm wav
+    pub waveform_similarity: Option<f32>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkingTreeEntryDiff {
+    // relative to repository root
+    pub path: String,
+    // old side is tree at ref and new side is working tree
+    pub status: EntryStatus,
+    // not tracked and not ignored. status is new only
+    pub untracked: bool,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct IgnoredHunkStore {
+    pub comparisons: Vec<ComparisonIgnoredHunks>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ComparisonIgnoredHunks {
+    pub old: String,
+    pub new: String,
+    // fingerprints of hunk contents on both sides
+    pub fingerprints: BTreeSet<String>,
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum DirRollupRank {
+    #[default]
+    ChangedFiles,
+    ChangedBytes,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DirRollup {
+    // relative directory path
+    pub path: String,
+    // files modified, old only or new only under directory recursively
+    pub changed_files: usize,
+    pub modified_files: usize,
+    pub old_only_files: usize,
+    pub new_only_files: usize,
+    // larger size of both sides of changed files
+    pub changed_bytes: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PathRemapRule {
+    // root on old side treated as equivalent to root on new side
+    pub old: String,
+    pub new: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PathRemapping {
+    pub rules: Vec<PathRemapRule>,
+    // `C:\x` and `D:\x` are treated as `\x`
+    pub ignore_drive_letters: bool,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CharsetMismatch {
+    pub old_charset: String,
+    pub new_charset: String,
+    // malformed sequences were replaced with U+FFFD on decode
+    pub old_lossy: bool,
+    pub new_lossy: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MetadataCopy {
+    pub path: String,
+    // false when contents differ or copy failed
+    pub copied: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase",
+    tag = "kind"
+)]
+pub enum Transform {
+    // filter run by jq command
+    Jq { filter: String },
+    SortLines,
+    // adjacent duplicate lines are merged as uniq command
+    Uniq,
+    Base64Decode,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LineSetDiff {
+    // diff of lines sorted and deduplicated on both sides
+    pub diff: LinesDiffResponse,
+    pub old_only: Vec<String>,
+    pub new_only: Vec<String>,
+    // count of distinct lines on both sides
+    pub common_count: usize,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FrontmatterDiff {
+    // fields of frontmatter flattened into dotted keys such as `author.name` and `tags[0]`
+    pub frontmatter: Vec<MetadataFieldDiff>,
+    pub body: LinesDiffResponse,
+    // 0-based line where body starts in file, after frontmatter
+    pub old_body_start_line: usize,
+    pub new_body_start_line: usize,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum HunkCopyFormat {
+    UnifiedDiff,
+    // old and new lines in columns of plain text
+    SideBySide,
+    MarkdownTable,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PaneLines {
+    // 0-based row in pane where lines start
+    pub start: usize,
+    // rows on side in whole pane
+    pub total_lines: usize,
+    pub lines: Vec<PaneLine>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PaneLine {
+    // line numbers on both sides and segment of long line such as `12:14:0`
+    // stable while lines above are not changed, and shared by lines of equal hunk on both sides
+    pub id: String,
+    // 1-based. segments of long line share line number
+    pub line_number: usize,
+    // true on segments of long line after first one
+    pub continuation: bool,
+    pub text: String,
+    pub diff_index: usize,
+    // true when line is in hunk other than equal one
+    pub changed: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LineRange {
+    // 1-based and inclusive
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnippetDiff {
+    pub diff: LinesDiffResponse,
+    // 1-based line in file where snippet starts
+    pub old_start_line: usize,
+    pub new_start_line: usize,
+    // chars of common leading whitespace removed from lines
+    pub old_indent_width: usize,
+    pub new_indent_width: usize,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct LocationStore {
+    // latest first
+    pub recent: Vec<RecentDir>,
+    pub pinned: Vec<PinnedDir>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RecentDir {
+    pub path: String,
+    pub visited_at: String,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PinnedDir {
+    pub path: String,
+    // shown instead of path when set
+    pub label: Option<String>,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct OperationProgressEvent {
+    pub operation_id: u64,
+    pub operation: OperationKind,
+    // file path, url or directory operated
+    pub target: String,
+    pub phase: ProgressPhase,
+    pub unit: ProgressUnit,
+    pub processed: u64,
+    // none when not known such as on ftp download
+    pub total: Option<u64>,
+    // estimated milliseconds to finish. none until total and rate are known
+    pub eta_millis: Option<u64>,
+    // set on failure
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum OperationKind {
+    Read,
+    Hash,
+    Scan,
+    Download,
+    Sync,
+}
+
+#[derive(Serialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum ProgressPhase {
+    Started,
+    Running,
+    Finished,
+    Failed,
+}
+
+#[derive(Serialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum ProgressUnit {
+    Bytes,
+    Entries,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ViewState {
+    // line at top of pane when last viewed
+    pub anchor: Option<ViewAnchor>,
+    // diff indices of equal hunks collapsed
+    #[serde(default)]
+    pub collapsed_hunks: BTreeSet<usize>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ViewAnchor {
+    pub side: DiffSide,
+    // 1-based line on side, kept meaningful when files change after saved
+    pub line_number: usize,
+    // hunk viewed last
+    pub diff_index: usize,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ViewStateStore {
+    // latest saved first
+    pub comparisons: Vec<ComparisonViewState>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ComparisonViewState {
+    pub old: String,
+    pub new: String,
+    pub view_state: ViewState,
+    pub saved_at: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoredView {
+    pub view_state: ViewState,
+    // 0-based row in pane on anchor side to scroll to. none when not diffed yet
+    pub anchor_row: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct WebCacheStore {
+    // latest fetched first
+    pub entries: Vec<WebCacheEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebCacheEntry {
+    pub url: String,
+    pub etag: String,
+    pub fetched_at: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HunkTextStats {
+    pub diff_index: usize,
+    pub diff_kind: DiffTag,
+    // chars without line endings
+    pub old_chars: usize,
+    pub new_chars: usize,
+    // columns on display where east asian wide chars take two
+    pub old_width: usize,
+    pub new_width: usize,
+    // new width minus old one
+    pub width_delta: i64,
+    // percentage of new width to old one. none when old side is empty
+    pub width_ratio: Option<f32>,
+    // script most letters are written in. none without letters
+    pub old_script: Option<TextScript>,
+    pub new_script: Option<TextScript>,
+    pub script_changed: bool,
+    // true when width changed beyond threshold or script changed
+    pub flagged: bool,
+    // 0-based offsets in hunk of lines whose width changed beyond threshold,
+    // only when both sides have the same number of lines
+    pub flagged_lines: Vec<usize>,
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(rename_all = "camelCase")]
+pub enum TextScript {
+    Latin,
+    Greek,
+    Cyrillic,
+    Hebrew,
+    Arabic,
+    Devanagari,
+    Thai,
+    Hangul,
+    // kana with or without kanji
+    Japanese,
+    Han,
+    Other,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalizationEntryDiff {
+    // msgid prefixed with msgctxt on gettext, or key on others
+    pub key: String,
+    pub status: LocalizationChange,
+    // msgid on gettext, or comment for translators on others
+    pub old_source: Option<String>,
+    pub new_source: Option<String>,
+    pub old_translation: Option<String>,
+    pub new_translation: Option<String>,
+    // true when gettext entry on new side is marked fuzzy
+    pub fuzzy: bool,
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum LocalizationChange {
+    Equal,
+    New,
+    Removed,
+    ChangedTranslation,
+    ChangedSource,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PathPolicy {
+    // paths outside them are refused when any is set
+    pub allowed_roots: Vec<String>,
+    // paths under them are refused even in allowed roots
+    pub blocked_roots: Vec<String>,
+    pub ignore_system_dir_warnings: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PathCheck {
+    // resolved with symlinks and `..`
+    pub path: String,
+    pub allowed: bool,
+    // reason why path is refused
+    pub refusal: Option<String>,
+    // to be confirmed before reading
+    pub warnings: Vec<PathWarning>,
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum PathWarning {
+    SystemDirectory,
+    NetworkMount,
+    // filesystem root or mount point whose whole tree may be too large
+    MountRoot,
 }
@@ -0,0 +1,380 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use chrono::{Local, TimeZone};
+use similar::{DiffTag, TextDiff};
+
+use super::diff::lines_diff_response;
+use super::field_diff::entry_status;
+use super::file::{bytes_content, filepaths_content};
+use super::os_path::{os_path, os_str_string, path_string};
+use super::path_policy::ensure_relative;
+use super::types::{
+    EntryStatus, FileRevision, FileTimelineStep, LineAuthorship, ReadContent, TreeEntryDiff,
+    WorkingTreeEntryDiff,
+};
+
+/// run git command in repository and get its stdout
+pub fn git(repo: &str, args: &[&str]) -> Result<Vec<u8>, String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(args)
+        .output()
+        .map_err(|err| format!("Failed to run git ({})", err))?;
+    if !output.status.success() {
+        return Err(format!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(output.stdout)
+}
+
+/// resolve ref given by user to commit hash
+/// so that ref such as `--output=x` is never taken as option by later commands
+pub fn commit_hash(repo: &str, git_ref: &str) -> Result<String, String> {
+    let stdout = git(
+        repo,
+        &[
+            "rev-parse",
+            "--verify",
+            "--end-of-options",
+            &format!("{}^{{commit}}", git_ref),
+        ],
+    )?;
+    Ok(String::from_utf8_lossy(&stdout).trim().to_owned())
+}
+
+/// list blob ids of files in tree at ref, keyed by path relative to repository root
+pub fn tree_blobs(repo: &str, git_ref: &str) -> Result<BTreeMap<String, String>, String> {
+    let commit = commit_hash(repo, git_ref)?;
+    let stdout = git(repo, &["ls-tree", "-r", "-z", "--full-tree", &commit])?;
+
+    let mut ret = BTreeMap::new();
+    for entry in stdout.split(|x| *x == 0).filter(|x| !x.is_empty()) {
+        // <mode> SP <type> SP <object> TAB <path>
+        let entry = String::from_utf8_lossy(entry);
+        let (meta, path) = match entry.split_once('\t') {
+            Some(x) => x,
+            None => continue,
+        };
+        let mut meta = meta.split(' ');
+        let object_type = meta.nth(1).unwrap_or_default();
+        let object = meta.next().unwrap_or_default();
+        if object_type == "blob" {
+            ret.insert(path.to_owned(), object.to_owned());
+        }
+    }
+    Ok(ret)
+}
+
+/// compare trees at two refs without checking them out
+/// only entries under `dir` are collected when it is specified
+pub fn ref_tree_diffs(
+    repo: &str,
+    old_ref: &str,
+    new_ref: &str,
+    dir: Option<&str>,
+) -> Result<Vec<TreeEntryDiff>, String> {
+    let old_blobs = tree_blobs(repo, old_ref)?;
+    let new_blobs = tree_blobs(repo, new_ref)?;
+    Ok(tree_blobs_diffs(&old_blobs, &new_blobs, dir))
+}
+
+/// compare blob ids of trees
+pub fn tree_blobs_diffs(
+    old_blobs: &BTreeMap<String, String>,
+    new_blobs: &BTreeMap<String, String>,
+    dir: Option<&str>,
+) -> Vec<TreeEntryDiff> {
+    let prefix = dir
+        .filter(|x| !x.is_empty())
+        .map(|x| format!("{}/", x.trim_end_matches('/')));
+    let in_dir = |path: &String| match &prefix {
+        Some(prefix) => path.starts_with(prefix.as_str()),
+        None => true,
+    };
+
+    let mut paths = old_blobs
+        .keys()
+        .chain(new_blobs.keys())
+        .filter(|x| in_dir(x))
+        .collect::<Vec<_>>();
+    paths.sort();
+    paths.dedup();
+
+    paths
+        .into_iter()
+        .filter_map(|path| {
+            let status = entry_status(old_blobs.get(path), new_blobs.get(path))?;
+            Some(TreeEntryDiff {
+                path: path.to_owned(),
+                status,
+            })
+        })
+        .collect()
+}
+
+/// get content of file at ref
+/// empty when file does not exist at ref
+pub fn ref_file_content(repo: &str, git_ref: &str, path: &str) -> Result<ReadContent, String> {
+    let object = format!("{}:{}", commit_hash(repo, git_ref)?, path);
+    // commit is already verified, so failure means path is missing at it
+    if git(repo, &["cat-file", "-e", &object]).is_err() {
+        return Ok(ReadContent::default());
+    }
+    let bytes = git(repo, &["cat-file", "blob", &object])?;
+    Ok(bytes_content(&bytes))
+}
+
+/// compare working tree of repository as new side with tree at ref as old side
+/// untracked files not ignored are flagged so that they are shown apart from added ones
+/// only entries under `dir` relative to repository root are collected when it is specified
+pub fn working_tree_diffs(
+    repo: &str,
+    git_ref: &str,
+    dir: Option<&str>,
+) -> Result<Vec<WorkingTreeEntryDiff>, String> {
+    let null_separated = |stdout: Vec<u8>| {
+        stdout
+            .split(|x| *x == 0)
+            .filter(|x| !x.is_empty())
+            .map(|x| String::from_utf8_lossy(x).to_string())
+            .collect::<Vec<_>>()
+    };
+    let commit = commit_hash(repo, git_ref)?;
+    let blobs = tree_blobs(repo, &commit)?;
+    // <status> NUL <path> NUL on each changed tracked file
+    let changes = null_separated(git(
+        repo,
+        &["diff", "--name-status", "--no-renames", "-z", &commit, "--"],
+    )?);
+    let statuses = changes
+        .chunks(2)
+        .filter(|x| x.len() == 2)
+        .map(|x| (x[1].to_owned(), x[0].to_owned()))
+        .collect::<BTreeMap<_, _>>();
+    let untracked = null_separated(git(
+        repo,
+        &[
+            "ls-files",
+            "-z",
+            "--full-name",
+            "--others",
+            "--exclude-standard",
+            // whole working tree even when repo is its subdirectory
+            ":/",
+        ],
+    )?);
+
+    let prefix = dir
+        .filter(|x| !x.is_empty())
+        .map(|x| format!("{}/", x.trim_end_matches('/')));
+    let in_dir = |path: &String| match &prefix {
+        Some(prefix) => path.starts_with(prefix.as_str()),
+        None => true,
+    };
+
+    let mut ret = blobs
+        .keys()
+        .chain(statuses.keys())
+        .filter(|x| in_dir(x))
+        .map(|path| {
+            let status = match statuses.get(path).map(|x| x.as_str()) {
+                None => EntryStatus::Equal,
+                Some("D") => EntryStatus::OldOnly,
+                Some("A") => EntryStatus::NewOnly,
+                Some(_) => EntryStatus::Modified,
+            };
+            (path.to_owned(), (status, false))
+        })
+        .collect::<BTreeMap<_, _>>();
+    ret.extend(
+        untracked
+            .into_iter()
+            .filter(|x| in_dir(x))
+            .map(|path| (path, (EntryStatus::NewOnly, true))),
+    );
+    Ok(ret
+        .into_iter()
+        .map(|(path, (status, untracked))| WorkingTreeEntryDiff {
+            path,
+            status,
+            untracked,
+        })
+        .collect())
+}
+
+/// get content of file in working tree by path relative to repository root
+/// empty when file does not exist. path escaping root by `..`, absolute path or symlink is refused
+pub fn working_file_content(repo: &str, path: &str) -> Result<ReadContent, String> {
+    ensure_relative(path)?;
+    let stdout = git(repo, &["rev-parse", "--show-toplevel"])?;
+    let root = String::from_utf8_lossy(&stdout).trim_end().to_owned();
+    let filepath = Path::new(&root).join(os_path(path));
+    if !filepath.is_file() {
+        return Ok(ReadContent::default());
+    }
+    let canonical_root =
+        fs::canonicalize(&root).map_err(|err| format!("Failed to read {} ({})", root, err))?;
+    let filepath = fs::canonicalize(&filepath)
+        .map_err(|err| format!("Failed to read {} ({})", filepath.display(), err))?;
+    if !filepath.starts_with(&canonical_root) {
+        return Err(format!("Path out of repository: {}", path));
+    }
+    let bytes = fs::read(&filepath)
+        .map_err(|err| format!("Failed to read {} ({})", filepath.display(), err))?;
+    Ok(bytes_content(&bytes))
+}
+
+/// get who last touched each line of file in working tree
+pub fn blame_lines(filepath: &str) -> Result<Vec<LineAuthorship>, String> {
+    let (repo, filename) = repo_and_filename(filepath)?;
+    let stdout = git(&repo, &["blame", "--line-porcelain", "--", &filename])?;
+
+    let mut ret = vec![];
+    let mut commit = String::new();
+    let mut author = String::new();
+    let mut authored_at = String::new();
+    let mut summary = String::new();
+    for line in String::from_utf8_lossy(&stdout).lines() {
+        // content line closes header of each line
+        if line.starts_with('\t') {
+            ret.push(LineAuthorship {
+                committed: commit.chars().any(|x| x != '0'),
+                commit: std::mem::take(&mut commit),
+                author: std::mem::take(&mut author),
+                authored_at: std::mem::take(&mut authored_at),
+                summary: std::mem::take(&mut summary),
+            });
+            continue;
+        }
+        let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+        match key {
+            "author" => author = value.to_owned(),
+            "author-time" => authored_at = local_datetime(value),
+            "summary" => summary = value.to_owned(),
+            _ if commit.is_empty() && key.len() == 40 => commit = key.to_owned(),
+            _ => {}
+        }
+    }
+    Ok(ret)
+}
+
+/// split file path into its dir, where git is run, and file name
+fn repo_and_filename(filepath: &str) -> Result<(String, String), String> {
+    let path = os_path(filepath);
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(filename)) => Ok((
+            if parent.as_os_str().is_empty() {
+                ".".to_owned()
+            } else {
+                path_string(parent)
+            },
+            os_str_string(filename),
+        )),
+        _ => Err(format!("Invalid path: {}", filepath)),
+    }
+}
+
+/// format unix time in local time zone
+fn local_datetime(unix_time: &str) -> String {
+    unix_time
+        .parse::<i64>()
+        .ok()
+        .and_then(|x| Local.timestamp_opt(x, 0).single())
+        .map(|x| x.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_default()
+}
+
+/// get authorship of lines added or changed on new side, parallel to its lines
+/// none on lines equal to old side
+pub fn blame_overlay(old: &str, new: &str) -> Result<Vec<Option<LineAuthorship>>, String> {
+    let read_contents = filepaths_content(old, new)?;
+    let mut authorships = blame_lines(new)?.into_iter().map(Some).collect::<Vec<_>>();
+
+    let text_diff = TextDiff::from_lines(
+        read_contents[0].content.as_str(),
+        read_contents[1].content.as_str(),
+    );
+    for op in text_diff.ops() {
+        if op.tag() == DiffTag::Equal {
+            for i in op.new_range() {
+                if let Some(x) = authorships.get_mut(i) {
+                    *x = None;
+                }
+            }
+        }
+    }
+    Ok(authorships)
+}
+
+/// commits touching file in working tree, newest first, following renames
+/// each revision has path of file at the commit relative to repository root
+pub fn file_revisions(filepath: &str) -> Result<Vec<FileRevision>, String> {
+    let (repo, filename) = repo_and_filename(filepath)?;
+    let stdout = git(
+        &repo,
+        &[
+            "-c",
+            "core.quotePath=off",
+            "log",
+            "--follow",
+            "--name-only",
+            "--format=%x1e%H%x1f%an%x1f%at%x1f%s",
+            "--",
+            &filename,
+        ],
+    )?;
+
+    let ret = String::from_utf8_lossy(&stdout)
+        .split('\x1e')
+        .filter_map(|record| {
+            let (header, paths) = record.split_once('\n')?;
+            let mut fields = header.split('\x1f');
+            let commit = fields.next()?.to_owned();
+            let author = fields.next().unwrap_or_default().to_owned();
+            let authored_at = local_datetime(fields.next().unwrap_or_default());
+            let summary = fields.next().unwrap_or_default().to_owned();
+            let path = paths.lines().rfind(|x| !x.is_empty())?.to_owned();
+            Some(FileRevision {
+                commit,
+                path,
+                author,
+                authored_at,
+                summary,
+            })
+        })
+        .collect();
+    Ok(ret)
+}
+
+/// compare file at revision of its history as old side with working copy as new side
+/// index is 0 for the newest revision and increases to step back in history
+pub fn timeline_step(filepath: &str, index: usize) -> Result<FileTimelineStep, String> {
+    let revisions = file_revisions(filepath)?;
+    let revision = revisions.get(index).cloned().ok_or(format!(
+        "Revision {} not found in history of {}",
+        index, filepath
+    ))?;
+    let (repo, _) = repo_and_filename(filepath)?;
+    let bytes = git(
+        &repo,
+        &[
+            "cat-file",
+            "blob",
+            &format!("{}:{}", revision.commit, revision.path),
+        ],
+    )?;
+    let new_read = filepaths_content(filepath, "")?.remove(0);
+    Ok(FileTimelineStep {
+        index,
+        revisions_count: revisions.len(),
+        revision,
+        diff: lines_diff_response(bytes_content(&bytes), new_read),
+    })
+}
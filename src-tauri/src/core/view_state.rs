@@ -0,0 +1,47 @@
+use chrono::Local;
+
+use super::store;
+use super::types::{ComparisonViewState, ViewState, ViewStateStore};
+
+/// file name to persist view states of comparisons
+const VIEW_STATES_STORE_FILENAME: &str = "view_states.json";
+/// max comparisons whose view states are kept. least recently saved ones are dropped
+const MAX_VIEW_STATES: usize = 500;
+
+/// get view state saved on file pair, or default one when not saved
+pub fn view_state(
+    app_handle: &tauri::AppHandle,
+    old: &str,
+    new: &str,
+) -> Result<ViewState, String> {
+    let store: ViewStateStore = store::load(app_handle, VIEW_STATES_STORE_FILENAME)?;
+    let ret = store
+        .comparisons
+        .into_iter()
+        .find(|x| x.old == old && x.new == new)
+        .map(|x| x.view_state)
+        .unwrap_or_default();
+    Ok(ret)
+}
+
+/// save view state of file pair as the latest one
+pub fn save_view_state(
+    app_handle: &tauri::AppHandle,
+    old: &str,
+    new: &str,
+    view_state: &ViewState,
+) -> Result<(), String> {
+    let mut store: ViewStateStore = store::load(app_handle, VIEW_STATES_STORE_FILENAME)?;
+    store.comparisons.retain(|x| x.old != old || x.new != new);
+    store.comparisons.insert(
+        0,
+        ComparisonViewState {
+            old: old.to_owned(),
+            new: new.to_owned(),
+            view_state: view_state.clone(),
+            saved_at: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        },
+    );
+    store.comparisons.truncate(MAX_VIEW_STATES);
+    store::save(app_handle, VIEW_STATES_STORE_FILENAME, &store)
+}
@@ -0,0 +1,418 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fs::{metadata, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use tauri::{Emitter, Manager};
+
+use super::diff::{collect_relative_filepaths, filepaths_digest_diff};
+use super::dir_diff::DirDiffResults;
+use super::os_path::{os_path, os_str_string};
+use super::types::{
+    DiffSide, DirEntryChange, DirEntryDiff, DirWatchEvent, EntryStatus, FileStamp, UnsettledFile,
+    UnsettledReason,
+};
+
+/// event emitted when entries in watched folder comparison change
+pub const DIR_WATCH_EVENT: &str = "dir-watch-changed";
+/// interval to poll watched directories when filesystem notifications are not available,
+/// and to check again files deferred as unsettled
+const WATCH_POLL_MILLIS: u64 = 2000;
+/// wait for more notifications after first one so that burst of writes is handled at once
+const WATCH_DEBOUNCE_MILLIS: u64 = 300;
+/// length of head and tail checked for nul padding of file being written
+const NUL_TAIL_LENGTH: u64 = 64;
+
+/// changes notified on watched roots
+enum Notified {
+    /// nothing changed and nothing is pending
+    Nothing,
+    /// paths relative to each root which may have changed
+    Paths(BTreeSet<String>, BTreeSet<String>),
+    /// events may have been lost so that whole trees are walked again
+    Rescan,
+}
+
+/// app state to keep watches on folder comparisons
+#[derive(Default)]
+pub struct DirWatches(Mutex<HashMap<(String, String), Arc<AtomicBool>>>);
+
+impl DirWatches {
+    /// start watching both roots of folder comparison
+    /// only changed files are compared again and their changes are emitted.
+    /// changes are taken from filesystem notifications, or by polling when they are not available
+    pub fn watch(
+        &self,
+        app_handle: tauri::AppHandle,
+        old_dir: &str,
+        new_dir: &str,
+    ) -> Result<(), String> {
+        let key = (old_dir.to_owned(), new_dir.to_owned());
+        if self.0.lock().unwrap().contains_key(&key) {
+            return Ok(());
+        }
+
+        let entries =
+            app_handle
+                .state::<DirDiffResults>()
+                .entries(&app_handle, old_dir, new_dir)?;
+        let old_stamps = file_stamps(old_dir)?;
+        let new_stamps = file_stamps(new_dir)?;
+
+        let running = Arc::new(AtomicBool::new(true));
+        self.0.lock().unwrap().insert(key, running.clone());
+
+        let old_dir = old_dir.to_owned();
+        let new_dir = new_dir.to_owned();
+        thread::spawn(move || {
+            let mut entries = entries
+                .into_iter()
+                .map(|x| (x.path.to_owned(), x))
+                .collect::<HashMap<_, _>>();
+            let mut old_stamps = old_stamps;
+            let mut new_stamps = new_stamps;
+            let mut pending_paths = BTreeSet::new();
+
+            let (sender, receiver) = mpsc::channel();
+            let watcher = notify::recommended_watcher(sender).and_then(|mut watcher| {
+                watcher.watch(&os_path(&old_dir), RecursiveMode::Recursive)?;
+                watcher.watch(&os_path(&new_dir), RecursiveMode::Recursive)?;
+                Ok(watcher)
+            });
+            // such as on watches exhausted or on network filesystem
+            let watcher: Option<RecommendedWatcher> = match watcher {
+                Ok(x) => Some(x),
+                Err(err) => {
+                    tracing::warn!("Failed to watch {} and {} ({})", old_dir, new_dir, err);
+                    None
+                }
+            };
+
+            while running.load(Ordering::Relaxed) {
+                let notified = match &watcher {
+                    Some(_) => {
+                        notified_paths(&receiver, &old_dir, &new_dir, !pending_paths.is_empty())
+                    }
+                    None => {
+                        thread::sleep(Duration::from_millis(WATCH_POLL_MILLIS));
+                        Notified::Rescan
+                    }
+                };
+                if !running.load(Ordering::Relaxed) {
+                    break;
+                }
+
+                // roots may be removed or unmounted temporarily
+                let current = match notified {
+                    Notified::Nothing => continue,
+                    Notified::Paths(old_paths, new_paths) => (
+                        updated_stamps(&old_dir, &old_stamps, &old_paths),
+                        updated_stamps(&new_dir, &new_stamps, &new_paths),
+                    ),
+                    Notified::Rescan => (file_stamps(&old_dir), file_stamps(&new_dir)),
+                };
+                let (Ok(old_current), Ok(new_current)) = current else {
+                    continue;
+                };
+                let old_changed = changed_paths(&old_stamps, &old_current);
+                let new_changed = changed_paths(&new_stamps, &new_current);
+                // files looking truncated or half-written are compared after they stabilize
+                let unsettled = old_changed
+                    .iter()
+                    .filter_map(|path| {
+                        partial_write(&old_dir, path, &old_stamps, &old_current)
+                            .map(|reason| (path, DiffSide::Old, reason))
+                    })
+                    .chain(new_changed.iter().filter_map(|path| {
+                        partial_write(&new_dir, path, &new_stamps, &new_current)
+                            .map(|reason| (path, DiffSide::New, reason))
+                    }))
+                    .map(|(path, side, reason)| UnsettledFile {
+                        path: path.to_owned(),
+                        side,
+                        reason,
+                    })
+                    .collect::<Vec<_>>();
+                let unsettled_paths = unsettled
+                    .iter()
+                    .map(|x| x.path.to_owned())
+                    .collect::<BTreeSet<_>>();
+                // paths deferred on previous poll are compared once they are settled
+                let mut paths = pending_paths.clone();
+                paths.extend(old_changed);
+                paths.extend(new_changed);
+                paths.retain(|x| !unsettled_paths.contains(x));
+                let unsettled_changed = pending_paths != unsettled_paths;
+                pending_paths = unsettled_paths;
+                old_stamps = old_current;
+                new_stamps = new_current;
+
+                let changes = paths
+                    .into_iter()
+                    .filter_map(|path| {
+                        let entry = entry_diff(&old_dir, &new_dir, &path, &old_stamps, &new_stamps);
+                        let previous = entries.get(&path);
+                        let previous_status = previous.map(|x| x.status);
+                        let unchanged = match (previous, &entry) {
+                            (Some(a), Some(b)) => {
+                                a.status == b.status
+                                    && a.old_size == b.old_size
+                                    && a.new_size == b.new_size
+                            }
+                            (None, None) => true,
+                            _ => false,
+                        };
+                        if unchanged {
+                            return None;
+                        }
+                        match &entry {
+                            Some(x) => entries.insert(path.to_owned(), x.clone()),
+                            None => entries.remove(&path),
+                        };
+                        Some(DirEntryChange {
+                            path,
+                            previous_status,
+                            entry,
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                if changes.is_empty() && !unsettled_changed {
+                    continue;
+                }
+
+                app_handle
+                    .state::<DirDiffResults>()
+                    .apply_changes(&old_dir, &new_dir, &changes);
+                let event = DirWatchEvent {
+                    old_dir: old_dir.to_owned(),
+                    new_dir: new_dir.to_owned(),
+                    changes,
+                    unsettled,
+                };
+                if let Err(err) = app_handle.emit(DIR_WATCH_EVENT, event) {
+                    tracing::warn!("Failed to emit dir watch ({})", err);
+                }
+            }
+        });
+        Ok(())
+    }
+
+    /// stop watching folder comparison
+    pub fn unwatch(&self, old_dir: &str, new_dir: &str) {
+        if let Some(running) = self
+            .0
+            .lock()
+            .unwrap()
+            .remove(&(old_dir.to_owned(), new_dir.to_owned()))
+        {
+            running.store(false, Ordering::Relaxed);
+        }
+    }
+}
+
+/// size and modified time of files under directory by relative path
+pub fn file_stamps(dir: &str) -> Result<HashMap<String, FileStamp>, String> {
+    let mut filepaths = vec![];
+    collect_relative_filepaths(&os_path(dir), "", &mut filepaths)?;
+    let ret = filepaths
+        .into_iter()
+        .filter_map(|path| {
+            let metadata = metadata(os_path(dir).join(os_path(&path))).ok()?;
+            Some((path, (metadata.len(), metadata.modified().ok())))
+        })
+        .collect();
+    Ok(ret)
+}
+
+/// wait for notifications and collect paths changed under each root
+/// waiting ends after poll interval with nothing notified, when files deferred are to be checked
+fn notified_paths(
+    receiver: &Receiver<notify::Result<notify::Event>>,
+    old_dir: &str,
+    new_dir: &str,
+    has_pending: bool,
+) -> Notified {
+    let first = match receiver.recv_timeout(Duration::from_millis(WATCH_POLL_MILLIS)) {
+        Ok(x) => x,
+        Err(RecvTimeoutError::Timeout) if has_pending => {
+            return Notified::Paths(BTreeSet::new(), BTreeSet::new())
+        }
+        Err(RecvTimeoutError::Timeout) => return Notified::Nothing,
+        // watcher is dropped
+        Err(RecvTimeoutError::Disconnected) => {
+            thread::sleep(Duration::from_millis(WATCH_POLL_MILLIS));
+            return Notified::Rescan;
+        }
+    };
+    let mut events = vec![first];
+    let deadline = Instant::now() + Duration::from_millis(WATCH_DEBOUNCE_MILLIS);
+    while let Ok(x) = receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+        events.push(x);
+    }
+
+    let roots = [old_dir, new_dir].map(|x| {
+        let root = os_path(x);
+        // notified paths may be canonical ones such as on macos
+        let canonical = root.canonicalize().unwrap_or_else(|_| root.clone());
+        (root, canonical)
+    });
+    let mut paths = [BTreeSet::new(), BTreeSet::new()];
+    for event in events {
+        let event = match event {
+            Ok(x) if !x.need_rescan() => x,
+            _ => return Notified::Rescan,
+        };
+        for path in event.paths {
+            for ((root, canonical), paths) in roots.iter().zip(paths.iter_mut()) {
+                let Ok(relative) = path
+                    .strip_prefix(root)
+                    .or_else(|_| path.strip_prefix(canonical))
+                else {
+                    continue;
+                };
+                if relative.as_os_str().is_empty() {
+                    return Notified::Rescan;
+                }
+                // separated in the same way as collected relative file paths
+                paths.insert(
+                    relative
+                        .components()
+                        .map(|x| os_str_string(x.as_os_str()))
+                        .collect::<Vec<_>>()
+                        .join("/"),
+                );
+            }
+        }
+    }
+    let [old_paths, new_paths] = paths;
+    Notified::Paths(old_paths, new_paths)
+}
+
+/// stamps with notified paths stat again, without walking whole tree
+/// path may be directory created or removed with files under it
+fn updated_stamps(
+    dir: &str,
+    previous: &HashMap<String, FileStamp>,
+    paths: &BTreeSet<String>,
+) -> Result<HashMap<String, FileStamp>, String> {
+    if !os_path(dir).is_dir() {
+        return Err(format!("Directory not found: {}", dir));
+    }
+    let mut ret = previous.clone();
+    for path in paths {
+        let prefix = format!("{}/", path);
+        ret.retain(|x, _| x != path && !x.starts_with(&prefix));
+        let filepath = os_path(dir).join(os_path(path));
+        let Ok(path_metadata) = metadata(&filepath) else {
+            continue;
+        };
+        if path_metadata.is_dir() {
+            let mut filepaths = vec![];
+            collect_relative_filepaths(&os_path(dir), path, &mut filepaths)?;
+            ret.extend(filepaths.into_iter().filter_map(|x| {
+                let metadata = metadata(os_path(dir).join(os_path(&x))).ok()?;
+                Some((x, (metadata.len(), metadata.modified().ok())))
+            }));
+        } else if path_metadata.is_file() {
+            ret.insert(
+                path.to_owned(),
+                (path_metadata.len(), path_metadata.modified().ok()),
+            );
+        }
+    }
+    Ok(ret)
+}
+
+/// paths added, removed or modified between snapshots
+pub fn changed_paths(
+    previous: &HashMap<String, FileStamp>,
+    current: &HashMap<String, FileStamp>,
+) -> BTreeSet<String> {
+    let mut ret = current
+        .iter()
+        .filter(|(path, stamp)| previous.get(*path) != Some(stamp))
+        .map(|(path, _)| path.to_owned())
+        .collect::<BTreeSet<_>>();
+    ret.extend(
+        previous
+            .keys()
+            .filter(|x| !current.contains_key(*x))
+            .cloned(),
+    );
+    ret
+}
+
+/// reason why file looks truncated or being written
+/// size dropped since previous poll, or text file ends with nul bytes
+/// such as space preallocated by writer
+fn partial_write(
+    dir: &str,
+    path: &str,
+    previous: &HashMap<String, FileStamp>,
+    current: &HashMap<String, FileStamp>,
+) -> Option<UnsettledReason> {
+    let size = current.get(path)?.0;
+    if previous.get(path).is_some_and(|x| size < x.0) {
+        return Some(UnsettledReason::Truncated);
+    }
+    if size < NUL_TAIL_LENGTH {
+        return None;
+    }
+    let mut file = File::open(os_path(dir).join(os_path(path))).ok()?;
+    let mut head = vec![0; NUL_TAIL_LENGTH as usize];
+    file.read_exact(&mut head).ok()?;
+    // binary file may end with nul bytes legitimately
+    if head.contains(&0) {
+        return None;
+    }
+    let mut tail = vec![0; NUL_TAIL_LENGTH as usize];
+    file.seek(SeekFrom::End(-(NUL_TAIL_LENGTH as i64))).ok()?;
+    file.read_exact(&mut tail).ok()?;
+    if tail.iter().all(|x| *x == 0) {
+        Some(UnsettledReason::NulPadded)
+    } else {
+        None
+    }
+}
+
+/// compare entry again. none when it exists on neither side
+fn entry_diff(
+    old_dir: &str,
+    new_dir: &str,
+    path: &str,
+    old_stamps: &HashMap<String, FileStamp>,
+    new_stamps: &HashMap<String, FileStamp>,
+) -> Option<DirEntryDiff> {
+    let old_size = old_stamps.get(path).map(|x| x.0);
+    let new_size = new_stamps.get(path).map(|x| x.0);
+    let status = match (old_size, new_size) {
+        (Some(_), Some(_)) => {
+            let equal = filepaths_digest_diff(
+                &os_path(old_dir).join(os_path(path)),
+                &os_path(new_dir).join(os_path(path)),
+            )
+            // file unreadable such as being replaced is shown as modified until next change
+            .unwrap_or_default();
+            if equal {
+                EntryStatus::Equal
+            } else {
+                EntryStatus::Modified
+            }
+        }
+        (Some(_), None) => EntryStatus::OldOnly,
+        (None, Some(_)) => EntryStatus::NewOnly,
+        (None, None) => return None,
+    };
+    Some(DirEntryDiff {
+        path: path.to_owned(),
+        status,
+        old_size,
+        new_size,
+        stale: false,
+    })
+}
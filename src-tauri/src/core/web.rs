@@ -0,0 +1,464 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::PathBuf;
+
+use chrono::Local;
+use serde::Deserialize;
+use tauri::Manager;
+
+use super::file::fnv1a_digest;
+use super::ftp::{percent_decode, safe_filename, tcp_connect, tls_connect};
+use super::store;
+use super::temp_file::{temp_dir, write_temp_file};
+use super::types::{WebCacheEntry, WebCacheStore};
+
+/// file name to persist etags of contents fetched
+const WEB_CACHE_STORE_FILENAME: &str = "web_cache.json";
+/// max urls whose etags are kept. least recently fetched ones are dropped
+const MAX_WEB_CACHE_ENTRIES: usize = 200;
+/// max redirects followed on fetch
+const MAX_REDIRECTS: usize = 5;
+/// max bytes of content fetched, which is compared as text
+const MAX_WEB_CONTENT_SIZE: u64 = 64 * 1024 * 1024;
+/// bytes read at once from response body
+const WEB_READ_CHUNK_SIZE: usize = 64 * 1024;
+/// user agent which github api requires
+const USER_AGENT: &str = "forskscope";
+
+/// location parsed from `http://` or `https://` url
+struct WebLocation {
+    secure: bool,
+    host: String,
+    port: u16,
+    // path with query
+    target: String,
+}
+
+/// result of conditional get
+enum Fetched {
+    Content {
+        bytes: Vec<u8>,
+        etag: Option<String>,
+    },
+    NotModified,
+}
+
+/// gist metadata from github api
+#[derive(Deserialize)]
+struct Gist {
+    files: BTreeMap<String, GistFile>,
+}
+
+#[derive(Deserialize)]
+struct GistFile {
+    filename: String,
+    raw_url: String,
+}
+
+/// check if path is http or https url
+pub fn is_web_url(path: &str) -> bool {
+    path.starts_with("http://") || path.starts_with("https://")
+}
+
+/// fetch text behind url such as gist, code permalink or paste into temporary file
+/// page urls are resolved into raw content ones, and line range in fragment such as `#L10-L20`
+/// cuts content. unchanged content is not downloaded again by its etag
+/// bytes received so far and total when known are reported to `on_progress`
+pub fn web_download(
+    app_handle: &tauri::AppHandle,
+    url: &str,
+    mut on_progress: impl FnMut(u64, Option<u64>),
+) -> Result<PathBuf, String> {
+    let (page_url, fragment) = match url.split_once('#') {
+        Some((page_url, fragment)) => (page_url, Some(fragment)),
+        None => (url, None),
+    };
+    let raw_url = raw_content_url(app_handle, page_url, fragment)?;
+    let mut bytes = cached_fetch(app_handle, &raw_url, &mut on_progress)?;
+    if let Some((start, end)) = fragment.and_then(line_range_fragment) {
+        bytes = lines_in_range(&bytes, start, end);
+    }
+
+    let dirpath = temp_dir("web")?;
+    // file name keeps extension so that compare mode is detected as of local file
+    // and is made safe because decoded one may have separators such as `%2F..%2F`
+    let filename = percent_decode(
+        raw_url
+            .split(['?', '#'])
+            .next()
+            .and_then(|x| x.rsplit('/').next())
+            .filter(|x| !x.is_empty())
+            .unwrap_or("download"),
+    );
+    let filepath = dirpath.join(format!(
+        "{}-{:016x}-{}",
+        std::process::id(),
+        fnv1a_digest(url.as_bytes()),
+        safe_filename(&filename)
+    ));
+    write_temp_file(&filepath, &bytes)?;
+    Ok(filepath)
+}
+
+/// url of raw content behind page of known services. other urls are fetched as they are
+fn raw_content_url(
+    app_handle: &tauri::AppHandle,
+    url: &str,
+    fragment: Option<&str>,
+) -> Result<String, String> {
+    let location = parse_web_url(url)?;
+    let path = location.target.split('?').next().unwrap_or_default();
+    let segments = path
+        .split('/')
+        .filter(|x| !x.is_empty())
+        .collect::<Vec<_>>();
+    let ret = match (location.host.as_str(), segments.as_slice()) {
+        // `gist.github.com/[user/]id`, whose file is selected by fragment such as `#file-main-rs`
+        ("gist.github.com", [.., id]) => {
+            return gist_raw_url(app_handle, id, fragment);
+        }
+        // `github.com/owner/repo/blob/ref/path`
+        ("github.com", [owner, repo, "blob", rest @ ..]) if !rest.is_empty() => format!(
+            "https://raw.githubusercontent.com/{}/{}/{}",
+            owner,
+            repo,
+            rest.join("/")
+        ),
+        // `gitlab.com/group/project/-/blob/ref/path`
+        ("gitlab.com", _) if path.contains("/-/blob/") => url.replacen("/-/blob/", "/-/raw/", 1),
+        // `gitlab.com/-/snippets/id`
+        ("gitlab.com", [.., "snippets", _]) => format!("{}/raw", url.trim_end_matches('/')),
+        ("pastebin.com", [id]) => format!("https://pastebin.com/raw/{}", id),
+        ("dpaste.org", [id]) => format!("https://dpaste.org/{}/raw", id),
+        _ => url.to_owned(),
+    };
+    Ok(ret)
+}
+
+/// raw url of gist file whose anchor matches fragment, or of the first file
+fn gist_raw_url(
+    app_handle: &tauri::AppHandle,
+    id: &str,
+    fragment: Option<&str>,
+) -> Result<String, String> {
+    let api_url = format!("https://api.github.com/gists/{}", id);
+    let bytes = cached_fetch(app_handle, &api_url, &mut |_, _| ())?;
+    let gist: Gist = serde_json::from_slice(&bytes)
+        .map_err(|err| format!("Failed to parse gist {} ({})", id, err))?;
+    let mut files = gist.files.into_values();
+    let file = match fragment {
+        Some(fragment) => files.find(|x| gist_file_anchor(&x.filename) == fragment),
+        None => files.next(),
+    };
+    file.map(|x| x.raw_url)
+        .ok_or(format!("File not found in gist {}", id))
+}
+
+/// anchor of gist file on page such as `file-main-rs` of `main.rs`
+fn gist_file_anchor(filename: &str) -> String {
+    let name = filename
+        .to_lowercase()
+        .chars()
+        .map(|x| {
+            if x.is_alphanumeric() || x == '_' {
+                x
+            } else {
+                '-'
+            }
+        })
+        .collect::<String>();
+    format!("file-{}", name)
+}
+
+/// 1-based inclusive line range from fragment such as `L10`, `L10-L20` or `L10-20`
+fn line_range_fragment(fragment: &str) -> Option<(usize, usize)> {
+    let (start, end) = match fragment.split_once('-') {
+        Some((start, end)) => (start, end.trim_start_matches('L')),
+        None => (fragment, fragment.trim_start_matches('L')),
+    };
+    let start = start.strip_prefix('L')?.parse::<usize>().ok()?;
+    let end = end.parse::<usize>().ok()?;
+    (0 < start && start <= end).then_some((start, end))
+}
+
+/// lines of content in 1-based inclusive range
+fn lines_in_range(bytes: &[u8], start: usize, end: usize) -> Vec<u8> {
+    bytes
+        .split_inclusive(|x| *x == b'\n')
+        .skip(start - 1)
+        .take(end - start + 1)
+        .flatten()
+        .copied()
+        .collect()
+}
+
+/// fetch url, or read content cached on previous fetch when server reports it is unchanged
+/// content is cached in app cache dir private to user, not in shared temp dir
+fn cached_fetch(
+    app_handle: &tauri::AppHandle,
+    url: &str,
+    on_progress: &mut dyn FnMut(u64, Option<u64>),
+) -> Result<Vec<u8>, String> {
+    let cache_filepath = app_handle
+        .path()
+        .app_cache_dir()
+        .map_err(|err| format!("Failed to get app cache dir ({})", err))?
+        .join("web")
+        .join(format!("{:016x}", fnv1a_digest(url.as_bytes())));
+    let mut store: WebCacheStore = store::load(app_handle, WEB_CACHE_STORE_FILENAME)?;
+    let etag = store
+        .entries
+        .iter()
+        .find(|x| x.url == url)
+        .map(|x| x.etag.to_owned())
+        .filter(|_| cache_filepath.exists());
+
+    let (bytes, etag) = match fetch(url, etag.as_deref(), on_progress)? {
+        Fetched::NotModified => {
+            let bytes = fs::read(&cache_filepath)
+                .map_err(|err| format!("Failed to read {} ({})", cache_filepath.display(), err))?;
+            (bytes, etag)
+        }
+        Fetched::Content { bytes, etag } => {
+            if etag.is_some() {
+                if let Some(dirpath) = cache_filepath.parent() {
+                    fs::create_dir_all(dirpath).map_err(|err| {
+                        format!("Failed to create {} ({})", dirpath.display(), err)
+                    })?;
+                }
+                fs::write(&cache_filepath, &bytes).map_err(|err| {
+                    format!("Failed to write {} ({})", cache_filepath.display(), err)
+                })?;
+            }
+            (bytes, etag)
+        }
+    };
+
+    store.entries.retain(|x| x.url != url);
+    if let Some(etag) = etag {
+        store.entries.insert(
+            0,
+            WebCacheEntry {
+                url: url.to_owned(),
+                etag,
+                fetched_at: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            },
+        );
+        store.entries.truncate(MAX_WEB_CACHE_ENTRIES);
+    }
+    store::save(app_handle, WEB_CACHE_STORE_FILENAME, &store)?;
+    Ok(bytes)
+}
+
+/// get url following redirects, conditionally on etag when given
+/// redirect from https to http is refused so that content is not tampered on the way
+fn fetch(
+    url: &str,
+    etag: Option<&str>,
+    on_progress: &mut dyn FnMut(u64, Option<u64>),
+) -> Result<Fetched, String> {
+    let mut url = url.to_owned();
+    for _ in 0..=MAX_REDIRECTS {
+        let location = parse_web_url(&url)?;
+        let mut reader = BufReader::new(send_request(&location, etag)?);
+        let (status, headers) = read_response_head(&mut reader, &url)?;
+        let header = |name: &str| headers.iter().find(|x| x.0 == name).map(|x| x.1.to_owned());
+        match status {
+            200 => {
+                let bytes = read_body(&mut reader, &header, &url, on_progress)?;
+                return Ok(Fetched::Content {
+                    bytes,
+                    // echoed later in request header
+                    etag: header("etag").filter(|x| is_header_safe(x)),
+                });
+            }
+            304 if etag.is_some() => return Ok(Fetched::NotModified),
+            301 | 302 | 303 | 307 | 308 => {
+                let redirected =
+                    header("location").ok_or(format!("Redirect without location from {}", url))?;
+                url = if is_web_url(&redirected) {
+                    if location.secure && redirected.starts_with("http://") {
+                        return Err(format!("Insecure redirect from {} to {}", url, redirected));
+                    }
+                    redirected
+                } else {
+                    format!(
+                        "{}://{}:{}{}",
+                        if location.secure { "https" } else { "http" },
+                        location.host,
+                        location.port,
+                        redirected
+                    )
+                };
+            }
+            _ => return Err(format!("Failed to fetch {} (status {})", url, status)),
+        }
+    }
+    Err(format!("Too many redirects from {}", url))
+}
+
+/// parse `http[s]://host[:port][/path][?query]`. fragment is dropped
+fn parse_web_url(url: &str) -> Result<WebLocation, String> {
+    let (secure, rest) = if let Some(rest) = url.strip_prefix("https://") {
+        (true, rest)
+    } else if let Some(rest) = url.strip_prefix("http://") {
+        (false, rest)
+    } else {
+        return Err(format!("Not http url: {}", url));
+    };
+    let rest = rest.split('#').next().unwrap_or_default();
+    let (hostport, target) = match rest.find(['/', '?']) {
+        Some(i) if rest[i..].starts_with('?') => (&rest[..i], format!("/{}", &rest[i..])),
+        Some(i) => (&rest[..i], rest[i..].to_owned()),
+        None => (rest, "/".to_owned()),
+    };
+    let default_port = if secure { 443 } else { 80 };
+    let (host, port) = match hostport.rsplit_once(':') {
+        Some((host, port)) => (
+            host.to_owned(),
+            port.parse::<u16>()
+                .map_err(|_| format!("Invalid port in {}", url))?,
+        ),
+        None => (hostport.to_owned(), default_port),
+    };
+    if host.is_empty() {
+        return Err(format!("Host is missing in {}", url));
+    }
+    // url is put into request as it is
+    if !is_header_safe(&host) || !is_header_safe(&target) {
+        return Err(format!("Invalid chars in url: {}", url.escape_debug()));
+    }
+    Ok(WebLocation {
+        secure,
+        host,
+        port,
+        target,
+    })
+}
+
+/// send get request and get stream to read response from
+fn send_request(location: &WebLocation, etag: Option<&str>) -> Result<Box<dyn Read>, String> {
+    let mut request = format!(
+        "GET {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: {}\r\nAccept: */*\r\nAccept-Encoding: identity\r\nConnection: close\r\n",
+        location.target, location.host, USER_AGENT
+    );
+    if let Some(etag) = etag.filter(|x| is_header_safe(x)) {
+        request.push_str(&format!("If-None-Match: {}\r\n", etag));
+    }
+    request.push_str("\r\n");
+
+    let stream = tcp_connect(&location.host, location.port)?;
+    let send = |stream: &mut dyn Write| {
+        stream
+            .write_all(request.as_bytes())
+            .and_then(|_| stream.flush())
+            .map_err(|err| format!("Failed to send request to {} ({})", location.host, err))
+    };
+    if location.secure {
+        let mut stream = tls_connect(&location.host, stream)?;
+        send(&mut stream)?;
+        Ok(Box::new(stream))
+    } else {
+        let mut stream = stream;
+        send(&mut stream)?;
+        Ok(Box::new(stream))
+    }
+}
+
+/// check if value is put into request line or header without breaking it
+fn is_header_safe(value: &str) -> bool {
+    !value.contains([' ', '\r', '\n'])
+}
+
+/// read status code and headers with lowercase names
+fn read_response_head(
+    reader: &mut impl BufRead,
+    url: &str,
+) -> Result<(u32, Vec<(String, String)>), String> {
+    let mut read_line = || {
+        let mut line = String::new();
+        reader
+            .read_line(&mut line)
+            .map_err(|err| format!("Failed to read response from {} ({})", url, err))?;
+        Ok::<_, String>(line.trim_end().to_owned())
+    };
+    let status_line = read_line()?;
+    let status = status_line
+        .split(' ')
+        .nth(1)
+        .and_then(|x| x.parse::<u32>().ok())
+        .ok_or(format!("Invalid response from {}: {}", url, status_line))?;
+    let mut headers = vec![];
+    loop {
+        let line = read_line()?;
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_lowercase(), value.trim().to_owned()));
+        }
+    }
+    Ok((status, headers))
+}
+
+/// read body sized by content length, chunked or ended by connection close
+fn read_body(
+    reader: &mut impl BufRead,
+    header: &dyn Fn(&str) -> Option<String>,
+    url: &str,
+    on_progress: &mut dyn FnMut(u64, Option<u64>),
+) -> Result<Vec<u8>, String> {
+    let read_err = |err: std::io::Error| format!("Failed to read response from {} ({})", url, err);
+    let total = header("content-length").and_then(|x| x.parse::<u64>().ok());
+    if MAX_WEB_CONTENT_SIZE < total.unwrap_or_default() {
+        return Err(format!("Content too large: {}", url));
+    }
+    let chunked = header("transfer-encoding").is_some_and(|x| x.to_lowercase().contains("chunked"));
+
+    let mut ret = vec![];
+    let mut buffer = vec![0; WEB_READ_CHUNK_SIZE];
+    // reads up to limit bytes of body, or to end of stream without limit
+    let mut read_up_to = |reader: &mut dyn Read, limit: Option<u64>, ret: &mut Vec<u8>| {
+        let mut remaining = limit.unwrap_or(u64::MAX);
+        while 0 < remaining {
+            let max_read = remaining.min(buffer.len() as u64) as usize;
+            let read_bytes = match reader.read(&mut buffer[..max_read]) {
+                Ok(0) if limit.is_none() => break,
+                Ok(0) => return Err(format!("Response from {} ended unexpectedly", url)),
+                Ok(x) => x,
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(read_err(err)),
+            };
+            ret.extend_from_slice(&buffer[..read_bytes]);
+            remaining -= read_bytes as u64;
+            if MAX_WEB_CONTENT_SIZE < ret.len() as u64 {
+                return Err(format!("Content too large: {}", url));
+            }
+            on_progress(ret.len() as u64, total);
+        }
+        Ok(())
+    };
+
+    if !chunked {
+        read_up_to(reader, total, &mut ret)?;
+        return Ok(ret);
+    }
+    // each chunk has its size in hex, optionally followed by extensions
+    loop {
+        let mut size_line = String::new();
+        reader.read_line(&mut size_line).map_err(read_err)?;
+        let size = size_line
+            .split(';')
+            .next()
+            .and_then(|x| u64::from_str_radix(x.trim(), 16).ok())
+            .ok_or(format!("Invalid chunk from {}: {}", url, size_line.trim()))?;
+        if size == 0 {
+            break;
+        }
+        read_up_to(reader, Some(size), &mut ret)?;
+        let mut line_ending = String::new();
+        reader.read_line(&mut line_ending).map_err(read_err)?;
+    }
+    Ok(ret)
+}
@@ -0,0 +1,155 @@
+/// ratio of code units in ascii range on which bom-less utf-32 is assumed
+const MIN_UTF32_ASCII_RATIO: f32 = 0.5;
+
+/// unicode encoding with multi-byte code units
+/// utf-32 is not supported by encoding_rs, and utf-16 is decoded but not encoded by it
+#[derive(Clone, Copy, PartialEq)]
+pub enum WideEncoding {
+    Utf16Le,
+    Utf16Be,
+    Utf32Le,
+    Utf32Be,
+}
+
+impl WideEncoding {
+    pub fn name(&self) -> &'static str {
+        match self {
+            WideEncoding::Utf16Le => "UTF-16LE",
+            WideEncoding::Utf16Be => "UTF-16BE",
+            WideEncoding::Utf32Le => "UTF-32LE",
+            WideEncoding::Utf32Be => "UTF-32BE",
+        }
+    }
+
+    fn bom(&self) -> &'static [u8] {
+        match self {
+            WideEncoding::Utf16Le => &[0xff, 0xfe],
+            WideEncoding::Utf16Be => &[0xfe, 0xff],
+            WideEncoding::Utf32Le => &[0xff, 0xfe, 0x00, 0x00],
+            WideEncoding::Utf32Be => &[0x00, 0x00, 0xfe, 0xff],
+        }
+    }
+
+    fn unit_length(&self) -> usize {
+        match self {
+            WideEncoding::Utf16Le | WideEncoding::Utf16Be => 2,
+            WideEncoding::Utf32Le | WideEncoding::Utf32Be => 4,
+        }
+    }
+
+    fn is_little_endian(&self) -> bool {
+        matches!(self, WideEncoding::Utf16Le | WideEncoding::Utf32Le)
+    }
+}
+
+/// wide encoding by charset label such as `utf-16le` or `UTF-32`
+/// `utf-16` means little endian as in encoding_rs, and `utf-32` big endian as in unicode standard
+pub fn wide_encoding_for_label(label: &str) -> Option<WideEncoding> {
+    match label.trim().to_lowercase().replace('_', "-").as_str() {
+        "utf-16le" | "utf16le" | "utf-16" | "unicode" | "ucs-2" => Some(WideEncoding::Utf16Le),
+        "utf-16be" | "utf16be" | "unicodefffe" => Some(WideEncoding::Utf16Be),
+        "utf-32le" | "utf32le" => Some(WideEncoding::Utf32Le),
+        "utf-32be" | "utf32be" | "utf-32" | "ucs-4" => Some(WideEncoding::Utf32Be),
+        _ => None,
+    }
+}
+
+/// wide encoding by bom at head, with bom length
+/// utf-32le bom is checked before utf-16le one which is its prefix
+pub fn wide_encoding_for_bom(bytes: &[u8]) -> Option<(WideEncoding, usize)> {
+    [
+        WideEncoding::Utf32Le,
+        WideEncoding::Utf32Be,
+        WideEncoding::Utf16Le,
+        WideEncoding::Utf16Be,
+    ]
+    .into_iter()
+    .find(|x| bytes.starts_with(x.bom()))
+    .map(|x| (x, x.bom().len()))
+}
+
+/// utf-32 guessed by code units which are valid and mostly in ascii range
+pub fn utf32_without_bom(head: &[u8]) -> Option<WideEncoding> {
+    let units = head.chunks_exact(4).collect::<Vec<_>>();
+    if units.is_empty() {
+        return None;
+    }
+    [WideEncoding::Utf32Le, WideEncoding::Utf32Be]
+        .into_iter()
+        .find(|encoding| {
+            let code_points = units
+                .iter()
+                .map(|x| code_unit(x, encoding.is_little_endian()))
+                .collect::<Vec<_>>();
+            let ascii_count = code_points.iter().filter(|x| 0 < **x && **x < 0x80).count();
+            code_points.iter().all(|x| char::from_u32(*x).is_some())
+                && MIN_UTF32_ASCII_RATIO <= ascii_count as f32 / units.len() as f32
+        })
+}
+
+/// decode bytes with bom skipped when present
+/// malformed code units are replaced with U+FFFD. second value tells if there were any
+pub fn decode_wide(bytes: &[u8], encoding: WideEncoding) -> (String, bool) {
+    let body = bytes.strip_prefix(encoding.bom()).unwrap_or(bytes);
+    let little_endian = encoding.is_little_endian();
+    let unit_length = encoding.unit_length();
+    let units = body.chunks(unit_length);
+
+    let mut had_errors = body.len() % unit_length != 0;
+    let ret = match encoding {
+        WideEncoding::Utf16Le | WideEncoding::Utf16Be => {
+            let units = units
+                .filter(|x| x.len() == unit_length)
+                .map(|x| code_unit(x, little_endian) as u16);
+            char::decode_utf16(units)
+                .map(|x| {
+                    x.unwrap_or_else(|_| {
+                        had_errors = true;
+                        '\u{FFFD}'
+                    })
+                })
+                .collect()
+        }
+        WideEncoding::Utf32Le | WideEncoding::Utf32Be => units
+            .filter(|x| x.len() == unit_length)
+            .map(|x| {
+                char::from_u32(code_unit(x, little_endian)).unwrap_or_else(|| {
+                    had_errors = true;
+                    '\u{FFFD}'
+                })
+            })
+            .collect(),
+    };
+    (ret, had_errors)
+}
+
+/// encode text with bom put at head when `with_bom` is true
+pub fn encode_wide(s: &str, encoding: WideEncoding, with_bom: bool) -> Vec<u8> {
+    let mut ret = Vec::with_capacity(s.len() * encoding.unit_length());
+    if with_bom {
+        ret.extend_from_slice(encoding.bom());
+    }
+    // bom kept in text as char is not written twice
+    let s = if with_bom {
+        s.strip_prefix('\u{FEFF}').unwrap_or(s)
+    } else {
+        s
+    };
+    match encoding {
+        WideEncoding::Utf16Le => s.encode_utf16().for_each(|x| ret.extend(x.to_le_bytes())),
+        WideEncoding::Utf16Be => s.encode_utf16().for_each(|x| ret.extend(x.to_be_bytes())),
+        WideEncoding::Utf32Le => s.chars().for_each(|x| ret.extend((x as u32).to_le_bytes())),
+        WideEncoding::Utf32Be => s.chars().for_each(|x| ret.extend((x as u32).to_be_bytes())),
+    }
+    ret
+}
+
+/// code unit of 2 or 4 bytes
+fn code_unit(bytes: &[u8], little_endian: bool) -> u32 {
+    let fold = |n: u32, x: &u8| n << 8 | *x as u32;
+    if little_endian {
+        bytes.iter().rev().fold(0, fold)
+    } else {
+        bytes.iter().fold(0, fold)
+    }
+}
@@ -0,0 +1,304 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use similar::DiffTag;
+
+use super::diff::partial_lines_diffs;
+use super::diff_rows::{
+    inline_rows, line_row, minimap, pane_index, pane_lines, split_rows, PaneIndex,
+};
+use super::types::{
+    ComparisonOptions, ComparisonSummary, DiffSide, LinesDiffResponse, OpenComparison, PaneEdit,
+    PaneLines, PartialLinesDiff, RestoredView, ViewState,
+};
+
+/// max edits kept per comparison to be undone
+const MAX_UNDO_EDITS: usize = 100;
+
+/// side and buffer replaced by edit
+type BufferEdit = (DiffSide, Option<String>);
+
+/// app state to keep comparisons open in tabs
+/// kept in backend so that they survive webview reloads
+#[derive(Default)]
+pub struct OpenComparisons {
+    comparisons: Mutex<BTreeMap<u64, OpenComparison>>,
+    next_id: AtomicU64,
+    /// buffers replaced by edits per comparison, latest last
+    undo_edits: Mutex<HashMap<u64, Vec<BufferEdit>>>,
+    /// index of rows of cached diff per comparison, built on first lines request
+    pane_indices: Mutex<HashMap<u64, PaneIndex>>,
+}
+
+impl OpenComparisons {
+    /// open comparison of file pair with view state saved on previous session
+    pub fn open(
+        &self,
+        old: &str,
+        new: &str,
+        options: ComparisonOptions,
+        view_state: ViewState,
+    ) -> OpenComparison {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let comparison = OpenComparison {
+            id,
+            old: old.to_owned(),
+            new: new.to_owned(),
+            options,
+            old_buffer: None,
+            new_buffer: None,
+            diff: None,
+            excluded_hunks: BTreeSet::new(),
+            view_state,
+        };
+        self.comparisons
+            .lock()
+            .unwrap()
+            .insert(id, comparison.clone());
+        comparison
+    }
+
+    /// open comparisons in order of opening
+    pub fn list(&self) -> Vec<ComparisonSummary> {
+        self.comparisons
+            .lock()
+            .unwrap()
+            .values()
+            .map(|x| ComparisonSummary {
+                id: x.id,
+                old: x.old.to_owned(),
+                new: x.new.to_owned(),
+                dirty: x.old_buffer.is_some() || x.new_buffer.is_some(),
+            })
+            .collect()
+    }
+
+    /// get comparison with its options, dirty buffers and cached diff
+    pub fn get(&self, id: u64) -> Result<OpenComparison, String> {
+        self.comparisons
+            .lock()
+            .unwrap()
+            .get(&id)
+            .cloned()
+            .ok_or(format!("Comparison not found: {}", id))
+    }
+
+    /// replace options. cached diff is discarded
+    pub fn update_options(&self, id: u64, options: ComparisonOptions) -> Result<(), String> {
+        self.modify(id, |x| {
+            x.options = options;
+            x.diff = None;
+        })?;
+        self.discard_pane_index(id);
+        Ok(())
+    }
+
+    /// keep text edited but not saved yet on side, or discard it with none
+    /// cached diff is discarded and buffer replaced is kept to be undone
+    pub fn update_buffer(
+        &self,
+        id: u64,
+        side: DiffSide,
+        content: Option<String>,
+    ) -> Result<(), String> {
+        let mut previous = None;
+        self.modify(id, |x| {
+            let buffer = match side {
+                DiffSide::Old => &mut x.old_buffer,
+                DiffSide::New => &mut x.new_buffer,
+            };
+            previous = Some(std::mem::replace(buffer, content));
+            x.diff = None;
+        })?;
+        self.discard_pane_index(id);
+        if let Some(previous) = previous {
+            self.push_undo_edit(id, side, previous);
+        }
+        Ok(())
+    }
+
+    /// apply edit in pane to cached diff re-computing only hunks around it
+    /// edited side of diff is kept as buffer, which can be undone
+    /// returns part of diffs changed so that view replaces only it
+    pub fn edit_partially(&self, id: u64, edit: &PaneEdit) -> Result<PartialLinesDiff, String> {
+        let mut comparisons = self.comparisons.lock().unwrap();
+        let comparison = comparisons
+            .get_mut(&id)
+            .ok_or(format!("Comparison not found: {}", id))?;
+        // diffed content is not the one to be saved when transformed
+        let transforms = match edit.side {
+            DiffSide::Old => &comparison.options.old_transforms,
+            DiffSide::New => &comparison.options.new_transforms,
+        };
+        if !transforms.is_empty() {
+            return Err(format!("Transformed side is not editable: {}", id));
+        }
+        let Some(diff) = &mut comparison.diff else {
+            return Err(format!("Comparison not diffed yet: {}", id));
+        };
+
+        let (updated, partial) = partial_lines_diffs(&diff.diffs, edit);
+        let content = updated
+            .iter()
+            .flat_map(|x| match edit.side {
+                DiffSide::Old => &x.old_lines,
+                DiffSide::New => &x.new_lines,
+            })
+            .map(|x| x.as_str())
+            .collect::<String>();
+        diff.minimap = minimap(&updated);
+        if diff.split_rows.is_some() {
+            diff.split_rows = Some(split_rows(&updated));
+        }
+        if diff.inline_rows.is_some() {
+            diff.inline_rows = Some(inline_rows(&updated));
+        }
+        diff.diffs = updated;
+        let buffer = match edit.side {
+            DiffSide::Old => &mut comparison.old_buffer,
+            DiffSide::New => &mut comparison.new_buffer,
+        };
+        let previous = buffer.replace(content);
+        drop(comparisons);
+        self.discard_pane_index(id);
+
+        self.push_undo_edit(id, edit.side, previous);
+        Ok(partial)
+    }
+
+    /// restore buffer replaced by the latest edit and get side restored
+    /// none when there is nothing to undo
+    pub fn undo_buffer(&self, id: u64) -> Result<Option<DiffSide>, String> {
+        let Some((side, content)) = self
+            .undo_edits
+            .lock()
+            .unwrap()
+            .get_mut(&id)
+            .and_then(|x| x.pop())
+        else {
+            return Ok(None);
+        };
+        self.modify(id, |x| {
+            match side {
+                DiffSide::Old => x.old_buffer = content,
+                DiffSide::New => x.new_buffer = content,
+            }
+            x.diff = None;
+        })?;
+        self.discard_pane_index(id);
+        Ok(Some(side))
+    }
+
+    /// include hunk in or exclude it from patch export by its diff index
+    pub fn set_hunk_included(
+        &self,
+        id: u64,
+        diff_index: usize,
+        included: bool,
+    ) -> Result<(), String> {
+        self.modify(id, |x| {
+            if included {
+                x.excluded_hunks.remove(&diff_index);
+            } else {
+                x.excluded_hunks.insert(diff_index);
+            }
+        })
+    }
+
+    /// cache diff of comparison
+    pub fn cache_diff(&self, id: u64, diff: LinesDiffResponse) -> Result<(), String> {
+        self.modify(id, |x| x.diff = Some(diff))?;
+        self.discard_pane_index(id);
+        Ok(())
+    }
+
+    /// lines in range on side of cached diff, without cloning whole comparison
+    pub fn pane_lines(
+        &self,
+        id: u64,
+        side: DiffSide,
+        start: usize,
+        count: usize,
+    ) -> Result<PaneLines, String> {
+        let comparisons = self.comparisons.lock().unwrap();
+        let comparison = comparisons
+            .get(&id)
+            .ok_or(format!("Comparison not found: {}", id))?;
+        let Some(diff) = &comparison.diff else {
+            return Err(format!("Comparison not diffed yet: {}", id));
+        };
+        let mut pane_indices = self.pane_indices.lock().unwrap();
+        let index = pane_indices
+            .entry(id)
+            .or_insert_with(|| pane_index(&diff.diffs));
+        Ok(pane_lines(&diff.diffs, index, side, start, count))
+    }
+
+    /// replace scroll position and collapsed hunks of comparison
+    pub fn update_view_state(&self, id: u64, view_state: ViewState) -> Result<(), String> {
+        self.modify(id, |x| x.view_state = view_state)
+    }
+
+    /// view state with row to scroll to on cached diff
+    /// collapsed hunks no longer equal such as after files changed are dropped
+    pub fn restored_view(&self, id: u64) -> Result<RestoredView, String> {
+        let comparisons = self.comparisons.lock().unwrap();
+        let comparison = comparisons
+            .get(&id)
+            .ok_or(format!("Comparison not found: {}", id))?;
+        let mut view_state = comparison.view_state.clone();
+        let Some(diff) = &comparison.diff else {
+            return Ok(RestoredView {
+                view_state,
+                anchor_row: None,
+            });
+        };
+        view_state.collapsed_hunks.retain(|diff_index| {
+            diff.diffs
+                .iter()
+                .any(|x| x.diff_index == *diff_index && x.diff_kind == DiffTag::Equal)
+        });
+        let anchor_row = view_state
+            .anchor
+            .as_ref()
+            .and_then(|x| line_row(&diff.diffs, x.side, x.line_number));
+        Ok(RestoredView {
+            view_state,
+            anchor_row,
+        })
+    }
+
+    /// close comparison
+    pub fn close(&self, id: u64) {
+        self.comparisons.lock().unwrap().remove(&id);
+        self.undo_edits.lock().unwrap().remove(&id);
+        self.discard_pane_index(id);
+    }
+
+    /// discard index of rows on diff changed
+    fn discard_pane_index(&self, id: u64) {
+        self.pane_indices.lock().unwrap().remove(&id);
+    }
+
+    /// keep buffer replaced by edit to be undone
+    fn push_undo_edit(&self, id: u64, side: DiffSide, previous: Option<String>) {
+        let mut undo_edits = self.undo_edits.lock().unwrap();
+        let edits = undo_edits.entry(id).or_default();
+        edits.push((side, previous));
+        if MAX_UNDO_EDITS < edits.len() {
+            edits.remove(0);
+        }
+    }
+
+    /// apply change to comparison
+    fn modify(&self, id: u64, f: impl FnOnce(&mut OpenComparison)) -> Result<(), String> {
+        match self.comparisons.lock().unwrap().get_mut(&id) {
+            Some(x) => {
+                f(x);
+                Ok(())
+            }
+            None => Err(format!("Comparison not found: {}", id)),
+        }
+    }
+}
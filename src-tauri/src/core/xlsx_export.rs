@@ -0,0 +1,427 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Write;
+
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use super::os_path::os_path;
+use super::spreadsheet::{column_letters, workbook_sheets, SheetRows};
+
+/// namespace of spreadsheetml parts
+const MAIN_NAMESPACE: &str = "http://schemas.openxmlformats.org/spreadsheetml/2006/main";
+/// namespace of relationship ids in parts
+const RELATIONSHIPS_NAMESPACE: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships";
+/// prefix of relationship types
+const RELATIONSHIP_TYPE_PREFIX: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships";
+/// author of comments with old values
+const COMMENT_AUTHOR: &str = "forskscope";
+/// fills of modified, added and removed cells in ARGB
+const CHANGE_FILLS: [&str; 3] = ["FFFFEB9C", "FFC6EFCE", "FFFFC7CE"];
+
+/// change of cell shown by fill, with style index in styles part
+#[derive(Clone, Copy, PartialEq)]
+enum CellChange {
+    Equal,
+    Modified,
+    Added,
+    Removed,
+}
+
+impl CellChange {
+    fn style_index(&self) -> usize {
+        match self {
+            CellChange::Equal => 0,
+            CellChange::Modified => 1,
+            CellChange::Added => 2,
+            CellChange::Removed => 3,
+        }
+    }
+}
+
+/// cell written into exported sheet
+struct ExportCell {
+    value: String,
+    change: CellChange,
+    // old value put as comment on modified and removed cells
+    old_value: Option<String>,
+}
+
+/// cells of sheet by 1-based row and 0-based column
+type ExportCells = BTreeMap<(usize, usize), ExportCell>;
+
+/// write workbook of new side with changed cells highlighted for those using only excel
+/// modified cells are yellow, added green and removed red, and comments have old values
+/// cells are compared by position. sheets only in old workbook are appended with old values
+/// only values are written, without formulas and formats of original workbooks
+pub fn export_annotated_workbook(
+    old: &str,
+    new: &str,
+    export_filepath: &str,
+) -> Result<(), String> {
+    let old_sheets = workbook_sheets(old)?;
+    let new_sheets = workbook_sheets(new)?;
+
+    let mut sheets = vec![];
+    for (name, new_rows) in &new_sheets {
+        let old_rows = old_sheets.iter().find(|x| &x.0 == name).map(|x| &x.1);
+        sheets.push((name.to_owned(), export_cells(old_rows, Some(new_rows))));
+    }
+    for (name, old_rows) in &old_sheets {
+        if !new_sheets.iter().any(|x| &x.0 == name) {
+            sheets.push((name.to_owned(), export_cells(Some(old_rows), None)));
+        }
+    }
+    write_workbook(export_filepath, &sheets)
+}
+
+/// cells of new rows marked by comparison with old ones at the same positions
+/// removed cells keep old value when sheet is removed entirely
+fn export_cells(old_rows: Option<&SheetRows>, new_rows: Option<&SheetRows>) -> ExportCells {
+    let cells = |rows: Option<&SheetRows>| {
+        rows.into_iter()
+            .flatten()
+            .flat_map(|(row, values)| {
+                values
+                    .iter()
+                    .enumerate()
+                    .filter(|x| !x.1.is_empty())
+                    .map(move |(column, value)| ((*row, column), value.to_owned()))
+            })
+            .collect::<BTreeMap<_, _>>()
+    };
+    let old_cells = cells(old_rows);
+    let mut new_cells = cells(new_rows);
+    let sheet_removed = new_rows.is_none();
+
+    let mut ret = ExportCells::new();
+    for (position, old_value) in old_cells {
+        let cell = match new_cells.remove(&position) {
+            Some(value) if value == old_value => ExportCell {
+                value,
+                change: CellChange::Equal,
+                old_value: None,
+            },
+            Some(value) => ExportCell {
+                value,
+                change: CellChange::Modified,
+                old_value: Some(old_value),
+            },
+            None if sheet_removed => ExportCell {
+                value: old_value,
+                change: CellChange::Removed,
+                old_value: None,
+            },
+            None => ExportCell {
+                value: String::new(),
+                change: CellChange::Removed,
+                old_value: Some(old_value),
+            },
+        };
+        ret.insert(position, cell);
+    }
+    for (position, value) in new_cells {
+        ret.insert(
+            position,
+            ExportCell {
+                value,
+                change: CellChange::Added,
+                old_value: None,
+            },
+        );
+    }
+    ret
+}
+
+/// write minimal xlsx package with sheets, styles, comments and vml drawings to show them
+fn write_workbook(export_filepath: &str, sheets: &[(String, ExportCells)]) -> Result<(), String> {
+    let file = File::create(os_path(export_filepath))
+        .map_err(|err| format!("Failed to create {} ({})", export_filepath, err))?;
+    let mut writer = ZipWriter::new(file);
+    let mut add_entry = |name: &str, content: &str| -> Result<(), String> {
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+        writer
+            .start_file(name, options)
+            .map_err(|err| format!("Failed to add {} to workbook ({})", name, err))?;
+        writer
+            .write_all(content.as_bytes())
+            .map_err(|err| format!("Failed to add {} to workbook ({})", name, err))
+    };
+
+    add_entry("[Content_Types].xml", &content_types_xml(sheets))?;
+    add_entry(
+        "_rels/.rels",
+        &relationships_xml(&[("officeDocument", "xl/workbook.xml".to_owned())]),
+    )?;
+    add_entry("xl/workbook.xml", &workbook_xml(sheets))?;
+    let mut workbook_relationships = (1..=sheets.len())
+        .map(|i| ("worksheet", format!("worksheets/sheet{}.xml", i)))
+        .collect::<Vec<_>>();
+    workbook_relationships.push(("styles", "styles.xml".to_owned()));
+    add_entry(
+        "xl/_rels/workbook.xml.rels",
+        &relationships_xml(&workbook_relationships),
+    )?;
+    add_entry("xl/styles.xml", &styles_xml())?;
+
+    for (i, (_, cells)) in sheets.iter().enumerate() {
+        let sheet_number = i + 1;
+        let has_comments = cells.values().any(|x| x.old_value.is_some());
+        add_entry(
+            &format!("xl/worksheets/sheet{}.xml", sheet_number),
+            &sheet_xml(cells, has_comments),
+        )?;
+        if !has_comments {
+            continue;
+        }
+        add_entry(
+            &format!("xl/worksheets/_rels/sheet{}.xml.rels", sheet_number),
+            &relationships_xml(&[
+                ("comments", format!("../comments{}.xml", sheet_number)),
+                (
+                    "vmlDrawing",
+                    format!("../drawings/vmlDrawing{}.vml", sheet_number),
+                ),
+            ]),
+        )?;
+        add_entry(
+            &format!("xl/comments{}.xml", sheet_number),
+            &comments_xml(cells),
+        )?;
+        add_entry(
+            &format!("xl/drawings/vmlDrawing{}.vml", sheet_number),
+            &vml_drawing(cells, sheet_number),
+        )?;
+    }
+    writer
+        .finish()
+        .map_err(|err| format!("Failed to write {} ({})", export_filepath, err))?;
+    Ok(())
+}
+
+/// content types of parts in package
+fn content_types_xml(sheets: &[(String, ExportCells)]) -> String {
+    const CONTENT_TYPE_PREFIX: &str = "application/vnd.openxmlformats-officedocument";
+    let mut ret = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+         <Types xmlns=\"http://schemas.openxmlformats.org/package/2006/content-types\">\
+         <Default Extension=\"rels\" ContentType=\"application/vnd.openxmlformats-package.relationships+xml\"/>\
+         <Default Extension=\"xml\" ContentType=\"application/xml\"/>\
+         <Default Extension=\"vml\" ContentType=\"{0}.vmlDrawing\"/>\
+         <Override PartName=\"/xl/workbook.xml\" ContentType=\"{0}.spreadsheetml.sheet.main+xml\"/>\
+         <Override PartName=\"/xl/styles.xml\" ContentType=\"{0}.spreadsheetml.styles+xml\"/>",
+        CONTENT_TYPE_PREFIX
+    );
+    for (i, (_, cells)) in sheets.iter().enumerate() {
+        ret.push_str(&format!(
+            "<Override PartName=\"/xl/worksheets/sheet{}.xml\" ContentType=\"{}.spreadsheetml.worksheet+xml\"/>",
+            i + 1,
+            CONTENT_TYPE_PREFIX
+        ));
+        if cells.values().any(|x| x.old_value.is_some()) {
+            ret.push_str(&format!(
+                "<Override PartName=\"/xl/comments{}.xml\" ContentType=\"{}.spreadsheetml.comments+xml\"/>",
+                i + 1,
+                CONTENT_TYPE_PREFIX
+            ));
+        }
+    }
+    ret.push_str("</Types>");
+    ret
+}
+
+/// relationships with ids `rId1`, `rId2`, ... in order
+fn relationships_xml(relationships: &[(&str, String)]) -> String {
+    let mut ret = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+                   <Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">"
+        .to_owned();
+    for (i, (kind, target)) in relationships.iter().enumerate() {
+        ret.push_str(&format!(
+            "<Relationship Id=\"rId{}\" Type=\"{}/{}\" Target=\"{}\"/>",
+            i + 1,
+            RELATIONSHIP_TYPE_PREFIX,
+            kind,
+            xml_escaped(target)
+        ));
+    }
+    ret.push_str("</Relationships>");
+    ret
+}
+
+/// workbook listing sheets
+fn workbook_xml(sheets: &[(String, ExportCells)]) -> String {
+    let mut ret = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+         <workbook xmlns=\"{}\" xmlns:r=\"{}\"><sheets>",
+        MAIN_NAMESPACE, RELATIONSHIPS_NAMESPACE
+    );
+    for (i, (name, _)) in sheets.iter().enumerate() {
+        ret.push_str(&format!(
+            "<sheet name=\"{}\" sheetId=\"{}\" r:id=\"rId{}\"/>",
+            xml_escaped(name),
+            i + 1,
+            i + 1
+        ));
+    }
+    ret.push_str("</sheets></workbook>");
+    ret
+}
+
+/// styles with cell formats in order of `CellChange::style_index`
+fn styles_xml() -> String {
+    let fills = CHANGE_FILLS
+        .iter()
+        .map(|x| {
+            format!(
+                "<fill><patternFill patternType=\"solid\"><fgColor rgb=\"{}\"/><bgColor indexed=\"64\"/></patternFill></fill>",
+                x
+            )
+        })
+        .collect::<String>();
+    // first two fills are reserved by excel
+    let cell_formats = (0..CHANGE_FILLS.len())
+        .map(|i| {
+            format!(
+                "<xf numFmtId=\"0\" fontId=\"0\" fillId=\"{}\" borderId=\"0\" xfId=\"0\" applyFill=\"1\"/>",
+                i + 2
+            )
+        })
+        .collect::<String>();
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+         <styleSheet xmlns=\"{}\">\
+         <fonts count=\"1\"><font><sz val=\"11\"/><name val=\"Calibri\"/></font></fonts>\
+         <fills count=\"{}\"><fill><patternFill patternType=\"none\"/></fill><fill><patternFill patternType=\"gray125\"/></fill>{}</fills>\
+         <borders count=\"1\"><border><left/><right/><top/><bottom/><diagonal/></border></borders>\
+         <cellStyleXfs count=\"1\"><xf numFmtId=\"0\" fontId=\"0\" fillId=\"0\" borderId=\"0\"/></cellStyleXfs>\
+         <cellXfs count=\"{}\"><xf numFmtId=\"0\" fontId=\"0\" fillId=\"0\" borderId=\"0\" xfId=\"0\"/>{}</cellXfs>\
+         <cellStyles count=\"1\"><cellStyle name=\"Normal\" xfId=\"0\" builtinId=\"0\"/></cellStyles>\
+         </styleSheet>",
+        MAIN_NAMESPACE,
+        CHANGE_FILLS.len() + 2,
+        fills,
+        CHANGE_FILLS.len() + 1,
+        cell_formats
+    )
+}
+
+/// worksheet with cells as numbers or inline strings
+fn sheet_xml(cells: &ExportCells, has_comments: bool) -> String {
+    let mut ret = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+         <worksheet xmlns=\"{}\" xmlns:r=\"{}\"><sheetData>",
+        MAIN_NAMESPACE, RELATIONSHIPS_NAMESPACE
+    );
+    let mut current_row = None;
+    for ((row, column), cell) in cells {
+        if current_row != Some(*row) {
+            if current_row.is_some() {
+                ret.push_str("</row>");
+            }
+            ret.push_str(&format!("<row r=\"{}\">", row));
+            current_row = Some(*row);
+        }
+        let reference = format!("{}{}", column_letters(*column), row);
+        let style = cell.change.style_index();
+        if cell.value.is_empty() {
+            ret.push_str(&format!("<c r=\"{}\" s=\"{}\"/>", reference, style));
+        } else if is_number(&cell.value) {
+            ret.push_str(&format!(
+                "<c r=\"{}\" s=\"{}\"><v>{}</v></c>",
+                reference, style, cell.value
+            ));
+        } else {
+            ret.push_str(&format!(
+                "<c r=\"{}\" s=\"{}\" t=\"inlineStr\"><is><t xml:space=\"preserve\">{}</t></is></c>",
+                reference,
+                style,
+                xml_escaped(&cell.value)
+            ));
+        }
+    }
+    if current_row.is_some() {
+        ret.push_str("</row>");
+    }
+    ret.push_str("</sheetData>");
+    // comments are shown through legacy drawing with relationship after comments one
+    if has_comments {
+        ret.push_str("<legacyDrawing r:id=\"rId2\"/>");
+    }
+    ret.push_str("</worksheet>");
+    ret
+}
+
+/// comments with old values of cells
+fn comments_xml(cells: &ExportCells) -> String {
+    let mut ret = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+         <comments xmlns=\"{}\"><authors><author>{}</author></authors><commentList>",
+        MAIN_NAMESPACE, COMMENT_AUTHOR
+    );
+    for ((row, column), cell) in cells {
+        let Some(old_value) = &cell.old_value else {
+            continue;
+        };
+        ret.push_str(&format!(
+            "<comment ref=\"{}{}\" authorId=\"0\"><text><t xml:space=\"preserve\">Old: {}</t></text></comment>",
+            column_letters(*column),
+            row,
+            xml_escaped(old_value)
+        ));
+    }
+    ret.push_str("</commentList></comments>");
+    ret
+}
+
+/// vml shapes of comment boxes which excel requires to show comments
+fn vml_drawing(cells: &ExportCells, sheet_number: usize) -> String {
+    let mut ret = format!(
+        "<xml xmlns:v=\"urn:schemas-microsoft-com:vml\" xmlns:o=\"urn:schemas-microsoft-com:office:office\" xmlns:x=\"urn:schemas-microsoft-com:office:excel\">\
+         <o:shapelayout v:ext=\"edit\"><o:idmap v:ext=\"edit\" data=\"{}\"/></o:shapelayout>\
+         <v:shapetype id=\"_x0000_t202\" coordsize=\"21600,21600\" o:spt=\"202\" path=\"m,l,21600r21600,l21600,xe\">\
+         <v:stroke joinstyle=\"miter\"/><v:path gradientshapeok=\"t\" o:connecttype=\"rect\"/></v:shapetype>",
+        sheet_number
+    );
+    let positions = cells
+        .iter()
+        .filter(|x| x.1.old_value.is_some())
+        .map(|x| *x.0);
+    for (i, (row, column)) in positions.enumerate() {
+        // shape ids are unique in workbook by block of 1024 per sheet
+        ret.push_str(&format!(
+            "<v:shape id=\"_x0000_s{}\" type=\"#_x0000_t202\" \
+             style=\"position:absolute;width:144pt;height:48pt;z-index:{};visibility:hidden\" fillcolor=\"#ffffe1\">\
+             <v:textbox/><x:ClientData ObjectType=\"Note\"><x:MoveWithCells/><x:SizeWithCells/>\
+             <x:Anchor>{}, 15, {}, 2, {}, 15, {}, 16</x:Anchor><x:AutoFill>False</x:AutoFill>\
+             <x:Row>{}</x:Row><x:Column>{}</x:Column></x:ClientData></v:shape>",
+            sheet_number * 1024 + i + 1,
+            i + 1,
+            column + 1,
+            row.saturating_sub(1),
+            column + 3,
+            row + 2,
+            row - 1,
+            column
+        ));
+    }
+    ret.push_str("</xml>");
+    ret
+}
+
+/// check if value is written as number cell
+fn is_number(value: &str) -> bool {
+    value
+        .chars()
+        .all(|x| x.is_ascii_digit() || matches!(x, '-' | '.' | 'e' | 'E'))
+        && value.parse::<f64>().is_ok_and(|x| x.is_finite())
+}
+
+/// text escaped for xml content and attribute
+fn xml_escaped(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -1,40 +1,278 @@
+use tauri::ipc::InvokeBody;
 use tauri::Manager;
 
 mod core;
 
+use core::credentials::RemoteCredentials;
+use core::dir_diff::DirDiffResults;
+use core::external_tool::is_tool_arg;
+use core::logging::init_logging;
+use core::navigation::NavigationStates;
+use core::path_policy::ensure_payload_permitted;
+use core::progress::Operations;
+use core::saved::SavedContents;
+use core::schedule::start_scheduler;
+use core::scratch::ScratchBuffers;
+use core::shell::handle_second_instance_args;
+use core::spool::SpoolStore;
+use core::watch::DirWatches;
+use core::workspace::OpenComparisons;
+
 use core::handlers::{
-    binary_comparison_only, diff_chars, diff_filepaths, dir_digest_diff, file_digest_diff, is_file,
-    list_dir, open_with_file_manager, path_separator, ready, save,
+    add_annotation, add_schedule, agent_list_dir, annotations, binary_comparison_only,
+    binary_template_fields, blame_overlay, check_binary_equality, check_path_policy,
+    clear_comparison_history, clear_left_side_selection, clipboard_image, close_comparison,
+    compare_file_pairings, compare_mode, compare_with_left_side, comparison, comparison_history,
+    comparison_pane_lines, comparison_rules, comparison_view_state, context_menu_supported,
+    copy_file_metadata, copy_hunks, create_scratch_buffer, diff_audio, diff_binary_template,
+    diff_chars, diff_commands, diff_comparison, diff_concatenated, diff_dir_archive,
+    diff_expanded_includes, diff_file_timeline, diff_filepath_ranges, diff_filepaths,
+    diff_frontmatter, diff_key_values, diff_kube_manifests, diff_line_sets, diff_localizations,
+    diff_markup, diff_media_metadata, diff_packages, diff_partially, diff_protected_filepaths,
+    diff_protobuf, diff_records, diff_refs_file, diff_refs_tree, diff_registry,
+    diff_remote_filepaths, diff_rows, diff_sheet_rows, diff_single_lines, diff_snippets,
+    diff_sql_dumps, diff_template, diff_timeout, diff_with_saved, diff_with_scratch,
+    diff_working_tree, diff_working_tree_file, dir_diff_rollups, dir_digest_diff,
+    dir_permission_diffs, execute_sync, export_annotated_workbook, export_bundle,
+    export_hunks_patch, external_tools, file_digest_diff, file_preview, file_timeline,
+    file_use_status, filtered_dir_diffs, finish_merge, focus_pane, ftp_list_dir, go_to_hunk,
+    hunk_text_stats, import_bundle, is_file, is_password_protected, launch_external_tool,
+    left_side_selection, line_map, list_comparisons, list_dir, locale_number_format,
+    low_memory_mode, low_memory_required, media_file_metadata, navigate_hunk, navigation_state,
+    number_format, open_comparison, open_with_file_manager, palette, path_policy, path_remapping,
+    path_separator, permission_diff, pin_dir, pinned_dirs, preview_replace, profile_filepaths,
+    propose_file_pairings, ready, recent_dirs, recent_log_entries, register_context_menu,
+    register_git_tool, release_dir_diffs, release_navigation, release_scratch_buffer,
+    release_spool, remove_annotation, remove_recent_dir, remove_schedule, replace_in_comparison,
+    run_schedule, save, save_scratch_as, schedules, select_left_side, set_collapsed,
+    set_comparison_rules, set_diff_timeout, set_external_tools, set_hunk_ignored,
+    set_hunk_included, set_low_memory_mode, set_navigation_hunks, set_number_format,
+    set_palette_kind, set_path_policy, set_path_remapping, similar_filepaths_pairs,
+    similarity_filepaths, similarity_texts, spool_file, spooled_lines, sync_journal, sync_plan,
+    sync_plan_script, undo_comparison_buffer, unpin_dir, unregister_context_menu,
+    unwatch_dir_diffs, update_comparison_buffer, update_comparison_options,
+    update_comparison_view_state, update_scratch_buffer, verify_manifest, watch_dir_diffs,
+    web_url_file,
 };
 
+/// headless entry point on `--cli old new`
+/// returns exit code, or none when app should start with window
+pub fn run_cli() -> Option<i32> {
+    core::cli::run(std::env::args_os().skip(1).collect())
+}
+
 /// lib entry point
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    tauri::Builder::default()
+    // launch by git as difftool or mergetool runs its own window because git waits for its exit
+    let launched_as_tool = std::env::args().nth(1).is_some_and(|x| is_tool_arg(&x));
+    let invoke_handler = tauri::generate_handler![
+        add_annotation,
+        add_schedule,
+        agent_list_dir,
+        annotations,
+        binary_comparison_only,
+        binary_template_fields,
+        blame_overlay,
+        check_binary_equality,
+        check_path_policy,
+        clear_comparison_history,
+        clear_left_side_selection,
+        clipboard_image,
+        close_comparison,
+        compare_file_pairings,
+        compare_mode,
+        compare_with_left_side,
+        comparison,
+        comparison_history,
+        comparison_pane_lines,
+        comparison_rules,
+        comparison_view_state,
+        context_menu_supported,
+        copy_file_metadata,
+        copy_hunks,
+        create_scratch_buffer,
+        diff_audio,
+        diff_binary_template,
+        diff_commands,
+        diff_comparison,
+        diff_concatenated,
+        diff_dir_archive,
+        diff_expanded_includes,
+        diff_file_timeline,
+        diff_filepath_ranges,
+        diff_filepaths,
+        diff_chars,
+        diff_frontmatter,
+        diff_key_values,
+        diff_kube_manifests,
+        diff_line_sets,
+        diff_localizations,
+        diff_markup,
+        diff_media_metadata,
+        diff_packages,
+        diff_partially,
+        diff_protected_filepaths,
+        diff_protobuf,
+        diff_records,
+        diff_refs_file,
+        diff_refs_tree,
+        diff_registry,
+        diff_remote_filepaths,
+        diff_rows,
+        diff_sheet_rows,
+        diff_single_lines,
+        diff_snippets,
+        diff_sql_dumps,
+        diff_template,
+        diff_timeout,
+        diff_with_saved,
+        diff_with_scratch,
+        diff_working_tree,
+        diff_working_tree_file,
+        dir_diff_rollups,
+        dir_digest_diff,
+        dir_permission_diffs,
+        execute_sync,
+        export_annotated_workbook,
+        export_bundle,
+        export_hunks_patch,
+        external_tools,
+        file_digest_diff,
+        file_preview,
+        file_timeline,
+        file_use_status,
+        filtered_dir_diffs,
+        finish_merge,
+        focus_pane,
+        ftp_list_dir,
+        go_to_hunk,
+        hunk_text_stats,
+        import_bundle,
+        is_file,
+        is_password_protected,
+        launch_external_tool,
+        left_side_selection,
+        line_map,
+        list_comparisons,
+        list_dir,
+        locale_number_format,
+        low_memory_mode,
+        low_memory_required,
+        media_file_metadata,
+        navigate_hunk,
+        navigation_state,
+        number_format,
+        open_comparison,
+        open_with_file_manager,
+        palette,
+        path_policy,
+        path_remapping,
+        path_separator,
+        permission_diff,
+        pin_dir,
+        pinned_dirs,
+        preview_replace,
+        profile_filepaths,
+        propose_file_pairings,
+        ready,
+        recent_dirs,
+        recent_log_entries,
+        register_context_menu,
+        register_git_tool,
+        release_dir_diffs,
+        release_navigation,
+        release_scratch_buffer,
+        release_spool,
+        remove_annotation,
+        remove_recent_dir,
+        remove_schedule,
+        replace_in_comparison,
+        run_schedule,
+        save,
+        save_scratch_as,
+        schedules,
+        select_left_side,
+        set_collapsed,
+        set_comparison_rules,
+        set_diff_timeout,
+        set_external_tools,
+        set_hunk_ignored,
+        set_hunk_included,
+        set_low_memory_mode,
+        set_navigation_hunks,
+        set_number_format,
+        set_palette_kind,
+        set_path_policy,
+        set_path_remapping,
+        similar_filepaths_pairs,
+        similarity_filepaths,
+        similarity_texts,
+        spool_file,
+        spooled_lines,
+        sync_journal,
+        sync_plan,
+        sync_plan_script,
+        undo_comparison_buffer,
+        unpin_dir,
+        unregister_context_menu,
+        unwatch_dir_diffs,
+        update_comparison_buffer,
+        update_comparison_options,
+        update_comparison_view_state,
+        update_scratch_buffer,
+        verify_manifest,
+        watch_dir_diffs,
+        web_url_file,
+    ];
+    let builder = tauri::Builder::default();
+    let builder = if launched_as_tool {
+        builder
+    } else {
+        // must be registered first to forward args of second launch to running app
+        builder.plugin(tauri_plugin_single_instance::init(|app, args, cwd| {
+            handle_second_instance_args(app, args, &cwd)
+        }))
+    };
+    builder
+        .plugin(tauri_plugin_clipboard_manager::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_opener::init())
+        .manage(SavedContents::default())
+        .manage(SpoolStore::default())
+        .manage(DirDiffResults::default())
+        .manage(NavigationStates::default())
+        .manage(ScratchBuffers::default())
+        .manage(DirWatches::default())
+        .manage(OpenComparisons::default())
+        .manage(Operations::default())
+        .manage(RemoteCredentials::default())
         .setup(|app| {
             #[cfg(debug_assertions)]
             {
                 let window = app.get_webview_window("main").unwrap();
                 window.open_devtools();
             }
+            // app runs without log files when logging is not available
+            if let Err(err) = init_logging(app.handle()) {
+                eprintln!("{}", err);
+            }
+            start_scheduler(app.handle().clone());
             Ok(())
         })
-        .invoke_handler(tauri::generate_handler![
-            binary_comparison_only,
-            diff_filepaths,
-            diff_chars,
-            dir_digest_diff,
-            file_digest_diff,
-            is_file,
-            list_dir,
-            open_with_file_manager,
-            path_separator,
-            ready,
-            save,
-        ])
+        .invoke_handler(move |invoke| {
+            // path policy is enforced here so that every command reading paths is guarded
+            if let InvokeBody::Json(payload) = invoke.message.payload() {
+                let app_handle = invoke.message.webview().app_handle().clone();
+                let command = invoke.message.command().to_owned();
+                if let Err(err) = ensure_payload_permitted(&app_handle, &command, payload) {
+                    invoke.resolver.reject(err);
+                    return true;
+                }
+            }
+            invoke_handler(invoke)
+        })
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
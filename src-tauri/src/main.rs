@@ -5,6 +5,10 @@ use std::process::Command;
 
 /// app entry point
 fn main() {
+    if let Some(exit_code) = forskscope_lib::run_cli() {
+        std::process::exit(exit_code);
+    }
+
     mitigate_nvidia_gbm_failure_on_linux_webkitgtk();
 
     forskscope_lib::run()
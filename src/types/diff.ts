@@ -8,6 +8,12 @@ export interface LinesDiffResponse {
     diffs: LinesDiff[]
 }
 
+// streamed from backend while lines diff is built
+export type LinesDiffStreamEvent =
+    | { event: 'started'; data: { oldCharset: string; newCharset: string } }
+    | { event: 'hunks'; data: { diffs: LinesDiff[] } }
+    | { event: 'finished'; data: { diffsCount: number; movedDiffIndices: number[] } }
+
 export interface LinesDiff {
     diffIndex: number
     diffKind: DiffKind